@@ -0,0 +1 @@
+DROP INDEX CONCURRENTLY IF EXISTS blocks_blue_score_idx;
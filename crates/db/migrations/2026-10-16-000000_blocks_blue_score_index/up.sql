@@ -0,0 +1,2 @@
+-- Highest blue score: /chain/stats, /stats/summary
+CREATE INDEX CONCURRENTLY IF NOT EXISTS blocks_blue_score_idx ON blocks (blue_score);
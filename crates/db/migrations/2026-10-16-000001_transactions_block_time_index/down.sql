@@ -0,0 +1 @@
+DROP INDEX CONCURRENTLY IF EXISTS transactions_block_time_idx;
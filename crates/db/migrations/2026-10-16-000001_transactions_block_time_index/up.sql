@@ -0,0 +1,2 @@
+-- Block time ranges and ordering: /transaction/export, /transaction/last
+CREATE INDEX CONCURRENTLY IF NOT EXISTS transactions_block_time_idx ON transactions (block_time);
@@ -0,0 +1 @@
+DROP INDEX CONCURRENTLY IF EXISTS transactions_outputs_address_prefix_idx;
@@ -0,0 +1,3 @@
+-- Address prefix matches (LIKE 'prefix%'), which the collation-aware default opclass cannot serve: /address/search
+CREATE INDEX CONCURRENTLY IF NOT EXISTS transactions_outputs_address_prefix_idx
+    ON transactions_outputs (script_public_key_address varchar_pattern_ops);
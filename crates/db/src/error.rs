@@ -8,6 +8,12 @@ pub enum Error {
     #[error(transparent)]
     DieselError(#[from] DieselError),
 
+    #[error(transparent)]
+    Pool(#[from] diesel::r2d2::PoolError),
+
+    #[error("Migration failed: {0}")]
+    Migration(String),
+
     #[error("{0}")]
     Generic(String),
     
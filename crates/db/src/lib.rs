@@ -1,5 +1,8 @@
 pub mod error;
+pub mod migrations;
 pub mod models;
+pub mod query;
 pub mod schema;
+pub mod store;
 
 pub use diesel;
@@ -0,0 +1,20 @@
+use diesel::pg::PgConnection;
+use diesel_migrations::{EmbeddedMigrations, MigrationHarness, embed_migrations};
+
+use crate::error::{Error, Result};
+
+/// Migrations under `crates/db/migrations`, compiled into the binary.
+///
+/// The tables are created by the indexer; these only add what the listener's
+/// queries need on top of them, such as the indexes noted in [`crate::schema::table`].
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+
+/// Apply the migrations not yet run on `conn`, returning their versions
+///
+/// # Errors
+///
+/// Fails when a migration cannot be applied; the ones before it stay applied.
+pub fn run_pending(conn: &mut PgConnection) -> Result<Vec<String>> {
+    let applied = conn.run_pending_migrations(MIGRATIONS).map_err(|e| Error::Migration(e.to_string()))?;
+    Ok(applied.iter().map(ToString::to_string).collect())
+}
@@ -3,7 +3,32 @@ use serde::{Deserialize, Serialize};
 
 use crate::schema::{table::THeader, tyext::hex::Hex};
 
-#[derive(Debug, Serialize, Deserialize, Queryable, Selectable)]
+/// Easiest target the proof of work accepts, `2^255 - 1` (as close as `f64` gets);
+/// difficulty is how many times smaller a target is
+const MAX_TARGET: f64 = 5.789_604_461_865_81e76;
+
+/// Difficulty of the compact target `bits`: `MAX_TARGET / target`, where `target` is the mantissa in the low
+/// 23 bits scaled by `256^(exponent - 3)` with the exponent in the high byte.
+/// `None` for values that encode no positive target (zero, negative or out of range).
+#[must_use]
+pub fn difficulty_from_bits(bits: i64) -> Option<f64> {
+    let bits = u32::try_from(bits).ok()?;
+    if bits & 0x0080_0000 != 0 {
+        return None;
+    }
+    let exponent = i32::try_from(bits >> 24).ok()?;
+    let mut mantissa = bits & 0x007f_ffff;
+    if exponent < 3 {
+        mantissa >>= 8 * (3 - exponent);
+    }
+    if mantissa == 0 {
+        return None;
+    }
+    let target = f64::from(mantissa) * 2f64.powi(8 * (exponent - 3).max(0));
+    Some(MAX_TARGET / target)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable)]
 #[diesel(table_name = THeader, check_for_backend(Pg))]
 #[serde(rename_all = "camelCase")]
 pub struct Header {
@@ -23,3 +48,49 @@ pub struct Header {
     pub utxo_commitment: Hex,
     pub version: i16,
 }
+
+impl Header {
+    /// See [`difficulty_from_bits`]
+    #[must_use]
+    pub fn difficulty(&self) -> Option<f64> {
+        difficulty_from_bits(self.bits)
+    }
+}
+
+/// Narrow projection of [`Header`] for listings and summaries, selecting only these columns
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable)]
+#[diesel(table_name = THeader, check_for_backend(Pg))]
+#[serde(rename_all = "camelCase")]
+pub struct HeaderSummary {
+    pub hash: Hex,
+    pub timestamp: i64,
+    pub blue_score: i64,
+    pub daa_score: i64,
+    pub bits: i64,
+    pub version: i16,
+}
+
+impl HeaderSummary {
+    /// See [`difficulty_from_bits`]
+    #[must_use]
+    pub fn difficulty(&self) -> Option<f64> {
+        difficulty_from_bits(self.bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn test_summary_selects_only_its_columns() {
+        let query = THeader::table.select(HeaderSummary::as_select());
+        let text = diesel::debug_query::<Pg, _>(&query).to_string();
+        let columns = ["hash", "timestamp", "blue_score", "daa_score", "bits", "version"]
+            .map(|column| format!(r#""blocks"."{column}""#))
+            .join(", ");
+        assert_eq!(text, format!(r#"SELECT {columns} FROM "blocks" -- binds: []"#));
+    }
+}
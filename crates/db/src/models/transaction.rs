@@ -2,23 +2,23 @@ use diesel::{Queryable, Selectable, pg::Pg};
 use serde::{Deserialize, Serialize};
 
 use crate::schema::{
-    table::{TTx, TTxOu},
-    tyext::hex::Hex,
+    table::{TTx, TTxIn, TTxOu},
+    tyext::{hex::Hex, subnetwork::SubnetworkId},
 };
 
-#[derive(Debug, Serialize, Deserialize, Queryable, Selectable)]
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable)]
 #[diesel(table_name = TTx, check_for_backend(Pg))]
 #[serde(rename_all = "camelCase")]
 pub struct Tx {
     pub transaction_id: Hex,
-    pub subnetwork_id: i32,
+    pub subnetwork_id: SubnetworkId,
     pub hash: Hex,
     pub mass: Option<i32>,
     pub payload: Option<Vec<u8>>,
     pub block_time: i64,
 }
 
-#[derive(Debug, Serialize, Deserialize, Queryable, Selectable)]
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable)]
 #[diesel(table_name = TTxOu, check_for_backend(Pg))]
 #[serde(rename_all = "camelCase")]
 pub struct TxOu {
@@ -29,3 +29,18 @@ pub struct TxOu {
     pub script_public_key_address: String,
     pub block_time: i64,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable)]
+#[diesel(table_name = TTxIn, check_for_backend(Pg))]
+#[serde(rename_all = "camelCase")]
+pub struct TxIn {
+    pub transaction_id: Hex,
+    pub index: i16,
+    pub previous_outpoint_hash: Hex,
+    pub previous_outpoint_index: i16,
+    pub signature_script: Vec<u8>,
+    pub sig_op_count: i16,
+    pub block_time: i64,
+    pub previous_outpoint_script: Vec<u8>,
+    pub previous_outpoint_amount: i64,
+}
@@ -0,0 +1,203 @@
+use std::cmp::Ordering;
+
+use diesel::{pg::Pg, prelude::*};
+use serde::Deserialize;
+
+use crate::{
+    error::Result,
+    models::transaction::Tx,
+    schema::{table::TTx, tyext::subnetwork::SubnetworkId},
+};
+
+/// Direction of [`TransactionQuery`] results by block time, newest first by default
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Order {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// Filters over `transactions`, composed into a single query applying only the ones set. Every value is bound
+/// as a typed parameter, so none reaches the SQL text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransactionQuery {
+    pub subnetwork_id: Option<SubnetworkId>,
+    /// Inclusive lower bound on `block_time`
+    pub block_time_from: Option<i64>,
+    /// Exclusive upper bound on `block_time`
+    pub block_time_to: Option<i64>,
+    /// Inclusive lower bound on `mass`; transactions without a mass never match a mass bound
+    pub min_mass: Option<i32>,
+    /// Inclusive upper bound on `mass`
+    pub max_mass: Option<i32>,
+    pub order: Order,
+    pub limit: Option<i64>,
+}
+
+impl TransactionQuery {
+    #[must_use]
+    pub fn subnetwork_id(self, subnetwork_id: SubnetworkId) -> Self {
+        Self { subnetwork_id: Some(subnetwork_id), ..self }
+    }
+
+    #[must_use]
+    pub fn block_time_from(self, block_time: i64) -> Self {
+        Self { block_time_from: Some(block_time), ..self }
+    }
+
+    #[must_use]
+    pub fn block_time_to(self, block_time: i64) -> Self {
+        Self { block_time_to: Some(block_time), ..self }
+    }
+
+    #[must_use]
+    pub fn min_mass(self, mass: i32) -> Self {
+        Self { min_mass: Some(mass), ..self }
+    }
+
+    #[must_use]
+    pub fn max_mass(self, mass: i32) -> Self {
+        Self { max_mass: Some(mass), ..self }
+    }
+
+    #[must_use]
+    pub fn order(self, order: Order) -> Self {
+        Self { order, ..self }
+    }
+
+    #[must_use]
+    pub fn limit(self, limit: i64) -> Self {
+        Self { limit: Some(limit), ..self }
+    }
+
+    /// The filtered query, ordered by block time and then transaction id so pages are stable
+    #[must_use]
+    pub fn build(&self) -> TTx::BoxedQuery<'static, Pg> {
+        let mut query = TTx::table.into_boxed();
+        if let Some(subnetwork_id) = self.subnetwork_id {
+            query = query.filter(TTx::subnetwork_id.eq(subnetwork_id.value()));
+        }
+        if let Some(from) = self.block_time_from {
+            query = query.filter(TTx::block_time.ge(from));
+        }
+        if let Some(to) = self.block_time_to {
+            query = query.filter(TTx::block_time.lt(to));
+        }
+        if let Some(min) = self.min_mass {
+            query = query.filter(TTx::mass.ge(min));
+        }
+        if let Some(max) = self.max_mass {
+            query = query.filter(TTx::mass.le(max));
+        }
+        query = match self.order {
+            Order::Asc => query.order((TTx::block_time.asc(), TTx::transaction_id.asc())),
+            Order::Desc => query.order((TTx::block_time.desc(), TTx::transaction_id.desc())),
+        };
+        if let Some(limit) = self.limit {
+            query = query.limit(limit);
+        }
+        query
+    }
+
+    /// Run the query on `conn`
+    ///
+    /// # Errors
+    ///
+    /// Fails when the query fails on the database.
+    pub fn load(&self, conn: &mut PgConnection) -> Result<Vec<Tx>> {
+        Ok(self.build().select(Tx::as_select()).load(conn)?)
+    }
+
+    /// Whether the query selects `tx`, for stores filtering in memory
+    #[must_use]
+    pub fn matches(&self, tx: &Tx) -> bool {
+        let mass_within = |bound: Option<i32>, within: fn(&i32, &i32) -> bool| {
+            bound.is_none_or(|bound| tx.mass.is_some_and(|mass| within(&mass, &bound)))
+        };
+        self.subnetwork_id.is_none_or(|id| tx.subnetwork_id == id)
+            && self.block_time_from.is_none_or(|from| tx.block_time >= from)
+            && self.block_time_to.is_none_or(|to| tx.block_time < to)
+            && mass_within(self.min_mass, i32::ge)
+            && mass_within(self.max_mass, i32::le)
+    }
+
+    /// Order of `a` and `b` in the results
+    #[must_use]
+    pub fn compare(&self, a: &Tx, b: &Tx) -> Ordering {
+        let ascending = (a.block_time, &*a.transaction_id).cmp(&(b.block_time, &*b.transaction_id));
+        match self.order {
+            Order::Asc => ascending,
+            Order::Desc => ascending.reverse(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sql(query: &TransactionQuery) -> String {
+        diesel::debug_query::<Pg, _>(&query.build()).to_string()
+    }
+
+    fn tx(block_time: i64, subnetwork_id: i32, mass: Option<i32>) -> Tx {
+        Tx {
+            transaction_id: format!("{block_time:064x}").into(),
+            subnetwork_id: subnetwork_id.into(),
+            hash: format!("{block_time:064x}").into(),
+            mass,
+            payload: None,
+            block_time,
+        }
+    }
+
+    #[test]
+    fn test_unset_filters_add_no_predicates() {
+        let text = sql(&TransactionQuery::default());
+        assert!(!text.contains("WHERE"), "{text}");
+        assert!(text.contains(r#"ORDER BY "transactions"."block_time" DESC, "transactions"."transaction_id" DESC"#));
+        assert!(!text.contains("LIMIT"), "{text}");
+    }
+
+    #[test]
+    fn test_set_filters_are_bound_parameters() {
+        let query = TransactionQuery::default().subnetwork_id(SubnetworkId::COINBASE).block_time_from(1_000);
+        let text = sql(&query);
+        assert!(text.contains(r#""transactions"."subnetwork_id" = $1"#), "{text}");
+        assert!(text.contains(r#""transactions"."block_time" >= $2"#), "{text}");
+        assert!(!text.contains(r#""mass" >="#), "{text}");
+        assert!(text.ends_with("-- binds: [1, 1000]"), "{text}");
+
+        let query = TransactionQuery::default()
+            .block_time_from(1_000)
+            .block_time_to(2_000)
+            .min_mass(100)
+            .max_mass(5_000)
+            .order(Order::Asc)
+            .limit(20);
+        let text = sql(&query);
+        for predicate in [r#""block_time" >= $1"#, r#""block_time" < $2"#, r#""mass" >= $3"#, r#""mass" <= $4"#] {
+            assert!(text.contains(predicate), "{predicate} in {text}");
+        }
+        assert!(text.contains(r#""transaction_id" ASC LIMIT $5"#), "{text}");
+        assert!(text.ends_with("-- binds: [1000, 2000, 100, 5000, 20]"), "{text}");
+    }
+
+    #[test]
+    fn test_matches_applies_the_same_filters() {
+        let rows = [tx(1, 0, Some(100)), tx(2, 1, Some(2_000)), tx(3, 0, None), tx(4, 0, Some(900))];
+        let selected = |query: TransactionQuery| {
+            let mut selected: Vec<_> = rows.iter().filter(|tx| query.matches(tx)).collect();
+            selected.sort_by(|a, b| query.compare(a, b));
+            selected.iter().map(|tx| tx.block_time).collect::<Vec<_>>()
+        };
+        assert_eq!(selected(TransactionQuery::default()), [4, 3, 2, 1]);
+        assert_eq!(selected(TransactionQuery::default().subnetwork_id(SubnetworkId::NATIVE)), [4, 3, 1]);
+        let window = TransactionQuery::default().block_time_from(2).block_time_to(4);
+        assert_eq!(selected(window.order(Order::Asc)), [2, 3]);
+        // A transaction without a mass falls outside any mass bound
+        assert_eq!(selected(TransactionQuery::default().min_mass(100).max_mass(1_000)), [4, 1]);
+        assert_eq!(selected(TransactionQuery::default().subnetwork_id(SubnetworkId::NATIVE).min_mass(500)), [4]);
+    }
+}
@@ -1,3 +1,9 @@
+// Indexes added by `crate::migrations` for range, pagination and search routes, which
+// would otherwise scan the whole table:
+// - `blocks_blue_score_idx` on `blocks.blue_score`: `/chain/stats`, `/stats/summary`
+// - `transactions_block_time_idx` on `transactions.block_time`: `/transaction/export`, `/transaction/last`
+// - `transactions_outputs_address_prefix_idx` on `transactions_outputs.script_public_key_address`
+//   (`varchar_pattern_ops`, for `LIKE 'prefix%'`): `/address/search`
 mod postgres {
     use diesel::table;
 
@@ -21,6 +27,14 @@ mod postgres {
         }
     }
 
+    table! {
+        blocks_transactions (block_hash, transaction_id) {
+            block_hash              -> Bytea,
+            transaction_id          -> Bytea,
+            index                   -> SmallInt,
+        }
+    }
+
     table! {
         transactions (transaction_id) {
             transaction_id          -> Bytea,
@@ -59,5 +73,6 @@ mod postgres {
 }
 
 pub use postgres::{
-    blocks as THeader, transactions as TTx,transactions_inputs as TTxIn, transactions_outputs as TTxOu,
+    blocks as THeader, blocks_transactions as TBlockTx, transactions as TTx,transactions_inputs as TTxIn,
+    transactions_outputs as TTxOu,
 };
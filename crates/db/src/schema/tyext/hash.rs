@@ -19,16 +19,16 @@ where
     type Error = Error;
 
     fn from_hex(hex: T) -> Result<Self, Self::Error> {
-        let hex_str = std::str::from_utf8(hex.as_ref())
-            .map_err(|e| Error::InternalServerError(format!("Invalid UTF-8: {}", e)))?;
-        
-        let bytes = Vec::<u8>::from_hex(hex_str)
-            .map_err(|e| Error::InternalServerError(format!("Invalid hex: {}", e)))?;
-        
-        if bytes.len() != N {
-            return Err(Error::InternalServerError(format!("Expected {} bytes, got {}", N, bytes.len())));
+        let hex_str = std::str::from_utf8(hex.as_ref()).map_err(|e| Error::Generic(format!("Invalid UTF-8: {e}")))?;
+
+        // Checked first so a short hash reads as one rather than as a decoding error
+        let (expected, len) = (N * 2, hex_str.len());
+        if len != expected {
+            return Err(Error::Generic(format!("Expected {expected} hex characters ({N} bytes), got {len}")));
         }
-        
+
+        let bytes = Vec::<u8>::from_hex(hex_str).map_err(|e| Error::Generic(format!("Invalid hex: {e}")))?;
+
         let mut dst = [0; N];
         dst.copy_from_slice(&bytes);
         Ok(dst)
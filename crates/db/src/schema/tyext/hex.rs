@@ -10,7 +10,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::error::Result;
 
-#[derive(Debug, Serialize, Deserialize, FromSqlRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromSqlRow)]
 #[serde(transparent, rename_all = "camelCase")]
 #[repr(transparent)]
 pub struct Hex {
@@ -0,0 +1,95 @@
+use std::{fmt, str::FromStr};
+
+use diesel::{
+    deserialize::{FromSql, FromSqlRow, Result as DResult},
+    pg::{Pg, PgValue},
+    sql_types::Integer,
+};
+use hex::FromHex;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+/// Bytes of a consensus subnetwork id
+pub const SUBNETWORK_ID_SIZE: usize = 20;
+
+/// Subnetwork of a transaction as the indexer stores it: the little-endian number in the leading
+/// bytes of the 20-byte consensus id, whose remaining bytes are zero (0 native, 1 coinbase, 2 registry).
+///
+/// Parses from that number or from the 40 hex characters of the consensus id, and serializes as the number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, FromSqlRow)]
+#[serde(transparent)]
+#[repr(transparent)]
+pub struct SubnetworkId(i32);
+
+impl SubnetworkId {
+    pub const NATIVE: Self = Self(0);
+    pub const COINBASE: Self = Self(1);
+    pub const REGISTRY: Self = Self(2);
+
+    /// Value of the `subnetwork_id` column
+    #[must_use]
+    pub fn value(self) -> i32 {
+        self.0
+    }
+
+    /// The consensus id
+    #[must_use]
+    pub fn to_bytes(self) -> [u8; SUBNETWORK_ID_SIZE] {
+        let mut bytes = [0; SUBNETWORK_ID_SIZE];
+        bytes[..4].copy_from_slice(&self.0.to_le_bytes());
+        bytes
+    }
+
+    /// Name of the built-in subnetworks
+    #[must_use]
+    pub fn builtin_name(self) -> Option<&'static str> {
+        match self {
+            Self::NATIVE => Some("native"),
+            Self::COINBASE => Some("coinbase"),
+            Self::REGISTRY => Some("registry"),
+            _ => None,
+        }
+    }
+}
+
+impl From<i32> for SubnetworkId {
+    fn from(value: i32) -> Self {
+        Self(value)
+    }
+}
+
+impl fmt::Display for SubnetworkId {
+    /// The consensus id in hex, as the node prints it
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.to_bytes()))
+    }
+}
+
+impl FromStr for SubnetworkId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| Error::Generic(format!("Invalid subnetwork id `{s}`: {reason}"));
+        if s.len() != SUBNETWORK_ID_SIZE * 2 {
+            return s.parse::<u32>().ok().and_then(|n| i32::try_from(n).ok()).map(Self).ok_or_else(|| {
+                invalid(&format!("expected a non-negative number or {} hex characters", SUBNETWORK_ID_SIZE * 2))
+            });
+        }
+        let bytes = Vec::<u8>::from_hex(s).map_err(|e| invalid(&e.to_string()))?;
+        if bytes[4..].iter().any(|&byte| byte != 0) {
+            return Err(invalid("not a subnetwork the indexer can store"));
+        }
+        let value = i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        if value < 0 {
+            return Err(invalid("not a subnetwork the indexer can store"));
+        }
+        Ok(Self(value))
+    }
+}
+
+impl FromSql<Integer, Pg> for SubnetworkId {
+    fn from_sql(value: PgValue) -> DResult<Self> {
+        <i32 as FromSql<Integer, Pg>>::from_sql(value).map(Self)
+    }
+}
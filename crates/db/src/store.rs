@@ -0,0 +1,361 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Debug,
+    sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use diesel::{
+    dsl::sql,
+    expression::SqlLiteral,
+    pg::PgConnection,
+    prelude::*,
+    r2d2::{ConnectionManager, Pool},
+    sql_types::{BigInt, Bool},
+};
+
+use crate::{
+    error::Result,
+    models::{
+        chain::Header,
+        transaction::{Tx, TxIn, TxOu},
+    },
+    query::TransactionQuery,
+    schema::{
+        table::{TBlockTx, THeader, TTx, TTxIn, TTxOu},
+        tyext::hex::Hex,
+    },
+};
+
+/// Queries the read routes need, so they do not depend on Postgres. Calls block; async callers run them
+/// on a blocking thread.
+pub trait Store: Debug + Send + Sync {
+    /// Most recent header by timestamp, `None` while nothing is indexed
+    ///
+    /// # Errors
+    ///
+    /// Fails when the store cannot be queried.
+    fn latest_header(&self) -> Result<Option<Header>>;
+
+    /// Hash of [`Store::latest_header`], for stores that can skip loading the rest of it
+    ///
+    /// # Errors
+    ///
+    /// Fails when the store cannot be queried.
+    fn latest_hash(&self) -> Result<Option<String>> {
+        Ok(self.latest_header()?.map(|header| header.hash.inner))
+    }
+
+    /// Header of block `hash`, `None` when it is not indexed
+    ///
+    /// # Errors
+    ///
+    /// Fails when the store cannot be queried.
+    fn header_by_hash(&self, hash: &[u8]) -> Result<Option<Header>>;
+
+    /// Indexed transactions of block `hash` in block order
+    ///
+    /// # Errors
+    ///
+    /// Fails when the store cannot be queried.
+    fn block_transactions(&self, hash: &[u8]) -> Result<Vec<Tx>>;
+
+    /// Transaction `id`, `None` when it is not indexed
+    ///
+    /// # Errors
+    ///
+    /// Fails when the store cannot be queried.
+    fn transaction_by_id(&self, id: &[u8]) -> Result<Option<Tx>>;
+
+    /// Transactions passing the filters of `query`, in its order
+    ///
+    /// # Errors
+    ///
+    /// Fails when the store cannot be queried.
+    fn transactions(&self, query: &TransactionQuery) -> Result<Vec<Tx>>;
+
+    /// Inputs of a transaction in index order
+    ///
+    /// # Errors
+    ///
+    /// Fails when the store cannot be queried.
+    fn inputs_for_tx(&self, id: &[u8]) -> Result<Vec<TxIn>>;
+
+    /// Outputs of a transaction in index order
+    ///
+    /// # Errors
+    ///
+    /// Fails when the store cannot be queried.
+    fn outputs_for_tx(&self, id: &[u8]) -> Result<Vec<TxOu>>;
+
+    /// Lowest DAA score of the indexed blocks including transaction `id`, `None` when none is indexed
+    ///
+    /// # Errors
+    ///
+    /// Fails when the store cannot be queried.
+    fn transaction_daa_score(&self, id: &[u8]) -> Result<Option<i64>>;
+
+    /// Sum of the unspent outputs paying to `address`, `0` without any
+    ///
+    /// # Errors
+    ///
+    /// Fails when the store cannot be queried.
+    fn balance_for_address(&self, address: &str) -> Result<i64>;
+
+    /// [`Store::balance_for_address`] of each of `addresses` with unspent outputs, the others left out
+    ///
+    /// # Errors
+    ///
+    /// Fails when the store cannot be queried.
+    fn balances_for_addresses(&self, addresses: &[String]) -> Result<Vec<(String, i64)>>;
+}
+
+/// Filter on `transactions_outputs` keeping the outputs no indexed input spends
+pub fn unspent() -> SqlLiteral<Bool> {
+    sql(concat!(
+        "NOT EXISTS (SELECT 1 FROM transactions_inputs",
+        " WHERE transactions_inputs.previous_outpoint_hash = transactions_outputs.transaction_id",
+        " AND transactions_inputs.previous_outpoint_index = transactions_outputs.index)",
+    ))
+}
+
+/// `SUM(amount)` of the selected outputs, `0` when there are none
+fn sum_amount() -> SqlLiteral<BigInt> {
+    sql("COALESCE(SUM(amount), 0)::BIGINT")
+}
+
+/// [`Store`] over the indexer's Postgres database
+#[derive(Debug, Clone)]
+pub struct PgStore {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl PgStore {
+    #[must_use]
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+}
+
+impl Store for PgStore {
+    fn latest_header(&self) -> Result<Option<Header>> {
+        let conn = &mut self.pool.get()?;
+        Ok(THeader::table.order(THeader::timestamp.desc()).select(Header::as_select()).first(conn).optional()?)
+    }
+
+    fn latest_hash(&self) -> Result<Option<String>> {
+        let conn = &mut self.pool.get()?;
+        let hash = THeader::table.order(THeader::timestamp.desc()).select(THeader::hash).first::<Hex>(conn);
+        Ok(hash.optional()?.map(String::from))
+    }
+
+    fn header_by_hash(&self, hash: &[u8]) -> Result<Option<Header>> {
+        let conn = &mut self.pool.get()?;
+        Ok(THeader::table.filter(THeader::hash.eq(hash)).select(Header::as_select()).first(conn).optional()?)
+    }
+
+    fn block_transactions(&self, hash: &[u8]) -> Result<Vec<Tx>> {
+        let conn = &mut self.pool.get()?;
+        let ids = TBlockTx::table
+            .filter(TBlockTx::block_hash.eq(hash))
+            .order(TBlockTx::index.asc())
+            .select(TBlockTx::transaction_id)
+            .load::<Vec<u8>>(conn)?;
+        let mut transactions =
+            TTx::table.filter(TTx::transaction_id.eq_any(&ids)).select(Tx::as_select()).load::<Tx>(conn)?;
+        // `ids` is in block order
+        let position: HashMap<_, _> = ids.iter().enumerate().map(|(at, id)| (hex::encode(id), at)).collect();
+        transactions.sort_by_key(|tx| position.get(&*tx.transaction_id).copied());
+        Ok(transactions)
+    }
+
+    fn transaction_by_id(&self, id: &[u8]) -> Result<Option<Tx>> {
+        let conn = &mut self.pool.get()?;
+        Ok(TTx::table.filter(TTx::transaction_id.eq(id)).select(Tx::as_select()).first(conn).optional()?)
+    }
+
+    fn transactions(&self, query: &TransactionQuery) -> Result<Vec<Tx>> {
+        let conn = &mut self.pool.get()?;
+        query.load(conn)
+    }
+
+    fn inputs_for_tx(&self, id: &[u8]) -> Result<Vec<TxIn>> {
+        let conn = &mut self.pool.get()?;
+        let inputs = TTxIn::table
+            .filter(TTxIn::transaction_id.eq(id))
+            .order(TTxIn::index.asc())
+            .select(TxIn::as_select())
+            .load(conn)?;
+        Ok(inputs)
+    }
+
+    fn outputs_for_tx(&self, id: &[u8]) -> Result<Vec<TxOu>> {
+        let conn = &mut self.pool.get()?;
+        let outputs = TTxOu::table
+            .filter(TTxOu::transaction_id.eq(id))
+            .order(TTxOu::index.asc())
+            .select(TxOu::as_select())
+            .load(conn)?;
+        Ok(outputs)
+    }
+
+    fn transaction_daa_score(&self, id: &[u8]) -> Result<Option<i64>> {
+        let conn = &mut self.pool.get()?;
+        let blocks = TBlockTx::table
+            .filter(TBlockTx::transaction_id.eq(id))
+            .select(TBlockTx::block_hash)
+            .load::<Vec<u8>>(conn)?;
+        if blocks.is_empty() {
+            return Ok(None);
+        }
+        let score = THeader::table
+            .filter(THeader::hash.eq_any(&blocks))
+            .select(diesel::dsl::min(THeader::daa_score))
+            .first::<Option<i64>>(conn)?;
+        Ok(score)
+    }
+
+    fn balance_for_address(&self, address: &str) -> Result<i64> {
+        let conn = &mut self.pool.get()?;
+        let balance = TTxOu::table
+            .filter(TTxOu::script_public_key_address.eq(address))
+            .filter(unspent())
+            .select(sum_amount())
+            .first(conn)?;
+        Ok(balance)
+    }
+
+    fn balances_for_addresses(&self, addresses: &[String]) -> Result<Vec<(String, i64)>> {
+        let conn = &mut self.pool.get()?;
+        let balances = TTxOu::table
+            .filter(TTxOu::script_public_key_address.eq_any(addresses))
+            .filter(unspent())
+            .group_by(TTxOu::script_public_key_address)
+            .select((TTxOu::script_public_key_address, sum_amount()))
+            .load(conn)?;
+        Ok(balances)
+    }
+}
+
+/// [`Store`] holding its rows in memory, for tests
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    rows: RwLock<MemoryRows>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryRows {
+    headers: Vec<Header>,
+    /// `(block hash, transaction id, index)`, hashes in hex
+    block_transactions: Vec<(String, String, i16)>,
+    transactions: Vec<Tx>,
+    inputs: Vec<TxIn>,
+    outputs: Vec<TxOu>,
+}
+
+impl MemoryStore {
+    pub fn insert_header(&self, header: Header) {
+        self.write().headers.push(header);
+    }
+
+    pub fn insert_transaction(&self, transaction: Tx, inputs: Vec<TxIn>, outputs: Vec<TxOu>) {
+        let mut rows = self.write();
+        rows.transactions.push(transaction);
+        rows.inputs.extend(inputs);
+        rows.outputs.extend(outputs);
+    }
+
+    /// Record that block `block_hash` includes transaction `transaction_id` at `index`, both hashes in hex
+    pub fn insert_block_transaction(&self, block_hash: &str, transaction_id: &str, index: i16) {
+        self.write().block_transactions.push((block_hash.to_string(), transaction_id.to_string(), index));
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, MemoryRows> {
+        self.rows.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, MemoryRows> {
+        self.rows.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl MemoryRows {
+    /// Outputs no input spends
+    fn unspent(&self) -> impl Iterator<Item = &TxOu> {
+        self.outputs.iter().filter(|output| {
+            !self.inputs.iter().any(|input| {
+                *input.previous_outpoint_hash == *output.transaction_id && input.previous_outpoint_index == output.index
+            })
+        })
+    }
+}
+
+impl Store for MemoryStore {
+    fn latest_header(&self) -> Result<Option<Header>> {
+        Ok(self.read().headers.iter().max_by_key(|header| header.timestamp).cloned())
+    }
+
+    fn header_by_hash(&self, hash: &[u8]) -> Result<Option<Header>> {
+        let hash = hex::encode(hash);
+        Ok(self.read().headers.iter().find(|header| *header.hash == hash).cloned())
+    }
+
+    fn block_transactions(&self, hash: &[u8]) -> Result<Vec<Tx>> {
+        let hash = hex::encode(hash);
+        let rows = self.read();
+        let mut included: Vec<_> = rows.block_transactions.iter().filter(|(block, ..)| *block == hash).collect();
+        included.sort_by_key(|(.., index)| *index);
+        let indexed = |id: &String| rows.transactions.iter().find(|tx| *tx.transaction_id == *id).cloned();
+        Ok(included.into_iter().filter_map(|(_, id, _)| indexed(id)).collect())
+    }
+
+    fn transaction_by_id(&self, id: &[u8]) -> Result<Option<Tx>> {
+        let id = hex::encode(id);
+        Ok(self.read().transactions.iter().find(|tx| *tx.transaction_id == id).cloned())
+    }
+
+    fn transactions(&self, query: &TransactionQuery) -> Result<Vec<Tx>> {
+        let mut transactions: Vec<_> =
+            self.read().transactions.iter().filter(|tx| query.matches(tx)).cloned().collect();
+        transactions.sort_by(|a, b| query.compare(a, b));
+        transactions.truncate(query.limit.map_or(usize::MAX, |limit| usize::try_from(limit).unwrap_or(0)));
+        Ok(transactions)
+    }
+
+    fn inputs_for_tx(&self, id: &[u8]) -> Result<Vec<TxIn>> {
+        let id = hex::encode(id);
+        let mut inputs: Vec<_> =
+            self.read().inputs.iter().filter(|input| *input.transaction_id == id).cloned().collect();
+        inputs.sort_by_key(|input| input.index);
+        Ok(inputs)
+    }
+
+    fn outputs_for_tx(&self, id: &[u8]) -> Result<Vec<TxOu>> {
+        let id = hex::encode(id);
+        let mut outputs: Vec<_> =
+            self.read().outputs.iter().filter(|output| *output.transaction_id == id).cloned().collect();
+        outputs.sort_by_key(|output| output.index);
+        Ok(outputs)
+    }
+
+    fn transaction_daa_score(&self, id: &[u8]) -> Result<Option<i64>> {
+        let id = hex::encode(id);
+        let rows = self.read();
+        let includes = |hash: &str| rows.block_transactions.iter().any(|(block, tx, _)| block == hash && *tx == id);
+        Ok(rows.headers.iter().filter(|header| includes(&header.hash)).map(|header| header.daa_score).min())
+    }
+
+    fn balance_for_address(&self, address: &str) -> Result<i64> {
+        let rows = self.read();
+        let paying = rows.unspent().filter(|output| output.script_public_key_address == address);
+        Ok(paying.map(|output| output.amount).sum())
+    }
+
+    fn balances_for_addresses(&self, addresses: &[String]) -> Result<Vec<(String, i64)>> {
+        let rows = self.read();
+        let mut balances = BTreeMap::<&str, i64>::new();
+        for output in rows.unspent().filter(|output| addresses.contains(&output.script_public_key_address)) {
+            *balances.entry(&output.script_public_key_address).or_default() += output.amount;
+        }
+        Ok(balances.into_iter().map(|(address, balance)| (address.to_string(), balance)).collect())
+    }
+}
@@ -0,0 +1,131 @@
+use std::{pin::Pin, sync::Arc};
+
+use futures::{Stream, stream};
+use tondi_listener_db::{
+    error::{Error as DbError, Result as DbResult},
+    models::{chain::Header as DbHeader, transaction::Tx},
+    schema::tyext::hex::Hex,
+    store::Store,
+};
+use tondi_listener_http2_client::{
+    protowire::{
+        Block, Hash, Header, Transaction,
+        explorer_service_server::{ExplorerService, ExplorerServiceServer},
+    },
+    tonic::{Request, Response, Status},
+};
+
+const HASH_SIZE: usize = 32;
+
+pub fn service(store: Arc<dyn Store>) -> ExplorerServiceServer<Explorer> {
+    ExplorerServiceServer::new(Explorer { store })
+}
+
+/// Explorer gRPC service over the indexed blocks and transactions
+#[derive(Debug, Clone)]
+pub struct Explorer {
+    store: Arc<dyn Store>,
+}
+
+impl Explorer {
+    /// Run a store query on the blocking pool
+    async fn query<T, F>(&self, query: F) -> Result<T, Status>
+    where
+        T: Send + 'static,
+        F: FnOnce(&dyn Store) -> DbResult<T> + Send + 'static,
+    {
+        let store = self.store.clone();
+        tokio::task::spawn_blocking(move || {
+            query(&*store).map_err(|e| match e {
+                DbError::Pool(e) => Status::unavailable(format!("Database unavailable: {e}")),
+                e => Status::internal(format!("Database error: {e}")),
+            })
+        })
+        .await
+        .map_err(|e| Status::internal(format!("Query task failed: {e}")))?
+    }
+}
+
+#[tonic::async_trait]
+impl ExplorerService for Explorer {
+    type GetBlockStream = Pin<Box<dyn Stream<Item = Result<Block, Status>> + Send>>;
+
+    async fn get_block(&self, request: Request<Hash>) -> Result<Response<Self::GetBlockStream>, Status> {
+        let block_hash = validate_hash(request.into_inner())?;
+        let (header, transactions) = self
+            .query(move |store| {
+                let Some(header) = store.header_by_hash(&block_hash)? else {
+                    return Ok(None);
+                };
+                Ok(Some((header, store.block_transactions(&block_hash)?)))
+            })
+            .await?
+            .ok_or_else(|| Status::not_found("Block not found"))?;
+
+        let transactions = transactions.into_iter().map(transaction_to_proto).collect::<Result<_, _>>()?;
+        let block = Block { header: Some(header_to_proto(header)?), transactions };
+        Ok(Response::new(Box::pin(stream::once(async { Ok(block) }))))
+    }
+
+    async fn get_transaction(&self, request: Request<Hash>) -> Result<Response<Transaction>, Status> {
+        let id = validate_hash(request.into_inner())?;
+        let tx = self
+            .query(move |store| store.transaction_by_id(&id))
+            .await?
+            .ok_or_else(|| Status::not_found("Transaction not found"))?;
+        Ok(Response::new(transaction_to_proto(tx)?))
+    }
+}
+
+fn validate_hash(hash: Hash) -> Result<Vec<u8>, Status> {
+    match hash.value.len() {
+        HASH_SIZE => Ok(hash.value),
+        len => Err(Status::invalid_argument(format!("Hash must be {HASH_SIZE} bytes, got {len}"))),
+    }
+}
+
+fn hash(hex: &Hex) -> Result<Option<Hash>, Status> {
+    let value = hex.decode().map_err(|e| Status::internal(e.to_string()))?;
+    Ok(Some(Hash { value }))
+}
+
+/// Widen a stored integer column to its unsigned protobuf field
+fn unsigned<T: TryFrom<i64>>(value: impl Into<i64>, field: &str) -> Result<T, Status> {
+    T::try_from(value.into()).map_err(|_| Status::internal(format!("Stored {field} is out of range")))
+}
+
+fn header_to_proto(header: DbHeader) -> Result<Header, Status> {
+    // Nonce is stored little-endian
+    let mut nonce = [0u8; 8];
+    let len = header.nonce.len().min(nonce.len());
+    nonce[..len].copy_from_slice(&header.nonce[..len]);
+
+    Ok(Header {
+        hash: hash(&header.hash)?,
+        version: unsigned(header.version, "version")?,
+        // Only the selected parent is stored
+        parents_by_level: Vec::new(),
+        hash_merkle_root: hash(&header.hash_merkle_root)?,
+        accepted_id_merkle_root: hash(&header.accepted_id_merkle_root)?,
+        utxo_commitment: hash(&header.utxo_commitment)?,
+        timestamp: unsigned(header.timestamp, "timestamp")?,
+        bits: unsigned(header.bits, "bits")?,
+        nonce: u64::from_le_bytes(nonce),
+        daa_score: unsigned(header.daa_score, "daa score")?,
+        blue_work: header.blue_work.iter().map(|byte| format!("{byte:02x}")).collect(),
+        blue_score: unsigned(header.blue_score, "blue score")?,
+        pruning_point: hash(&header.pruning_point)?,
+    })
+}
+
+fn transaction_to_proto(tx: Tx) -> Result<Transaction, Status> {
+    Ok(Transaction {
+        transaction_id: hash(&tx.transaction_id)?,
+        version: 0,
+        hash: hash(&tx.hash)?,
+        subnetwork_id: unsigned(tx.subnetwork_id.value(), "subnetwork id")?,
+        mass: tx.mass.map(|mass| unsigned(mass, "mass")).transpose()?,
+        payload: tx.payload,
+        block_time: tx.block_time,
+    })
+}
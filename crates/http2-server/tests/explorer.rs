@@ -0,0 +1,95 @@
+use std::sync::Arc;
+
+use futures::stream;
+use tokio::net::TcpListener;
+use tondi_listener_db::{
+    models::{chain::Header, transaction::Tx},
+    schema::tyext::subnetwork::SubnetworkId,
+    store::MemoryStore,
+};
+use tondi_listener_http2_client::{
+    protowire::{Hash, explorer_service_client::ExplorerServiceClient},
+    tonic::{Code, transport::Server},
+};
+use tondi_listener_http2_server::explorer;
+
+/// Serve the explorer over `store` on an ephemeral port and return its address
+async fn spawn_explorer(store: MemoryStore) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = stream::unfold(listener, |listener| async move {
+        let accepted = listener.accept().await.map(|(stream, _)| stream);
+        Some((accepted, listener))
+    });
+    tokio::spawn(Server::builder().add_service(explorer::service(Arc::new(store))).serve_with_incoming(incoming));
+    format!("http://{addr}")
+}
+
+fn header(hash: &str) -> Header {
+    let zero = || "00".repeat(32).into();
+    Header {
+        hash: hash.to_string().into(),
+        accepted_id_merkle_root: zero(),
+        merge_set_blues_hashes: Vec::new(),
+        merge_set_reds_hashes: None,
+        selected_parent_hash: zero(),
+        bits: 503_382_015,
+        blue_score: 7,
+        blue_work: vec![1],
+        daa_score: 7,
+        hash_merkle_root: zero(),
+        nonce: vec![0],
+        pruning_point: zero(),
+        timestamp: 1_700_000_000_000,
+        utxo_commitment: zero(),
+        version: 1,
+    }
+}
+
+fn tx(id: &str) -> Tx {
+    Tx {
+        transaction_id: id.to_string().into(),
+        subnetwork_id: SubnetworkId::NATIVE,
+        hash: id.to_string().into(),
+        mass: None,
+        payload: None,
+        block_time: 1_700_000_000_000,
+    }
+}
+
+#[tokio::test]
+async fn returns_blocks_with_their_transactions() {
+    let store = MemoryStore::default();
+    let (block, first, second) = ("aa".repeat(32), "01".repeat(32), "02".repeat(32));
+    store.insert_header(header(&block));
+    for (id, index) in [(&second, 1), (&first, 0)] {
+        store.insert_transaction(tx(id), Vec::new(), Vec::new());
+        store.insert_block_transaction(&block, id, index);
+    }
+    // Included by another block only
+    store.insert_transaction(tx(&"03".repeat(32)), Vec::new(), Vec::new());
+    store.insert_block_transaction(&"bb".repeat(32), &"03".repeat(32), 0);
+    let mut client = ExplorerServiceClient::connect(spawn_explorer(store).await).await.unwrap();
+
+    let mut blocks = client.get_block(Hash { value: vec![0xaa; 32] }).await.unwrap().into_inner();
+    let found = blocks.message().await.unwrap().unwrap();
+    assert_eq!(found.header.unwrap().hash.unwrap().value, vec![0xaa; 32]);
+    let ids: Vec<_> = found.transactions.into_iter().map(|tx| tx.transaction_id.unwrap().value).collect();
+    assert_eq!(ids, [vec![0x01; 32], vec![0x02; 32]]);
+    assert!(blocks.message().await.unwrap().is_none());
+
+    let status = client.get_block(Hash { value: vec![0xbc; 32] }).await.unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+}
+
+#[tokio::test]
+async fn rejects_malformed_hashes() {
+    let mut client = ExplorerServiceClient::connect(spawn_explorer(MemoryStore::default()).await).await.unwrap();
+    let short = Hash { value: vec![0; 16] };
+
+    let status = client.get_transaction(short.clone()).await.unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    let status = client.get_block(short).await.unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
@@ -1,10 +1,45 @@
 pub use tracing::*;
 
+/// Swaps in a new log output filter; the subscribers differ in type, so each installs its own
+type Reload = Box<dyn Fn(tracing_subscriber::EnvFilter) -> Result<(), String> + Send + Sync>;
+
+/// Reloads the filter installed by [`init_tracing_subscriber_log`] or `init_tracing_subscriber_log_with_console`
+static FILTER: std::sync::OnceLock<Reload> = std::sync::OnceLock::new();
+
 pub fn init_tracing_subscriber_log() {
     use tracing_subscriber::{EnvFilter, fmt::format::FmtSpan};
     let span = FmtSpan::NEW | FmtSpan::CLOSE;
     let filter = EnvFilter::from_default_env();
-    tracing_subscriber::fmt().with_span_events(span).with_env_filter(filter).init();
+    let builder = tracing_subscriber::fmt().with_span_events(span).with_env_filter(filter).with_filter_reloading();
+    let handle = builder.reload_handle();
+    let _ = FILTER.set(Box::new(move |filter| handle.reload(filter).map_err(|e| e.to_string())));
+    builder.init();
+}
+
+/// Replace the `RUST_LOG` filter of the installed subscriber while running, e.g. with `debug`
+pub fn set_filter(directives: &str) -> Result<(), String> {
+    let reload = FILTER.get().ok_or("no log subscriber is installed")?;
+    let filter = tracing_subscriber::EnvFilter::try_new(directives).map_err(|e| e.to_string())?;
+    reload(filter)
+}
+
+/// [`init_tracing_subscriber_log`], plus a `tokio-console` server on `console` when set.
+///
+/// `RUST_LOG` filters the log output only, and [`set_filter`] replaces it the same way; the console
+/// layer always sees the runtime's task spans, which exist in binaries built with
+/// `RUSTFLAGS="--cfg tokio_unstable"`.
+#[cfg(feature = "tokio-console")]
+pub fn init_tracing_subscriber_log_with_console(console: Option<std::net::SocketAddr>) {
+    use tracing_subscriber::{
+        EnvFilter, Layer, fmt::format::FmtSpan, layer::SubscriberExt, reload, util::SubscriberInitExt,
+    };
+    let span = FmtSpan::NEW | FmtSpan::CLOSE;
+    let (filter, handle) = reload::Layer::new(EnvFilter::from_default_env());
+    let fmt = tracing_subscriber::fmt::layer().with_span_events(span).with_filter(filter);
+    let console = console.map(|addr| console_subscriber::ConsoleLayer::builder().server_addr(addr).spawn());
+    let subscriber = tracing_subscriber::registry().with(console).with(fmt);
+    let _ = FILTER.set(Box::new(move |filter| handle.reload(filter).map_err(|e| e.to_string())));
+    subscriber.init();
 }
 
 #[cfg(feature = "tracing-browser")]
@@ -1,26 +1,66 @@
 use nill::{Nil, nil};
 use std::net::SocketAddr;
 use tokio::net::TcpListener;
-use tondi_listener_library::log::{info, init_tracing_subscriber_log};
+use tondi_listener_library::log::{info, warn};
 use tondi_listener_server::{
-    ctx::Context,
+    ctx::{
+        Context,
+        cli::{Args, Command, USAGE},
+        reload,
+    },
     error::Result,
     routes,
+    shared::{runtime, shutdown},
 };
 
-#[tokio::main]
-async fn main() -> Result<Nil> {
-    init_tracing_subscriber_log();
+fn main() -> Result<Nil> {
+    let args = match Args::parse(std::env::args().skip(1))? {
+        Command::Help => {
+            println!("{USAGE}");
+            return Ok(nil);
+        },
+        Command::Run(args) => args,
+    };
 
-    let ctx = Context::from_env()?;
+    // Logging is configured by the runtime settings, so it starts after they load
+    let config = args.load_config()?;
+    runtime::init_log_with_level(&config.runtime, &config.log_level);
+    config.log_summary();
+
+    let ctx = Context::new(config)?;
+    if args.migrate {
+        ctx.migrate()?;
+    }
+    let runtime = runtime::build(&ctx.config.runtime)?;
+    runtime.block_on(serve(ctx, args))
+}
+
+async fn serve(ctx: Context, args: Args) -> Result<Nil> {
+    if !args.skip_self_check {
+        ctx.self_check().await?;
+    }
     let socket: SocketAddr = ctx.config.host_url.parse()?;
     info!("Server running: http://{socket}");
+    if ctx.config.tls.is_some() {
+        warn!("TLS is configured but the REST router serves plain HTTP; terminate TLS in front of it");
+    }
+
+    // The config file, environment and flags are read again on SIGHUP
+    reload::reload_on_hangup(ctx.config_updates.clone(), move || args.load_config());
 
-    let router = routes::router(ctx).await?;
+    let ctx_shutdown = ctx.shutdown.clone();
+    // Plain TCP, so no `Strict-Transport-Security`
+    let router = routes::router(ctx, false).await?;
 
     let listen = TcpListener::bind(socket).await?;
-    axum::serve(listen, router.into_make_service()).await?;
-    // .with_graceful_shutdown();
+    axum::serve(listen, router.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(async move {
+            shutdown::signal().await;
+            info!("Shutting down, draining WebSocket connections");
+            ctx_shutdown.run().await;
+        })
+        .await?;
 
+    info!("Server stopped");
     Ok(nil)
 }
@@ -3,22 +3,49 @@ use tondi_listener_http2_client::{
     tonic::{codec::CompressionEncoding::Gzip, transport::Server},
     web::GrpcWebLayer,
 };
-use tondi_listener_http2_server::pingpong;
-use tondi_listener_library::log::{info, init_tracing_subscriber_log};
+use tondi_listener_http2_server::{explorer, pingpong};
+use tondi_listener_library::log::info;
 use tondi_listener_server::{
-    ctx::Context,
+    ctx::{
+        Context,
+        cli::{Args, Command, USAGE},
+        config::{ConfigError, TlsConfig},
+    },
     error::Result,
     middleware,
+    shared::runtime,
 };
 
-#[tokio::main]
-async fn main() -> Result<Nil> {
-    // Initialize logging
-    init_tracing_subscriber_log();
-    
-    // Create configuration and context from environment variables
-    let ctx = Context::from_env()?;
+fn main() -> Result<Nil> {
+    let args = match Args::parse(std::env::args().skip(1))? {
+        Command::Help => {
+            println!("{USAGE}");
+            return Ok(nil);
+        },
+        Command::Run(args) => args,
+    };
+
+    // Create configuration from the config file, environment variables and flags
+    let config = args.load_config()?;
+
+    // Logging is configured by the runtime settings, so it starts after they load
+    runtime::init_log_with_level(&config.runtime, &config.log_level);
+    config.log_summary();
+
+    let ctx = Context::new(config)?;
+    if args.migrate {
+        ctx.migrate()?;
+    }
     
+    // Build the runtime from config instead of #[tokio::main] defaults
+    let runtime = runtime::build(&ctx.config.runtime)?;
+    runtime.block_on(serve(ctx, args))
+}
+
+async fn serve(ctx: Context, args: Args) -> Result<Nil> {
+    if !args.skip_self_check {
+        ctx.self_check().await?;
+    }
     info!("Server starting...");
     info!("Environment: {}", ctx.config.environment);
     info!("Log level: {}", ctx.log_level());
@@ -26,7 +53,8 @@ async fn main() -> Result<Nil> {
     
     let socket = ctx.config.host_url.parse()?;
 
-    let service = pingpong::service().accept_compressed(Gzip).send_compressed(Gzip);
+    let pingpong = pingpong::service().accept_compressed(Gzip).send_compressed(Gzip);
+    let explorer = explorer::service(ctx.store.clone()).accept_compressed(Gzip).send_compressed(Gzip);
 
     // Select middleware based on environment
     let cors_layer = if ctx.is_production() {
@@ -35,14 +63,39 @@ async fn main() -> Result<Nil> {
         middleware::cors::cors(ctx.cors_config())
     };
 
-    let server = Server::builder()
+    let mut builder = Server::builder();
+    if let Some(tls) = &ctx.config.tls {
+        builder = with_tls(builder, tls)?;
+        info!("TLS enabled with certificate {}", tls.cert_path);
+    }
+
+    let server = builder
         .accept_http1(true)
         .layer(cors_layer)
-        .layer(GrpcWebLayer::new());
+        .layer(GrpcWebLayer::new())
+        .add_service(pingpong)
+        .add_service(explorer);
 
-    // Use the service directly
-    server.serve(socket, service).await?;
+    server.serve(socket).await?;
 
     info!("Server stopped");
     Ok(nil)
 }
+
+#[cfg(feature = "tls")]
+fn with_tls(builder: Server, tls: &TlsConfig) -> Result<Server> {
+    use tondi_listener_http2_client::tonic::transport::{Identity, ServerTlsConfig};
+
+    let (cert, key) = tls.read_pem()?;
+    let tls_config = ServerTlsConfig::new().identity(Identity::from_pem(cert, key));
+    let builder = builder
+        .tls_config(tls_config)
+        .map_err(|e| ConfigError::InvalidTlsConfig(format!("Invalid certificate or private key: {e}")))?;
+    Ok(builder)
+}
+
+#[cfg(not(feature = "tls"))]
+fn with_tls(_builder: Server, _tls: &TlsConfig) -> Result<Server> {
+    let message = "TLS is configured but the server was built without the `tls` feature";
+    Err(ConfigError::InvalidTlsConfig(message.to_string()).into())
+}
@@ -0,0 +1,150 @@
+use std::env;
+
+use crate::ctx::config::{Config, ConfigError, Overrides};
+
+pub const USAGE: &str = "\
+Usage: server [OPTIONS]
+
+Options:
+  --config <PATH>      TOML config file, overridden by environment variables
+  --host <ADDR>        Listening address, e.g. 0.0.0.0:3000
+  --log-level <LEVEL>  trace, debug, info, warn or error
+  --skip-self-check    Serve without checking the database and the node first
+  --migrate            Apply the embedded database migrations before serving
+  --help               Print this message and exit
+
+Precedence: command line > TONDI_LISTENER_* environment variables > config file > defaults";
+
+/// What the command line asks the binary to do
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    Help,
+    Run(Args),
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Args {
+    pub config: Option<String>,
+    pub overrides: Overrides,
+    /// Start without [`Context::self_check`](crate::ctx::Context::self_check)
+    pub skip_self_check: bool,
+    /// Apply the database migrations first, see [`Context::migrate`](crate::ctx::Context::migrate)
+    pub migrate: bool,
+}
+
+impl Args {
+    /// Parse the arguments after the program name; `--flag value` and `--flag=value` are both accepted
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, ConfigError> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            let slot = match flag.as_str() {
+                "--help" | "-h" => return Ok(Command::Help),
+                "--skip-self-check" if inline.is_none() => {
+                    parsed.skip_self_check = true;
+                    continue;
+                },
+                "--migrate" if inline.is_none() => {
+                    parsed.migrate = true;
+                    continue;
+                },
+                "--config" => &mut parsed.config,
+                "--host" => &mut parsed.overrides.host_url,
+                "--log-level" => &mut parsed.overrides.log_level,
+                _ => return Err(ConfigError::InvalidArgument(format!("Unknown argument `{flag}`"))),
+            };
+            let value = inline
+                .or_else(|| args.next())
+                .ok_or_else(|| ConfigError::InvalidArgument(format!("`{flag}` requires a value")))?;
+            *slot = Some(value);
+        }
+        Ok(Command::Run(parsed))
+    }
+
+    /// Config file (or defaults), then the environment, then the flags
+    pub fn load_config(&self) -> Result<Config, ConfigError> {
+        let base = match &self.config {
+            Some(path) => Config::from_file(path)?,
+            None => Config::default(),
+        };
+        Config::load(base, |key| env::var(key), &self.overrides)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, ConfigError> {
+        Args::parse(args.iter().map(ToString::to_string))
+    }
+
+    #[test]
+    fn test_flags_are_parsed() {
+        let command = parse(&["--config", "listener.json", "--host=0.0.0.0:4000", "--log-level", "debug"]).unwrap();
+        let Command::Run(args) = command else { panic!("expected run") };
+        assert_eq!(args.config.as_deref(), Some("listener.json"));
+        assert_eq!(args.overrides.host_url.as_deref(), Some("0.0.0.0:4000"));
+        assert_eq!(args.overrides.log_level.as_deref(), Some("debug"));
+        assert!(!args.skip_self_check);
+        assert!(!args.migrate);
+
+        let Command::Run(args) = parse(&["--skip-self-check", "--migrate", "--host", "0.0.0.0:4000"]).unwrap() else {
+            panic!("expected run")
+        };
+        assert!(args.skip_self_check);
+        assert!(args.migrate);
+        assert_eq!(args.overrides.host_url.as_deref(), Some("0.0.0.0:4000"));
+        assert!(matches!(parse(&["--skip-self-check=yes"]), Err(ConfigError::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn test_help_and_bad_arguments() {
+        assert_eq!(parse(&["--host", "a:1", "--help"]).unwrap(), Command::Help);
+        assert!(matches!(parse(&["--port", "1"]), Err(ConfigError::InvalidArgument(_))));
+        assert!(matches!(parse(&["--config"]), Err(ConfigError::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn test_cli_beats_env_beats_file() {
+        let file = Config::from_toml(
+            r#"
+            [server]
+            host_url = "10.0.0.1:3000"
+            log_level = "warn"
+            environment = "staging"
+            grpc_url = "grpc://file:16610"
+            "#,
+        )
+        .unwrap();
+        let env = |key: &str| match key {
+            "TONDI_LISTENER_LOG_LEVEL" => Ok("debug".to_string()),
+            "TONDI_LISTENER_ENVIRONMENT" => Ok("production".to_string()),
+            _ => Err(env::VarError::NotPresent),
+        };
+        let overrides = Overrides { host_url: None, log_level: Some("trace".to_string()) };
+
+        let config = Config::load(file, env, &overrides).unwrap();
+        // File only
+        assert_eq!(config.host_url, "10.0.0.1:3000");
+        assert_eq!(config.grpc_url, "grpc://file:16610");
+        // Env over file
+        assert_eq!(config.environment, "production");
+        // CLI over env and file
+        assert_eq!(config.log_level, "trace");
+        // Defaults where nothing is set
+        assert_eq!(config.database_url, Config::default().database_url);
+    }
+
+    #[test]
+    fn test_example_config_file_loads() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../../config.example.toml");
+        let config = Config::from_file(path).unwrap();
+        assert_eq!(config.host_url, "127.0.0.1:3000");
+        assert_eq!(config.security.rate_limit, 100);
+    }
+}
@@ -68,6 +68,33 @@ pub struct EventConfig {
     /// 是否启用事件去重
     #[serde(default = "default_deduplication")]
     pub enable_deduplication: bool,
+
+    /// Overflow of events waiting for dispatch to disk
+    #[serde(default)]
+    pub spill: SpillConfig,
+}
+
+/// Events waiting for WebSocket dispatch past `high_water` are appended to a file in `dir` and read back as
+/// dispatch catches up; past `max_bytes` on disk they are dropped
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpillConfig {
+    /// Directory of the spill file; unset keeps every waiting event in memory
+    #[serde(default)]
+    pub dir: Option<String>,
+
+    /// Events held in memory before spilling
+    #[serde(default = "default_spill_high_water")]
+    pub high_water: usize,
+
+    /// Size the spill file may grow to
+    #[serde(default = "default_spill_max_bytes")]
+    pub max_bytes: u64,
+}
+
+impl Default for SpillConfig {
+    fn default() -> Self {
+        Self { dir: None, high_water: default_spill_high_water(), max_bytes: default_spill_max_bytes() }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -109,6 +136,14 @@ fn default_deduplication() -> bool {
     true
 }
 
+fn default_spill_high_water() -> usize {
+    10_000
+}
+
+fn default_spill_max_bytes() -> u64 {
+    1 << 30
+}
+
 impl EventConfig {
     /// Parse configured event type strings into EventType enums
     pub fn parse_event_types(&self) -> Result<HashSet<EventType>, String> {
@@ -150,6 +185,11 @@ impl EventConfig {
                     .map_err(|e| format!("Invalid priority event type '{}': {}", event_str, e))?;
             }
         }
+
+        // Check spill configuration
+        if self.spill.dir.is_some() && self.spill.high_water == 0 {
+            return Err("Spill high-water mark must be greater than 0".to_string());
+        }
         
         Ok(())
     }
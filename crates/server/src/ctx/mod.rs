@@ -1,18 +1,43 @@
+pub mod cli;
 pub mod config;
 pub mod event_config;
 pub mod pg_database;
+pub mod reload;
+pub mod self_check;
+pub mod store;
 
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use tokio::sync::watch;
 
 use crate::{
-    ctx::{config::Config, pg_database::PgDatabase},
+    ctx::{
+        config::Config,
+        pg_database::PgDatabase,
+        store::{PgStore, Store},
+    },
     error::{Error, Result},
+    shared::{metrics::METRICS, shutdown::Shutdown},
 };
+use tondi_listener_library::log::info;
 
 #[derive(Debug, Clone)]
 pub struct Context {
     pub config: Arc<Config>,
+    /// Pool of the HTTP handlers
     pub pg_database: Arc<PgDatabase>,
+    /// Smaller pool of the event pipeline, so its bursts never take the handlers' connections
+    pub events_database: Arc<PgDatabase>,
+    /// Read queries of the routes, over `pg_database` unless replaced
+    pub store: Arc<dyn Store>,
+    pub started_at: Instant,
+    /// Started by the binaries on a termination signal
+    pub shutdown: Shutdown,
+    /// Configuration with the settings reloaded while serving, see [`reload`]
+    pub config_updates: watch::Sender<Arc<Config>>,
 }
 
 impl Context {
@@ -27,10 +52,51 @@ impl Context {
     /// Create new Context with specified configuration
     pub fn new(config: Config) -> Result<Self> {
         let pg_database = PgDatabase::new(&config.database_url)?;
-        Ok(Self { 
-            config: Arc::new(config), 
-            pg_database: Arc::new(pg_database) 
-        })
+        let events_database =
+            PgDatabase::with_max_connections(&config.database_url, config.events_db_max_connections)?;
+        Ok(Self::with_databases(config, pg_database, events_database))
+    }
+    
+    fn with_databases(config: Config, pg_database: PgDatabase, events_database: PgDatabase) -> Self {
+        METRICS.register_db_pool("http", (*pg_database).clone());
+        METRICS.register_db_pool("events", (*events_database).clone());
+        let config = Arc::new(config);
+        Self {
+            config_updates: watch::Sender::new(config.clone()),
+            config,
+            store: Arc::new(PgStore::new((*pg_database).clone())),
+            pg_database: Arc::new(pg_database),
+            events_database: Arc::new(events_database),
+            started_at: Instant::now(),
+            shutdown: Shutdown::default(),
+        }
+    }
+    
+    /// Apply the embedded database migrations not yet run. Only on request (`--migrate`): the indexer owns
+    /// the schema, so serving never changes it unasked.
+    pub fn migrate(&self) -> Result<()> {
+        let applied = self.pg_database.run_migrations()?;
+        if applied.is_empty() {
+            info!("Database migrations are up to date");
+        } else {
+            info!("Applied database migrations: {}", applied.join(", "));
+        }
+        Ok(())
+    }
+
+    /// Follow the settings reloaded while serving, starting from the current ones
+    pub fn subscribe_config(&self) -> watch::Receiver<Arc<Config>> {
+        self.config_updates.subscribe()
+    }
+    
+    /// Get the instant the process started serving
+    pub fn started_at(&self) -> Instant {
+        self.started_at
+    }
+    
+    /// Get how long the process has been up
+    pub fn uptime(&self) -> Duration {
+        self.started_at.elapsed()
     }
     
     /// Check if production environment
@@ -58,3 +124,38 @@ impl Context {
         &self.config.cors
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> Context {
+        let config = Config::default();
+        let pg_database = PgDatabase::lazy(&config.database_url);
+        let events_database =
+            PgDatabase::lazy_with_max_connections(&config.database_url, config.events_db_max_connections);
+        Context::with_databases(config, pg_database, events_database)
+    }
+
+    #[test]
+    fn test_uptime_is_monotonic() {
+        let ctx = context();
+        let first = ctx.uptime();
+        std::thread::sleep(Duration::from_millis(5));
+        let second = ctx.uptime();
+        assert!(second > first);
+        assert!(ctx.started_at() <= Instant::now());
+    }
+
+    #[test]
+    fn test_event_pipeline_has_its_own_pool() {
+        let ctx = context();
+        assert!(!Arc::ptr_eq(&ctx.pg_database, &ctx.events_database));
+        assert_eq!(ctx.events_database.max_size(), ctx.config.events_db_max_connections);
+        assert_ne!(ctx.pg_database.max_size(), ctx.events_database.max_size());
+
+        let rendered = METRICS.render();
+        assert!(rendered.contains("tondi_listener_db_pool_max_connections{pool=\"http\"} 10\n"));
+        assert!(rendered.contains("tondi_listener_db_pool_max_connections{pool=\"events\"} 4\n"));
+    }
+}
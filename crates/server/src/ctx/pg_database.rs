@@ -4,7 +4,10 @@ use tondi_listener_db::diesel::{
     r2d2::{ConnectionManager, Pool, PooledConnection},
 };
 
-use crate::{error::Result, ctx::Context};
+use crate::{
+    ctx::Context,
+    error::{Error, Result},
+};
 
 pub type PgPool = Pool<ConnectionManager<PgConnection>>;
 
@@ -20,6 +23,41 @@ impl PgDatabase {
         Ok(Self { pool })
     }
     
+    /// Pool of at most `max_connections`, e.g. one reserved for background work
+    pub fn with_max_connections(url: &str, max_connections: u32) -> Result<Self> {
+        let manager = ConnectionManager::new(url);
+        let pool = Pool::builder().max_size(max_connections).build(manager)?;
+        Ok(Self { pool })
+    }
+    
+    /// Pool that connects on first use, for tests that never touch the database
+    #[cfg(test)]
+    pub(crate) fn lazy(url: &str) -> Self {
+        let manager = ConnectionManager::new(url);
+        Self { pool: Pool::builder().build_unchecked(manager) }
+    }
+    
+    /// [`PgDatabase::lazy`] of at most `max_connections`
+    #[cfg(test)]
+    pub(crate) fn lazy_with_max_connections(url: &str, max_connections: u32) -> Self {
+        let manager = ConnectionManager::new(url);
+        Self { pool: Pool::builder().max_size(max_connections).build_unchecked(manager) }
+    }
+    
+    /// Pool whose every checkout fails quickly, nothing listens on its port
+    #[cfg(test)]
+    pub(crate) fn unreachable() -> Self {
+        let manager = ConnectionManager::new("postgres://127.0.0.1:1/none");
+        let pool = Pool::builder().connection_timeout(std::time::Duration::from_millis(100)).build_unchecked(manager);
+        Self { pool }
+    }
+    
+    /// Apply the embedded migrations not yet run, returning their versions
+    pub fn run_migrations(&self) -> Result<Vec<String>> {
+        let mut conn = self.get_connection()?;
+        Ok(tondi_listener_db::migrations::run_pending(&mut conn)?)
+    }
+    
     pub fn get_connection(&self) -> Result<PooledConnection<ConnectionManager<PgConnection>>> {
         Ok(self.pool.get()?)
     }
@@ -42,3 +80,32 @@ impl FromRef<Context> for &PgDatabase {
 }
 
 pub type PgDb<'a> = State<&'a PgDatabase>;
+
+/// Run `query` with a connection from `pool` on the blocking pool, so neither waiting
+/// for a connection nor the Diesel round trips hold up an async worker
+pub async fn run_blocking<T, F>(pool: &PgPool, query: F) -> Result<T>
+where
+    F: FnOnce(&mut PgConnection) -> Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    let pool = pool.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = pool.get()?;
+        query(&mut conn)
+    })
+    .await
+    .map_err(|e| Error::InternalServerError(format!("Database query panicked: {e}")))?
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_unavailable_database_is_503() {
+        let err = run_blocking(&PgDatabase::unreachable(), |_| Ok(())).await.unwrap_err();
+        assert_eq!(err.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
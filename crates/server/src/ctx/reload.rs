@@ -0,0 +1,200 @@
+use std::sync::Arc;
+
+use tokio::sync::watch;
+use tondi_listener_library::log::{info, warn};
+
+use crate::ctx::config::{Config, ConfigError};
+
+/// Settings applied while serving; changing any other one takes a restart
+pub const LIVE_SETTINGS: &[&str] = &[
+    "log_level",
+    "security.rate_limit",
+    "security.submit_rate_limit",
+    "security.route_rate_limits",
+    "export.rate_limit",
+    "cors.allowed_origins",
+    "cache.tip_max_age_secs",
+    "cache.history_max_age_secs",
+    "logging.notification_sample_rate",
+];
+
+/// Outcome of re-reading the configuration
+#[derive(Debug)]
+pub struct Reload {
+    /// The running settings with the live ones replaced
+    pub config: Config,
+    /// Live settings that changed
+    pub applied: Vec<String>,
+    /// Changed settings that only take effect after a restart
+    pub restart_required: Vec<String>,
+}
+
+/// Whether the setting at `path` is one of the [`LIVE_SETTINGS`] or an entry of one
+fn is_live(path: &str) -> bool {
+    LIVE_SETTINGS
+        .iter()
+        .any(|live| path.strip_prefix(live).is_some_and(|rest| rest.is_empty() || rest.starts_with('.')))
+}
+
+/// Take the [`LIVE_SETTINGS`] of `reloaded` over `current`, keeping everything else as it runs
+pub fn reload(current: &Config, reloaded: Config) -> Reload {
+    let (applied, restart_required): (Vec<_>, Vec<_>) =
+        current.changed_settings(&reloaded).into_iter().partition(|path| is_live(path));
+    let mut config = current.clone();
+    config.log_level = reloaded.log_level;
+    config.security.rate_limit = reloaded.security.rate_limit;
+    config.security.submit_rate_limit = reloaded.security.submit_rate_limit;
+    config.security.route_rate_limits = reloaded.security.route_rate_limits;
+    config.export.rate_limit = reloaded.export.rate_limit;
+    config.cors.allowed_origins = reloaded.cors.allowed_origins;
+    config.cache = reloaded.cache;
+    config.logging.notification_sample_rate = reloaded.logging.notification_sample_rate;
+    Reload { config, applied, restart_required }
+}
+
+/// Push the live settings of `reloaded` to every subscriber of `updates`; returns whether any changed.
+/// Settings that would not pass [`Config::validate`] once merged are rejected and the running ones kept.
+pub fn publish(updates: &watch::Sender<Arc<Config>>, reloaded: Config) -> bool {
+    let current = updates.borrow().clone();
+    let Reload { config, applied, restart_required } = reload(&current, reloaded);
+    if let Err(e) = config.validate() {
+        warn!("Reloaded settings rejected, keeping the running ones: {e}");
+        return false;
+    }
+    for path in &restart_required {
+        warn!("`{path}` changed, restart to apply it");
+    }
+    if applied.is_empty() {
+        info!("No live setting changed");
+        return false;
+    }
+    info!("Applying reloaded settings: {}", applied.join(", "));
+    updates.send_replace(Arc::new(config));
+    true
+}
+
+/// Re-read the configuration with `load` on every SIGHUP and [`publish`] it.
+/// An invalid configuration is logged and the running one kept.
+pub fn reload_on_hangup(
+    updates: watch::Sender<Arc<Config>>,
+    load: impl Fn() -> Result<Config, ConfigError> + Send + 'static,
+) {
+    #[cfg(unix)]
+    tokio::spawn(async move {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let mut hangups = match signal(SignalKind::hangup()) {
+            Ok(hangups) => hangups,
+            Err(e) => {
+                warn!("Cannot listen for SIGHUP, configuration reloading is disabled: {e}");
+                return;
+            },
+        };
+        while hangups.recv().await.is_some() {
+            info!("SIGHUP received, reloading configuration");
+            match load() {
+                Ok(reloaded) => {
+                    publish(&updates, reloaded);
+                },
+                Err(e) => warn!("Configuration not reloaded, keeping the running one: {e}"),
+            }
+        }
+    });
+    #[cfg(not(unix))]
+    drop((updates, load));
+}
+
+/// Call `apply(previous, current)` for every configuration pushed to `updates`
+pub fn on_change(
+    mut updates: watch::Receiver<Arc<Config>>,
+    mut apply: impl FnMut(&Config, &Config) + Send + 'static,
+) {
+    tokio::spawn(async move {
+        let mut previous = updates.borrow_and_update().clone();
+        while updates.changed().await.is_ok() {
+            let current = updates.borrow_and_update().clone();
+            apply(&previous, &current);
+            previous = current;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::IpAddr, time::Duration};
+
+    use super::*;
+    use crate::middleware::rate_limit::RateLimiter;
+
+    fn reloaded() -> Config {
+        let mut config = Config::default();
+        config.security.submit_rate_limit = 3;
+        config.host_url = "0.0.0.0:9999".to_string();
+        config
+    }
+
+    #[test]
+    fn test_only_live_settings_are_taken() {
+        let current = Config::default();
+        let Reload { config, applied, restart_required } = reload(&current, reloaded());
+        assert_eq!(applied, ["security.submit_rate_limit"]);
+        assert_eq!(restart_required, ["host_url"]);
+        assert_eq!(config.security.submit_rate_limit, 3);
+        assert_eq!(config.host_url, current.host_url);
+    }
+
+    #[test]
+    fn test_route_rate_limits_are_live() {
+        let mut reloaded = Config::default();
+        reloaded.security.route_rate_limits.insert("grpc".to_string(), 20);
+        let Reload { config, applied, restart_required } = reload(&Config::default(), reloaded);
+        assert_eq!(applied, ["security.route_rate_limits.grpc"]);
+        assert!(restart_required.is_empty());
+        assert_eq!(config.security.route_rate_limit("grpc"), 20);
+    }
+
+    #[tokio::test]
+    async fn test_pushed_config_reaches_observers() {
+        let (updates, _) = watch::channel(Arc::new(Config::default()));
+        let limiter = RateLimiter::<IpAddr>::per_minute(Config::default().security.submit_rate_limit);
+        on_change(updates.subscribe(), {
+            let limiter = limiter.clone();
+            move |previous, current| {
+                if previous.security.submit_rate_limit != current.security.submit_rate_limit {
+                    limiter.set_limit(current.security.submit_rate_limit, Duration::from_secs(60));
+                }
+            }
+        });
+        tokio::task::yield_now().await;
+
+        assert!(publish(&updates, reloaded()));
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while limiter.limit().0 != 3 {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("observer was not updated");
+
+        // Nothing live changed, so nothing is pushed
+        assert!(!publish(&updates, reloaded()));
+        assert_eq!(updates.borrow().host_url, Config::default().host_url);
+    }
+
+    #[test]
+    fn test_invalid_merged_settings_are_rejected() {
+        let mut running = Config::default();
+        running.cors.allow_credentials = true;
+        running.cors.allowed_origins = vec!["https://a.example".to_string()];
+        running.cors.allowed_methods = vec!["GET".to_string()];
+        running.cors.allowed_headers = vec!["content-type".to_string()];
+        assert!(running.validate().is_ok());
+        let (updates, _) = watch::channel(Arc::new(running.clone()));
+
+        // Emptying the origins would open credentialed requests to every site
+        let mut reloaded = running;
+        reloaded.cors.allowed_origins.clear();
+        assert!(!publish(&updates, reloaded));
+        assert_eq!(updates.borrow().cors.allowed_origins, ["https://a.example"]);
+    }
+}
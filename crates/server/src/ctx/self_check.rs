@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+use diesel::{
+    dsl::sql,
+    prelude::*,
+    sql_types::{Bool, Text},
+};
+use tondi_listener_library::log::{error, info};
+
+use crate::{
+    ctx::{Context, pg_database::run_blocking},
+    error::{Error, Result},
+    extensions::client_pool::Client,
+    shared::pool::HealthCheck,
+};
+
+/// Tables the routes read; the indexer creates them
+pub const REQUIRED_TABLES: &[&str] =
+    &["blocks", "blocks_transactions", "transactions", "transactions_inputs", "transactions_outputs"];
+
+/// How long the node has to accept the connection and answer the ping
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Outcome of one startup check
+#[derive(Debug)]
+pub struct Check {
+    pub name: &'static str,
+    /// Why the check failed
+    pub outcome: Result<(), String>,
+}
+
+/// Every check of [`Context::self_check`], in the order they ran
+#[derive(Debug)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.outcome.is_ok())
+    }
+
+    /// One line per check, failures at `error`
+    pub fn log(&self) {
+        for Check { name, outcome } in &self.checks {
+            match outcome {
+                Ok(()) => info!("Self-check {name}: ok"),
+                Err(reason) => error!("Self-check {name}: FAILED, {reason}"),
+            }
+        }
+    }
+}
+
+impl Context {
+    /// Check that the database answers, that the tables the routes read exist and that the node answers a
+    /// ping, logging the report. Fails naming the failed checks, so a misconfigured deployment stops at
+    /// startup instead of answering 503 to every request.
+    pub async fn self_check(&self) -> Result<Report> {
+        let database = self.check_database().await;
+        let tables = if database.is_ok() {
+            self.check_tables().await
+        } else {
+            Err("skipped, the database is unreachable".to_string())
+        };
+        let upstream = self.check_upstream().await;
+        let report = Report {
+            checks: vec![
+                Check { name: "database", outcome: database },
+                Check { name: "tables", outcome: tables },
+                Check { name: "upstream", outcome: upstream },
+            ],
+        };
+        report.log();
+        if report.passed() {
+            return Ok(report);
+        }
+        let failed: Vec<_> = report
+            .checks
+            .iter()
+            .filter_map(|Check { name, outcome }| outcome.as_ref().err().map(|reason| format!("{name} ({reason})")))
+            .collect();
+        Err(Error::ServiceUnavailable(format!("Startup self-check failed: {}", failed.join(", "))))
+    }
+
+    async fn check_database(&self) -> Result<(), String> {
+        run_blocking(&self.pg_database, |conn| {
+            diesel::sql_query("SELECT 1").execute(conn)?;
+            Ok(())
+        })
+        .await
+        .map_err(|e| e.to_string())
+    }
+
+    async fn check_tables(&self) -> Result<(), String> {
+        let missing = run_blocking(&self.pg_database, |conn| {
+            let mut missing = Vec::new();
+            for &table in REQUIRED_TABLES {
+                let exists = sql::<Bool>("to_regclass(").bind::<Text, _>(table).sql(") IS NOT NULL");
+                if !diesel::select(exists).get_result::<bool>(conn)? {
+                    missing.push(table);
+                }
+            }
+            Ok(missing)
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+        if missing.is_empty() {
+            return Ok(());
+        }
+        Err(format!("missing {}, has the indexer run against this database?", missing.join(", ")))
+    }
+
+    async fn check_upstream(&self) -> Result<(), String> {
+        let (url, protocol) = self.config.upstream_url().map_err(|e| e.to_string())?;
+        let ping = async {
+            let client = Client::connect(url).await.map_err(|e| e.to_string())?;
+            if client.probe().await { Ok(()) } else { Err(format!("the {protocol} node did not answer the ping")) }
+        };
+        tokio::time::timeout(UPSTREAM_TIMEOUT, ping)
+            .await
+            .unwrap_or_else(|_| Err(format!("no answer from the {protocol} node in {}s", UPSTREAM_TIMEOUT.as_secs())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+    use crate::ctx::{config::Config, pg_database::PgDatabase};
+
+    #[tokio::test]
+    async fn test_unreachable_database_fails_the_check() {
+        let mut config = Config::default();
+        config.grpc_url = "grpc://127.0.0.1:1".to_string();
+        let ctx = Context::with_databases(config, PgDatabase::unreachable(), PgDatabase::unreachable());
+
+        let err = ctx.self_check().await.unwrap_err();
+        assert_eq!(err.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+        let message = err.to_string();
+        assert!(message.contains("database ("), "{message}");
+        assert!(message.contains("tables (skipped"), "{message}");
+    }
+}
@@ -0,0 +1,30 @@
+use std::sync::Arc;
+
+use axum::extract::{FromRef, State};
+pub use tondi_listener_db::store::{MemoryStore, PgStore, Store};
+
+use crate::{
+    ctx::Context,
+    error::{Error, Result},
+};
+
+impl FromRef<Context> for Arc<dyn Store> {
+    fn from_ref(ctx: &Context) -> Self {
+        ctx.store.clone()
+    }
+}
+
+/// The read queries of the routes, whichever backend serves them
+pub type Db = State<Arc<dyn Store>>;
+
+/// Run `query` against `store` on the blocking pool, as [`run_blocking`](super::pg_database::run_blocking) does
+/// for Diesel queries
+pub async fn run_store<T, F>(store: Arc<dyn Store>, query: F) -> Result<T>
+where
+    F: FnOnce(&dyn Store) -> tondi_listener_db::error::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(move || query(&*store).map_err(Error::from))
+        .await
+        .map_err(|e| Error::InternalServerError(format!("Database query panicked: {e}")))?
+}
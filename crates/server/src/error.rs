@@ -10,10 +10,11 @@ use tondi_listener_db::{
     error::Error as TondiListenerDbError,
 };
 use tondi_listener_http2_client::tonic::transport::Error as TonicTransportError;
+use tondi_rpc_core::RpcError;
 
 use crate::{
     ctx::config::ConfigError,
-    shared::pool::Error as ClientPoolError,
+    shared::{language::Language, pool::Error as ClientPoolError},
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -40,8 +41,9 @@ pub enum Error {
     #[error("Database connection error: {0}")]
     DieselConnectionError(#[from] DieselConnectionError),
 
+    /// Never `DieselError::NotFound`, which converts to [`Error::NotFound`]
     #[error("Database query error: {0}")]
-    DieselError(#[from] DieselError),
+    DieselError(DieselError),
 
     #[error("Database operation error: {0}")]
     TondiListenerDbError(#[from] TondiListenerDbError),
@@ -54,18 +56,42 @@ pub enum Error {
     #[error("Resource not found: {0}")]
     NotFound(String),
 
+    #[error("Authentication required: {0}")]
+    Unauthorized(String),
+
     #[error("Permission denied: {0}")]
     Forbidden(String),
 
     #[error("Invalid request parameters: {0}")]
     BadRequest(String),
 
+    #[error("Conflicts with existing state: {0}")]
+    Conflict(String),
+
+    #[error("Invalid JSON body: {0}")]
+    JsonParse(String),
+
+    #[error("Unsupported media type: {0}")]
+    UnsupportedMediaType(String),
+
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+
+    #[error("Transaction rejected: {0}")]
+    TransactionRejected(String),
+
+    #[error("Too many requests: {0}")]
+    TooManyRequests(String),
+
     #[error("Internal server error: {0}")]
     InternalServerError(String),
 
     #[error("Service temporarily unavailable: {0}")]
     ServiceUnavailable(String),
 
+    #[error("Upstream deadline exceeded: {0}")]
+    GatewayTimeout(String),
+
     // Generic error
     #[error("{0}")]
     Generic(String),
@@ -82,35 +108,94 @@ impl Error {
             Self::DieselR2d2PoolError(_) => StatusCode::SERVICE_UNAVAILABLE,
             Self::DieselConnectionError(_) => StatusCode::SERVICE_UNAVAILABLE,
             Self::DieselError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::TondiListenerDbError(TondiListenerDbError::Pool(_)) => StatusCode::SERVICE_UNAVAILABLE,
             Self::TondiListenerDbError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::ClientPoolError(_) => StatusCode::SERVICE_UNAVAILABLE,
             Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
             Self::Forbidden(_) => StatusCode::FORBIDDEN,
             Self::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::JsonParse(_) => StatusCode::BAD_REQUEST,
+            Self::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::TransactionRejected(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
             Self::InternalServerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::ServiceUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::GatewayTimeout(_) => StatusCode::GATEWAY_TIMEOUT,
             Self::Generic(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 
-    /// Get user-friendly error message
-    pub fn user_message(&self) -> String {
+    /// English and Chinese lead-in of the user message, indexed by [`Language::index`]
+    fn lead_in(&self) -> [&'static str; 2] {
         match self {
-            Self::Config(e) => format!("System configuration error: {}", e),
-            Self::StdIoError(_e) => "System internal error".to_string(),
-            Self::StdNetAddrParseError(e) => format!("Invalid network address: {}", e),
-            Self::TonicTransportError(e) => format!("Network connection error: {}", e),
-            Self::DieselR2d2PoolError(e) => format!("Database connection pool error: {}", e),
-            Self::DieselConnectionError(e) => format!("Database connection error: {}", e),
-            Self::DieselError(e) => format!("Database operation error: {}", e),
-            Self::TondiListenerDbError(e) => format!("Database error: {}", e),
-            Self::ClientPoolError(e) => format!("Client pool error: {}", e),
-            Self::NotFound(msg) => format!("Resource not found: {}", msg),
-            Self::Forbidden(msg) => format!("Access denied: {}", msg),
-            Self::BadRequest(msg) => format!("Invalid request: {}", msg),
-            Self::InternalServerError(msg) => format!("Internal server error: {}", msg),
-            Self::ServiceUnavailable(msg) => format!("Service temporarily unavailable: {}", msg),
-            Self::Generic(msg) => msg.clone(),
+            Self::Config(_) => ["System configuration error", "系统配置错误"],
+            Self::StdIoError(_) => ["System internal error", "系统内部错误"],
+            Self::StdNetAddrParseError(_) => ["Invalid network address", "无效的网络地址"],
+            Self::TonicTransportError(_) => ["Network connection error", "网络连接错误"],
+            Self::DieselR2d2PoolError(_) => ["Database connection pool error", "数据库连接池错误"],
+            Self::DieselConnectionError(_) => ["Database connection error", "数据库连接错误"],
+            Self::DieselError(_) => ["Database operation error", "数据库操作错误"],
+            Self::TondiListenerDbError(TondiListenerDbError::Pool(_)) => {
+                ["Database connection pool error", "数据库连接池错误"]
+            },
+            Self::TondiListenerDbError(_) => ["Database error", "数据库错误"],
+            Self::ClientPoolError(_) => ["Client pool error", "客户端连接池错误"],
+            Self::NotFound(_) => ["Resource not found", "资源未找到"],
+            Self::Unauthorized(_) => ["Authentication required", "需要身份验证"],
+            Self::Forbidden(_) => ["Access denied", "访问被拒绝"],
+            Self::BadRequest(_) => ["Invalid request", "无效的请求"],
+            Self::Conflict(_) => ["Already exists", "资源已存在"],
+            Self::JsonParse(_) => ["Invalid JSON body", "无效的 JSON 请求体"],
+            Self::UnsupportedMediaType(_) => ["Unsupported content type", "不支持的内容类型"],
+            Self::PayloadTooLarge(_) => ["Request body too large", "请求体过大"],
+            Self::TransactionRejected(_) => ["Transaction rejected by the node", "交易被节点拒绝"],
+            Self::TooManyRequests(_) => ["Too many requests", "请求过于频繁"],
+            Self::InternalServerError(_) => ["Internal server error", "服务器内部错误"],
+            Self::ServiceUnavailable(_) => ["Service temporarily unavailable", "服务暂时不可用"],
+            Self::GatewayTimeout(_) => ["Upstream node did not answer in time", "上游节点响应超时"],
+            Self::Generic(_) => ["", ""],
+        }
+    }
+
+    /// Detail following the lead-in; `None` where it would expose internals
+    fn detail(&self) -> Option<String> {
+        match self {
+            Self::StdIoError(_) => None,
+            Self::Config(e) => Some(e.to_string()),
+            Self::StdNetAddrParseError(e) => Some(e.to_string()),
+            Self::TonicTransportError(e) => Some(e.to_string()),
+            Self::DieselR2d2PoolError(e) => Some(e.to_string()),
+            Self::DieselConnectionError(e) => Some(e.to_string()),
+            Self::DieselError(e) => Some(e.to_string()),
+            Self::TondiListenerDbError(e) => Some(e.to_string()),
+            Self::ClientPoolError(e) => Some(e.to_string()),
+            Self::NotFound(msg)
+            | Self::Unauthorized(msg)
+            | Self::Forbidden(msg)
+            | Self::BadRequest(msg)
+            | Self::Conflict(msg)
+            | Self::JsonParse(msg)
+            | Self::UnsupportedMediaType(msg)
+            | Self::PayloadTooLarge(msg)
+            | Self::TransactionRejected(msg)
+            | Self::TooManyRequests(msg)
+            | Self::InternalServerError(msg)
+            | Self::ServiceUnavailable(msg)
+            | Self::GatewayTimeout(msg)
+            | Self::Generic(msg) => Some(msg.clone()),
+        }
+    }
+
+    /// Get user-friendly error message in `language`
+    pub fn user_message(&self, language: Language) -> String {
+        let lead_in = self.lead_in()[language.index()];
+        match self.detail() {
+            Some(detail) if lead_in.is_empty() => detail,
+            Some(detail) => format!("{lead_in}{}{detail}", language.separator()),
+            None => lead_in.to_string(),
         }
     }
 
@@ -124,18 +209,71 @@ impl Error {
             Self::DieselR2d2PoolError(_) => "DB_POOL_ERROR",
             Self::DieselConnectionError(_) => "DB_CONNECTION_ERROR",
             Self::DieselError(_) => "DB_QUERY_ERROR",
+            Self::TondiListenerDbError(TondiListenerDbError::Pool(_)) => "DB_POOL_ERROR",
             Self::TondiListenerDbError(_) => "DB_OPERATION_ERROR",
             Self::ClientPoolError(_) => "CLIENT_POOL_ERROR",
             Self::NotFound(_) => "NOT_FOUND",
+            Self::Unauthorized(_) => "UNAUTHORIZED",
             Self::Forbidden(_) => "FORBIDDEN",
             Self::BadRequest(_) => "BAD_REQUEST",
+            Self::Conflict(_) => "CONFLICT",
+            Self::JsonParse(_) => "JSON_PARSE_ERROR",
+            Self::UnsupportedMediaType(_) => "UNSUPPORTED_MEDIA_TYPE",
+            Self::PayloadTooLarge(_) => "PAYLOAD_TOO_LARGE",
+            Self::TransactionRejected(_) => "TRANSACTION_REJECTED",
+            Self::TooManyRequests(_) => "TOO_MANY_REQUESTS",
             Self::InternalServerError(_) => "INTERNAL_SERVER_ERROR",
             Self::ServiceUnavailable(_) => "SERVICE_UNAVAILABLE",
+            Self::GatewayTimeout(_) => "GATEWAY_TIMEOUT",
             Self::Generic(_) => "GENERIC_ERROR",
         }
     }
 }
 
+impl From<DieselError> for Error {
+    fn from(err: DieselError) -> Self {
+        match err {
+            DieselError::NotFound => Self::NotFound("Requested record does not exist".to_string()),
+            err => Self::DieselError(err),
+        }
+    }
+}
+
+/// Node errors by what they say about the request: unknown resources are `404`, duplicates `409`,
+/// arguments the node refused `400`, and anything else means the node could not serve it (`503`)
+impl From<RpcError> for Error {
+    fn from(err: RpcError) -> Self {
+        match err {
+            err @ (RpcError::BlockNotFound(_) | RpcError::TransactionNotFound(_) | RpcError::MergerNotFound(_)) => {
+                Self::NotFound(err.to_string())
+            },
+            RpcError::RejectedTransaction(_, reason) => Self::TransactionRejected(reason),
+            RpcError::NotImplemented => Self::ServiceUnavailable("The node does not serve this call".to_string()),
+            // Over gRPC most errors arrive as the node's message alone
+            err => {
+                let message = err.to_string();
+                let lower = message.to_ascii_lowercase();
+                let says = |markers: &[&str]| markers.iter().any(|marker| lower.contains(marker));
+                if says(&["not found", "unknown block", "unknown transaction"]) {
+                    Self::NotFound(message)
+                } else if says(&["already exists", "already in the mempool", "already known"]) {
+                    Self::Conflict(message)
+                } else if says(&["invalid", "malformed", "out of range", "cannot parse", "failed to parse"]) {
+                    Self::BadRequest(message)
+                } else {
+                    Self::ServiceUnavailable(message)
+                }
+            },
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::JsonParse(err.to_string())
+    }
+}
+
 impl From<String> for Error {
     fn from(err: String) -> Self {
         Self::Generic(err)
@@ -154,7 +292,7 @@ impl IntoResponse for Error {
         let error_response = serde_json::json!({
             "error": {
                 "code": self.error_code(),
-                "message": self.user_message(),
+                "message": self.user_message(Language::current()),
                 "status": status.as_u16()
             }
         });
@@ -164,3 +302,68 @@ impl IntoResponse for Error {
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diesel_not_found_is_404() {
+        let err = Error::from(DieselError::NotFound);
+        assert!(matches!(err, Error::NotFound(_)));
+        assert_eq!(err.status_code(), StatusCode::NOT_FOUND);
+
+        let err = Error::from(DieselError::QueryBuilderError("broken query".into()));
+        assert_eq!(err.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(err.error_code(), "DB_QUERY_ERROR");
+    }
+
+    #[test]
+    fn test_rpc_errors_map_to_granular_statuses() {
+        use tondi_rpc_core::RpcHash;
+
+        let cases = [
+            (RpcError::BlockNotFound(RpcHash::from_bytes([1; 32])), StatusCode::NOT_FOUND, "NOT_FOUND"),
+            (RpcError::General("Subnetwork 0a not found".to_string()), StatusCode::NOT_FOUND, "NOT_FOUND"),
+            (
+                RpcError::General("transaction 3f already exists in the mempool".to_string()),
+                StatusCode::CONFLICT,
+                "CONFLICT",
+            ),
+            (RpcError::General("invalid address prefix".to_string()), StatusCode::BAD_REQUEST, "BAD_REQUEST"),
+            (
+                RpcError::General("connection refused".to_string()),
+                StatusCode::SERVICE_UNAVAILABLE,
+                "SERVICE_UNAVAILABLE",
+            ),
+            (RpcError::NotImplemented, StatusCode::SERVICE_UNAVAILABLE, "SERVICE_UNAVAILABLE"),
+        ];
+        for (rpc_error, status, code) in cases {
+            let description = rpc_error.to_string();
+            let err = Error::from(rpc_error);
+            assert_eq!((err.status_code(), err.error_code()), (status, code), "{description}");
+        }
+    }
+
+    #[test]
+    fn test_chinese_accept_language_localizes_not_found() {
+        let err = Error::NotFound("block 42".to_string());
+        let language = Language::from_accept_language("zh-CN,zh;q=0.9,en;q=0.8");
+        assert_eq!(err.user_message(language), "资源未找到：block 42");
+        assert_eq!(err.user_message(Language::English), "Resource not found: block 42");
+        assert_eq!(err.error_code(), "NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_error_body_follows_the_request_language() {
+        use axum::body::to_bytes;
+
+        use crate::shared::language::LANGUAGE;
+
+        let response = LANGUAGE.scope(Language::Chinese, async { Error::NotFound("x".into()).into_response() }).await;
+        let body: serde_json::Value =
+            serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!(body["error"]["message"], "资源未找到：x");
+        assert_eq!(body["error"]["code"], "NOT_FOUND");
+    }
+}
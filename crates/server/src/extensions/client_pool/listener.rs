@@ -1,5 +1,14 @@
-use std::{collections::HashMap, ops::Deref, sync::Arc};
-use tokio::sync::mpsc::{Receiver, Sender};
+use std::{
+    collections::HashMap,
+    ops::Deref,
+    sync::{
+        Arc, Mutex,
+        atomic::Ordering,
+    },
+    time::Duration,
+};
+use tokio::{sync::broadcast::Sender, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
 use workflow_rpc::client::RpcClient;
 use workflow_rpc::client::notification::Notification as WrpcNotification;
 use workflow_rpc::client::rpc::RpcApi;
@@ -9,29 +18,100 @@ use workflow_rpc::encoding::Encoding;
 use workflow_rpc::client::JsonProtocol;
 use workflow_rpc::client::BorshProtocol;
 use log;
+use tondi_grpc_client::GrpcClient;
+use tondi_notify::{connection::ChannelType, events::EventType as TondiEventType};
+use tondi_rpc_core::{
+    Notification as RpcNotification, api::rpc::RpcApi as _, notify::connection::ChannelConnection,
+};
+use tondi_utils::channel::Channel;
 
 use crate::{
     ctx::event_config::EventType,
     error::{Error as AppError, Result},
-    shared::pool::{Error as PoolError, Notification, NotificationChannel},
+    extensions::client_pool::observer::{Observers, ReconnectState},
+    shared::{
+        log_sampler::NOTIFICATION_LOG,
+        metrics::METRICS,
+        pool::{Error as PoolError, Notification, NotificationChannel, NotificationPayload, NotificationReceiver},
+    },
 };
 
+/// Background task that stops when its client's `shutdown` token is cancelled
+/// and is aborted when its owner drops it, so loops cannot outlive the
+/// listener or handler they feed
+#[derive(Debug)]
+pub struct TaskGuard(JoinHandle<()>);
+
+impl TaskGuard {
+    pub fn spawn(shutdown: &CancellationToken, task: impl Future<Output = ()> + Send + 'static) -> Self {
+        let shutdown = shutdown.clone();
+        Self(tokio::spawn(async move {
+            tokio::select! {
+                _ = shutdown.cancelled() => {},
+                _ = task => {},
+            }
+        }))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.0.is_finished()
+    }
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
 #[derive(Debug)]
 pub struct Listener {
     pub id: u64,
     pub channel: NotificationChannel,
+    /// Loop feeding `channel`, stopped with the listener
+    task: Mutex<Option<TaskGuard>>,
 }
 
 impl Listener {
-    pub async fn subscribe(client: &GrpcClient, ev: EventType) -> Result<Listener, PoolError> {
-        let channel = NotificationChannel::default();
-        let conn = ChannelConnection::new("Listener", channel.sender(), ChannelType::Closable);
+    pub fn new(id: u64, channel: NotificationChannel) -> Self {
+        Self { id, channel, task: Mutex::default() }
+    }
+
+    /// Run `task` for as long as this listener lives, replacing any previous one
+    fn own(&self, task: TaskGuard) {
+        if let Ok(mut slot) = self.task.lock() {
+            *slot = Some(task);
+        }
+    }
+
+    pub async fn subscribe(
+        client: &GrpcClient,
+        ev: EventType,
+        shutdown: &CancellationToken,
+    ) -> Result<Listener, PoolError> {
+        let upstream = Channel::<RpcNotification>::default();
+        let conn = ChannelConnection::new("Listener", upstream.sender(), ChannelType::Closable);
         let id = client.register_new_listener(conn);
         
         // Convert our EventType to Tondi's EventType
         let tondi_event: TondiEventType = ev.into();
         client.start_notify(id, tondi_event.into()).await?;
-        Ok(Self { id, channel })
+
+        // Decode upstream notifications into the pool's typed form
+        let channel = NotificationChannel::default();
+        let sender = channel.sender();
+        let receiver = upstream.receiver();
+        let listener = Self::new(id, channel);
+        let counters = METRICS.event(ev);
+        listener.own(TaskGuard::spawn(shutdown, async move {
+            while let Ok(notification) = receiver.recv().await {
+                counters.received.fetch_add(1, Ordering::Relaxed);
+                NOTIFICATION_LOG.record(ev, &notification);
+                // No receivers yet is fine, the event is just not wanted
+                let _ = sender.send(notification.into());
+            }
+        }));
+        Ok(listener)
     }
     
     pub async fn subscribe_wrpc(
@@ -67,36 +147,29 @@ impl Listener {
         // 注意：workflow-rpc的具体订阅API可能需要根据实际使用情况调整
         // 这里我们创建一个基础的订阅框架，等待后续完善
         
-        Ok(Self { 
-            id,
-            channel 
-        })
+        Ok(Self::new(id, channel))
     }
     
-    /// 处理wRPC事件通知
-    pub async fn handle_wrpc_event(&self, event_data: serde_json::Value) -> Result<(), PoolError> {
-        // 将事件数据转换为我们的Notification格式
-        let notification = Notification {
-            event_type: "wrpc-event".to_string(),
-            data: event_data,
-            timestamp: chrono::Utc::now(),
-        };
-        
-        // 发送到通知通道
-        if let Err(e) = self.channel.sender().send(notification).await {
-            return Err(PoolError::from(format!("Failed to send wRPC event: {}", e)));
+    /// Forward a decoded event to every receiver of this listener's channel
+    pub async fn forward(&self, payload: NotificationPayload) -> Result<(), PoolError> {
+        if let Some(ev) = payload.event_type() {
+            METRICS.event(ev).received.fetch_add(1, Ordering::Relaxed);
         }
-        
+        self.channel.send(payload.into());
         Ok(())
     }
     
     /// 启动wRPC事件监听
-    pub async fn start_wrpc_listening(&self, client: &Arc<RpcClient<(), Id64>>) -> Result<(), PoolError> {
+    pub async fn start_wrpc_listening(
+        &self,
+        client: &Arc<RpcClient<(), Id64>>,
+        shutdown: &CancellationToken,
+    ) -> Result<(), PoolError> {
         // 启动wRPC事件监听逻辑
         let channel_sender = self.channel.sender().clone();
         let client_clone = client.clone();
         
-        tokio::spawn(async move {
+        self.own(TaskGuard::spawn(shutdown, async move {
             log::info!("Starting wRPC event listening loop");
             
             loop {
@@ -114,8 +187,6 @@ impl Listener {
                 // 尝试接收通知
                 match client_clone.receive_notification().await {
                     Ok(notification) => {
-                        log::debug!("Received wRPC notification: {:?}", notification);
-                        
                         // 处理通知
                         if let Err(e) = Self::process_wrpc_notification(notification, &channel_sender).await {
                             log::error!("Failed to process wRPC notification: {}", e);
@@ -133,7 +204,7 @@ impl Listener {
                 
                 tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
             }
-        });
+        }));
         
         Ok(())
     }
@@ -153,16 +224,14 @@ impl Listener {
             }
         };
         
-        // 创建通知
-        let notification = Notification {
-            event_type: "wrpc-event".to_string(),
-            data: event_data,
-            timestamp: chrono::Utc::now(),
-        };
-        
-        // 发送到通知通道
-        sender.send(notification).await
-            .map_err(|e| PoolError::from(format!("Failed to send wRPC event: {}", e)))?;
+        let payload = NotificationPayload::from_json(event_data);
+        if let Some(ev) = payload.event_type() {
+            METRICS.event(ev).received.fetch_add(1, Ordering::Relaxed);
+            NOTIFICATION_LOG.record(ev, &payload);
+        }
+        let notification = Notification::from(payload);
+        // Dropped when nobody is subscribed
+        let _ = sender.send(notification);
         
         Ok(())
     }
@@ -193,21 +262,62 @@ impl From<EventType> for TondiEventType {
     }
 }
 
+/// How often the gRPC supervisor checks the connection
+const GRPC_RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
 #[derive(Debug)]
 pub struct ListenerManager {
     listeners: HashMap<EventType, Listener>,
     wrpc_event_handler: Option<WrpcEventHandler>,
+    grpc_supervisor: Option<TaskGuard>,
+    observers: Observers,
+    /// Stops every loop spawned for this client, cancelled on drop
+    shutdown: CancellationToken,
 }
 
 impl ListenerManager {
     /// Create a new ListenerManager with all event types
     pub async fn new(client: &GrpcClient) -> Result<Self, PoolError> {
+        let shutdown = CancellationToken::new();
         let mut listeners = HashMap::new();
         for ev in EventType::get_all_event_types() {
-            let listener = Listener::subscribe(&client, ev).await?;
+            let listener = Listener::subscribe(&client, ev, &shutdown).await?;
             listeners.insert(ev, listener);
         }
-        Ok(Self { listeners, wrpc_event_handler: None })
+        let subscriptions = listeners.iter().map(|(ev, listener)| (listener.id, *ev)).collect();
+        let observers = Observers::default();
+        let grpc_supervisor =
+            TaskGuard::spawn(&shutdown, Self::supervise_grpc(client.clone(), subscriptions, observers.clone()));
+        Ok(Self { listeners, wrpc_event_handler: None, grpc_supervisor: Some(grpc_supervisor), observers, shutdown })
+    }
+
+    /// Reconnect a dropped gRPC client and re-issue `start_notify` for every
+    /// listener, so delivery resumes on the receivers already handed out
+    async fn supervise_grpc(client: GrpcClient, subscriptions: Vec<(u64, EventType)>, observers: Observers) {
+        let mut interval = tokio::time::interval(GRPC_RECONNECT_INTERVAL);
+        let mut state = ReconnectState::default();
+        loop {
+            interval.tick().await;
+            if client.is_connected() {
+                continue;
+            }
+
+            log::warn!("gRPC client disconnected, attempting to reconnect...");
+            state.disconnected(&observers);
+            if let Err(e) = client.reconnect().await {
+                log::error!("Failed to reconnect gRPC client: {}", e);
+                state.failed(&observers);
+                continue;
+            }
+            for (id, ev) in &subscriptions {
+                let tondi_event: TondiEventType = (*ev).into();
+                if let Err(e) = client.start_notify(*id, tondi_event.into()).await {
+                    log::error!("Failed to re-subscribe to {}: {}", ev, e);
+                }
+            }
+            log::info!("gRPC client reconnected, re-subscribed {} event types", subscriptions.len());
+            state.connected(&observers);
+        }
     }
     
     /// Create a new ListenerManager for wRPC client
@@ -216,9 +326,12 @@ impl ListenerManager {
         events: &[EventType]
     ) -> Result<Self, PoolError> {
         let mut listeners = HashMap::new();
+        let shutdown = CancellationToken::new();
         
         // 创建wRPC事件处理器
-        let event_handler = WrpcEventHandler::new(client.clone(), events.to_vec());
+        let observers = Observers::default();
+        let mut event_handler =
+            WrpcEventHandler::new(client.clone(), events.to_vec(), observers.clone(), shutdown.clone());
         
         // 启动事件监听
         event_handler.start_listening().await?;
@@ -230,18 +343,58 @@ impl ListenerManager {
         
         Ok(Self { 
             listeners, 
-            wrpc_event_handler: Some(event_handler) 
+            wrpc_event_handler: Some(event_handler),
+            grpc_supervisor: None,
+            observers,
+            shutdown,
         })
     }
 
-    /// Get receiver for a specific event type
-    pub fn get(&self, ev: &EventType) -> Result<Receiver<Notification>> {
+    /// Manager over listeners fed by something other than an upstream subscription
+    pub(crate) fn from_listeners(listeners: HashMap<EventType, Listener>) -> Self {
+        Self {
+            listeners,
+            wrpc_event_handler: None,
+            grpc_supervisor: None,
+            observers: Observers::default(),
+            shutdown: CancellationToken::new(),
+        }
+    }
+
+    /// Stop every loop spawned for this client; receivers see their channels close
+    pub fn shutdown(&self) {
+        self.shutdown.cancel();
+    }
+
+    /// Observer slot consulted by this manager's reconnect loop
+    pub fn observers(&self) -> &Observers {
+        &self.observers
+    }
+
+    /// Hand a decoded event to the listener of its type
+    pub async fn forward(&self, payload: NotificationPayload) -> Result<(), PoolError> {
+        let Some(event_type) = payload.event_type() else {
+            return Err(PoolError::from(format!("Cannot route unknown event: {:?}", payload)));
+        };
+        match self.listeners.get(&event_type) {
+            Some(listener) => listener.forward(payload).await,
+            None => Err(PoolError::from(format!("No listener for event type: {}", event_type))),
+        }
+    }
+
+    /// New receiver for a specific event type; each receiver gets every event
+    pub fn get(&self, ev: &EventType) -> Result<NotificationReceiver> {
         match self.listeners.get(ev) {
             Some(listener) => Ok(listener.receiver()),
             None => Err(AppError::NotFound("EventType not found".to_string())),
         }
     }
 
+    /// Upstream listener id registered for an event type
+    pub fn listener_id(&self, ev: &EventType) -> Option<u64> {
+        self.listeners.get(ev).map(|listener| listener.id)
+    }
+
     /// Check if an event type is being listened to
     pub fn has_event(&self, ev: &EventType) -> bool {
         self.listeners.contains_key(ev)
@@ -272,11 +425,21 @@ impl ListenerManager {
     }
 }
 
+impl Drop for ListenerManager {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
 /// wRPC事件处理器
 pub struct WrpcEventHandler {
     client: Arc<RpcClient<(), Id64>>,
     event_types: Vec<EventType>,
     listeners: HashMap<EventType, Arc<Listener>>,
+    observers: Observers,
+    /// Receive/reconnect loop, aborted when the handler is dropped
+    listening: Option<TaskGuard>,
+    shutdown: CancellationToken,
 }
 
 impl std::fmt::Debug for WrpcEventHandler {
@@ -285,6 +448,7 @@ impl std::fmt::Debug for WrpcEventHandler {
             .field("client", &"Arc<RpcClient<(), Id64>>")
             .field("event_types", &self.event_types)
             .field("listeners", &self.listeners.len())
+            .field("listening", &self.listening.as_ref().is_some_and(|task| !task.is_finished()))
             .finish()
     }
 }
@@ -292,12 +456,17 @@ impl std::fmt::Debug for WrpcEventHandler {
 impl WrpcEventHandler {
     pub fn new(
         client: Arc<RpcClient<(), Id64>>, 
-        event_types: Vec<EventType>
+        event_types: Vec<EventType>,
+        observers: Observers,
+        shutdown: CancellationToken,
     ) -> Self {
         Self {
             client,
             event_types,
             listeners: HashMap::new(),
+            observers,
+            listening: None,
+            shutdown,
         }
     }
     
@@ -316,21 +485,26 @@ impl WrpcEventHandler {
     }
     
     /// 启动WebSocket消息监听
-    async fn start_websocket_listening(&self) -> Result<(), PoolError> {
+    async fn start_websocket_listening(&mut self) -> Result<(), PoolError> {
         let client = self.client.clone();
         let listeners = self.listeners.clone();
+        let observers = self.observers.clone();
         
-        tokio::spawn(async move {
+        self.listening = Some(TaskGuard::spawn(&self.shutdown, async move {
+            let mut state = ReconnectState::default();
             loop {
                 // 检查连接状态
                 if !client.is_connected() {
                     log::warn!("wRPC client disconnected, attempting to reconnect...");
+                    state.disconnected(&observers);
                     if let Err(e) = client.connect(workflow_rpc::client::ConnectOptions::default()).await {
                         log::error!("Failed to reconnect wRPC client: {}", e);
+                        state.failed(&observers);
                         tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
                         continue;
                     }
                     log::info!("wRPC client reconnected successfully");
+                    state.connected(&observers);
                 }
                 
                 // 监听WebSocket消息
@@ -340,7 +514,7 @@ impl WrpcEventHandler {
                 
                 tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
             }
-        });
+        }));
         
         Ok(())
     }
@@ -360,71 +534,93 @@ impl WrpcEventHandler {
             }
         };
         
-        log::debug!("Received wRPC notification: {:?}", notification);
-        
-        // 尝试解析事件类型
-        let event_type = event_data.get("type")
-            .and_then(|v| v.as_str());
+        let payload = NotificationPayload::from_json(event_data);
+        let Some(event_type) = payload.event_type() else {
+            log::warn!("Unknown wRPC event: {:?}", payload);
+            return;
+        };
+        NOTIFICATION_LOG.record(event_type, &payload);
         
-        if let Some(event_type_str) = event_type {
-            // 根据事件类型找到对应的监听器
-            let event_enum = match event_type_str {
-                "block-added" => EventType::BlockAdded,
-                "virtual-chain-changed" => EventType::VirtualChainChanged,
-                "finality-conflict" => EventType::FinalityConflict,
-                "finality-conflict-resolved" => EventType::FinalityConflictResolved,
-                "utxos-changed" => EventType::UtxosChanged,
-                "sink-blue-score-changed" => EventType::SinkBlueScoreChanged,
-                "virtual-daa-score-changed" => EventType::VirtualDaaScoreChanged,
-                "pruning-point-utxo-set-override" => EventType::PruningPointUtxoSetOverride,
-                "new-block-template" => EventType::NewBlockTemplate,
-                _ => {
-                    log::warn!("Unknown event type: {}", event_type_str);
-                    return;
-                }
-            };
-            
-            // 发送到对应的监听器
-            if let Some(listener) = listeners.get(&event_enum) {
-                if let Err(e) = listener.handle_wrpc_event(event_data).await {
+        // 发送到对应的监听器
+        match listeners.get(&event_type) {
+            Some(listener) => {
+                if let Err(e) = listener.forward(payload).await {
                     log::error!("Failed to handle wRPC event: {}", e);
                 }
-            } else {
-                log::warn!("No listener found for event type: {}", event_type_str);
-            }
-        } else {
-            log::warn!("No event type found in wRPC notification");
+            },
+            None => log::warn!("No listener found for event type: {}", event_type),
         }
     }
     
     /// 处理事件
     pub async fn handle_event(&self, event_data: serde_json::Value) -> Result<(), PoolError> {
-        // 解析事件类型
-        let event_type = event_data.get("type")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| PoolError::from("Missing event type"))?;
-        
-        // 根据事件类型找到对应的监听器
-        let event_enum = match event_type {
-            "block-added" => EventType::BlockAdded,
-            "virtual-chain-changed" => EventType::VirtualChainChanged,
-            "finality-conflict" => EventType::FinalityConflict,
-            "finality-conflict-resolved" => EventType::FinalityConflictResolved,
-            "utxos-changed" => EventType::UtxosChanged,
-            "sink-blue-score-changed" => EventType::SinkBlueScoreChanged,
-            "virtual-daa-score-changed" => EventType::VirtualDaaScoreChanged,
-            "pruning-point-utxo-set-override" => EventType::PruningPointUtxoSetOverride,
-            "new-block-template" => EventType::NewBlockTemplate,
-            _ => {
-                log::warn!("Unknown event type: {}", event_type);
-                return Ok(());
-            }
+        let payload = NotificationPayload::from_json(event_data);
+        let Some(event_type) = payload.event_type() else {
+            log::warn!("Unknown wRPC event: {:?}", payload);
+            return Ok(());
         };
         
-        if let Some(listener) = self.listeners.get(&event_enum) {
-            listener.handle_wrpc_event(event_data).await?;
+        if let Some(listener) = self.listeners.get(&event_type) {
+            listener.forward(payload).await?;
         }
         
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    /// Loop ticking into `ticks` until aborted
+    fn ticking(ticks: mpsc::UnboundedSender<()>) -> impl Future<Output = ()> + Send + 'static {
+        async move {
+            while ticks.send(()).is_ok() {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dropping_guard_stops_the_loop() {
+        let (ticks, mut received) = mpsc::unbounded_channel();
+        let guard = TaskGuard::spawn(&CancellationToken::new(), ticking(ticks));
+        assert!(received.recv().await.is_some());
+
+        drop(guard);
+        // The aborted task drops its sender, closing the channel
+        while received.recv().await.is_some() {}
+    }
+
+    #[tokio::test]
+    async fn test_dropping_listener_stops_its_loop() {
+        let (ticks, mut received) = mpsc::unbounded_channel();
+        let listener = Listener::new(1, NotificationChannel::default());
+        listener.own(TaskGuard::spawn(&CancellationToken::new(), ticking(ticks)));
+        assert!(received.recv().await.is_some());
+
+        drop(listener);
+        while received.recv().await.is_some() {}
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_stops_every_loop() {
+        let manager = ListenerManager::from_listeners(HashMap::new());
+        let mut loops = Vec::new();
+        for _ in 0..3 {
+            let (ticks, received) = mpsc::unbounded_channel();
+            loops.push((TaskGuard::spawn(&manager.shutdown, ticking(ticks)), received));
+        }
+        for (_, received) in &mut loops {
+            assert!(received.recv().await.is_some());
+        }
+
+        manager.shutdown();
+        // The guards are still held, so only the token can have stopped the loops
+        for (_guard, mut received) in loops {
+            while received.recv().await.is_some() {}
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use crate::{
+    ctx::event_config::EventType,
+    extensions::client_pool::listener::{Listener, ListenerManager},
+    shared::pool::{Error as PoolError, NotificationChannel, NotificationPayload},
+};
+
+/// Stand-in for the upstream node, reached through `mock://` URLs.
+/// Tests script notifications with [`MockClient::emit`].
+#[derive(Debug)]
+pub struct MockClient {
+    pub listener_manager: Arc<ListenerManager>,
+    live: AtomicBool,
+}
+
+impl MockClient {
+    pub fn new(events: &[EventType]) -> Self {
+        let listeners = events
+            .iter()
+            .zip(0_u64..)
+            .map(|(ev, id)| (*ev, Listener::new(id, NotificationChannel::default())))
+            .collect::<HashMap<_, _>>();
+        let listener_manager = Arc::new(ListenerManager::from_listeners(listeners));
+        Self { listener_manager, live: AtomicBool::new(true) }
+    }
+
+    /// Deliver `payload` as if the upstream had sent it
+    pub async fn emit(&self, payload: NotificationPayload) -> Result<(), PoolError> {
+        self.listener_manager.forward(payload).await
+    }
+
+    /// Simulate the upstream going away (or coming back)
+    pub fn set_live(&self, live: bool) {
+        self.live.store(live, Ordering::Relaxed);
+    }
+
+    pub fn is_live(&self) -> bool {
+        self.live.load(Ordering::Relaxed)
+    }
+}
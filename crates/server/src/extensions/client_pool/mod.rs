@@ -1,22 +1,33 @@
 pub mod listener;
+#[cfg(any(test, feature = "test-util"))]
+pub mod mock;
+pub mod observer;
+pub mod retry;
 
-use std::{ops::Deref, sync::Arc};
+use std::{ops::Deref, sync::Arc, time::Duration};
 
 use axum::Extension;
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
 use tondi_grpc_client::{GrpcClient, error::Error as GrpcClientError};
-use tondi_listener_library::log::info;
+use tondi_listener_library::log::{info, warn};
+use tondi_rpc_core::api::rpc::RpcApi;
 use workflow_rpc::client::{RpcClient, ConnectOptions};
 
 use crate::{
-    ctx::event_config::EventType,
+    ctx::{config::UpstreamConfig, event_config::EventType},
     error::{Error, Result},
-    extensions::client_pool::listener::ListenerManager,
-    shared::pool::{Error as PoolError, HealthCheck, Metadata, Pool},
+    extensions::client_pool::{listener::ListenerManager, observer::ConnectionObserver},
+    shared::{
+        metrics::METRICS,
+        pool::{Error as PoolError, HealthCheck, Metadata, Pool},
+    },
 };
 
 pub enum Client {
     Grpc(GrpcClientWrapper),
     Wrpc(WrpcClientWrapper),
+    #[cfg(any(test, feature = "test-util"))]
+    Mock(mock::MockClient),
 }
 
 impl std::fmt::Debug for Client {
@@ -24,6 +35,8 @@ impl std::fmt::Debug for Client {
         match self {
             Client::Grpc(_) => write!(f, "Client::Grpc"),
             Client::Wrpc(_) => write!(f, "Client::Wrpc"),
+            #[cfg(any(test, feature = "test-util"))]
+            Client::Mock(_) => write!(f, "Client::Mock"),
         }
     }
 }
@@ -41,13 +54,27 @@ pub struct WrpcClientWrapper {
 
 impl Client {
     pub async fn connect(url: String) -> Result<Self, PoolError> {
-        Self::connect_with_events(url, &[]).await
+        Self::connect_with_events(url, &[], None).await
     }
 
+    /// Upstream stand-in that emits scripted notifications, see [`mock::MockClient`]
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn mock(events: &[EventType]) -> Self {
+        Self::Mock(mock::MockClient::new(events))
+    }
+
+    /// `call_timeout` bounds how long a gRPC call waits for its answer, the client's default when `None`
     pub async fn connect_with_events(
         url: String, 
-        events: &[EventType]
+        events: &[EventType],
+        call_timeout: Option<Duration>,
     ) -> Result<Self, PoolError> {
+        #[cfg(any(test, feature = "test-util"))]
+        if url.starts_with("mock://") {
+            info!("Using mock upstream: {}", url);
+            return Ok(Self::mock(events));
+        }
+
         // Check if the URL starts with ws:// or wss://
         if url.starts_with("ws://") || url.starts_with("wss://") {
             info!("Connecting to wRPC endpoint: {}", url);
@@ -75,7 +102,7 @@ impl Client {
                 true,
                 None,
                 false,
-                None,
+                call_timeout.map(|timeout| u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX)),
                 Default::default(),
             )
             .await?;
@@ -91,7 +118,7 @@ impl Client {
                 // 可能是IP:PORT格式，默认使用wRPC
                 let wrpc_url = format!("ws://{}", url);
                 info!("Auto-detected wRPC format, using: {}", wrpc_url);
-                Box::pin(Self::connect_with_events(wrpc_url, events)).await
+                Box::pin(Self::connect_with_events(wrpc_url, events, call_timeout)).await
             } else {
                 Err(PoolError::from(format!("Unsupported URL format: {}", url)))
             }
@@ -102,6 +129,20 @@ impl Client {
         match self {
             Client::Grpc(client) => &client.listener_manager,
             Client::Wrpc(client) => &client.listener_manager,
+            #[cfg(any(test, feature = "test-util"))]
+            Client::Mock(client) => &client.listener_manager,
+        }
+    }
+
+    /// Typed RPC access to the upstream node (only the gRPC transport exposes `RpcApi`)
+    pub fn rpc(&self) -> Result<&GrpcClient, PoolError> {
+        match self {
+            Client::Grpc(client) => Ok(client),
+            Client::Wrpc(_) => {
+                Err(PoolError::from("RPC calls are not supported over the wRPC transport".to_string()))
+            },
+            #[cfg(any(test, feature = "test-util"))]
+            Client::Mock(_) => Err(PoolError::from("RPC calls are not supported by the mock client".to_string())),
         }
     }
 }
@@ -129,12 +170,21 @@ impl WrpcClientWrapper {
     }
 }
 
+/// What a replacement client connects to: the same node, subscribed to the same events
+#[derive(Debug, Clone)]
+pub struct Upstream {
+    pub url: String,
+    pub events: Vec<EventType>,
+    /// See [`Client::connect_with_events`]
+    pub call_timeout: Option<Duration>,
+}
+
 impl Metadata for Client {
     type Error = PoolError;
-    type Meta = String;
+    type Meta = Upstream;
 
-    async fn try_from(url: &Self::Meta) -> Result<Self, Self::Error> {
-        Ok(Self::connect(url.clone()).await?)
+    async fn try_from(upstream: &Self::Meta) -> Result<Self, Self::Error> {
+        Self::connect_with_events(upstream.url.clone(), &upstream.events, upstream.call_timeout).await
     }
 }
 
@@ -143,6 +193,17 @@ impl HealthCheck for Client {
         match self {
             Client::Grpc(client) => client.is_connected(),
             Client::Wrpc(client) => client.is_connected(),
+            #[cfg(any(test, feature = "test-util"))]
+            Client::Mock(client) => client.is_live(),
+        }
+    }
+
+    async fn probe(&self) -> bool {
+        match self {
+            Client::Grpc(client) => client.ping().await.is_ok(),
+            Client::Wrpc(client) => client.is_connected(),
+            #[cfg(any(test, feature = "test-util"))]
+            Client::Mock(client) => client.is_live(),
         }
     }
 }
@@ -189,23 +250,57 @@ impl From<tondi_rpc_core::RpcError> for PoolError {
     }
 }
 
-impl From<tondi_rpc_core::RpcError> for Error {
-    fn from(err: tondi_rpc_core::RpcError) -> Self {
-        Self::from(format!("RPC Failed: {err}"))
+pub type ClientPool = Extension<Arc<Pool<Client>>>;
+
+impl Pool<Client> {
+    /// Route reconnect events of the upstream client to `observer`, and of every client replacing it
+    pub async fn set_connection_observer(&self, observer: Arc<dyn ConnectionObserver>) -> Result<(), PoolError> {
+        self.get().await?.listener_manager().observers().set(observer.clone());
+        self.on_refresh(move |client| client.listener_manager().observers().set(observer.clone()));
+        Ok(())
     }
-}
 
-pub type ClientPool = Extension<Arc<Pool<Client>>>;
+    /// Every `interval`, probe the connection once it has idled for `max_idle`, replacing it
+    /// before a request runs into it when it no longer answers. Stops once the pool is dropped.
+    pub fn spawn_idle_checks(self: &Arc<Self>, interval: Duration, max_idle: Duration) -> JoinHandle<()> {
+        let pool = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                let Some(pool) = pool.upgrade() else { return };
+                match pool.evict_idle(max_idle).await {
+                    Ok(true) => info!("Replaced an idle upstream connection that stopped answering"),
+                    Ok(false) => {},
+                    Err(e) => warn!("Idle upstream connection check failed: {e}"),
+                }
+            }
+        })
+    }
+}
 
 pub async fn extension(url: &String) -> Result<ClientPool, PoolError> {
-    extension_with_events(url, &[]).await
+    extension_with_events(url, &[], &UpstreamConfig::default(), None).await
 }
 
+/// Pool of one upstream client. The node client sends every call over a single stream, so a call cannot carry
+/// its own `grpc-timeout`; `call_timeout` caps all of them instead, and
+/// [`Deadline`](crate::middleware::timeout::Deadline) gives up on each one sooner when its request has less time left.
 pub async fn extension_with_events(
     url: &String, 
-    events: &[EventType]
+    events: &[EventType],
+    upstream: &UpstreamConfig,
+    call_timeout: Option<Duration>,
 ) -> Result<ClientPool, PoolError> {
-    let client = Client::connect_with_events(url.into(), events).await?;
-    let pool = Pool::new(url.into(), client);
+    let client = Client::connect_with_events(url.into(), events, call_timeout).await?;
+    let mut pool = Pool::new(Upstream { url: url.into(), events: events.to_vec(), call_timeout }, client);
+    if upstream.max_upstream_concurrency > 0 {
+        let wait = Duration::from_millis(upstream.upstream_permit_wait_ms);
+        pool = pool.with_concurrency_limit(upstream.max_upstream_concurrency, wait);
+    }
+    if let Some(permits) = pool.permits() {
+        let _ = METRICS.upstream_permits.set(permits);
+    }
     Ok(Extension(Arc::new(pool)))
 }
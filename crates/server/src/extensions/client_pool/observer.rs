@@ -0,0 +1,161 @@
+use std::{
+    fmt::Debug,
+    sync::{Arc, RwLock},
+};
+
+/// Hooks for embedders reacting to the upstream connection flapping.
+/// Every method defaults to a no-op.
+pub trait ConnectionObserver: Debug + Send + Sync {
+    /// The upstream connection was (re-)established
+    fn on_connect(&self) {}
+
+    /// The upstream connection was found down
+    fn on_disconnect(&self) {}
+
+    /// Reconnect attempt number `attempt` (1-based) since the drop failed
+    fn on_reconnect_failed(&self, _attempt: u32) {}
+}
+
+#[derive(Debug, Default)]
+pub struct NoopObserver;
+
+impl ConnectionObserver for NoopObserver {}
+
+/// Observer slot shared with the reconnect loops, replaceable while they run
+#[derive(Debug, Clone)]
+pub struct Observers(Arc<RwLock<Arc<dyn ConnectionObserver>>>);
+
+impl Default for Observers {
+    fn default() -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(NoopObserver))))
+    }
+}
+
+impl Observers {
+    pub fn set(&self, observer: Arc<dyn ConnectionObserver>) {
+        if let Ok(mut slot) = self.0.write() {
+            *slot = observer;
+        }
+    }
+
+    fn current(&self) -> Arc<dyn ConnectionObserver> {
+        match self.0.read() {
+            Ok(slot) => slot.clone(),
+            Err(_) => Arc::new(NoopObserver),
+        }
+    }
+
+    pub fn on_connect(&self) {
+        self.current().on_connect();
+    }
+
+    pub fn on_disconnect(&self) {
+        self.current().on_disconnect();
+    }
+
+    pub fn on_reconnect_failed(&self, attempt: u32) {
+        self.current().on_reconnect_failed(attempt);
+    }
+}
+
+/// Tracks one reconnect loop's state so observers see each transition once
+#[derive(Debug, Default)]
+pub(crate) struct ReconnectState {
+    down: bool,
+    attempts: u32,
+}
+
+impl ReconnectState {
+    /// The loop found the connection down
+    pub(crate) fn disconnected(&mut self, observers: &Observers) {
+        if !self.down {
+            self.down = true;
+            observers.on_disconnect();
+        }
+    }
+
+    pub(crate) fn failed(&mut self, observers: &Observers) {
+        self.attempts += 1;
+        observers.on_reconnect_failed(self.attempts);
+    }
+
+    pub(crate) fn connected(&mut self, observers: &Observers) {
+        *self = Self::default();
+        observers.on_connect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct Counting {
+        connects: AtomicU32,
+        disconnects: AtomicU32,
+        last_failed_attempt: AtomicU32,
+    }
+
+    impl ConnectionObserver for Counting {
+        fn on_connect(&self) {
+            self.connects.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn on_disconnect(&self) {
+            self.disconnects.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn on_reconnect_failed(&self, attempt: u32) {
+            self.last_failed_attempt.store(attempt, Ordering::Relaxed);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_observer_follows_a_replaced_client() {
+        use crate::{
+            ctx::event_config::EventType,
+            extensions::client_pool::{Client, Upstream},
+            shared::pool::Pool,
+        };
+
+        let ev = EventType::VirtualDaaScoreChanged;
+        let upstream = Upstream { url: "mock://node".to_string(), events: vec![ev], call_timeout: None };
+        let pool = Pool::new(upstream, Client::mock(&[ev]));
+        let counting = Arc::new(Counting::default());
+        pool.set_connection_observer(counting.clone()).await.unwrap();
+
+        if let Client::Mock(upstream) = &*pool.get().await.unwrap() {
+            upstream.set_live(false);
+        }
+        // The next use replaces the client, whose reconnect loop reports to the same observer
+        pool.get().await.unwrap().listener_manager().observers().on_connect();
+        assert_eq!(counting.connects.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_counting_observer_sees_each_transition() {
+        let counting = Arc::new(Counting::default());
+        let observers = Observers::default();
+        observers.set(counting.clone());
+
+        // Drop noticed on several ticks, two failed attempts, then recovery
+        let mut state = ReconnectState::default();
+        state.disconnected(&observers);
+        state.failed(&observers);
+        state.disconnected(&observers);
+        state.failed(&observers);
+        state.connected(&observers);
+
+        assert_eq!(counting.disconnects.load(Ordering::Relaxed), 1);
+        assert_eq!(counting.last_failed_attempt.load(Ordering::Relaxed), 2);
+        assert_eq!(counting.connects.load(Ordering::Relaxed), 1);
+
+        // A later drop starts counting attempts afresh
+        state.disconnected(&observers);
+        state.failed(&observers);
+        assert_eq!(counting.disconnects.load(Ordering::Relaxed), 2);
+        assert_eq!(counting.last_failed_attempt.load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use tondi_listener_library::log::warn;
+use tondi_rpc_core::RpcResult;
+
+use crate::ctx::config::RetryConfig;
+
+/// Retry an upstream RPC call with exponential backoff.
+///
+/// Only wrap idempotent reads (`get_block_count`, `get_sink`, ...): submissions,
+/// bans and other state-changing calls must go through exactly once.
+pub async fn retry_rpc<T, F, Fut>(policy: &RetryConfig, mut call: F) -> RpcResult<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = RpcResult<T>>,
+{
+    let max_backoff = Duration::from_millis(policy.max_backoff_ms);
+    let mut backoff = Duration::from_millis(policy.initial_backoff_ms).min(max_backoff);
+    let mut attempt = 0;
+
+    loop {
+        match call().await {
+            Ok(value) => return Ok(value),
+            Err(err) if attempt < policy.max_retries => {
+                attempt += 1;
+                warn!("Upstream RPC failed, retrying ({attempt}/{}): {err}", policy.max_retries);
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(max_backoff);
+            },
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use tondi_rpc_core::RpcError;
+
+    use super::*;
+
+    fn policy(max_retries: u32) -> RetryConfig {
+        RetryConfig { max_retries, initial_backoff_ms: 1, max_backoff_ms: 2 }
+    }
+
+    #[tokio::test]
+    async fn test_retry_succeeds_after_failures() {
+        let calls = AtomicU32::new(0);
+        let result = retry_rpc(&policy(3), || async {
+            match calls.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => Err(RpcError::General("transient".to_string())),
+                _ => Ok(42),
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 42);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_gives_up_after_max_retries() {
+        let calls = AtomicU32::new(0);
+        let result: RpcResult<()> = retry_rpc(&policy(1), || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(RpcError::General("down".to_string()))
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -1,5 +1,3 @@
-#![feature(variant_count)]
-
 pub mod ctx;
 pub mod error;
 pub mod extensions;
@@ -0,0 +1,95 @@
+use std::{
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    task::{Context, Poll},
+};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use http_body::{Body as HttpBody, Frame, SizeHint};
+
+use crate::shared::metrics::{METRICS, RouteTraffic};
+
+/// Label for requests no route matched
+const UNMATCHED: &str = "unmatched";
+
+/// Count requests and body bytes per matched route into [`METRICS`].
+/// Bodies are counted frame by frame as they stream, never buffered.
+pub async fn account(request: Request, next: Next) -> Response {
+    let route = request.extensions().get::<MatchedPath>().map_or(UNMATCHED, MatchedPath::as_str);
+    let traffic = METRICS.route(route);
+    traffic.requests.fetch_add(1, Ordering::Relaxed);
+
+    let request = request.map(|body| CountingBody::wrap(body, traffic.clone(), |t| &t.bytes_in));
+    next.run(request).await.map(|body| CountingBody::wrap(body, traffic, |t| &t.bytes_out))
+}
+
+struct CountingBody {
+    inner: Body,
+    traffic: Arc<RouteTraffic>,
+    counter: fn(&RouteTraffic) -> &AtomicU64,
+}
+
+impl CountingBody {
+    fn wrap(inner: Body, traffic: Arc<RouteTraffic>, counter: fn(&RouteTraffic) -> &AtomicU64) -> Body {
+        Body::new(Self { inner, traffic, counter })
+    }
+}
+
+impl HttpBody for CountingBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let poll = Pin::new(&mut self.inner).poll_frame(cx);
+        let len = match &poll {
+            Poll::Ready(Some(Ok(frame))) => frame.data_ref().map_or(0, Bytes::len),
+            _ => 0,
+        };
+        (self.counter)(&self.traffic).fetch_add(u64::try_from(len).unwrap_or(u64::MAX), Ordering::Relaxed);
+        poll
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, middleware::from_fn, routing::post};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_bytes_are_counted_per_route() {
+        let router = Router::new()
+            .route("/accounting/{id}", post(|body: String| async move { body.repeat(3) }))
+            .layer(from_fn(account));
+
+        let request = Request::post("/accounting/1").body(Body::from("hello")).unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body.len(), 15);
+
+        let traffic = METRICS.route("/accounting/{id}");
+        assert_eq!(traffic.requests.load(Ordering::Relaxed), 1);
+        assert_eq!(traffic.bytes_in.load(Ordering::Relaxed), 5);
+        assert_eq!(traffic.bytes_out.load(Ordering::Relaxed), 15);
+    }
+}
@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use http::header::AUTHORIZATION;
+
+use crate::error::{Error, Result};
+
+/// Bearer token guarding the operator-only `/admin` routes
+#[derive(Debug, Clone)]
+pub struct AdminToken(Arc<str>);
+
+impl AdminToken {
+    pub fn new(token: &str) -> Self {
+        Self(Arc::from(token))
+    }
+
+    /// Constant-time comparison so the token can't be probed byte by byte
+    pub fn matches(&self, presented: &str) -> bool {
+        let (expected, presented) = (self.0.as_bytes(), presented.as_bytes());
+        expected.len() == presented.len()
+            && expected.iter().zip(presented).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+    }
+}
+
+/// Reject requests that don't carry `Authorization: Bearer <admin token>`
+pub async fn require_admin_token(
+    State(token): State<AdminToken>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    let presented = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match presented {
+        Some(presented) if token.matches(presented) => Ok(next.run(request).await),
+        Some(_) => Err(Error::Unauthorized("Invalid admin token".to_string())),
+        None => Err(Error::Unauthorized("Missing admin bearer token".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, body::Body, middleware::from_fn_with_state, routing::post};
+    use http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{ctx::config::SecurityConfig, routes::admin};
+
+    fn router() -> Router {
+        Router::new()
+            .route("/admin/shutdown", post(|| async { "ok" }))
+            .route_layer(from_fn_with_state(AdminToken::new("secret"), require_admin_token))
+    }
+
+    fn shutdown_request(authorization: Option<&str>) -> Request<Body> {
+        let mut builder = Request::post("/admin/shutdown");
+        if let Some(authorization) = authorization {
+            builder = builder.header(AUTHORIZATION, authorization);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_admin_route_requires_token() {
+        let response = router().oneshot(shutdown_request(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = router().oneshot(shutdown_request(Some("Bearer wrong"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = router().oneshot(shutdown_request(Some("Bearer secret"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_admin_router_absent_without_token() {
+        assert!(admin::router(&SecurityConfig::default()).is_none());
+
+        let security = SecurityConfig { admin_token: Some("secret".to_string()), ..Default::default() };
+        assert!(admin::router(&security).is_some());
+    }
+}
@@ -0,0 +1,90 @@
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http::{
+    StatusCode,
+    header::{CONTENT_LENGTH, CONTENT_TYPE},
+};
+
+use crate::error::Error;
+
+/// Rejects bodies over `max` bytes with the JSON error envelope.
+///
+/// A `Content-Length` over the limit is refused before the handler runs; bodies without one are
+/// cut off by `DefaultBodyLimit` in the extractors, whose plain-text 413 is rewritten here.
+pub async fn limit_body(State(max): State<usize>, request: Request, next: Next) -> Response {
+    let declared = request
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    if declared.is_some_and(|length| length > u64::try_from(max).unwrap_or(u64::MAX)) {
+        return too_large(max)
+    }
+
+    let response = next.run(request).await;
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if response.status() == StatusCode::PAYLOAD_TOO_LARGE && !is_json { too_large(max) } else { response }
+}
+
+fn too_large(max: usize) -> Response {
+    Error::PayloadTooLarge(format!("the limit is {max} bytes")).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        Router,
+        body::{Body, Bytes, to_bytes},
+        extract::DefaultBodyLimit,
+        middleware::from_fn_with_state,
+        routing::post,
+    };
+    use tower::ServiceExt;
+
+    use super::*;
+
+    const MAX: usize = 16;
+
+    fn router() -> Router {
+        Router::new()
+            .route("/grpc", post(|body: Bytes| async move { body.len().to_string() }))
+            .layer(DefaultBodyLimit::max(MAX))
+            .layer(from_fn_with_state(MAX, limit_body))
+    }
+
+    async fn assert_too_large(response: Response) {
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body: serde_json::Value =
+            serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!(body["error"]["code"], "PAYLOAD_TOO_LARGE");
+        assert_eq!(body["error"]["status"], 413);
+        assert!(body["error"]["message"].as_str().unwrap().contains(&MAX.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_declared_oversized_body_is_rejected() {
+        let body = "x".repeat(MAX + 1);
+        let request = Request::post("/grpc").header(CONTENT_LENGTH, body.len()).body(Body::from(body)).unwrap();
+        assert_too_large(router().oneshot(request).await.unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn test_undeclared_oversized_body_is_rejected() {
+        let request = Request::post("/grpc").body(Body::from("x".repeat(MAX + 1))).unwrap();
+        assert_too_large(router().oneshot(request).await.unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn test_body_within_limit_passes() {
+        let request = Request::post("/grpc").body(Body::from("x".repeat(MAX))).unwrap();
+        let response = router().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,111 @@
+use std::sync::{Arc, PoisonError, RwLock};
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use http::{HeaderValue, StatusCode, header::CACHE_CONTROL};
+
+/// Longest `max-age` caches are expected to honour, one year
+pub const MAX_AGE_IMMUTABLE_SECS: u64 = 31_536_000;
+
+/// `Cache-Control` for responses shared caches may keep `max_age_secs`; `immutable` ones never change
+/// while fresh. `0` asks caches to revalidate every time.
+pub fn directive(max_age_secs: u64, immutable: bool) -> HeaderValue {
+    let value = match (max_age_secs, immutable) {
+        (0, _) => return HeaderValue::from_static("no-cache"),
+        (secs, false) => format!("public, max-age={secs}"),
+        (secs, true) => format!("public, max-age={secs}, immutable"),
+    };
+    HeaderValue::from_str(&value).unwrap_or(HeaderValue::from_static("no-cache"))
+}
+
+/// [`directive`] of a route group, replaceable while serving. Clones share it.
+#[derive(Debug, Clone)]
+pub struct SharedDirective(Arc<RwLock<HeaderValue>>);
+
+impl SharedDirective {
+    pub fn new(directive: HeaderValue) -> Self {
+        Self(Arc::new(RwLock::new(directive)))
+    }
+
+    pub fn get(&self) -> HeaderValue {
+        self.0.read().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Send `directive` from the next response on
+    pub fn set(&self, directive: HeaderValue) {
+        *self.0.write().unwrap_or_else(PoisonError::into_inner) = directive;
+    }
+}
+
+/// Mark successful responses of a route group with `directive`. Errors stay uncached, and
+/// handlers that know better (a transaction still in the mempool) set their own header.
+pub async fn cache_control(State(directive): State<SharedDirective>, request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    let status = response.status();
+    if status.is_success() || status == StatusCode::NOT_MODIFIED {
+        response.headers_mut().entry(CACHE_CONTROL).or_insert_with(|| directive.get());
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, body::Body, middleware::from_fn_with_state, response::IntoResponse, routing::get};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::error::Error;
+
+    fn router() -> Router {
+        router_with(SharedDirective::new(directive(1, false)))
+    }
+
+    fn router_with(tip_directive: SharedDirective) -> Router {
+        let tip = Router::new()
+            .route("/chain/last", get(|| async { "tip" }))
+            .layer(from_fn_with_state(tip_directive, cache_control));
+        let history = Router::new()
+            .route("/transaction/confirmed", get(|| async { "confirmed" }))
+            .route("/transaction/pending", get(|| async { ([(CACHE_CONTROL, "no-cache")], "pending") }))
+            .route("/transaction/missing", get(|| async { Error::NotFound("missing".to_string()).into_response() }))
+            .layer(from_fn_with_state(SharedDirective::new(directive(MAX_AGE_IMMUTABLE_SECS, true)), cache_control));
+        tip.merge(history)
+    }
+
+    async fn cache_header(path: &str) -> Option<String> {
+        let response = router().oneshot(Request::get(path).body(Body::empty()).unwrap()).await.unwrap();
+        response.headers().get(CACHE_CONTROL).map(|value| value.to_str().unwrap().to_string())
+    }
+
+    #[tokio::test]
+    async fn test_tip_and_history_headers() {
+        assert_eq!(cache_header("/chain/last").await.as_deref(), Some("public, max-age=1"));
+        assert_eq!(
+            cache_header("/transaction/confirmed").await.as_deref(),
+            Some("public, max-age=31536000, immutable")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_errors_and_handler_headers_are_left_alone() {
+        assert_eq!(cache_header("/transaction/pending").await.as_deref(), Some("no-cache"));
+        assert_eq!(cache_header("/transaction/missing").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_directive_changes_while_serving() {
+        let tip = SharedDirective::new(directive(1, false));
+        let app = router_with(tip.clone());
+        tip.set(directive(5, false));
+        let response = app.oneshot(Request::get("/chain/last").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.headers()[CACHE_CONTROL], "public, max-age=5");
+    }
+
+    #[test]
+    fn test_zero_max_age_revalidates() {
+        assert_eq!(directive(0, true), "no-cache");
+    }
+}
@@ -1,5 +1,8 @@
-use tower_http::cors::{Any, CorsLayer};
-use crate::ctx::config::CorsConfig;
+use std::sync::Arc;
+
+use tokio::sync::watch;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use crate::ctx::config::{Config, CorsConfig};
 
 pub fn cors(config: &CorsConfig) -> CorsLayer {
     let mut cors = CorsLayer::new();
@@ -45,19 +48,36 @@ pub fn cors(config: &CorsConfig) -> CorsLayer {
     // Set max age for preflight request caching
     cors = cors.max_age(std::time::Duration::from_secs(config.max_age));
     
-    // Allow credentials (cookies, etc.)
-    cors = cors.allow_credentials(true);
+    // Let browser JS read our custom response headers
+    let exposed_headers: Vec<http::HeaderName> =
+        config.exposed_headers.iter().filter_map(|header| header.parse().ok()).collect();
+    cors = cors.expose_headers(exposed_headers);
+    
+    // Allow credentials (cookies, etc.); only valid without wildcards, see `CorsConfig::validate`
+    cors = cors.allow_credentials(config.allow_credentials);
     
     cors
 }
 
+/// [`cors`] of the current settings, with the allowed origins following the reloaded ones. No origins allow
+/// every one, except with credentials, which never go to a wildcard.
+pub fn live_cors(updates: watch::Receiver<Arc<Config>>) -> CorsLayer {
+    let layer = cors(&updates.borrow().cors);
+    layer.allow_origin(AllowOrigin::predicate(move |origin, _| {
+        let config = updates.borrow();
+        let allowed = &config.cors.allowed_origins;
+        (allowed.is_empty() && !config.cors.allow_credentials)
+            || allowed.iter().any(|allowed| allowed.as_bytes() == origin.as_bytes())
+    }))
+}
+
 /// Fully open CORS configuration (equivalent to no CORS restrictions)
 pub fn open_cors() -> CorsLayer {
     CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
         .allow_headers(Any)
-        .allow_credentials(true)
+        .allow_credentials(false) // Credentials are rejected alongside wildcards
         .max_age(std::time::Duration::from_secs(86400)) // 24 hours
 }
 
@@ -70,3 +90,71 @@ pub fn strict_cors() -> CorsLayer {
         .max_age(std::time::Duration::from_secs(3600))
         .allow_credentials(false)
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, body::Body, routing::get};
+    use http::{Request, header::{ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, ORIGIN}};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_exposed_headers_are_advertised() {
+        let app = Router::new().route("/", get(|| async { "ok" })).layer(cors(&CorsConfig::default()));
+        let request = Request::get("/").header(ORIGIN, "https://example.com").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+
+        let exposed = response.headers().get(ACCESS_CONTROL_EXPOSE_HEADERS).unwrap().to_str().unwrap();
+        assert!(exposed.contains("x-request-id"));
+        assert!(exposed.contains("x-ratelimit-remaining"));
+    }
+
+    #[tokio::test]
+    async fn test_reloaded_origins_apply_to_the_next_request() {
+        let mut config = Config::default();
+        config.cors.allowed_origins = vec!["https://a.example".to_string()];
+        let (updates, receiver) = watch::channel(Arc::new(config.clone()));
+        let app = Router::new().route("/", get(|| async { "ok" })).layer(live_cors(receiver));
+        let allowed = |origin: &'static str| {
+            let app = app.clone();
+            async move {
+                let request = Request::get("/").header(ORIGIN, origin).body(Body::empty()).unwrap();
+                app.oneshot(request).await.unwrap().headers().contains_key(ACCESS_CONTROL_ALLOW_ORIGIN)
+            }
+        };
+        assert!(allowed("https://a.example").await);
+        assert!(!allowed("https://b.example").await);
+
+        config.cors.allowed_origins = vec!["https://b.example".to_string()];
+        updates.send_replace(Arc::new(config));
+        assert!(!allowed("https://a.example").await);
+        assert!(allowed("https://b.example").await);
+    }
+
+    #[tokio::test]
+    async fn test_no_origins_with_credentials_allow_none() {
+        let mut config = Config::default();
+        config.cors = CorsConfig {
+            allowed_origins: vec!["https://a.example".to_string()],
+            allowed_methods: vec!["GET".to_string()],
+            allowed_headers: vec!["content-type".to_string()],
+            allow_credentials: true,
+            ..Default::default()
+        };
+        let (updates, receiver) = watch::channel(Arc::new(config.clone()));
+        let app = Router::new().route("/", get(|| async { "ok" })).layer(live_cors(receiver));
+
+        config.cors.allowed_origins.clear();
+        updates.send_replace(Arc::new(config));
+        let request = Request::get("/").header(ORIGIN, "https://b.example").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert!(!response.headers().contains_key(ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[test]
+    fn test_credentials_require_explicit_rules() {
+        let config = CorsConfig { allow_credentials: true, ..Default::default() };
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,44 @@
+use axum::{extract::Request, middleware::Next, response::Response};
+use http::header::ACCEPT_LANGUAGE;
+
+use crate::shared::language::{LANGUAGE, Language};
+
+/// Localize error messages of the request by its `Accept-Language` header
+pub async fn language(request: Request, next: Next) -> Response {
+    let language = request
+        .headers()
+        .get(ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .map(Language::from_accept_language)
+        .unwrap_or_default();
+    LANGUAGE.scope(language, next.run(request)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        Router,
+        body::{Body, to_bytes},
+        middleware::from_fn,
+        routing::get,
+    };
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::error::Error;
+
+    #[tokio::test]
+    async fn test_not_found_in_chinese() {
+        let router = Router::new()
+            .route("/chain/last", get(|| async { Err::<(), _>(Error::NotFound("header".to_string())) }))
+            .layer(from_fn(language));
+        let request =
+            Request::get("/chain/last").header(ACCEPT_LANGUAGE, "zh-CN,zh;q=0.9").body(Body::empty()).unwrap();
+        let response = router.oneshot(request).await.unwrap();
+
+        let body: serde_json::Value =
+            serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!(body["error"]["message"], "资源未找到：header");
+        assert_eq!(body["error"]["code"], "NOT_FOUND");
+    }
+}
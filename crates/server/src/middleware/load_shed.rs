@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use axum::{BoxError, error_handling::HandleErrorLayer};
+use futures::future::{Ready, ready};
+use tokio::sync::Semaphore;
+use tower::{
+    layer::util::Stack,
+    limit::GlobalConcurrencyLimitLayer,
+    load_shed::{LoadShedLayer, error::Overloaded},
+};
+
+use crate::error::Error;
+
+type Shed = HandleErrorLayer<fn(BoxError) -> Ready<Error>, ()>;
+
+/// Answer `503` at once to requests beyond `max_in_flight` running ones instead of queueing them;
+/// `0` lets any number run. The limit is shared by every route the layer is applied to.
+pub fn load_shed(max_in_flight: usize) -> Stack<GlobalConcurrencyLimitLayer, Stack<LoadShedLayer, Shed>> {
+    let permits = match max_in_flight {
+        0 => Semaphore::MAX_PERMITS,
+        max => max.min(Semaphore::MAX_PERMITS),
+    };
+    let limit = GlobalConcurrencyLimitLayer::with_semaphore(Arc::new(Semaphore::new(permits)));
+    Stack::new(limit, Stack::new(LoadShedLayer::new(), HandleErrorLayer::new(overloaded as fn(_) -> _)))
+}
+
+fn overloaded(err: BoxError) -> Ready<Error> {
+    ready(if err.is::<Overloaded>() {
+        Error::ServiceUnavailable("Server is at its concurrent request limit, retry shortly".to_string())
+    } else {
+        Error::InternalServerError(err.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, body::Body, extract::Request, routing::get};
+    use http::StatusCode;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_requests_over_the_limit_are_shed() {
+        let gate = Arc::new(Semaphore::new(0));
+        let held = gate.clone();
+        let router = Router::new()
+            .route(
+                "/slow",
+                get(move || {
+                    let held = held.clone();
+                    async move {
+                        let _ = held.acquire().await;
+                        "done"
+                    }
+                }),
+            )
+            .route("/fast", get(|| async { "done" }))
+            .layer(load_shed(2));
+        let request = |path| Request::get(path).body(Body::empty()).unwrap();
+
+        let running: Vec<_> = (0..2).map(|_| tokio::spawn(router.clone().oneshot(request("/slow")))).collect();
+
+        // Once both slow requests hold a permit, a third is shed instead of waiting;
+        // the limit spans routes, so that goes for `/fast` too
+        let mut shed = false;
+        for _ in 0..1000 {
+            let response = router.clone().oneshot(request("/fast")).await.unwrap();
+            if response.status() == StatusCode::SERVICE_UNAVAILABLE {
+                shed = true;
+                break;
+            }
+            assert_eq!(response.status(), StatusCode::OK);
+            tokio::task::yield_now().await;
+        }
+        assert!(shed, "no request was shed while the limit was reached");
+
+        gate.add_permits(2);
+        for response in running {
+            assert_eq!(response.await.unwrap().unwrap().status(), StatusCode::OK);
+        }
+        assert_eq!(router.oneshot(request("/fast")).await.unwrap().status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_overloaded_is_503() {
+        let err = overloaded(Box::new(Overloaded::new())).into_inner();
+        assert_eq!(err.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
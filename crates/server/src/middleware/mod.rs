@@ -1,16 +1,36 @@
+pub mod accounting;
+pub mod admin;
+pub mod body_limit;
+pub mod cache_control;
 pub mod cors;
+pub mod language;
+pub mod load_shed;
+pub mod pretty;
+pub mod queue_time;
+pub mod rate_limit;
+pub mod security;
+pub mod timeout;
 pub mod trace;
 
 use tower::ServiceBuilder;
 use tower_http::trace::TraceLayer;
 
-use crate::middleware::{cors::cors, trace::trace};
+use crate::{
+    ctx::config::{CorsConfig, SecurityConfig},
+    middleware::{
+        cors::cors,
+        security::{RequestValidationLayer, SecurityHeadersLayer},
+        trace::trace,
+    },
+};
 
 /// Create middleware stack for the application
 pub fn create_middleware_stack() -> impl tower::Layer<axum::routing::Route> + Clone + Send + Sync + 'static {
     ServiceBuilder::new()
+        .layer(SecurityHeadersLayer::new(&SecurityConfig::default(), false))
         .layer(TraceLayer::new_for_http())
         .layer(trace())
-        .layer(cors(&crate::ctx::config::CorsConfig::default()))
+        .layer(cors(&CorsConfig::default()))
+        .layer(RequestValidationLayer::new(&SecurityConfig::default()))
         .into_inner()
 }
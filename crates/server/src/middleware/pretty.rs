@@ -0,0 +1,43 @@
+use axum::{extract::Request, middleware::Next, response::Response};
+
+use crate::shared::data::PRETTY;
+
+/// Header alternative to the `?pretty=true` query parameter
+pub const X_PRETTY: &str = "x-pretty";
+
+/// Indent JSON responses when the request asks for it via `?pretty=true` or `X-Pretty: true`
+pub async fn pretty(request: Request, next: Next) -> Response {
+    let pretty = wants_pretty(&request);
+    PRETTY.scope(pretty, next.run(request)).await
+}
+
+fn wants_pretty(request: &Request) -> bool {
+    let enabled = |value: &str| matches!(value, "" | "1" | "true");
+    let by_query = request.uri().query().is_some_and(|query| {
+        query.split('&').any(|pair| match pair.split_once('=') {
+            Some((key, value)) => key == "pretty" && enabled(value),
+            None => pair == "pretty",
+        })
+    });
+    let by_header = request.headers().get(X_PRETTY).and_then(|value| value.to_str().ok()).is_some_and(enabled);
+    by_query || by_header
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+
+    use super::*;
+
+    #[test]
+    fn test_pretty_flag_sources() {
+        let request = |uri: &str| Request::get(uri).body(Body::empty()).unwrap();
+        assert!(wants_pretty(&request("/chain/last?pretty=true")));
+        assert!(wants_pretty(&request("/chain/last?limit=5&pretty")));
+        assert!(!wants_pretty(&request("/chain/last?pretty=false")));
+        assert!(!wants_pretty(&request("/chain/last")));
+
+        let request = Request::get("/chain/last").header(X_PRETTY, "1").body(Body::empty()).unwrap();
+        assert!(wants_pretty(&request));
+    }
+}
@@ -0,0 +1,89 @@
+use std::{sync::atomic::Ordering, time::Instant};
+
+use axum::{extract::Request, middleware::Next, response::Response};
+
+use crate::shared::metrics::METRICS;
+
+/// When the outermost layer first saw a request
+#[derive(Debug, Clone, Copy)]
+struct Arrival(Instant);
+
+/// Outermost: count the request as in flight until its response is produced, and stamp its arrival
+/// for [`handler_start`]
+pub async fn arrival(mut request: Request, next: Next) -> Response {
+    request.extensions_mut().insert(Arrival(Instant::now()));
+    let _in_flight = InFlight::enter();
+    next.run(request).await
+}
+
+/// Innermost: record how long the request took to get through every layer above to its handler, which
+/// grows when the server is overloaded rather than when handlers are slow
+pub async fn handler_start(request: Request, next: Next) -> Response {
+    if let Some(Arrival(at)) = request.extensions().get() {
+        METRICS.request_queue_seconds.observe(at.elapsed());
+    }
+    next.run(request).await
+}
+
+/// Leaves the in-flight count when dropped, so cancelled requests leave it too
+struct InFlight;
+
+impl InFlight {
+    fn enter() -> Self {
+        METRICS.requests_in_flight.fetch_add(1, Ordering::Relaxed);
+        Self
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        METRICS.requests_in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{Router, body::Body, middleware::from_fn, routing::get};
+    use tokio::sync::Semaphore;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_in_flight_rises_during_a_slow_handler() {
+        let gate = Arc::new(Semaphore::new(0));
+        let held = gate.clone();
+        let router = Router::new()
+            .route(
+                "/slow",
+                get(move || {
+                    let held = held.clone();
+                    async move {
+                        let _ = held.acquire().await;
+                        "done"
+                    }
+                }),
+            )
+            .layer(from_fn(handler_start))
+            .layer(from_fn(arrival));
+        // Deltas, as the counters are process-wide
+        let queued = METRICS.request_queue_seconds.count();
+        let in_flight = METRICS.requests_in_flight.load(Ordering::Relaxed);
+
+        let running = tokio::spawn(router.oneshot(Request::get("/slow").body(Body::empty()).unwrap()));
+        tokio::time::timeout(std::time::Duration::from_secs(1), async {
+            while METRICS.requests_in_flight.load(Ordering::Relaxed) == in_flight {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("the slow request was never counted in flight");
+        assert!(METRICS.request_queue_seconds.count() > queued);
+
+        gate.add_permits(1);
+        assert!(running.await.unwrap().unwrap().status().is_success());
+        assert_eq!(METRICS.requests_in_flight.load(Ordering::Relaxed), in_flight);
+    }
+}
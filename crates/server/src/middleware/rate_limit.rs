@@ -0,0 +1,194 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicU32, AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http::{HeaderValue, header::RETRY_AFTER};
+
+use crate::error::Error;
+
+/// Clients tracked before buckets that have refilled completely are forgotten
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Token bucket per client, by IP unless keyed otherwise: `max_requests` in a burst, refilled evenly over the
+/// window. Clones share the buckets and the limit, which [`RateLimiter::set_limit`] changes live.
+#[derive(Debug, Clone)]
+pub struct RateLimiter<K = IpAddr> {
+    /// `0` lets every request through
+    max_requests: Arc<AtomicU32>,
+    window_millis: Arc<AtomicU64>,
+    buckets: Arc<Mutex<HashMap<K, Bucket>>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl<K: Eq + Hash> RateLimiter<K> {
+    /// `per_minute` requests a minute; `0` disables the limit
+    pub fn per_minute(per_minute: u32) -> Self {
+        Self::new(per_minute, Duration::from_secs(60))
+    }
+
+    pub fn new(max_requests: u32, window: Duration) -> Self {
+        let limiter = Self {
+            max_requests: Arc::default(),
+            window_millis: Arc::default(),
+            buckets: Arc::default(),
+        };
+        limiter.set_limit(max_requests, window);
+        limiter
+    }
+
+    /// Requests allowed per window, and the window
+    pub fn limit(&self) -> (u32, Duration) {
+        let window = Duration::from_millis(self.window_millis.load(Ordering::Relaxed));
+        (self.max_requests.load(Ordering::Relaxed), window)
+    }
+
+    /// Change the limit of every client from their next request on. Buckets keep their tokens,
+    /// capped at the new burst; [`RateLimiter::clear`] gives everyone a full one.
+    pub fn set_limit(&self, max_requests: u32, window: Duration) {
+        let window_millis = u64::try_from(window.as_millis()).unwrap_or(u64::MAX).max(1);
+        self.window_millis.store(window_millis, Ordering::Relaxed);
+        self.max_requests.store(max_requests, Ordering::Relaxed);
+    }
+
+    /// Forget every client's bucket
+    pub fn clear(&self) {
+        self.buckets.lock().unwrap_or_else(PoisonError::into_inner).clear();
+    }
+
+    /// Forget the bucket of `client`, which will not come back
+    pub fn forget(&self, client: &K) {
+        self.buckets.lock().unwrap_or_else(PoisonError::into_inner).remove(client);
+    }
+
+    /// Spend one request of `client`; otherwise how long until it may retry
+    pub fn acquire(&self, client: K) -> Result<(), Duration> {
+        let (max_requests, window) = self.limit();
+        if max_requests == 0 {
+            return Ok(());
+        }
+        let capacity = f64::from(max_requests);
+        let per_sec = capacity / window.as_secs_f64();
+        let now = Instant::now();
+        let refill = |bucket: &mut Bucket| {
+            let elapsed = now.saturating_duration_since(bucket.refilled_at).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * per_sec).min(capacity);
+            bucket.refilled_at = now;
+        };
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        if buckets.len() >= MAX_TRACKED_CLIENTS && !buckets.contains_key(&client) {
+            buckets.retain(|_, bucket| {
+                refill(bucket);
+                bucket.tokens < capacity
+            });
+        }
+        let bucket = buckets.entry(client).or_insert(Bucket { tokens: capacity, refilled_at: now });
+        refill(bucket);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_sec))
+        }
+    }
+}
+
+/// Answers `429` with `Retry-After` once the caller's IP is out of requests.
+///
+/// Callers without `ConnectInfo` (tests, in-process services) share one bucket.
+pub async fn rate_limit(State(limiter): State<RateLimiter>, request: Request, next: Next) -> Response {
+    let client = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED), |ConnectInfo(addr)| addr.ip());
+    match limiter.acquire(client) {
+        Ok(()) => next.run(request).await,
+        Err(wait) => {
+            let retry_after = wait.as_secs() + 1;
+            let (max_requests, window) = limiter.limit();
+            let message =
+                format!("at most {max_requests} requests per {}s, retry in {retry_after}s", window.as_secs());
+            let mut response = Error::TooManyRequests(message).into_response();
+            response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(retry_after));
+            response
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, body::Body, middleware::from_fn_with_state, routing::post};
+    use http::StatusCode;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[test]
+    fn test_each_client_has_its_own_bucket() {
+        let limiter = RateLimiter::per_minute(2);
+        let first: IpAddr = "10.0.0.1".parse().unwrap();
+        assert!(limiter.acquire(first).is_ok());
+        assert!(limiter.acquire(first).is_ok());
+        let wait = limiter.acquire(first).unwrap_err();
+        assert!(wait > Duration::from_secs(25) && wait <= Duration::from_secs(30), "{wait:?}");
+
+        assert!(limiter.acquire("10.0.0.2".parse().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_new_limit_applies_to_the_next_request() {
+        let limiter = RateLimiter::per_minute(1);
+        let client: IpAddr = "10.0.0.1".parse().unwrap();
+        assert!(limiter.acquire(client).is_ok());
+        assert!(limiter.acquire(client).is_err());
+
+        // Raising the limit does not hand out tokens by itself; clearing does
+        limiter.set_limit(3, Duration::from_secs(60));
+        assert!(limiter.acquire(client).is_err());
+        limiter.clear();
+        for _ in 0..3 {
+            assert!(limiter.acquire(client).is_ok());
+        }
+        assert!(limiter.acquire(client).is_err());
+
+        // Lowering it caps the tokens a client still holds: 2 left of 3, then 1 under the new limit
+        limiter.clear();
+        assert!(limiter.acquire(client).is_ok());
+        limiter.set_limit(1, Duration::from_secs(60));
+        assert!(limiter.acquire(client).is_ok());
+        assert!(limiter.acquire(client).is_err());
+
+        limiter.set_limit(0, Duration::from_secs(60));
+        assert!(limiter.acquire(client).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_client_gets_429() {
+        let router = Router::new()
+            .route("/transaction", post(|| async { "submitted" }))
+            .layer(from_fn_with_state(RateLimiter::per_minute(1), rate_limit));
+        let request = || Request::post("/transaction").body(Body::empty()).unwrap();
+
+        assert_eq!(router.clone().oneshot(request()).await.unwrap().status(), StatusCode::OK);
+        let response = router.oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key(RETRY_AFTER));
+    }
+}
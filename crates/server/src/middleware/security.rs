@@ -0,0 +1,283 @@
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use axum::response::{IntoResponse, Response};
+use futures::future::{BoxFuture, Either, FutureExt, Ready, ready};
+use http::{
+    HeaderName, HeaderValue, Method, Request,
+    header::{CONTENT_TYPE, SERVER, STRICT_TRANSPORT_SECURITY, USER_AGENT, X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS},
+};
+use tower::{Layer, Service};
+
+use crate::{
+    ctx::config::SecurityConfig,
+    error::{Error, Result},
+};
+
+#[derive(Debug)]
+struct Rules {
+    /// Lowercased media types, without parameters
+    allowed_content_types: Vec<String>,
+    max_user_agent_length: usize,
+}
+
+impl Rules {
+    fn check<B>(&self, request: &Request<B>) -> Result<()> {
+        let headers = request.headers();
+
+        if let Some(user_agent) = headers.get(USER_AGENT) {
+            if user_agent.len() > self.max_user_agent_length {
+                return Err(Error::BadRequest(format!(
+                    "User-Agent exceeds {} bytes",
+                    self.max_user_agent_length
+                )));
+            }
+        }
+
+        if matches!(*request.method(), Method::GET | Method::HEAD) {
+            return Ok(());
+        }
+        let Some(content_type) = headers.get(CONTENT_TYPE) else {
+            return Ok(());
+        };
+        let media_type = content_type.to_str().unwrap_or_default();
+        let media_type = media_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+        if self.allowed_content_types.contains(&media_type) {
+            Ok(())
+        } else {
+            Err(Error::UnsupportedMediaType(media_type))
+        }
+    }
+}
+
+/// Rejects requests with an oversized `User-Agent` (400) or a body
+/// content type outside `SecurityConfig::allowed_content_types` (415)
+#[derive(Debug, Clone)]
+pub struct RequestValidationLayer {
+    rules: Arc<Rules>,
+}
+
+impl RequestValidationLayer {
+    pub fn new(config: &SecurityConfig) -> Self {
+        let allowed_content_types =
+            config.allowed_content_types.iter().map(|media_type| media_type.trim().to_ascii_lowercase()).collect();
+        let rules = Rules { allowed_content_types, max_user_agent_length: config.max_user_agent_length };
+        Self { rules: Arc::new(rules) }
+    }
+}
+
+impl<S> Layer<S> for RequestValidationLayer {
+    type Service = RequestValidation<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestValidation { inner, rules: self.rules.clone() }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestValidation<S> {
+    inner: S,
+    rules: Arc<Rules>,
+}
+
+impl<S, B> Service<Request<B>> for RequestValidation<S>
+where
+    S: Service<Request<B>, Response = Response>,
+{
+    type Error = S::Error;
+    type Future = Either<Ready<Result<Response, S::Error>>, S::Future>;
+    type Response = Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        match self.rules.check(&request) {
+            Ok(()) => Either::Right(self.inner.call(request)),
+            Err(err) => Either::Left(ready(Ok(err.into_response()))),
+        }
+    }
+}
+
+/// Headers stamped on every response; a `None` value removes the header
+#[derive(Debug)]
+struct ResponseHeaders(Vec<(HeaderName, Option<HeaderValue>)>);
+
+impl ResponseHeaders {
+    fn apply(&self, response: &mut Response) {
+        let headers = response.headers_mut();
+        for (name, value) in &self.0 {
+            match value {
+                Some(value) => {
+                    headers.insert(name.clone(), value.clone());
+                },
+                None => {
+                    headers.remove(name);
+                },
+            }
+        }
+    }
+}
+
+/// Adds `X-Content-Type-Options`, `X-Frame-Options`, the configured `Server` header
+/// and, when served over TLS, `Strict-Transport-Security`; each can be turned off in [`SecurityConfig`]
+#[derive(Debug, Clone)]
+pub struct SecurityHeadersLayer {
+    headers: Arc<ResponseHeaders>,
+}
+
+impl SecurityHeadersLayer {
+    pub fn new(config: &SecurityConfig, tls: bool) -> Self {
+        let mut headers = Vec::new();
+        if config.content_type_options {
+            headers.push((X_CONTENT_TYPE_OPTIONS, Some(HeaderValue::from_static("nosniff"))));
+        }
+        if config.frame_options {
+            headers.push((X_FRAME_OPTIONS, Some(HeaderValue::from_static("DENY"))));
+        }
+        // An empty or unrepresentable value removes whatever `Server` an inner layer set
+        let server = HeaderValue::from_str(config.server_header.trim()).ok().filter(|value| !value.is_empty());
+        headers.push((SERVER, server));
+        if tls && config.hsts_max_age_secs > 0 {
+            let hsts = HeaderValue::from_str(&format!("max-age={}", config.hsts_max_age_secs)).ok();
+            headers.push((STRICT_TRANSPORT_SECURITY, hsts));
+        }
+        Self { headers: Arc::new(ResponseHeaders(headers)) }
+    }
+}
+
+impl<S> Layer<S> for SecurityHeadersLayer {
+    type Service = SecurityHeaders<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SecurityHeaders { inner, headers: self.headers.clone() }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SecurityHeaders<S> {
+    inner: S,
+    headers: Arc<ResponseHeaders>,
+}
+
+impl<S, B> Service<Request<B>> for SecurityHeaders<S>
+where
+    S: Service<Request<B>, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+    type Response = Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let headers = self.headers.clone();
+        self.inner
+            .call(request)
+            .map(move |result| {
+                result.map(|mut response| {
+                    headers.apply(&mut response);
+                    response
+                })
+            })
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        Router,
+        body::Body,
+        routing::{get, post},
+    };
+    use http::StatusCode;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn router() -> Router {
+        Router::new()
+            .route("/grpc", post(|| async { "ok" }).get(|| async { "ok" }))
+            .layer(RequestValidationLayer::new(&SecurityConfig::default()))
+    }
+
+    fn request(method: Method, content_type: &str) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .uri("/grpc")
+            .header(CONTENT_TYPE, content_type)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_content_type_is_rejected() {
+        let response = router().oneshot(request(Method::POST, "application/xml")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let response = router().oneshot(request(Method::POST, "application/json; charset=utf-8")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Content type is not checked for reads
+        let response = router().oneshot(request(Method::GET, "application/xml")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_user_agent_is_rejected() {
+        let request = Request::get("/grpc").header(USER_AGENT, "x".repeat(2048)).body(Body::empty()).unwrap();
+        let response = router().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    fn headers_router(config: &SecurityConfig, tls: bool) -> Router {
+        Router::new()
+            .route("/grpc", get(|| async { ([(SERVER, "axum")], "ok") }))
+            .layer(SecurityHeadersLayer::new(config, tls))
+    }
+
+    #[tokio::test]
+    async fn test_security_headers_are_present() {
+        let response = headers_router(&SecurityConfig::default(), true)
+            .oneshot(Request::get("/grpc").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let headers = response.headers();
+        assert_eq!(headers[X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert_eq!(headers[X_FRAME_OPTIONS], "DENY");
+        assert_eq!(headers[SERVER], "tondi-listener");
+        assert_eq!(headers[STRICT_TRANSPORT_SECURITY], "max-age=31536000");
+
+        // Also on rejections of inner layers
+        let response = headers_router(&SecurityConfig::default(), false)
+            .oneshot(Request::get("/missing").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[X_FRAME_OPTIONS], "DENY");
+        assert!(!response.headers().contains_key(STRICT_TRANSPORT_SECURITY));
+    }
+
+    #[tokio::test]
+    async fn test_security_headers_can_be_disabled() {
+        let config = SecurityConfig {
+            server_header: String::new(),
+            content_type_options: false,
+            frame_options: false,
+            hsts_max_age_secs: 0,
+            ..SecurityConfig::default()
+        };
+        let response =
+            headers_router(&config, true).oneshot(Request::get("/grpc").body(Body::empty()).unwrap()).await.unwrap();
+        let headers = response.headers();
+        for name in [X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS, SERVER, STRICT_TRANSPORT_SECURITY] {
+            assert!(!headers.contains_key(&name), "{name} should be absent");
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use std::{
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use axum::{extract::FromRequestParts, http::request::Parts};
+use http::{Request, StatusCode};
+use tower::{Layer, Service, layer::util::Stack};
+use tower_http::timeout::TimeoutLayer;
+
+use crate::error::{Error, Result};
+
+/// Fail requests that run longer than `secs` with `504 Gateway Timeout`,
+/// recording the instant they will fail at as their [`Deadline`]
+pub fn timeout(secs: u64) -> Stack<DeadlineLayer, TimeoutLayer> {
+    let budget = Duration::from_secs(secs);
+    Stack::new(DeadlineLayer { budget }, TimeoutLayer::with_status_code(StatusCode::GATEWAY_TIMEOUT, budget))
+}
+
+/// When the request timeout fires; unbounded outside a [`timeout`] layer.
+/// Upstream calls are bounded by it so no node work outlives the client waiting for it. The node client
+/// sends every call over one stream, so the deadline can't travel as a per-call `grpc-timeout`; the pool's
+/// call timeout caps calls made outside a request instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Deadline(Option<Instant>);
+
+impl Deadline {
+    pub fn after(budget: Duration) -> Self {
+        Self(Some(Instant::now() + budget))
+    }
+
+    /// Time left, `None` when unbounded
+    pub fn remaining(&self) -> Option<Duration> {
+        self.0.map(|at| at.saturating_duration_since(Instant::now()))
+    }
+
+    /// Run an upstream call, failing with `504` once the deadline passes
+    pub async fn run<T, E>(&self, call: impl Future<Output = Result<T, E>>) -> Result<T>
+    where
+        E: Into<Error>,
+    {
+        let Some(remaining) = self.remaining() else {
+            return call.await.map_err(Into::into);
+        };
+        match tokio::time::timeout(remaining, call).await {
+            Ok(result) => result.map_err(Into::into),
+            Err(_) => Err(Error::GatewayTimeout(format!("no response within {}ms", remaining.as_millis()))),
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Deadline {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<Deadline>().copied().unwrap_or_default())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DeadlineLayer {
+    budget: Duration,
+}
+
+impl<S> Layer<S> for DeadlineLayer {
+    type Service = DeadlineService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        DeadlineService { inner, budget: self.budget }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DeadlineService<S> {
+    inner: S,
+    budget: Duration,
+}
+
+impl<S, B> Service<Request<B>> for DeadlineService<S>
+where
+    S: Service<Request<B>>,
+{
+    type Error = S::Error;
+    type Future = S::Future;
+    type Response = S::Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<B>) -> Self::Future {
+        // The tighter deadline wins when timeout layers are nested
+        let deadline = Deadline::after(self.budget);
+        let current = request.extensions().get::<Deadline>().copied().unwrap_or_default();
+        if current.remaining().is_none_or(|remaining| remaining > self.budget) {
+            request.extensions_mut().insert(deadline);
+        }
+        self.inner.call(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, body::Body, routing::get};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn slow() -> &'static str {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        "done"
+    }
+
+    #[tokio::test]
+    async fn test_slow_handler_in_short_group_times_out() {
+        let app = Router::new()
+            .route("/lookup", get(slow))
+            .layer(timeout(0))
+            .merge(Router::new().route("/export", get(slow)).layer(timeout(60)));
+
+        let request = Request::builder().uri("/lookup").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+
+        let request = Request::builder().uri("/export").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    /// Stand-in for an upstream node that never answers in time
+    async fn slow_node() -> Result<u64, Error> {
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        Ok(42)
+    }
+
+    #[tokio::test]
+    async fn test_slow_upstream_call_is_cut_at_the_deadline() {
+        let err = Deadline::after(Duration::from_millis(10)).run(slow_node()).await.unwrap_err();
+        assert!(matches!(err, Error::GatewayTimeout(_)));
+        assert_eq!(err.status_code(), StatusCode::GATEWAY_TIMEOUT);
+
+        let fast = Deadline::after(Duration::from_secs(5)).run(async { Ok::<_, Error>(7) }).await;
+        assert_eq!(fast.unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_handler_sees_the_request_deadline() {
+        async fn remaining(deadline: Deadline) -> String {
+            deadline.remaining().map(|left| left.as_secs()).unwrap_or(u64::MAX).to_string()
+        }
+        let app = Router::new().route("/", get(remaining)).layer(timeout(30));
+
+        let response = app.oneshot(Request::builder().uri("/").body(Body::empty()).unwrap()).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let left: u64 = std::str::from_utf8(&body).unwrap().parse().unwrap();
+        assert!((29..=30).contains(&left));
+    }
+}
@@ -0,0 +1,110 @@
+use axum::extract::State;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ctx::store::{Db, run_store},
+    shared::{address::Address, data::Data},
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressBalance {
+    pub address: String,
+    /// Sum of the indexed outputs paying to the address that no indexed input spends
+    pub balance: i64,
+}
+
+/// Balance of one address; addresses without unspent outputs have a zero balance
+pub async fn balance(address: Address, State(store): Db) -> Data<AddressBalance> {
+    let address = address.to_string();
+    let balance = run_store(store, {
+        let address = address.clone();
+        move |store| store.balance_for_address(&address)
+    })
+    .await?;
+    Ok(AddressBalance { address, balance }.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tondi_addresses::{Prefix, Version};
+    use tondi_listener_db::{
+        models::transaction::{Tx, TxIn, TxOu},
+        schema::tyext::subnetwork::SubnetworkId,
+    };
+    use tondi_rpc_core::RpcAddress;
+
+    use super::*;
+    use crate::ctx::store::{MemoryStore, Store};
+
+    fn output(address: &str, index: i16, amount: i64) -> TxOu {
+        TxOu {
+            transaction_id: "01".repeat(32).into(),
+            index,
+            amount,
+            script_public_key: Vec::new(),
+            script_public_key_address: address.to_string(),
+            block_time: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_balance_sums_the_outputs_of_the_address() {
+        let paid = RpcAddress::new(Prefix::Mainnet, Version::PubKey, &[1; 32]).to_string();
+        let other = RpcAddress::new(Prefix::Mainnet, Version::PubKey, &[2; 32]).to_string();
+        let store = MemoryStore::default();
+        let outputs = vec![output(&paid, 0, 1_500), output(&other, 1, 7), output(&paid, 2, 500)];
+        let transaction = Tx {
+            transaction_id: "01".repeat(32).into(),
+            subnetwork_id: SubnetworkId::NATIVE,
+            hash: "02".repeat(32).into(),
+            mass: None,
+            payload: None,
+            block_time: 0,
+        };
+        store.insert_transaction(transaction, Vec::new(), outputs);
+        let store: Arc<dyn Store> = Arc::new(store);
+
+        let balance_of = |address: &str| {
+            let (address, store) = (address.parse::<Address>().unwrap(), store.clone());
+            async move { balance(address, State(store)).await.unwrap().data.unwrap().balance }
+        };
+        assert_eq!(balance_of(&paid).await, 2_000);
+        let unknown = RpcAddress::new(Prefix::Mainnet, Version::PubKey, &[3; 32]).to_string();
+        assert_eq!(balance_of(&unknown).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_spent_outputs_do_not_count() {
+        let paid = RpcAddress::new(Prefix::Mainnet, Version::PubKey, &[1; 32]).to_string();
+        let store = MemoryStore::default();
+        let transaction = |id: &str| Tx {
+            transaction_id: id.repeat(32).into(),
+            subnetwork_id: SubnetworkId::NATIVE,
+            hash: id.repeat(32).into(),
+            mass: None,
+            payload: None,
+            block_time: 0,
+        };
+        store.insert_transaction(transaction("01"), Vec::new(), vec![output(&paid, 0, 1_500), output(&paid, 1, 500)]);
+        // A later transaction spends the first output
+        let spend = TxIn {
+            transaction_id: "03".repeat(32).into(),
+            index: 0,
+            previous_outpoint_hash: "01".repeat(32).into(),
+            previous_outpoint_index: 0,
+            signature_script: Vec::new(),
+            sig_op_count: 1,
+            block_time: 1,
+            previous_outpoint_script: Vec::new(),
+            previous_outpoint_amount: 1_500,
+        };
+        store.insert_transaction(transaction("03"), vec![spend], Vec::new());
+        let store: Arc<dyn Store> = Arc::new(store);
+
+        let balance = balance(paid.parse().unwrap(), State(store)).await.unwrap().data.unwrap().balance;
+        assert_eq!(balance, 500);
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+
+use axum::extract::State;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ctx::{
+        config::Config,
+        store::{Db, run_store},
+    },
+    error::{Error, Result},
+    shared::{address::Address, data::Data, json::Json},
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BalancesRequest {
+    pub addresses: Vec<String>,
+}
+
+/// Balances of many addresses from one grouped query over the unspent outputs; unknown addresses map to `0`
+pub async fn post(
+    State(config): State<&'static Config>,
+    State(store): Db,
+    Json(request): Json<BalancesRequest>,
+) -> Data<BTreeMap<String, i64>> {
+    if request.addresses.len() > config.address.max_balance_addresses {
+        return Err(Error::BadRequest(format!(
+            "{} addresses exceed the maximum of {}",
+            request.addresses.len(),
+            config.address.max_balance_addresses
+        )));
+    }
+    let network = config.upstream_network().map_err(|e| Error::InternalServerError(e.to_string()))?;
+    let addresses = request
+        .addresses
+        .iter()
+        .map(|address| {
+            let address: Address = address.parse()?;
+            address.check_network(network)?;
+            Ok(address.to_string())
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let found = run_store(store, {
+        let addresses = addresses.clone();
+        move |store| store.balances_for_addresses(&addresses)
+    })
+    .await?;
+    Ok(with_zero_balances(addresses, found).into())
+}
+
+/// Every requested address, with the balances the query found
+fn with_zero_balances(addresses: Vec<String>, found: Vec<(String, i64)>) -> BTreeMap<String, i64> {
+    let mut balances: BTreeMap<_, _> = addresses.into_iter().map(|address| (address, 0)).collect();
+    balances.extend(found);
+    balances
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tondi_addresses::{Prefix, Version};
+    use tondi_listener_db::{
+        models::transaction::{Tx, TxIn, TxOu},
+        schema::tyext::subnetwork::SubnetworkId,
+    };
+    use tondi_rpc_core::RpcAddress;
+
+    use super::*;
+    use crate::ctx::store::{MemoryStore, Store};
+
+    #[test]
+    fn test_known_and_unknown_addresses_are_all_listed() {
+        let addresses = vec!["tondi:known".to_string(), "tondi:unknown".to_string(), "tondi:known".to_string()];
+        let found = vec![("tondi:known".to_string(), 1_500)];
+
+        let balances = with_zero_balances(addresses, found);
+        assert_eq!(balances.len(), 2);
+        assert_eq!(balances["tondi:known"], 1_500);
+        assert_eq!(balances["tondi:unknown"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_spent_outputs_are_left_out_of_the_balances() {
+        let config: &'static Config = Box::leak(Box::default());
+        let prefix = Prefix::from(config.upstream_network().unwrap());
+        let address = |byte| RpcAddress::new(prefix, Version::PubKey, &[byte; 32]).to_string();
+        let (paid, spent, unknown) = (address(1), address(2), address(3));
+        let output = |address: &str, index, amount| TxOu {
+            transaction_id: "01".repeat(32).into(),
+            index,
+            amount,
+            script_public_key: Vec::new(),
+            script_public_key_address: address.to_string(),
+            block_time: 0,
+        };
+        let transaction = |id: &str| Tx {
+            transaction_id: id.repeat(32).into(),
+            subnetwork_id: SubnetworkId::NATIVE,
+            hash: id.repeat(32).into(),
+            mass: None,
+            payload: None,
+            block_time: 0,
+        };
+        let spend = TxIn {
+            transaction_id: "02".repeat(32).into(),
+            index: 0,
+            previous_outpoint_hash: "01".repeat(32).into(),
+            previous_outpoint_index: 1,
+            signature_script: Vec::new(),
+            sig_op_count: 1,
+            block_time: 1,
+            previous_outpoint_script: Vec::new(),
+            previous_outpoint_amount: 700,
+        };
+        let store = MemoryStore::default();
+        store.insert_transaction(transaction("01"), Vec::new(), vec![output(&paid, 0, 1_500), output(&spent, 1, 700)]);
+        store.insert_transaction(transaction("02"), vec![spend], Vec::new());
+        let store: Arc<dyn Store> = Arc::new(store);
+
+        let request = BalancesRequest { addresses: vec![paid.clone(), spent.clone(), unknown.clone()] };
+        let balances = post(State(config), State(store), Json(request)).await.unwrap().data.unwrap();
+        assert_eq!(balances[&paid], 1_500);
+        assert_eq!(balances[&spent], 0);
+        assert_eq!(balances[&unknown], 0);
+    }
+}
@@ -0,0 +1,3 @@
+pub mod _address_;
+pub mod balances;
+pub mod search;
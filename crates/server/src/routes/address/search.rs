@@ -0,0 +1,109 @@
+use axum::extract::{Query, State};
+use diesel::prelude::*;
+use serde::Deserialize;
+use tondi_listener_db::schema::table::TTxOu;
+
+use crate::{
+    ctx::{
+        config::{AddressConfig, Config},
+        pg_database::{PgDb, run_blocking},
+    },
+    error::{Error, Result},
+    shared::data::Data,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    prefix: String,
+    /// Capped at `address.max_search_results`, which is also the default
+    limit: Option<usize>,
+}
+
+/// Distinct indexed addresses starting with `prefix`, sorted, for autocomplete
+pub async fn get(
+    Query(query): Query<SearchQuery>,
+    State(config): State<&'static Config>,
+    State(db): PgDb<'static>,
+) -> Data<Vec<String>> {
+    let limit = query.limit(&config.address)?;
+    let pattern = like_prefix(query.prefix.trim());
+    let addresses = run_blocking(db, move |conn| {
+        Ok(TTxOu::table
+            .filter(TTxOu::script_public_key_address.like(pattern))
+            .select(TTxOu::script_public_key_address)
+            .distinct()
+            .order(TTxOu::script_public_key_address.asc())
+            .limit(limit)
+            .load::<String>(conn)?)
+    })
+    .await?;
+    Ok(addresses.into())
+}
+
+impl SearchQuery {
+    /// Rows to return; prefixes shorter than `min_search_prefix_length` would scan most of the index
+    fn limit(&self, config: &AddressConfig) -> Result<i64> {
+        let length = self.prefix.trim().chars().count();
+        if length < config.min_search_prefix_length {
+            return Err(Error::BadRequest(format!(
+                "`prefix` must have at least {} characters",
+                config.min_search_prefix_length
+            )));
+        }
+        let limit = match self.limit {
+            Some(0) => return Err(Error::BadRequest("`limit` must be positive".to_string())),
+            Some(limit) => limit.min(config.max_search_results),
+            None => config.max_search_results,
+        };
+        Ok(i64::try_from(limit).unwrap_or(i64::MAX))
+    }
+}
+
+/// `LIKE` pattern matching values that start with `prefix` literally
+fn like_prefix(prefix: &str) -> String {
+    let mut pattern = String::with_capacity(prefix.len() + 1);
+    for c in prefix.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(prefix: &str, limit: Option<usize>) -> SearchQuery {
+        SearchQuery { prefix: prefix.to_string(), limit }
+    }
+
+    #[test]
+    fn test_prefix_is_matched_literally() {
+        assert_eq!(like_prefix("tondi:qz"), "tondi:qz%");
+        assert_eq!(like_prefix("tondi:q_%"), "tondi:q\\_\\%%");
+        assert_eq!(like_prefix("a\\b"), "a\\\\b%");
+    }
+
+    #[test]
+    fn test_short_prefix_is_rejected() {
+        let config = AddressConfig::default();
+        let short = "t".repeat(config.min_search_prefix_length - 1);
+        assert!(matches!(query(&short, None).limit(&config), Err(Error::BadRequest(_))));
+        // Surrounding whitespace does not count
+        assert!(matches!(query(&format!("  {short}  "), None).limit(&config), Err(Error::BadRequest(_))));
+    }
+
+    #[test]
+    fn test_limit_is_capped() {
+        let config = AddressConfig::default();
+        let prefix = "tondi:qypq";
+        let max = i64::try_from(config.max_search_results).unwrap();
+        assert_eq!(query(prefix, None).limit(&config).unwrap(), max);
+        assert_eq!(query(prefix, Some(3)).limit(&config).unwrap(), 3);
+        assert_eq!(query(prefix, Some(10_000)).limit(&config).unwrap(), max);
+        assert!(query(prefix, Some(0)).limit(&config).is_err());
+    }
+}
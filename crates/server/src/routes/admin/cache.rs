@@ -0,0 +1,82 @@
+use std::sync::LazyLock;
+
+use axum::extract::Path;
+use serde::Serialize;
+use tondi_listener_library::log::warn;
+
+use crate::{
+    error::Error,
+    routes::{block, daa_timestamp, fee_estimate, headers, mempool, node, peers, stats, transaction, utxo},
+    shared::{cache::CACHES, data::Data},
+};
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Flushed {
+    /// Entries dropped, expired ones included
+    pub evicted: u64,
+}
+
+/// Create the route caches up front, so each name is flushable before its route first runs
+pub fn register_route_caches() {
+    LazyLock::force(&block::color::COLORS);
+    LazyLock::force(&block::status::STATUSES);
+    LazyLock::force(&daa_timestamp::ESTIMATES);
+    LazyLock::force(&fee_estimate::EXPERIMENTAL);
+    LazyLock::force(&headers::HEADERS);
+    LazyLock::force(&mempool::SIZE);
+    LazyLock::force(&node::status::STATUS);
+    LazyLock::force(&peers::CONNECTED);
+    LazyLock::force(&peers::KNOWN);
+    LazyLock::force(&stats::SUMMARY);
+    LazyLock::force(&transaction::_id_::MEMPOOL_MISSES);
+    LazyLock::force(&utxo::RETURN_ADDRESSES);
+}
+
+/// Empty every registered cache, so the next requests reach the node and the database again
+pub async fn flush_all() -> Data<Flushed> {
+    let evicted = CACHES.flush_all();
+    warn!("Admin flushed every cache, {evicted} entries evicted");
+    Ok(Flushed { evicted }.into())
+}
+
+/// Empty the cache `name`, as reported on `/metrics`; 404 when no cache has that name
+pub async fn flush(Path(name): Path<String>) -> Data<Flushed> {
+    let evicted = CACHES.flush(&name).ok_or_else(|| {
+        Error::NotFound(format!("Cache `{name}`, known caches are {}", CACHES.names().join(", ")))
+    })?;
+    warn!("Admin flushed cache {name}, {evicted} entries evicted");
+    Ok(Flushed { evicted }.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use http::StatusCode;
+
+    use super::*;
+    use crate::shared::cache::TtlCache;
+
+    #[tokio::test]
+    async fn test_flush_by_name_answers_the_evicted_count() {
+        let cache = TtlCache::new(Duration::from_secs(60)).named("test_admin_flush");
+        cache.insert("tip", 1);
+        cache.insert("history", 2);
+
+        let flushed = flush(Path("test_admin_flush".to_string())).await.unwrap().data.unwrap();
+        assert_eq!(flushed, Flushed { evicted: 2 });
+        assert_eq!(cache.get(&"tip"), None);
+
+        let unknown = flush(Path("test_admin_unknown".to_string())).await.unwrap_err();
+        assert_eq!(unknown.status_code(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_route_caches_are_known_before_first_use() {
+        register_route_caches();
+        for name in ["block_color", "headers", "utxo_return_address"] {
+            assert!(flush(Path(name.to_string())).await.is_ok(), "{name}");
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use axum::{Extension, extract::Path};
+use tondi_listener_library::log::warn;
+
+use crate::{
+    error::{Error, Result},
+    routes::websocket::{
+        address_index::ConnId,
+        registry::{ConnectionInfo, ConnectionRegistry},
+    },
+    shared::data::Data,
+};
+
+/// Open WebSocket connections with their remote address, connect time and subscribed events
+pub async fn list(Extension(registry): Extension<ConnectionRegistry>) -> Data<Vec<ConnectionInfo>> {
+    Ok(registry.list().into())
+}
+
+/// Close one WebSocket connection with a policy close frame; 404 when it is not open
+pub async fn close(
+    Path(id): Path<ConnId>,
+    Extension(registry): Extension<ConnectionRegistry>,
+) -> Data<ConnectionInfo> {
+    Ok(evict(&registry, id)?.into())
+}
+
+fn evict(registry: &ConnectionRegistry, id: ConnId) -> Result<ConnectionInfo> {
+    let info = registry.close(id).ok_or_else(|| Error::NotFound(format!("WebSocket connection {id}")))?;
+    warn!("Admin closed WebSocket connection {id} from {}", info.remote_address);
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_close_evicts_a_listed_connection() {
+        let registry = ConnectionRegistry::default();
+        let registration = registry.register(3, "10.0.0.1:4000".parse().unwrap());
+        let listed = list(Extension(registry.clone())).await.unwrap().data.unwrap();
+        assert_eq!(listed.iter().map(|info| info.id).collect::<Vec<_>>(), [3]);
+
+        assert_eq!(evict(&registry, 3).unwrap().id, 3);
+        registration.evicted().await;
+        assert_eq!(evict(&registry, 4).unwrap_err().status_code(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,36 @@
+pub mod cache;
+pub mod connections;
+pub mod rate_limit;
+pub mod shutdown;
+
+use axum::{
+    Router,
+    middleware::from_fn_with_state,
+    routing::{delete, get, post},
+};
+
+use crate::{
+    ctx::{Context, config::SecurityConfig},
+    middleware::admin::{AdminToken, require_admin_token},
+};
+
+/// Operator-only routes, mounted under `/admin` only when an admin token is configured
+pub fn router(security: &SecurityConfig) -> Option<Router<Context>> {
+    cache::register_route_caches();
+    guarded(
+        security,
+        Router::new()
+            .route("/shutdown", post(shutdown::post))
+            .route("/rate-limit", post(rate_limit::post))
+            .route("/connections", get(connections::list))
+            .route("/connections/{id}", delete(connections::close))
+            .route("/cache/flush", post(cache::flush_all))
+            .route("/cache/flush/{name}", post(cache::flush)),
+    )
+}
+
+/// Put `router` behind the admin bearer token; `None` when no token is configured
+pub fn guarded(security: &SecurityConfig, router: Router<Context>) -> Option<Router<Context>> {
+    let token = AdminToken::new(security.admin_token.as_deref()?);
+    Some(router.route_layer(from_fn_with_state(token, require_admin_token)))
+}
@@ -0,0 +1,122 @@
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use axum::Extension;
+use serde::{Deserialize, Serialize};
+use tondi_listener_library::log::warn;
+
+use crate::{
+    error::{Error, Result},
+    middleware::rate_limit::RateLimiter,
+    shared::{data::Data, json::Json},
+};
+
+/// Live limiters by group: `submit` for `POST /transaction`, `export` for the streaming routes and each of the
+/// [`RATE_LIMIT_GROUPS`](crate::ctx::config::RATE_LIMIT_GROUPS)
+pub type RateLimiters = Arc<BTreeMap<&'static str, RateLimiter>>;
+
+/// Group changed when an update names none
+const DEFAULT_GROUP: &str = "submit";
+
+/// Changes to one group's rate limit; omitted fields keep their value
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimitUpdate {
+    /// Key of [`RateLimiters`], `submit` when omitted
+    pub group: Option<String>,
+    /// Requests per window and client IP, `0` disabling the limit
+    pub max_requests: Option<u32>,
+    pub window_secs: Option<u64>,
+    /// Give every client a full bucket under the new limit
+    #[serde(default)]
+    pub clear: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimitSettings {
+    pub group: String,
+    pub max_requests: u32,
+    pub window_secs: u64,
+}
+
+/// Adjust a group's rate limit without a restart; answers the limit now in force
+pub async fn post(
+    Extension(limiters): Extension<RateLimiters>,
+    Json(update): Json<RateLimitUpdate>,
+) -> Data<RateLimitSettings> {
+    Ok(apply(&limiters, update)?.into())
+}
+
+/// `400` for an unknown group or an empty window
+fn apply(limiters: &RateLimiters, update: RateLimitUpdate) -> Result<RateLimitSettings> {
+    let group = update.group.as_deref().unwrap_or(DEFAULT_GROUP);
+    let Some(limiter) = limiters.get(group) else {
+        let known = limiters.keys().copied().collect::<Vec<_>>().join(", ");
+        return Err(Error::BadRequest(format!("unknown rate limit group `{group}`, expected one of {known}")));
+    };
+    let (max_requests, window) = limiter.limit();
+    let max_requests = update.max_requests.unwrap_or(max_requests);
+    let window = match update.window_secs {
+        Some(0) => return Err(Error::BadRequest("`windowSecs` must be positive".to_string())),
+        Some(secs) => Duration::from_secs(secs),
+        None => window,
+    };
+    limiter.set_limit(max_requests, window);
+    if update.clear {
+        limiter.clear();
+    }
+    warn!("Rate limit of `{group}` set to {max_requests} per {}s (cleared: {})", window.as_secs(), update.clear);
+    Ok(RateLimitSettings { group: group.to_string(), max_requests, window_secs: window.as_secs() })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use super::*;
+
+    fn limiters(groups: &[&'static str], per_minute: u32) -> RateLimiters {
+        Arc::new(groups.iter().map(|&group| (group, RateLimiter::per_minute(per_minute))).collect())
+    }
+
+    fn settings(group: &str, max_requests: u32) -> RateLimitSettings {
+        RateLimitSettings { group: group.to_string(), max_requests, window_secs: 60 }
+    }
+
+    #[test]
+    fn test_update_changes_the_live_limiter() {
+        let limiters = limiters(&["submit"], 1);
+        let limiter = &limiters["submit"];
+        let client: IpAddr = "10.0.0.1".parse().unwrap();
+        assert!(limiter.acquire(client).is_ok());
+        assert!(limiter.acquire(client).is_err());
+
+        let update = RateLimitUpdate { max_requests: Some(2), clear: true, ..Default::default() };
+        assert_eq!(apply(&limiters, update).unwrap(), settings("submit", 2));
+        assert!(limiter.acquire(client).is_ok());
+        assert!(limiter.acquire(client).is_ok());
+        assert!(limiter.acquire(client).is_err());
+    }
+
+    #[test]
+    fn test_group_selects_the_limiter() {
+        let limiters = limiters(&["submit", "reads", "grpc", "export"], 5);
+        let update = RateLimitUpdate { group: Some("grpc".to_string()), max_requests: Some(1), ..Default::default() };
+        assert_eq!(apply(&limiters, update).unwrap(), settings("grpc", 1));
+        assert_eq!(limiters["grpc"].limit(), (1, Duration::from_secs(60)));
+        for group in ["submit", "reads", "export"] {
+            assert_eq!(limiters[group].limit(), (5, Duration::from_secs(60)), "{group}");
+        }
+
+        let update = RateLimitUpdate { group: Some("blocks".to_string()), ..Default::default() };
+        assert!(matches!(apply(&limiters, update), Err(Error::BadRequest(_))));
+    }
+
+    #[test]
+    fn test_zero_window_is_rejected() {
+        let limiters = limiters(&["submit"], 5);
+        let update = RateLimitUpdate { window_secs: Some(0), ..Default::default() };
+        assert!(matches!(apply(&limiters, update), Err(Error::BadRequest(_))));
+        assert_eq!(limiters["submit"].limit(), (5, Duration::from_secs(60)));
+    }
+}
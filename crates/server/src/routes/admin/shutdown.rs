@@ -0,0 +1,29 @@
+use std::{
+    net::SocketAddr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::{Extension, extract::ConnectInfo};
+use tondi_listener_library::log::warn;
+use tondi_rpc_core::{ShutdownRequest, ShutdownResponse, api::rpc::RpcApi};
+
+use crate::{extensions::client_pool::ClientPool, shared::data::Data};
+
+/// Ask the upstream node to shut down
+///
+/// Routers served without `ConnectInfo` (tests, in-process services) still reach the node; the caller is
+/// logged as unknown.
+pub async fn post(
+    connect_info: Option<Extension<ConnectInfo<SocketAddr>>>,
+    client_pool: ClientPool,
+) -> Data<ShutdownResponse> {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let caller = connect_info
+        .map_or_else(|| "an unknown caller".to_string(), |Extension(ConnectInfo(addr))| addr.to_string());
+    warn!("Admin shutdown requested by {caller} at {timestamp}");
+
+    let client = client_pool.get().await?;
+    let response = client.rpc()?.shutdown_call(None, ShutdownRequest {}).await?;
+    client_pool.record_success();
+    Ok(response.into())
+}
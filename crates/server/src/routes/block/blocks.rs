@@ -0,0 +1,86 @@
+use axum::extract::Query;
+use serde::{Deserialize, Serialize};
+use tondi_rpc_core::{GetBlocksRequest, RpcBlock, RpcError, RpcHash, api::rpc::RpcApi};
+
+use crate::{
+    error::{Error, Result},
+    extensions::client_pool::ClientPool,
+    middleware::timeout::Deadline,
+    shared::{data::Data, hash::parse_hash256},
+};
+
+#[derive(Debug, Default, Deserialize)]
+pub struct BlocksQuery {
+    /// Hash of the block to continue after; the node starts at its pruning point without one
+    low_hash: Option<String>,
+    /// Whether blocks carry their transactions; headers only by default
+    #[serde(default)]
+    include_transactions: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Blocks {
+    pub block_hashes: Vec<RpcHash>,
+    pub blocks: Vec<RpcBlock>,
+}
+
+/// The blocks following `low_hash`, as many as the node returns in one call. Pass the last hash of a
+/// page as the next `low_hash` to walk the DAG in bulk; a `low_hash` the node does not know is a 404.
+pub async fn get(Query(query): Query<BlocksQuery>, client_pool: ClientPool, deadline: Deadline) -> Data<Blocks> {
+    let request = query.request()?;
+    let client = client_pool.get().await?;
+    let call = client.rpc()?.get_blocks_call(None, request);
+    let response = deadline.run(async { Ok::<_, Error>(call.await) }).await?.map_err(|e| match e {
+        RpcError::BlockNotFound(hash) => Error::NotFound(format!("Block {hash}")),
+        e => Error::ServiceUnavailable(e.to_string()),
+    })?;
+    client_pool.record_success();
+    Ok(Blocks { block_hashes: response.block_hashes, blocks: response.blocks }.into())
+}
+
+impl BlocksQuery {
+    fn request(&self) -> Result<GetBlocksRequest> {
+        let low_hash =
+            self.low_hash.as_deref().map(|hash| parse_hash256(hash, "low hash").map(RpcHash::from_bytes)).transpose()?;
+        Ok(GetBlocksRequest { low_hash, include_blocks: true, include_transactions: self.include_transactions })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::extract::Query;
+    use http::{StatusCode, Uri};
+
+    use super::*;
+
+    fn query(query: &str) -> BlocksQuery {
+        let uri: Uri = format!("/blocks?{query}").parse().unwrap();
+        Query::try_from_uri(&uri).unwrap().0
+    }
+
+    #[test]
+    fn test_invalid_low_hash_is_400() {
+        for hash in ["xyz", "ab", &"g".repeat(64)] {
+            let err = query(&format!("low_hash={hash}")).request().unwrap_err();
+            assert_eq!(err.status_code(), StatusCode::BAD_REQUEST, "{hash}");
+        }
+        let request = query(&format!("low_hash={}", "ab".repeat(32))).request().unwrap();
+        assert_eq!(request.low_hash, Some(RpcHash::from_bytes([0xab; 32])));
+        assert_eq!(query("").request().unwrap().low_hash, None);
+    }
+
+    #[test]
+    fn test_transactions_are_left_out_unless_asked_for() {
+        let low_hash = "ab".repeat(32);
+        for (params, include_transactions) in [
+            (format!("low_hash={low_hash}"), false),
+            (format!("low_hash={low_hash}&include_transactions=false"), false),
+            (format!("low_hash={low_hash}&include_transactions=true"), true),
+        ] {
+            let request = query(&params).request().unwrap();
+            assert!(request.include_blocks);
+            assert_eq!(request.include_transactions, include_transactions, "{params}");
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::{sync::LazyLock, time::Duration};
+
+use axum::extract::Path;
+use serde::{Deserialize, Serialize};
+use tondi_rpc_core::{GetCurrentBlockColorRequest, RpcError, RpcHash, api::rpc::RpcApi};
+
+use crate::{
+    error::{Error, Result},
+    extensions::client_pool::ClientPool,
+    middleware::timeout::Deadline,
+    shared::{cache::TtlCache, data::Data, hash::parse_hash256},
+};
+
+/// Colors can change until finality, so they are kept only about a block
+const COLOR_TTL: Duration = Duration::from_secs(1);
+
+pub static COLORS: LazyLock<TtlCache<RpcHash, BlockColor>> =
+    LazyLock::new(|| TtlCache::new(COLOR_TTL).named("block_color"));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockColor {
+    /// Blue in the GHOSTDAG ordering of the chain block that merged it, red otherwise
+    pub blue: bool,
+}
+
+/// GHOSTDAG color of a block as the node sees it now; 404 for blocks the node does not know or that no
+/// chain block has merged yet
+pub async fn get(Path(hash): Path<String>, client_pool: ClientPool, deadline: Deadline) -> Data<BlockColor> {
+    let hash = parse_hash(&hash)?;
+    let color = COLORS
+        .get_or_try_insert_with(hash, || async {
+            let client = client_pool.get().await?;
+            let call = client.rpc()?.get_current_block_color_call(None, GetCurrentBlockColorRequest { hash });
+            let response = deadline.run(async { Ok::<_, Error>(call.await) }).await?.map_err(|e| match e {
+                RpcError::BlockNotFound(_) => Error::NotFound(format!("Block {hash}")),
+                RpcError::MergerNotFound(_) => Error::NotFound(format!("No chain block has merged block {hash} yet")),
+                e => Error::ServiceUnavailable(e.to_string()),
+            })?;
+            client_pool.record_success();
+            Ok::<_, Error>(BlockColor { blue: response.blue })
+        })
+        .await?;
+    Ok(color.into())
+}
+
+fn parse_hash(hash: &str) -> Result<RpcHash> {
+    Ok(RpcHash::from_bytes(parse_hash256(hash, "block hash")?))
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+
+    #[test]
+    fn test_invalid_hash_is_400() {
+        for input in ["", "xyz", &"ab".repeat(10), &"g".repeat(64)] {
+            let err = parse_hash(input).unwrap_err();
+            assert_eq!(err.status_code(), StatusCode::BAD_REQUEST, "{input}");
+            assert!(err.to_string().contains("block hash"), "{err}");
+        }
+        assert_eq!(parse_hash(&"ab".repeat(32)).unwrap(), RpcHash::from_bytes([0xab; 32]));
+    }
+}
@@ -0,0 +1,93 @@
+use std::{sync::LazyLock, time::Duration};
+
+use axum::extract::Path;
+use serde::{Deserialize, Serialize};
+use tondi_rpc_core::{
+    GetBlockDagInfoRequest, GetBlockRequest, GetCurrentBlockColorRequest, RpcError, RpcHash, api::rpc::RpcApi,
+};
+
+use crate::{
+    error::{Error, Result},
+    extensions::client_pool::ClientPool,
+    middleware::timeout::Deadline,
+    shared::{cache::TtlCache, data::Data, hash::parse_hash256},
+};
+
+/// Short enough that a reorg is visible within a block or two
+const STATUS_TTL: Duration = Duration::from_secs(1);
+
+pub static STATUSES: LazyLock<TtlCache<RpcHash, BlockStatus>> =
+    LazyLock::new(|| TtlCache::new(STATUS_TTL).named("block_status"));
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockStatus {
+    pub hash: RpcHash,
+    /// On the selected parent chain of the virtual
+    pub is_chain_block: bool,
+    /// Blue in the mergeset of the chain block that merged it, so its transactions are accepted;
+    /// `false` for merged red blocks, `None` while no chain block has merged it yet
+    pub is_accepted: Option<bool>,
+    /// Among the DAG tips, i.e. not referenced by any other block yet
+    pub is_tip: bool,
+}
+
+/// Chain membership, acceptance and tip status of a block as the node sees it now
+pub async fn get(Path(hash): Path<String>, client_pool: ClientPool, deadline: Deadline) -> Data<BlockStatus> {
+    let hash = parse_hash(&hash)?;
+    let status = STATUSES
+        .get_or_try_insert_with(hash, || async {
+            let client = client_pool.get().await?;
+            let rpc = client.rpc()?;
+            let (block, dag_info, color) = deadline
+                .run(async {
+                    Ok::<_, Error>(tokio::join!(
+                        rpc.get_block_call(None, GetBlockRequest { hash, include_transactions: false }),
+                        rpc.get_block_dag_info_call(None, GetBlockDagInfoRequest {}),
+                        rpc.get_current_block_color_call(None, GetCurrentBlockColorRequest { hash }),
+                    ))
+                })
+                .await?;
+
+            let block = block.map_err(|e| match e {
+                RpcError::BlockNotFound(_) => Error::NotFound(format!("Block {hash}")),
+                e => Error::ServiceUnavailable(e.to_string()),
+            })?;
+            let dag_info = dag_info.map_err(|e| Error::ServiceUnavailable(e.to_string()))?;
+            let is_accepted = match color {
+                Ok(color) => Some(color.blue),
+                Err(RpcError::MergerNotFound(_)) => None,
+                Err(e) => return Err(Error::ServiceUnavailable(e.to_string())),
+            };
+            client_pool.record_success();
+
+            Ok::<_, Error>(BlockStatus {
+                hash,
+                is_chain_block: block.block.verbose_data.is_some_and(|data| data.is_chain_block),
+                is_accepted,
+                is_tip: dag_info.tip_hashes.contains(&hash),
+            })
+        })
+        .await?;
+    Ok(status.into())
+}
+
+fn parse_hash(hash: &str) -> Result<RpcHash> {
+    Ok(RpcHash::from_bytes(parse_hash256(hash, "block hash")?))
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+
+    #[test]
+    fn test_invalid_hash_is_400() {
+        for input in ["", "xyz", "ab", &"ab".repeat(10), &"g".repeat(64), &"ab".repeat(33)] {
+            let err = parse_hash(input).unwrap_err();
+            assert_eq!(err.status_code(), StatusCode::BAD_REQUEST, "{input}");
+        }
+        assert!(parse_hash(&"ab".repeat(32)).is_ok());
+    }
+}
@@ -1,111 +1,253 @@
-use axum::{
-    extract::State,
-    http::StatusCode,
-    response::Json,
-};
+use axum::{extract::State, response::Response};
+use diesel::prelude::*;
+use http::HeaderMap;
+use serde::{Deserialize, Serialize};
 use tondi_listener_db::{
-    models::chain::Header,
+    models::chain::{Header, HeaderSummary},
     schema::table::THeader,
-    DieselPool,
 };
-use diesel::prelude::*;
-use serde_json::Value;
-
-use crate::error::Result;
-
-/// Get the latest block header information
-pub async fn get_last_header(
-    State(pool): State<DieselPool>,
-) -> Result<Json<Value>, (StatusCode, String)> {
-    let conn = pool.get().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Database connection error: {}", e),
-        )
-    })?;
-
-    // Get the latest header by timestamp
-    let result: Result<Header, diesel::result::Error> = conn
-        .transaction(|conn| {
-            THeader::table
-                .order(THeader::timestamp.desc())
-                .first::<Header>(conn)
-        });
-
-    match result {
-        Ok(header) => {
-            let response = serde_json::json!({
-                "success": true,
-                "data": {
-                    "hash": header.hash,
-                    "timestamp": header.timestamp,
-                    "blue_score": header.blue_score,
-                    "daa_score": header.daa_score,
-                    "bits": header.bits,
-                    "version": header.version
-                }
-            });
-            Ok(Json(response))
-        }
-        Err(e) => {
-            log::error!("Failed to fetch latest header: {}", e);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to fetch latest header: {}", e),
-            ))
-        }
+
+use crate::{
+    ctx::{
+        pg_database::{PgDb, run_blocking},
+        store::{Db, run_store},
+    },
+    error::{Error, Result},
+    shared::{
+        data::{Data, Inner},
+        etag::{conditional, conditional_head, etag},
+    },
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainStats {
+    pub total_blocks: i64,
+    /// `0` while no block has been indexed
+    pub latest_timestamp: i64,
+    pub latest_blue_score: i64,
+}
+
+/// A header with the difficulty its compact `bits` target encodes; `null` for degenerate `bits`
+#[derive(Debug, Serialize)]
+pub struct WithDifficulty<T> {
+    #[serde(flatten)]
+    pub header: T,
+    pub difficulty: Option<f64>,
+}
+
+impl From<Header> for WithDifficulty<Header> {
+    fn from(header: Header) -> Self {
+        Self { difficulty: header.difficulty(), header }
     }
 }
 
-/// Get chain statistics
-pub async fn get_chain_stats(
-    State(pool): State<DieselPool>,
-) -> Result<Json<Value>, (StatusCode, String)> {
-    let conn = pool.get().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Database connection error: {}", e),
-        )
-    })?;
-
-    // Get chain statistics
-    let result: Result<(i64, i64, i64), diesel::result::Error> = conn
-        .transaction(|conn| {
-            let total_blocks = THeader::table.count().get_result::<i64>(conn)?;
-            let latest_timestamp = THeader::table
-                .select(THeader::timestamp)
-                .order(THeader::timestamp.desc())
-                .first::<i64>(conn)
-                .optional()?
-                .unwrap_or(0);
-            let latest_blue_score = THeader::table
-                .select(THeader::blue_score)
-                .order(THeader::blue_score.desc())
-                .first::<i64>(conn)
-                .optional()?
-                .unwrap_or(0);
-            
-            Ok((total_blocks, latest_timestamp, latest_blue_score))
-        });
-
-    match result {
-        Ok((total_blocks, latest_timestamp, latest_blue_score)) => {
-            let response = serde_json::json!({
-                "success": true,
-                "data": {
-                    "total_blocks": total_blocks,
-                    "latest_timestamp": latest_timestamp,
-                    "latest_blue_score": latest_blue_score
-                }
-            });
-            Ok(Json(response))
+impl From<HeaderSummary> for WithDifficulty<HeaderSummary> {
+    fn from(header: HeaderSummary) -> Self {
+        Self { difficulty: header.difficulty(), header }
+    }
+}
+
+/// Get the latest block header, tagged with its hash; 304 while the client has it,
+/// 404 while no block has been indexed
+pub async fn get(State(store): Db, headers: HeaderMap) -> Result<Response> {
+    let header = run_store(store, |store| store.latest_header()).await?.ok_or_else(nothing_indexed)?;
+    Ok(latest(&headers, header))
+}
+
+/// Headers of [`get`] without its body: the `ETag` is computed from the latest hash alone,
+/// so monitors can cheaply check whether the tip moved
+pub async fn head(State(store): Db, headers: HeaderMap) -> Result<Response> {
+    let hash = run_store(store, |store| store.latest_hash()).await?.ok_or_else(nothing_indexed)?;
+    Ok(latest_head(&headers, &hash))
+}
+
+fn nothing_indexed() -> Error {
+    Error::NotFound("No block has been indexed yet".to_string())
+}
+
+fn latest(headers: &HeaderMap, header: Header) -> Response {
+    let tag = etag(&*header.hash);
+    conditional(headers, &tag, Inner::new(WithDifficulty::from(header)))
+}
+
+fn latest_head(headers: &HeaderMap, hash: &str) -> Response {
+    conditional_head(headers, &etag(hash))
+}
+
+/// Get the latest block header's summary columns only
+pub async fn summary(State(db): PgDb<'static>) -> Data<WithDifficulty<HeaderSummary>> {
+    let header = run_blocking(db, |conn| {
+        Ok(THeader::table.order(THeader::timestamp.desc()).select(HeaderSummary::as_select()).first(conn)?)
+    })
+    .await?;
+    Ok(WithDifficulty::from(header).into())
+}
+
+/// Get chain statistics, tagged with the latest blue score; 304 while the client has them
+pub async fn stats(State(db): PgDb<'static>, headers: HeaderMap) -> Result<Response> {
+    let stats = run_blocking(db, |conn| Ok(load_stats(conn)?)).await?;
+    let tag = etag(stats.latest_blue_score);
+    Ok(conditional(&headers, &tag, Inner::new(stats)))
+}
+
+pub(crate) fn load_stats(conn: &mut PgConnection) -> QueryResult<ChainStats> {
+    conn.transaction(|conn| {
+        let latest_timestamp = THeader::table
+            .select(THeader::timestamp)
+            .order(THeader::timestamp.desc())
+            .first::<i64>(conn)
+            .optional()?
+            .unwrap_or(0);
+        let latest_blue_score = THeader::table
+            .select(THeader::blue_score)
+            .order(THeader::blue_score.desc())
+            .first::<i64>(conn)
+            .optional()?
+            .unwrap_or(0);
+        Ok(ChainStats { total_blocks: THeader::table.count().get_result(conn)?, latest_timestamp, latest_blue_score })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{
+        Router,
+        body::{Body, to_bytes},
+        http::StatusCode,
+        routing,
+    };
+    use http::Request;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::ctx::store::{MemoryStore, Store};
+
+    fn header_json() -> serde_json::Value {
+        let zero = "00".repeat(32);
+        serde_json::json!({
+            "hash": "ab".repeat(32),
+            "acceptedIdMerkleRoot": zero,
+            "mergeSetBluesHashes": [],
+            "mergeSetRedsHashes": null,
+            "selectedParentHash": zero,
+            "bits": 503_382_015,
+            "blueScore": 42,
+            "blueWork": [1],
+            "daaScore": 43,
+            "hashMerkleRoot": zero,
+            "nonce": [0],
+            "pruningPoint": zero,
+            "timestamp": 1_700_000_000_000_i64,
+            "utxoCommitment": zero,
+            "version": 1,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_empty_blocks_table_is_404() {
+        let store: Arc<dyn Store> = Arc::new(MemoryStore::default());
+        let app = Router::new().route("/chain/last", routing::get(get)).with_state(store);
+
+        let response = app.oneshot(Request::get("/chain/last").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body: serde_json::Value =
+            serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!(body["error"]["code"], "NOT_FOUND");
+        assert_eq!(body["error"]["status"], 404);
+        assert!(body["error"]["message"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_head_carries_the_etag_without_a_body() {
+        use axum::middleware::from_fn_with_state;
+        use http::header::{CACHE_CONTROL, ETAG};
+
+        use crate::middleware::cache_control::{SharedDirective, cache_control, directive};
+
+        let header = header_json();
+        let store = Arc::new(MemoryStore::default());
+        let app = Router::new()
+            .route("/chain/last", routing::get(get).head(head))
+            .layer(from_fn_with_state(SharedDirective::new(directive(1, false)), cache_control))
+            .with_state(store.clone() as Arc<dyn Store>);
+        let send = |request: Request<Body>| {
+            let app = app.clone();
+            async move { app.oneshot(request).await.unwrap() }
+        };
+
+        let empty = send(Request::head("/chain/last").body(Body::empty()).unwrap()).await;
+        assert_eq!(empty.status(), StatusCode::NOT_FOUND);
+        store.insert_header(serde_json::from_value(header.clone()).unwrap());
+
+        let get = send(Request::get("/chain/last").body(Body::empty()).unwrap()).await;
+        let head = send(Request::head("/chain/last").body(Body::empty()).unwrap()).await;
+        assert_eq!(head.status(), StatusCode::OK);
+        for name in [ETAG, CACHE_CONTROL] {
+            assert_eq!(head.headers()[&name], get.headers()[&name]);
         }
-        Err(e) => {
-            log::error!("Failed to fetch chain stats: {}", e);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to fetch chain stats: {}", e),
-            ))
+        assert!(to_bytes(head.into_body(), usize::MAX).await.unwrap().is_empty());
+        let body: serde_json::Value =
+            serde_json::from_slice(&to_bytes(get.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!(body["data"]["hash"], header["hash"]);
+        assert_eq!(body["data"]["blueScore"], 42);
+
+        let current = Request::head("/chain/last").header(http::header::IF_NONE_MATCH, etag("ab".repeat(32)));
+        assert_eq!(send(current.body(Body::empty()).unwrap()).await.status(), StatusCode::NOT_MODIFIED);
+    }
+
+    #[tokio::test]
+    async fn test_chain_last_has_the_model_field_names() {
+        let header: Header = serde_json::from_value(header_json()).unwrap();
+        let store = Arc::new(MemoryStore::default());
+        store.insert_header(header.clone());
+        let app = Router::new().route("/chain/last", routing::get(get)).with_state(store as Arc<dyn Store>);
+
+        let response = app.oneshot(Request::get("/chain/last").body(Body::empty()).unwrap()).await.unwrap();
+        let body: serde_json::Value =
+            serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        // The route adds `difficulty` to the model as it serializes itself, renaming nothing
+        let mut model = serde_json::to_value(&header).unwrap();
+        model["difficulty"] = serde_json::json!(header.difficulty());
+        assert_eq!(body["data"], model);
+    }
+
+    #[test]
+    fn test_header_summary_deserializes() {
+        let summary: HeaderSummary = serde_json::from_value(serde_json::json!({
+            "hash": "ab".repeat(32),
+            "timestamp": 1_700_000_000_000_i64,
+            "blueScore": 42,
+            "daaScore": 43,
+            "bits": 503_382_015,
+            "version": 1,
+        }))
+        .unwrap();
+        assert_eq!(summary.blue_score, 42);
+        assert_eq!(summary.version, 1);
+        assert_eq!(*summary.hash, "ab".repeat(32));
+
+        let body = serde_json::to_value(WithDifficulty::from(summary)).unwrap();
+        assert_eq!(body["bits"], 503_382_015);
+        assert_eq!(body["blueScore"], 42);
+        assert!((body["difficulty"].as_f64().unwrap() - 8_388_736.001_953).abs() < 1e-3, "{body}");
+    }
+
+    #[test]
+    fn test_difficulty_from_bits() {
+        use tondi_listener_db::models::chain::difficulty_from_bits;
+
+        // 0x1d00ffff: target 0xffff * 256^26, so 2^255 / target = 2^47 / 0xffff
+        let difficulty = difficulty_from_bits(0x1d00_ffff).unwrap();
+        assert!((difficulty - 2_147_516_416.500_008).abs() < 1e-3, "{difficulty}");
+        // 0x207fffff is the easiest target there is
+        assert!((difficulty_from_bits(0x207f_ffff).unwrap() - 1.0).abs() < 1e-6);
+
+        // Zero, negative and out of range targets
+        for bits in [0, 0x1d00_0000, 0x0100_00ff, 0x1d80_ffff, -1, i64::from(u32::MAX) + 1] {
+            assert_eq!(difficulty_from_bits(bits), None, "{bits:#x}");
         }
     }
 }
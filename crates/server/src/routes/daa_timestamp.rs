@@ -0,0 +1,98 @@
+use std::{sync::LazyLock, time::Duration};
+
+use axum::extract::Query;
+use serde::{Deserialize, Serialize};
+use tondi_rpc_core::{GetDaaScoreTimestampEstimateRequest, api::rpc::RpcApi};
+
+use crate::{
+    error::{Error, Result},
+    extensions::client_pool::ClientPool,
+    middleware::timeout::Deadline,
+    shared::{cache::TtlCache, data::Data},
+};
+
+/// Most DAA scores accepted by a single request
+const MAX_DAA_SCORES: usize = 100;
+
+const ESTIMATE_TTL: Duration = Duration::from_secs(2);
+
+pub static ESTIMATES: LazyLock<TtlCache<Vec<u64>, Vec<DaaTimestamp>>> =
+    LazyLock::new(|| TtlCache::new(ESTIMATE_TTL).with_max_entries(1024).named("daa_timestamps"));
+
+#[derive(Debug, Deserialize)]
+pub struct DaaTimestampQuery {
+    /// One score or a comma-separated list
+    daa_score: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DaaTimestamp {
+    pub daa_score: u64,
+    /// Estimated Unix timestamp in milliseconds
+    pub timestamp: u64,
+}
+
+/// Estimated wall-clock times for DAA scores, in the order they were given
+pub async fn get(
+    Query(DaaTimestampQuery { daa_score }): Query<DaaTimestampQuery>,
+    client_pool: ClientPool,
+    deadline: Deadline,
+) -> Data<Vec<DaaTimestamp>> {
+    let daa_scores = parse_scores(&daa_score)?;
+    let estimates = ESTIMATES
+        .get_or_try_insert_with(daa_scores.clone(), || async {
+            let client = client_pool.get().await?;
+            let request = GetDaaScoreTimestampEstimateRequest { daa_scores: daa_scores.clone() };
+            let call = client.rpc()?.get_daa_score_timestamp_estimate_call(None, request);
+            let response =
+                deadline.run(async { call.await.map_err(|e| Error::ServiceUnavailable(e.to_string())) }).await?;
+            client_pool.record_success();
+            Ok::<_, Error>(
+                daa_scores
+                    .iter()
+                    .zip(response.timestamps)
+                    .map(|(&daa_score, timestamp)| DaaTimestamp { daa_score, timestamp })
+                    .collect(),
+            )
+        })
+        .await?;
+    Ok(estimates.into())
+}
+
+fn parse_scores(daa_score: &str) -> Result<Vec<u64>> {
+    let scores: Vec<_> = daa_score.split(',').map(str::trim).filter(|score| !score.is_empty()).collect();
+    if scores.is_empty() {
+        return Err(Error::BadRequest("`daa_score` must list at least one score".to_string()))
+    }
+    if scores.len() > MAX_DAA_SCORES {
+        return Err(Error::BadRequest(format!("At most {MAX_DAA_SCORES} DAA scores per request")))
+    }
+    scores
+        .into_iter()
+        .map(|score| match score.parse::<u64>() {
+            Ok(score) if score > 0 => Ok(score),
+            _ => Err(Error::BadRequest(format!("Invalid DAA score `{score}`: expected a positive integer"))),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scores_accepts_lists() {
+        assert_eq!(parse_scores("42").unwrap(), vec![42]);
+        assert_eq!(parse_scores("1, 2,3").unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_parse_scores_rejects_bad_input() {
+        for input in ["", ",", "0", "-1", "abc", "1,x"] {
+            assert!(matches!(parse_scores(input), Err(Error::BadRequest(_))), "{input}");
+        }
+        let too_many = vec!["1"; MAX_DAA_SCORES + 1].join(",");
+        assert!(matches!(parse_scores(&too_many), Err(Error::BadRequest(_))));
+    }
+}
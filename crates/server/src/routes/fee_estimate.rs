@@ -0,0 +1,126 @@
+use std::{sync::LazyLock, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tondi_rpc_core::{GetFeeEstimateExperimentalRequest, GetFeeEstimateExperimentalResponse, api::rpc::RpcApi};
+
+use crate::{
+    error::{Error, Result},
+    extensions::client_pool::ClientPool,
+    middleware::timeout::Deadline,
+    shared::{cache::TtlCache, data::Data},
+};
+
+/// The node recomputes estimates as the mempool changes, about once a block
+const ESTIMATE_TTL: Duration = Duration::from_secs(1);
+
+pub static EXPERIMENTAL: LazyLock<TtlCache<(), ExperimentalFeeEstimate>> =
+    LazyLock::new(|| TtlCache::new(ESTIMATE_TTL).named("fee_estimate_experimental"));
+
+/// How soon a bucket is expected to be mined, as the node ranks it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Tier {
+    Priority,
+    Normal,
+    Low,
+}
+
+/// One point of the fee slider
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeBucket {
+    pub tier: Tier,
+    /// Fee per gram of transaction mass, in sompi
+    pub feerate: f64,
+    /// Expected wait until a transaction paying `feerate` is included
+    pub estimated_seconds: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExperimentalFeeEstimate {
+    pub buckets: Vec<FeeBucket>,
+}
+
+/// Every bucket of the node's experimental fee estimate, for wallets offering a fee slider:
+/// `{"buckets": [{"tier", "feerate", "estimatedSeconds"}, ..]}`, highest feerate first. The single
+/// `priority` bucket comes first, then the `normal` and `low` ones. Cached for a second.
+pub async fn experimental(client_pool: ClientPool, deadline: Deadline) -> Data<ExperimentalFeeEstimate> {
+    let estimate = EXPERIMENTAL
+        .get_or_try_insert_with((), || async {
+            let client = client_pool.get().await?;
+            let request = GetFeeEstimateExperimentalRequest { verbose: false };
+            let call = client.rpc()?.get_fee_estimate_experimental_call(None, request);
+            let response = deadline.run(async { Ok::<_, Error>(call.await) }).await??;
+            client_pool.record_success();
+            buckets(response)
+        })
+        .await?;
+    Ok(estimate.into())
+}
+
+/// Flatten the node's tiers, refusing estimates no wallet could use
+fn buckets(response: GetFeeEstimateExperimentalResponse) -> Result<ExperimentalFeeEstimate> {
+    let estimate = response.estimate;
+    let buckets = std::iter::once((Tier::Priority, estimate.priority_bucket))
+        .chain(estimate.normal_buckets.into_iter().map(|bucket| (Tier::Normal, bucket)))
+        .chain(estimate.low_buckets.into_iter().map(|bucket| (Tier::Low, bucket)))
+        .map(|(tier, bucket)| {
+            let valid = |value: f64| value.is_finite() && value >= 0.0;
+            if !valid(bucket.feerate) || !valid(bucket.estimated_seconds) {
+                return Err(Error::ServiceUnavailable(format!(
+                    "Node returned an invalid fee bucket (feerate {}, {}s)",
+                    bucket.feerate, bucket.estimated_seconds
+                )));
+            }
+            Ok(FeeBucket { tier, feerate: bucket.feerate, estimated_seconds: bucket.estimated_seconds })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(ExperimentalFeeEstimate { buckets })
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+    use serde_json::json;
+    use tondi_rpc_core::{RpcFeeEstimate, RpcFeerateBucket};
+
+    use super::*;
+
+    fn bucket(feerate: f64, estimated_seconds: f64) -> RpcFeerateBucket {
+        RpcFeerateBucket { feerate, estimated_seconds }
+    }
+
+    fn response(priority: RpcFeerateBucket, normal: Vec<RpcFeerateBucket>) -> GetFeeEstimateExperimentalResponse {
+        GetFeeEstimateExperimentalResponse {
+            estimate: RpcFeeEstimate {
+                priority_bucket: priority,
+                normal_buckets: normal,
+                low_buckets: vec![bucket(1.0, 3600.0)],
+            },
+            verbose: None,
+        }
+    }
+
+    #[test]
+    fn test_every_bucket_is_returned_in_order() {
+        let estimate = buckets(response(bucket(10.0, 1.0), vec![bucket(5.0, 30.0), bucket(2.0, 60.0)])).unwrap();
+        assert_eq!(
+            serde_json::to_value(&estimate).unwrap(),
+            json!({ "buckets": [
+                { "tier": "priority", "feerate": 10.0, "estimatedSeconds": 1.0 },
+                { "tier": "normal", "feerate": 5.0, "estimatedSeconds": 30.0 },
+                { "tier": "normal", "feerate": 2.0, "estimatedSeconds": 60.0 },
+                { "tier": "low", "feerate": 1.0, "estimatedSeconds": 3600.0 },
+            ]})
+        );
+    }
+
+    #[test]
+    fn test_unusable_buckets_are_refused() {
+        for priority in [bucket(f64::NAN, 1.0), bucket(1.0, f64::INFINITY), bucket(-1.0, 1.0)] {
+            let err = buckets(response(priority, Vec::new())).unwrap_err();
+            assert_eq!(err.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+        }
+    }
+}
@@ -1,10 +1,13 @@
 use serde::{Deserialize, Serialize};
+use tondi_grpc_client::GrpcClient;
 use tondi_grpc_core::{ops::TondidPayloadOps, protowire::TondidRequest};
-use tondi_rpc_core::*;
+use tondi_rpc_core::{api::rpc::RpcApi, *};
+
+use crate::routes::grpc::grpc_return::GrpcReturn;
 
 // TODO: Make prost build Message Serialize/Deserialize
 // TODO: Use paste::paste!
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "op", content = "params")]
 pub enum GrpcCall {
     Ping(PingRequest),
@@ -53,6 +56,97 @@ pub enum GrpcCall {
     GetUtxoReturnAddress(GetUtxoReturnAddressRequest),
 }
 
+macro_rules! dispatch {
+    ($call:expr, $rpc:expr; $($variant:ident => $method:ident),* $(,)?) => {
+        match $call {
+            $(GrpcCall::$variant(request) => GrpcReturn::$variant($rpc.$method(None, request).await?),)*
+        }
+    };
+}
+
+impl GrpcCall {
+    /// Calls that are only reachable through the dedicated `/admin` routes
+    pub fn is_admin_only(&self) -> bool {
+        matches!(self, Self::Shutdown(_))
+    }
+
+    /// Calls that are safe to repeat, i.e. eligible for automatic retries
+    pub fn is_idempotent(&self) -> bool {
+        !matches!(
+            self,
+            Self::SubmitBlock(_)
+                | Self::SubmitTransaction(_)
+                | Self::SubmitTransactionReplacement(_)
+                | Self::AddPeer(_)
+                | Self::Ban(_)
+                | Self::Unban(_)
+                | Self::ResolveFinalityConflict(_)
+                | Self::Shutdown(_)
+        )
+    }
+
+    /// Method name for submissions that honour an `Idempotency-Key`
+    pub fn submission_method(&self) -> Option<&'static str> {
+        match self {
+            Self::SubmitBlock(_) => Some("SubmitBlock"),
+            Self::SubmitTransaction(_) => Some("SubmitTransaction"),
+            Self::SubmitTransactionReplacement(_) => Some("SubmitTransactionReplacement"),
+            _ => None,
+        }
+    }
+
+    /// Execute the call against the upstream node
+    pub async fn call(self, rpc: &GrpcClient) -> RpcResult<GrpcReturn> {
+        let ret = dispatch!(self, rpc;
+            Ping => ping_call,
+            GetSyncStatus => get_sync_status_call,
+            GetServerInfo => get_server_info_call,
+            GetMetrics => get_metrics_call,
+            GetConnections => get_connections_call,
+            GetSystemInfo => get_system_info_call,
+            SubmitBlock => submit_block_call,
+            GetBlockTemplate => get_block_template_call,
+            GetBlock => get_block_call,
+            GetBlockStatus => get_block_status_call,
+            GetTransaction => get_transaction_call,
+            GetInfo => get_info_call,
+            GetCurrentNetwork => get_current_network_call,
+            GetPeerAddresses => get_peer_addresses_call,
+            GetSink => get_sink_call,
+            GetMempoolEntry => get_mempool_entry_call,
+            GetMempoolEntries => get_mempool_entries_call,
+            GetConnectedPeerInfo => get_connected_peer_info_call,
+            AddPeer => add_peer_call,
+            SubmitTransaction => submit_transaction_call,
+            SubmitTransactionReplacement => submit_transaction_replacement_call,
+            GetSubnetwork => get_subnetwork_call,
+            GetVirtualChainFromBlock => get_virtual_chain_from_block_call,
+            GetBlocks => get_blocks_call,
+            GetBlockCount => get_block_count_call,
+            GetBlockDagInfo => get_block_dag_info_call,
+            ResolveFinalityConflict => resolve_finality_conflict_call,
+            Shutdown => shutdown_call,
+            GetHeader => get_header_call,
+            GetHeaders => get_headers_call,
+            GetUtxosByAddresses => get_utxos_by_addresses_call,
+            GetBalanceByAddress => get_balance_by_address_call,
+            GetBalancesByAddresses => get_balances_by_addresses_call,
+            GetSinkBlueScore => get_sink_blue_score_call,
+            Ban => ban_call,
+            Unban => unban_call,
+            EstimateNetworkHashesPerSecond => estimate_network_hashes_per_second_call,
+            GetMempoolEntriesByAddresses => get_mempool_entries_by_addresses_call,
+            GetCoinSupply => get_coin_supply_call,
+            GetDaaScoreTimestampEstimate => get_daa_score_timestamp_estimate_call,
+            GetFeeEstimate => get_fee_estimate_call,
+            GetFeeEstimateExperimental => get_fee_estimate_experimental_call,
+            GetCurrentBlockColor => get_current_block_color_call,
+            GetUtxoReturnAddress => get_utxo_return_address_call,
+        );
+        Ok(ret)
+    }
+}
+
 impl From<GrpcCall> for (TondidPayloadOps, TondidRequest) {
     fn from(grpc_call: GrpcCall) -> Self {
         use TondidPayloadOps::*;
@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 use tondi_grpc_core::protowire::tondid_response::Payload;
 use tondi_rpc_core::*;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum GrpcReturn {
     Ping(PingResponse),
@@ -0,0 +1,91 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::http::{HeaderMap, HeaderName};
+use tokio::sync::OnceCell;
+
+use crate::{
+    error::{Error, Result},
+    shared::cache::TtlCache,
+};
+
+pub const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+
+const MAX_KEY_LEN: usize = 255;
+
+/// Remembers submission results by `(method, Idempotency-Key)` so a retried
+/// submission returns the first result instead of reaching the node again
+#[derive(Debug)]
+pub struct IdempotencyStore<V> {
+    entries: TtlCache<(&'static str, String), Arc<OnceCell<V>>>,
+}
+
+impl<V> Clone for IdempotencyStore<V> {
+    fn clone(&self) -> Self {
+        Self { entries: self.entries.clone() }
+    }
+}
+
+impl<V: Clone> IdempotencyStore<V> {
+    pub fn new(ttl: Duration) -> Self {
+        Self { entries: TtlCache::new(ttl).counted("grpc_idempotency") }
+    }
+
+    /// Run `submit` at most once per live key. Concurrent repeats wait for the
+    /// first attempt; failed attempts are not remembered, so they can be retried.
+    pub async fn run<F, Fut, E>(&self, method: &'static str, key: String, submit: F) -> Result<V, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, E>>,
+    {
+        let cell = self.entries.get_or_insert_with((method, key), Default::default);
+        cell.get_or_try_init(submit).await.cloned()
+    }
+}
+
+/// Read the `Idempotency-Key` header, if present
+pub fn idempotency_key(headers: &HeaderMap) -> Result<Option<String>> {
+    let Some(value) = headers.get(IDEMPOTENCY_KEY) else {
+        return Ok(None)
+    };
+    match value.to_str() {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LEN => Ok(Some(key.to_string())),
+        _ => Err(Error::BadRequest(format!(
+            "`{IDEMPOTENCY_KEY}` must be 1 to {MAX_KEY_LEN} visible ASCII characters"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_same_key_submits_once() {
+        let store = IdempotencyStore::new(Duration::from_secs(60));
+        let upstream_calls = AtomicUsize::new(0);
+        let submit = || async move {
+            upstream_calls.fetch_add(1, Ordering::SeqCst);
+            Ok::<_, ()>("txid")
+        };
+
+        let first = store.run("SubmitTransaction", "key-1".to_string(), submit).await;
+        let second = store.run("SubmitTransaction", "key-1".to_string(), submit).await;
+
+        assert_eq!((first, second), (Ok("txid"), Ok("txid")));
+        assert_eq!(upstream_calls.load(Ordering::SeqCst), 1);
+
+        // Keys are scoped per method
+        store.run("SubmitBlock", "key-1".to_string(), submit).await.unwrap();
+        assert_eq!(upstream_calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_submission_is_not_remembered() {
+        let store = IdempotencyStore::new(Duration::from_secs(60));
+        let failed = store.run("SubmitTransaction", "key-1".to_string(), || async { Err("timeout") }).await;
+        let retried = store.run("SubmitTransaction", "key-1".to_string(), || async { Ok("txid") }).await;
+        assert_eq!((failed, retried), (Err("timeout"), Ok("txid")));
+    }
+}
@@ -1,19 +1,137 @@
 pub mod grpc_call;
 pub mod grpc_return;
+pub mod idempotency;
 
-use axum::extract::Json;
+use axum::{Extension, extract::State, http::HeaderMap};
+use futures::future::join_all;
+use tondi_grpc_client::GrpcClient;
+use tondi_rpc_core::RpcResult;
 
 use crate::{
+    ctx::config::Config,
     error::Error as AppError,
-    extensions::client_pool::ClientPool,
-    routes::grpc::{grpc_call::GrpcCall, grpc_return::GrpcReturn},
-    shared::data::Data,
+    extensions::client_pool::{ClientPool, retry::retry_rpc},
+    middleware::timeout::Deadline,
+    routes::grpc::{
+        grpc_call::GrpcCall,
+        grpc_return::GrpcReturn,
+        idempotency::{IdempotencyStore, idempotency_key},
+    },
+    shared::{
+        data::{Data, Inner},
+        json::Json,
+    },
 };
 
-pub async fn post(_client_pool: ClientPool, Json(_grpc_call): Json<GrpcCall>) -> Data<GrpcReturn> {
-    // 暂时简化gRPC调用，因为具体的类型需要根据实际的API来实现
-    // TODO: 实现真正的gRPC调用逻辑
-    Err(AppError::InternalServerError(
-        "gRPC calls not yet implemented".to_string()
-    ))
+pub(crate) const ADMIN_ONLY: &str = "This call is only available through /admin";
+
+pub async fn post(
+    State(config): State<&'static Config>,
+    client_pool: ClientPool,
+    Extension(idempotency): Extension<IdempotencyStore<GrpcReturn>>,
+    deadline: Deadline,
+    headers: HeaderMap,
+    Json(grpc_call): Json<GrpcCall>,
+) -> Data<GrpcReturn> {
+    if grpc_call.is_admin_only() {
+        return Err(AppError::Forbidden(ADMIN_ONLY.to_string()));
+    }
+
+    let client = client_pool.get().await?;
+    let rpc = client.rpc()?;
+    let ret = match (grpc_call.submission_method(), idempotency_key(&headers)?) {
+        (Some(method), Some(key)) => deadline.run(idempotency.run(method, key, || grpc_call.call(rpc))).await?,
+        _ => deadline.run(execute(config, rpc, grpc_call)).await?,
+    };
+    client_pool.record_success();
+    Ok(ret.into())
+}
+
+/// Run several calls concurrently; each result succeeds or fails on its own, in request order
+pub async fn batch(
+    State(config): State<&'static Config>,
+    client_pool: ClientPool,
+    deadline: Deadline,
+    Json(grpc_calls): Json<Vec<GrpcCall>>,
+) -> Data<Vec<Inner<GrpcReturn>>> {
+    if grpc_calls.len() > config.grpc.max_batch_size {
+        return Err(AppError::BadRequest(format!(
+            "Batch of {} calls exceeds the maximum of {}",
+            grpc_calls.len(),
+            config.grpc.max_batch_size
+        )));
+    }
+
+    let client = client_pool.get().await?;
+    let rpc = client.rpc()?;
+    let results = run_batch(grpc_calls, |grpc_call| deadline.run(execute(config, rpc, grpc_call))).await;
+    if results.iter().any(|result| result.data.is_some()) {
+        client_pool.record_success();
+    }
+    Ok(results.into())
+}
+
+/// Single call, retried when it is safe to repeat
+pub(crate) async fn execute(config: &Config, rpc: &GrpcClient, grpc_call: GrpcCall) -> RpcResult<GrpcReturn> {
+    if grpc_call.is_idempotent() {
+        retry_rpc(&config.retry, || grpc_call.clone().call(rpc)).await
+    } else {
+        grpc_call.call(rpc).await
+    }
+}
+
+async fn run_batch<F, Fut, E>(grpc_calls: Vec<GrpcCall>, execute: F) -> Vec<Inner<GrpcReturn>>
+where
+    F: Fn(GrpcCall) -> Fut,
+    Fut: Future<Output = Result<GrpcReturn, E>>,
+    E: std::error::Error,
+{
+    let execute = &execute;
+    join_all(grpc_calls.into_iter().map(|grpc_call| async move {
+        if grpc_call.is_admin_only() {
+            return Inner::fail(ADMIN_ONLY.to_string());
+        }
+        Inner::from(execute(grpc_call).await)
+    }))
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use tondi_rpc_core::{
+        GetBlockCountRequest, GetBlockCountResponse, GetSinkRequest, PingRequest, PingResponse, RpcError,
+        ShutdownRequest,
+    };
+
+    use super::*;
+    use crate::shared::data::Status;
+
+    #[tokio::test]
+    async fn test_batch_keeps_order_and_isolates_failures() {
+        let calls = vec![
+            GrpcCall::Ping(PingRequest {}),
+            GrpcCall::GetSink(GetSinkRequest {}),
+            GrpcCall::Shutdown(ShutdownRequest {}),
+            GrpcCall::GetBlockCount(GetBlockCountRequest {}),
+        ];
+        let results = run_batch(calls, |grpc_call| async move {
+            match grpc_call {
+                GrpcCall::Ping(_) => Ok(GrpcReturn::Ping(PingResponse {})),
+                GrpcCall::GetBlockCount(_) => {
+                    Ok(GrpcReturn::GetBlockCount(GetBlockCountResponse { header_count: 10, block_count: 9 }))
+                },
+                _ => Err(RpcError::General("upstream failed".to_string())),
+            }
+        })
+        .await;
+
+        assert_eq!(results.len(), 4);
+        assert!(matches!(results[0].data, Some(GrpcReturn::Ping(_))));
+        assert_eq!(results[1].status, Status::Fail);
+        assert_eq!(results[2].cause.as_deref(), Some(ADMIN_ONLY));
+        assert!(matches!(
+            results[3].data,
+            Some(GrpcReturn::GetBlockCount(GetBlockCountResponse { block_count: 9, .. }))
+        ));
+    }
 }
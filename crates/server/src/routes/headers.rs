@@ -0,0 +1,104 @@
+use std::{sync::LazyLock, time::Duration};
+
+use axum::extract::{Query, State};
+use serde::Deserialize;
+use tondi_rpc_core::{GetHeadersRequest, RpcError, RpcHash, RpcHeader, api::rpc::RpcApi};
+
+use crate::{
+    ctx::config::{Config, HeadersConfig},
+    error::{Error, Result},
+    extensions::client_pool::ClientPool,
+    middleware::timeout::Deadline,
+    shared::{cache::TtlCache, data::Data, hash::parse_hash256},
+};
+
+/// Brief, so syncing clients see new headers within about a block
+const HEADERS_TTL: Duration = Duration::from_secs(1);
+
+pub static HEADERS: LazyLock<TtlCache<(RpcHash, u64), Vec<RpcHeader>>> =
+    LazyLock::new(|| TtlCache::new(HEADERS_TTL).with_max_entries(256).named("headers"));
+
+#[derive(Debug, Deserialize)]
+pub struct HeadersQuery {
+    /// Hash of the first block
+    start: String,
+    /// Defaults to `headers.max_count`, which it may not exceed
+    count: Option<u64>,
+}
+
+/// Up to `count` headers from `start` onwards, for light clients syncing headers in bulk
+pub async fn get(
+    Query(query): Query<HeadersQuery>,
+    State(config): State<&'static Config>,
+    client_pool: ClientPool,
+    deadline: Deadline,
+) -> Data<Vec<RpcHeader>> {
+    let start = query.start()?;
+    let count = query.count(&config.headers)?;
+    let headers = HEADERS
+        .get_or_try_insert_with((start, count), || async {
+            let client = client_pool.get().await?;
+            let request = GetHeadersRequest { start_hash: start, limit: count, is_ascending: true };
+            let call = client.rpc()?.get_headers_call(None, request);
+            let response = deadline.run(async { Ok::<_, Error>(call.await) }).await?.map_err(|e| match e {
+                RpcError::BlockNotFound(_) => Error::NotFound(format!("Block {start}")),
+                e => Error::ServiceUnavailable(e.to_string()),
+            })?;
+            client_pool.record_success();
+
+            let mut headers = response.headers;
+            headers.truncate(usize::try_from(count).unwrap_or(usize::MAX));
+            Ok::<_, Error>(headers)
+        })
+        .await?;
+    Ok(headers.into())
+}
+
+impl HeadersQuery {
+    fn start(&self) -> Result<RpcHash> {
+        Ok(RpcHash::from_bytes(parse_hash256(&self.start, "block hash")?))
+    }
+
+    fn count(&self, config: &HeadersConfig) -> Result<u64> {
+        match self.count {
+            None => Ok(config.max_count),
+            Some(0) => Err(Error::BadRequest("`count` must be positive".to_string())),
+            Some(count) if count > config.max_count => {
+                Err(Error::BadRequest(format!("`count` may not exceed {}", config.max_count)))
+            },
+            Some(count) => Ok(count),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+
+    fn query(count: Option<u64>) -> HeadersQuery {
+        HeadersQuery { start: "ab".repeat(32), count }
+    }
+
+    #[test]
+    fn test_count_is_capped() {
+        let config = HeadersConfig::default();
+        assert_eq!(query(None).count(&config).unwrap(), config.max_count);
+        assert_eq!(query(Some(10)).count(&config).unwrap(), 10);
+        assert_eq!(query(Some(config.max_count)).count(&config).unwrap(), config.max_count);
+        for count in [0, config.max_count + 1, u64::MAX] {
+            let err = query(Some(count)).count(&config).unwrap_err();
+            assert_eq!(err.status_code(), StatusCode::BAD_REQUEST, "{count}");
+        }
+    }
+
+    #[test]
+    fn test_invalid_start_is_400() {
+        for input in ["", "xyz", &"g".repeat(64)] {
+            let bad = HeadersQuery { start: input.to_string(), count: None };
+            assert_eq!(bad.start().unwrap_err().status_code(), StatusCode::BAD_REQUEST, "{input}");
+        }
+        assert_eq!(query(None).start().unwrap(), RpcHash::from_bytes([0xab; 32]));
+    }
+}
@@ -0,0 +1,97 @@
+use std::time::Duration;
+
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ctx::{
+        Context,
+        pg_database::{PgDb, PgPool},
+    },
+    extensions::client_pool::ClientPool,
+    shared::data::Data,
+};
+
+/// Longest `/readyz` waits for a database connection
+const READY_DB_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Health {
+    pub upstream_live: bool,
+    /// Whether the startup warm-up round trip succeeded (`false` when it is disabled)
+    pub upstream_warmed_up: bool,
+    pub uptime_secs: u64,
+    /// `None` when the upstream has never answered since startup
+    pub seconds_since_last_upstream_contact: Option<u64>,
+}
+
+/// Process and upstream liveness overview
+pub async fn get(State(ctx): State<Context>, client_pool: ClientPool) -> Data<Health> {
+    let health = Health {
+        upstream_live: client_pool.is_live(),
+        upstream_warmed_up: client_pool.is_warmed_up(),
+        uptime_secs: ctx.uptime().as_secs(),
+        seconds_since_last_upstream_contact: client_pool.since_last_success().map(|d| d.as_secs()),
+    };
+    Ok(health.into())
+}
+
+/// Plain-text liveness probe: answering at all means the process is up
+pub async fn livez() -> Response {
+    probe(true)
+}
+
+/// Plain-text readiness probe: the upstream node is connected and the database answers
+pub async fn readyz(State(db): PgDb<'static>, client_pool: ClientPool) -> Response {
+    probe(client_pool.is_live() && database_ready(&db).await)
+}
+
+/// Whether a connection is checked out within [`READY_DB_TIMEOUT`], waiting on a blocking thread
+async fn database_ready(pool: &PgPool) -> bool {
+    let pool = pool.clone();
+    tokio::task::spawn_blocking(move || pool.get_timeout(READY_DB_TIMEOUT).is_ok()).await.unwrap_or(false)
+}
+
+fn probe(ready: bool) -> Response {
+    let (status, body) = if ready { (StatusCode::OK, "ok") } else { (StatusCode::SERVICE_UNAVAILABLE, "not ready") };
+    (status, body).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::to_bytes;
+
+    use super::*;
+    use crate::ctx::pg_database::PgDatabase;
+
+    async fn text(response: Response) -> String {
+        String::from_utf8(to_bytes(response.into_body(), usize::MAX).await.unwrap().to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_probe_status_codes() {
+        let ready = probe(true);
+        assert_eq!(ready.status(), StatusCode::OK);
+        assert_eq!(text(ready).await, "ok");
+
+        let not_ready = probe(false);
+        assert_eq!(not_ready.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(text(not_ready).await, "not ready");
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_database_is_not_ready() {
+        let db = PgDatabase::unreachable();
+        assert!(!database_ready(&db).await);
+    }
+
+    #[tokio::test]
+    async fn test_livez_is_always_ok() {
+        assert_eq!(livez().await.status(), StatusCode::OK);
+    }
+}
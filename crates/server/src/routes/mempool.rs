@@ -0,0 +1,66 @@
+use std::{sync::LazyLock, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tondi_rpc_core::{GetInfoRequest, GetInfoResponse, api::rpc::RpcApi};
+
+use crate::{
+    error::Error,
+    extensions::client_pool::ClientPool,
+    middleware::timeout::Deadline,
+    shared::{cache::TtlCache, data::Data},
+};
+
+pub static SIZE: LazyLock<TtlCache<(), MempoolSize>> =
+    LazyLock::new(|| TtlCache::new(Duration::from_secs(2)).named("mempool_size"));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MempoolSize {
+    /// Transactions in the node's mempool
+    pub count: u64,
+}
+
+impl From<GetInfoResponse> for MempoolSize {
+    fn from(info: GetInfoResponse) -> Self {
+        Self { count: info.mempool_size }
+    }
+}
+
+/// Number of transactions in the mempool, `{"count": N}`, for status widgets polling often. Read from
+/// `GetInfo` rather than by listing the entries, and cached for two seconds.
+pub async fn size(client_pool: ClientPool, deadline: Deadline) -> Data<MempoolSize> {
+    let size = SIZE
+        .get_or_try_insert_with((), || async {
+            let client = client_pool.get().await?;
+            let call = client.rpc()?.get_info_call(None, GetInfoRequest {});
+            let info = deadline
+                .run(async { Ok::<_, Error>(call.await) })
+                .await?
+                .map_err(|e| Error::ServiceUnavailable(e.to_string()))?;
+            client_pool.record_success();
+            Ok::<_, Error>(MempoolSize::from(info))
+        })
+        .await?;
+    Ok(size.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_count_is_the_node_mempool_size() {
+        let info = GetInfoResponse {
+            p2p_id: "peer".to_string(),
+            mempool_size: 42,
+            server_version: "1.0.0".to_string(),
+            is_utxo_indexed: true,
+            is_synced: true,
+            has_notify_command: true,
+            has_message_id: true,
+        };
+        let size = MempoolSize::from(info);
+        assert_eq!(serde_json::to_value(size).unwrap(), json!({ "count": 42 }));
+    }
+}
@@ -0,0 +1,9 @@
+use axum::response::IntoResponse;
+use http::header::CONTENT_TYPE;
+
+use crate::shared::metrics::METRICS;
+
+/// Prometheus text exposition of the process metrics
+pub async fn get() -> impl IntoResponse {
+    ([(CONTENT_TYPE, "text/plain; version=0.0.4")], METRICS.render())
+}
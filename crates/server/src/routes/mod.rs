@@ -1,19 +1,69 @@
+pub mod address;
+pub mod admin;
+pub mod block;
 pub mod chain;
+pub mod daa_timestamp;
+pub mod fee_estimate;
 pub mod grpc;
+pub mod headers;
+pub mod health;
+pub mod mempool;
+pub mod metrics;
+pub mod node;
+pub mod peers;
+pub mod stats;
+pub mod subnetwork;
 pub mod transaction;
+pub mod utxo;
+pub mod version;
 pub mod websocket;
 
-use axum::{Router, response::Html, routing::{get,post}};
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
 
-use crate::{ctx::Context, error::Result, extensions::client_pool};
-use tondi_listener_library::log::info;
+use axum::{
+    Extension, Router,
+    extract::DefaultBodyLimit,
+    middleware::{from_fn, from_fn_with_state},
+    response::Html,
+    routing::{get, post},
+};
+
+use crate::{
+    ctx::{Context, config::SecurityConfig, reload},
+    error::Result,
+    extensions::client_pool,
+    middleware::{
+        accounting::account,
+        body_limit::limit_body,
+        cache_control::{SharedDirective, cache_control, directive},
+        language::language,
+        load_shed::load_shed,
+        pretty::pretty,
+        queue_time::{arrival, handler_start},
+        rate_limit::{RateLimiter, rate_limit},
+        security::{RequestValidationLayer, SecurityHeadersLayer},
+        timeout::timeout,
+    },
+    routes::{
+        admin::rate_limit::RateLimiters,
+        grpc::{grpc_return::GrpcReturn, idempotency::IdempotencyStore},
+        websocket::{
+            hub::Hub, limit::ConnectionLimit, priority::PriorityQueue, registry::ConnectionRegistry,
+            spill::SpillBuffer, throttle::MessageLimit,
+        },
+    },
+    shared::{log_sampler::NOTIFICATION_LOG, pool},
+};
+use tondi_listener_library::log::{self, info, warn};
 
 pub async fn index() -> Html<&'static str> {
     Html("Axum Serve")
 }
 
 // TODO: Route trait
-pub async fn router(ctx: Context) -> Result<Router> {
+/// The REST router; `terminates_tls` says whether the listener serving it speaks TLS itself, which is what
+/// `Strict-Transport-Security` is sent on
+pub async fn router(ctx: Context, terminates_tls: bool) -> Result<Router> {
     let Context { config, .. } = &ctx;
     
     // Parse configured event types
@@ -21,36 +71,314 @@ pub async fn router(ctx: Context) -> Result<Router> {
         .map_err(|e| crate::error::Error::InternalServerError(format!("Invalid event config: {}", e)))?;
     
     // Select URL and protocol based on configuration
-    let (rpc_url, protocol_type) = if config.wrpc.enabled {
-        let url = config.wrpc.build_url();
-        (url, "wRPC")
-    } else {
-        (config.grpc_url.clone(), "gRPC")
-    };
+    let (rpc_url, protocol_type) = config.upstream_url()?;
     
     // Log selected protocol
     info!("Using {} protocol with URL: {}", protocol_type, rpc_url);
     
-    // Create client pool with configured events
+    // Every receiver of an event type may lag this many events before skipping ahead
+    pool::set_notification_capacity(config.events.buffer_size);
+    NOTIFICATION_LOG.set_sample_rate(config.logging.notification_sample_rate);
+    if config.logging.notification_summary_secs > 0 {
+        NOTIFICATION_LOG.spawn_summaries(Duration::from_secs(config.logging.notification_summary_secs));
+    }
+
+    // Create client pool with configured events; no node call outlives the longest request waiting for it
+    let security = &config.security;
+    let call_timeout = Duration::from_secs(security.request_timeout_secs.max(security.slow_request_timeout_secs));
     let client_pool = client_pool::extension_with_events(
         &rpc_url, 
-        &event_types.into_iter().collect::<Vec<_>>()
+        &event_types.into_iter().collect::<Vec<_>>(),
+        &config.upstream,
+        Some(call_timeout),
     ).await?;
+    if config.upstream.warm_connections {
+        match client_pool.warm_up().await {
+            Ok(()) => info!("Upstream connection warmed up"),
+            Err(e) => warn!("Upstream warm-up failed, the first request will connect: {e}"),
+        }
+    }
+    if config.upstream.idle_check_secs > 0 {
+        client_pool.spawn_idle_checks(
+            Duration::from_secs(config.upstream.idle_check_secs),
+            Duration::from_secs(config.upstream.max_idle_secs),
+        );
+    }
 
-    let router = Router::new()
-        .route("/", get(index))
-        .route("/chain/last", get(chain::last::get))
+    // Upstream notifications fanned out to WebSocket subscribers
+    let mut hub = Hub::with_replay(config.websocket.replay_buffer_size)
+        .with_enrichment(ctx.events_database.clone())
+        .with_reconnect_after(config.websocket.reconnect_after_ms)
+        .with_deduplication(config.events.enable_deduplication);
+    let spill = &config.events.spill;
+    if let Some(queue) = PriorityQueue::from_strategy(&config.events.event_strategy, config.events.buffer_size) {
+        info!("Dispatching WebSocket events by priority");
+        hub = hub.with_priority(queue);
+        if let Some(dir) = &spill.dir {
+            warn!("Not spilling WebSocket events to {dir}: the priority strategy keeps its own bounded queue");
+        }
+    } else if let Some(dir) = &spill.dir {
+        let buffer = SpillBuffer::new(dir, spill.high_water, spill.max_bytes)?;
+        info!("Spilling WebSocket events past {} waiting to {}", spill.high_water, buffer.path().display());
+        hub = hub.with_spill(buffer);
+    }
+    let hub = Arc::new(hub);
+    hub.spawn_dispatch();
+    hub.attach(client_pool.get().await?.listener_manager());
+    // A replaced upstream client closes the receivers the hub drains, so attach to its successor
+    client_pool.on_refresh({
+        let hub = hub.clone();
+        move |client| {
+            hub.attach(client.listener_manager());
+        }
+    });
+    // Tell clients to reconnect elsewhere and give them a moment to go before the server stops
+    let drain_timeout = Duration::from_millis(config.websocket.drain_timeout_ms);
+    ctx.shutdown.on_shutdown({
+        let hub = hub.clone();
+        async move {
+            if !hub.drain(drain_timeout).await {
+                warn!("WebSocket connections still open after the {drain_timeout:?} drain timeout");
+            }
+        }
+    });
+
+    // Open WebSocket connections, listed and closed through `/admin/connections`
+    let connections = ConnectionRegistry::default();
+
+    // Submission results remembered per `Idempotency-Key`
+    let idempotency: IdempotencyStore<GrpcReturn> =
+        IdempotencyStore::new(Duration::from_secs(config.grpc.idempotency_ttl_secs));
+
+    // Submissions are limited apart from reads, so a flood of them cannot crowd out queries.
+    // Always in place, so `/admin/rate-limit` can turn it on during an incident.
+    let submit_limiter = RateLimiter::per_minute(config.security.submit_rate_limit);
+    // Each group spends its own budget, so heavy calls cannot use up the cheap reads'. Groups without a
+    // limit of their own take the global `security.rate_limit`.
+    let reads_limiter = RateLimiter::per_minute(config.security.route_rate_limit("reads"));
+    let grpc_limiter = RateLimiter::per_minute(config.security.route_rate_limit("grpc"));
+    let export_limiter = RateLimiter::per_minute(config.export.rate_limit);
+    // Every limiter, so `/admin/rate-limit` can tighten any group during an incident
+    let limiters: RateLimiters = Arc::new(BTreeMap::from([
+        ("submit", submit_limiter.clone()),
+        ("reads", reads_limiter.clone()),
+        ("grpc", grpc_limiter.clone()),
+        ("export", export_limiter.clone()),
+    ]));
+    let submit = post(transaction::submit::post)
+        .layer(Extension(idempotency.clone()))
+        .layer(from_fn_with_state(submit_limiter.clone(), rate_limit));
+
+    let tip_directive = SharedDirective::new(directive(config.cache.tip_max_age_secs, false));
+    let history_directive = SharedDirective::new(directive(config.cache.history_max_age_secs, true));
+
+    // Reads that follow the tip, cacheable briefly
+    let tip = Router::new()
+        .route("/node/status", get(node::status::get))
+        .route("/mempool/size", get(mempool::size))
+        .route("/daa-timestamp", get(daa_timestamp::get))
+        .route("/fee-estimate/experimental", get(fee_estimate::experimental))
+        .route("/headers", get(headers::get))
+        .route("/address/search", get(address::search::get))
+        .route("/address/{address}/balance", get(address::_address_::balance))
+        .route("/blocks", get(block::blocks::get))
+        .route("/block/{hash}/status", get(block::status::get))
+        .route("/block/{hash}/color", get(block::color::get))
+        .route("/chain/last", get(chain::last::get).head(chain::last::head))
+        .route("/chain/last/summary", get(chain::last::summary))
+        .route("/chain/stats", get(chain::last::stats))
+        .route("/subnetwork/{id}", get(subnetwork::get))
         .route("/transaction/last", get(transaction::last::get))
+        .route("/transaction/stats", get(transaction::last::stats))
+        .route("/transactions", get(transaction::list::get))
+        .route("/stats/summary", get(stats::summary))
+        .layer(from_fn_with_state(tip_directive.clone(), cache_control));
+
+    // Confirmed transactions never change
+    let history = Router::new()
         .route("/transaction/{id}", get(transaction::_id_::get))
-        .route("/grpc", post(grpc::post))
-        .route("/websocket", get(websocket::handler))
-        .with_state(client_pool)
+        .route("/transaction/{id}/raw", get(transaction::_id_::raw))
+        .route("/transaction/{id}/merkle-proof", get(transaction::merkle_proof::get))
+        .route("/utxo/{txid}/{index}/return-address", get(utxo::return_address))
+        .layer(from_fn_with_state(history_directive.clone(), cache_control));
+
+    // Settings reloaded while serving; CORS origins are followed by the CORS layer itself
+    reload::on_change(ctx.subscribe_config(), {
+        let submit_limiter = submit_limiter.clone();
+        let (reads_limiter, grpc_limiter, export_limiter) =
+            (reads_limiter.clone(), grpc_limiter.clone(), export_limiter.clone());
+        move |previous, current| {
+            if previous.log_level != current.log_level {
+                match log::set_filter(&current.log_level) {
+                    Ok(()) => info!("Log level set to {}", current.log_level),
+                    Err(e) => warn!("Log level `{}` not applied: {e}", current.log_level),
+                }
+            }
+            if previous.security.submit_rate_limit != current.security.submit_rate_limit {
+                submit_limiter.set_limit(current.security.submit_rate_limit, Duration::from_secs(60));
+            }
+            for (limiter, group) in [(&reads_limiter, "reads"), (&grpc_limiter, "grpc")] {
+                if previous.security.route_rate_limit(group) != current.security.route_rate_limit(group) {
+                    limiter.set_limit(current.security.route_rate_limit(group), Duration::from_secs(60));
+                }
+            }
+            if previous.export.rate_limit != current.export.rate_limit {
+                export_limiter.set_limit(current.export.rate_limit, Duration::from_secs(60));
+            }
+            tip_directive.set(directive(current.cache.tip_max_age_secs, false));
+            history_directive.set(directive(current.cache.history_max_age_secs, true));
+            NOTIFICATION_LOG.set_sample_rate(current.logging.notification_sample_rate);
+        }
+    });
+
+    let mut router = Router::new()
+        .route("/", get(index))
+        .route("/health", get(health::get))
+        .route("/livez", get(health::livez))
+        .route("/readyz", get(health::readyz))
+        .route("/version", get(version::get))
+        .route("/metrics", get(metrics::get))
+        .route("/transaction", submit)
+        // Batch balances are added after the cache directives, as POST responses are not cached
+        .merge(limited(
+            tip.merge(history).route("/address/balances", post(address::balances::post)),
+            reads_limiter,
+        ))
+        .merge(limited(
+            Router::new()
+                .route("/grpc", post(grpc::post).layer(Extension(idempotency)))
+                .route("/grpc/batch", post(grpc::batch)),
+            grpc_limiter,
+        ))
+        .route(
+            "/websocket",
+            get(websocket::handler)
+                .layer(Extension(hub))
+                .layer(Extension(connections.clone()))
+                .layer(Extension(ConnectionLimit::from_config(&config.websocket)))
+                .layer(Extension(MessageLimit::from_config(&config.websocket)?)),
+        );
+
+    if let Some(admin) = admin::router(&config.security) {
+        info!("Admin routes enabled under /admin");
+        router = router.nest("/admin", admin.layer(Extension(limiters)).layer(Extension(connections)));
+    }
+
+    if let Some(peers) = admin::guarded(&config.security, peers::router()) {
+        router = router.merge(peers);
+    }
+
+    // Long-running responses, limited per client by their own budget; see `with_streaming`
+    let streaming = Router::new()
+        .route("/transaction/export", get(transaction::export::get))
+        .route("/transaction/{id}/confirmations/stream", get(transaction::confirmations::stream));
+    let streaming = limited(streaming, export_limiter);
+
+    let router = with_streaming(router, streaming, &config.security)
+        // Innermost, so the queue time covers every layer ahead of the handler
+        .layer(from_fn(handler_start))
+        .layer(from_fn(pretty))
+        // Per-route traffic, counted inside routing so the matched path is known
+        .layer(from_fn(account))
+        // Shed requests are neither timed nor counted
+        .layer(load_shed(config.security.max_concurrent_requests))
+        .with_state(ctx.clone())
+        .layer(client_pool)
         .layer(
             tower::ServiceBuilder::new()
+                .layer(SecurityHeadersLayer::new(&ctx.config.security, terminates_tls))
                 .layer(tower_http::trace::TraceLayer::new_for_http())
                 .layer(crate::middleware::trace::trace())
-                .layer(crate::middleware::cors::cors(&ctx.config.cors))
-        );
+                .layer(crate::middleware::cors::live_cors(ctx.subscribe_config()))
+                .layer(RequestValidationLayer::new(&ctx.config.security))
+        )
+        // Outside every layer that can fail, so their errors are localized too
+        .layer(from_fn(language))
+        // Stamps arrival before anything else runs; see `handler_start`
+        .layer(from_fn(arrival));
 
     Ok(router)
 }
+
+/// Limit every client of `group` by `limiter`, counted apart from other groups
+fn limited<S>(group: Router<S>, limiter: RateLimiter) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    group.layer(from_fn_with_state(limiter, rate_limit))
+}
+
+/// Give each route group exactly one timeout: `regular` routes the default one and the body limit, `streaming`
+/// routes the longer `slow_request_timeout_secs` and no body limit, so long exports are not cut off. Everything
+/// layered on the result (load shedding, accounting, security headers, CORS, tracing) covers both.
+fn with_streaming<S>(regular: Router<S>, streaming: Router<S>, security: &SecurityConfig) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    regular
+        .layer(timeout(security.request_timeout_secs))
+        .layer(DefaultBodyLimit::max(security.max_body_size))
+        .layer(from_fn_with_state(security.max_body_size, limit_body))
+        .merge(streaming.layer(timeout(security.slow_request_timeout_secs)))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use http::{Request, StatusCode, header::CONTENT_LENGTH};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_streaming_routes_outlive_the_regular_timeout() {
+        let security = SecurityConfig {
+            request_timeout_secs: 1,
+            slow_request_timeout_secs: 5,
+            max_body_size: 4,
+            ..Default::default()
+        };
+        let slow = || async {
+            tokio::time::sleep(Duration::from_millis(1500)).await;
+            "done"
+        };
+        let router = with_streaming(
+            Router::new().route("/transaction", post(slow)),
+            Router::new().route("/transaction/export", post(slow)),
+            &security,
+        );
+        let request = |path: &str, body: &'static str| {
+            Request::post(path).header(CONTENT_LENGTH, body.len()).body(Body::from(body)).unwrap()
+        };
+
+        let (regular, export) = tokio::join!(
+            router.clone().oneshot(request("/transaction", "")),
+            router.clone().oneshot(request("/transaction/export", "over four bytes")),
+        );
+        assert_eq!(regular.unwrap().status(), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(export.unwrap().status(), StatusCode::OK);
+
+        let oversized = router.oneshot(request("/transaction", "over four bytes")).await.unwrap();
+        assert_eq!(oversized.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_export_leaves_the_reads_budget() {
+        let mut security = SecurityConfig::default();
+        security.route_rate_limits.insert("reads".to_string(), 2);
+        let reads = RateLimiter::per_minute(security.route_rate_limit("reads"));
+        let ok = || async { "ok" };
+        let router = limited(Router::new().route("/chain/last", get(ok)), reads)
+            .merge(limited(Router::new().route("/transaction/export", get(ok)), RateLimiter::per_minute(1)));
+        let status = |path: &'static str| {
+            let router = router.clone();
+            async move { router.oneshot(Request::get(path).body(Body::empty()).unwrap()).await.unwrap().status() }
+        };
+
+        assert_eq!(status("/transaction/export").await, StatusCode::OK);
+        assert_eq!(status("/transaction/export").await, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(status("/chain/last").await, StatusCode::OK);
+        assert_eq!(status("/chain/last").await, StatusCode::OK);
+        assert_eq!(status("/chain/last").await, StatusCode::TOO_MANY_REQUESTS);
+    }
+}
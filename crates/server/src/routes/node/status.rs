@@ -0,0 +1,57 @@
+use std::{sync::LazyLock, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tondi_rpc_core::{GetInfoRequest, GetServerInfoRequest, GetSyncStatusRequest, api::rpc::RpcApi};
+
+use crate::{
+    error::Error,
+    extensions::client_pool::ClientPool,
+    middleware::timeout::Deadline,
+    shared::{cache::TtlCache, data::Data},
+};
+
+pub static STATUS: LazyLock<TtlCache<(), NodeStatus>> =
+    LazyLock::new(|| TtlCache::new(Duration::from_secs(2)).named("node_status"));
+
+/// Flattened view of `GetServerInfo`, `GetSyncStatus` and `GetInfo`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeStatus {
+    pub is_synced: bool,
+    pub is_utxo_indexed: bool,
+    pub server_version: String,
+    pub network: String,
+    pub virtual_daa_score: u64,
+    pub mempool_size: u64,
+}
+
+/// Consolidated node status for dashboards
+pub async fn get(client_pool: ClientPool, deadline: Deadline) -> Data<NodeStatus> {
+    let status = STATUS
+        .get_or_try_insert_with((), || async {
+            let client = client_pool.get().await?;
+            let rpc = client.rpc()?;
+            let (server_info, sync_status, info) = deadline
+                .run(async {
+                    tokio::try_join!(
+                        rpc.get_server_info_call(None, GetServerInfoRequest {}),
+                        rpc.get_sync_status_call(None, GetSyncStatusRequest {}),
+                        rpc.get_info_call(None, GetInfoRequest {}),
+                    )
+                    .map_err(|e| Error::ServiceUnavailable(e.to_string()))
+                })
+                .await?;
+            client_pool.record_success();
+
+            Ok::<_, Error>(NodeStatus {
+                is_synced: sync_status.is_synced,
+                is_utxo_indexed: server_info.has_utxo_index,
+                server_version: server_info.server_version,
+                network: server_info.network_id.to_string(),
+                virtual_daa_score: server_info.virtual_daa_score,
+                mempool_size: info.mempool_size,
+            })
+        })
+        .await?;
+    Ok(status.into())
+}
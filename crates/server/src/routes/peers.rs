@@ -0,0 +1,92 @@
+use std::{sync::LazyLock, time::Duration};
+
+use axum::{Router, routing::get};
+use serde::{Deserialize, Serialize};
+use tondi_rpc_core::{
+    GetConnectedPeerInfoRequest, GetPeerAddressesRequest, RpcPeerInfo, api::rpc::RpcApi,
+};
+
+use crate::{
+    ctx::Context,
+    error::Error,
+    extensions::client_pool::ClientPool,
+    middleware::timeout::Deadline,
+    shared::{cache::TtlCache, data::Data},
+};
+
+const PEERS_TTL: Duration = Duration::from_secs(2);
+
+pub static CONNECTED: LazyLock<TtlCache<(), Vec<ConnectedPeer>>> =
+    LazyLock::new(|| TtlCache::new(PEERS_TTL).named("connected_peers"));
+pub static KNOWN: LazyLock<TtlCache<(), KnownPeers>> =
+    LazyLock::new(|| TtlCache::new(PEERS_TTL).named("known_peers"));
+
+/// Peer routes; peer data is sensitive, so callers mount these behind the admin token
+pub fn router() -> Router<Context> {
+    Router::new().route("/peers", get(connected)).route("/peers/known", get(known))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectedPeer {
+    pub id: String,
+    pub address: String,
+    pub is_outbound: bool,
+    pub user_agent: String,
+    pub last_ping_ms: u64,
+    pub time_connected: u64,
+    pub is_ibd_peer: bool,
+}
+
+impl From<RpcPeerInfo> for ConnectedPeer {
+    fn from(peer: RpcPeerInfo) -> Self {
+        Self {
+            id: peer.id.to_string(),
+            address: peer.address.to_string(),
+            is_outbound: peer.is_outbound,
+            user_agent: peer.user_agent,
+            last_ping_ms: peer.last_ping_duration,
+            time_connected: peer.time_connected,
+            is_ibd_peer: peer.is_ibd_peer,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnownPeers {
+    pub known: Vec<String>,
+    pub banned: Vec<String>,
+}
+
+/// Peers the node is currently connected to
+pub async fn connected(client_pool: ClientPool, deadline: Deadline) -> Data<Vec<ConnectedPeer>> {
+    let peers = CONNECTED
+        .get_or_try_insert_with((), || async {
+            let client = client_pool.get().await?;
+            let call = client.rpc()?.get_connected_peer_info_call(None, GetConnectedPeerInfoRequest {});
+            let response =
+                deadline.run(async { call.await.map_err(|e| Error::ServiceUnavailable(e.to_string())) }).await?;
+            client_pool.record_success();
+            Ok::<_, Error>(response.peer_info.into_iter().map(ConnectedPeer::from).collect())
+        })
+        .await?;
+    Ok(peers.into())
+}
+
+/// Addresses the node knows about, plus banned ones
+pub async fn known(client_pool: ClientPool, deadline: Deadline) -> Data<KnownPeers> {
+    let peers = KNOWN
+        .get_or_try_insert_with((), || async {
+            let client = client_pool.get().await?;
+            let call = client.rpc()?.get_peer_addresses_call(None, GetPeerAddressesRequest {});
+            let response =
+                deadline.run(async { call.await.map_err(|e| Error::ServiceUnavailable(e.to_string())) }).await?;
+            client_pool.record_success();
+            Ok::<_, Error>(KnownPeers {
+                known: response.known_addresses.iter().map(ToString::to_string).collect(),
+                banned: response.banned_addresses.iter().map(ToString::to_string).collect(),
+            })
+        })
+        .await?;
+    Ok(peers.into())
+}
@@ -0,0 +1,77 @@
+use std::{sync::LazyLock, time::Duration};
+
+use axum::extract::State;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ctx::pg_database::{PgDb, run_blocking},
+    error::Error,
+    routes::{
+        chain::last::{self as chain, ChainStats},
+        transaction::last::{self as transaction, TransactionStats},
+    },
+    shared::{cache::TtlCache, data::Data},
+};
+
+const SUMMARY_TTL: Duration = Duration::from_secs(5);
+
+pub static SUMMARY: LazyLock<TtlCache<(), StatsSummary>> =
+    LazyLock::new(|| TtlCache::new(SUMMARY_TTL).named("stats_summary"));
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsSummary {
+    pub total_blocks: i64,
+    pub total_transactions: i64,
+    pub total_outputs: i64,
+    pub latest_blue_score: i64,
+    pub latest_block_time: i64,
+}
+
+impl From<(ChainStats, TransactionStats)> for StatsSummary {
+    fn from((chain, transaction): (ChainStats, TransactionStats)) -> Self {
+        Self {
+            total_blocks: chain.total_blocks,
+            total_transactions: transaction.total_transactions,
+            total_outputs: transaction.total_outputs,
+            latest_blue_score: chain.latest_blue_score,
+            latest_block_time: transaction.latest_block_time,
+        }
+    }
+}
+
+/// Chain and transaction statistics in one object, queried concurrently
+pub async fn summary(State(db): PgDb<'static>) -> Data<StatsSummary> {
+    let summary = SUMMARY
+        .get_or_try_insert_with((), || async {
+            let chain = run_blocking(db, |conn| Ok(chain::load_stats(conn)?));
+            let transaction = run_blocking(db, |conn| Ok(transaction::load_stats(conn)?));
+            let (chain, transaction) = tokio::try_join!(chain, transaction)?;
+            Ok::<_, Error>(StatsSummary::from((chain, transaction)))
+        })
+        .await?;
+    Ok(summary.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_shape() {
+        let chain = ChainStats { total_blocks: 10, latest_timestamp: 1_000, latest_blue_score: 9 };
+        let transaction = TransactionStats { total_transactions: 20, total_outputs: 40, latest_block_time: 1_001 };
+
+        let summary = serde_json::to_value(StatsSummary::from((chain, transaction))).unwrap();
+        assert_eq!(
+            summary,
+            serde_json::json!({
+                "totalBlocks": 10,
+                "totalTransactions": 20,
+                "totalOutputs": 40,
+                "latestBlueScore": 9,
+                "latestBlockTime": 1_001,
+            })
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use axum::extract::{Path, State};
+use serde::Serialize;
+use tondi_listener_db::{
+    models::transaction::Tx,
+    query::TransactionQuery,
+    schema::tyext::subnetwork::SubnetworkId,
+};
+use tondi_rpc_core::{GetSubnetworkRequest, RpcError, RpcSubnetworkId, api::rpc::RpcApi};
+
+use crate::{
+    ctx::pg_database::{PgDb, run_blocking},
+    error::{Error, Result},
+    extensions::client_pool::ClientPool,
+    middleware::timeout::Deadline,
+    shared::data::Data,
+};
+
+/// Most recent transactions listed with a subnetwork
+const RECENT_TRANSACTIONS: i64 = 20;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Subnetwork {
+    /// The consensus id in hex
+    pub subnetwork_id: String,
+    /// The id as stored with transactions
+    pub index: SubnetworkId,
+    /// `native`, `coinbase` or `registry` for the built-in subnetworks
+    pub name: Option<&'static str>,
+    /// `None` when the node has no gas limit for the subnetwork
+    pub gas_limit: Option<u64>,
+    /// Latest indexed transactions of the subnetwork, newest first
+    pub recent_transactions: Vec<Tx>,
+}
+
+/// Gas limit of a subnetwork from the node and its latest indexed transactions;
+/// 404 when neither knows it and it is not built in
+pub async fn get(
+    Path(id): Path<String>,
+    State(db): PgDb<'static>,
+    client_pool: ClientPool,
+    deadline: Deadline,
+) -> Data<Subnetwork> {
+    let id = parse_id(&id)?;
+
+    let recent = run_blocking(db, move |conn| {
+        Ok(TransactionQuery::default().subnetwork_id(id).limit(RECENT_TRANSACTIONS).load(conn)?)
+    });
+    let gas_limit = async {
+        let client = client_pool.get().await?;
+        let request = GetSubnetworkRequest { subnetwork_id: RpcSubnetworkId::from_bytes(id.to_bytes()) };
+        let call = client.rpc()?.get_subnetwork_call(None, request);
+        let gas_limit = match deadline.run(async { Ok::<_, Error>(call.await) }).await? {
+            Ok(response) => Some(response.gas_limit),
+            // The node does not serve the call at all
+            Err(RpcError::NotImplemented) => None,
+            Err(e) => match Error::from(e) {
+                // The node has no record of the subnetwork
+                Error::NotFound(_) => None,
+                e => return Err(e),
+            },
+        };
+        client_pool.record_success();
+        Ok(gas_limit)
+    };
+    let (recent_transactions, gas_limit) = tokio::try_join!(recent, gas_limit)?;
+
+    if gas_limit.is_none() && recent_transactions.is_empty() && id.builtin_name().is_none() {
+        return Err(Error::NotFound(format!("Subnetwork {id}")));
+    }
+    Ok(Subnetwork {
+        subnetwork_id: id.to_string(),
+        index: id,
+        name: id.builtin_name(),
+        gas_limit,
+        recent_transactions,
+    }
+    .into())
+}
+
+pub(crate) fn parse_id(id: &str) -> Result<SubnetworkId> {
+    id.trim().parse().map_err(|e: tondi_listener_db::error::Error| Error::BadRequest(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+
+    #[test]
+    fn test_invalid_id_is_400() {
+        let too_wide = format!("00{}", "00".repeat(18) + "01");
+        for input in ["", "abc", "-1", "4294967296", &"zz".repeat(20), &too_wide, &"ff".repeat(4).repeat(5)] {
+            let err = parse_id(input).unwrap_err();
+            assert_eq!(err.status_code(), StatusCode::BAD_REQUEST, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_number_and_consensus_forms_agree() {
+        let coinbase = format!("01{}", "00".repeat(19));
+        assert_eq!(parse_id("1").unwrap(), SubnetworkId::COINBASE);
+        assert_eq!(parse_id(&coinbase).unwrap(), SubnetworkId::COINBASE);
+        assert_eq!(SubnetworkId::COINBASE.to_string(), coinbase);
+        assert_eq!(parse_id(&"00".repeat(20)).unwrap().builtin_name(), Some("native"));
+    }
+}
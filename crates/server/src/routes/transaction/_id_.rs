@@ -1,140 +1,403 @@
+use std::{str::FromStr, sync::LazyLock, time::Duration};
+
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::Json,
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
 };
-use tondi_listener_db::{
-    models::transaction::{Tx, TxOu},
-    schema::table::{TTx, TTxOu},
-    DieselPool,
+use http::header::{CACHE_CONTROL, CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+use tondi_rpc_core::{
+    GetMempoolEntryRequest, RpcError, RpcTransaction, RpcTransactionId, api::rpc::RpcApi,
 };
-use diesel::prelude::*;
-use serde_json::Value;
+use tondi_listener_db::models::transaction::{Tx, TxIn, TxOu};
 
-use crate::error::Result;
+use crate::{
+    ctx::store::{Db, Store, run_store},
+    error::{Error, Result},
+    extensions::client_pool::ClientPool,
+    middleware::timeout::Deadline,
+    shared::{
+        cache::TtlCache,
+        data::{Data, Inner},
+        hash::parse_hash256,
+    },
+};
 
-/// Get transaction by ID
-pub async fn get_transaction_by_id(
-    Path(transaction_id): Path<String>,
-    State(pool): State<DieselPool>,
-) -> Result<Json<Value>, (StatusCode, String)> {
-    let conn = pool.get().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Database connection error: {}", e),
-        )
-    })?;
-
-    // Get transaction by ID
-    let result: Result<Option<Tx>, diesel::result::Error> = conn
-        .transaction(|conn| {
-            TTx::table
-                .filter(TTx::transaction_id.eq(transaction_id.clone()))
-                .first::<Tx>(conn)
-                .optional()
-        });
-
-    match result {
-        Ok(Some(tx)) => {
-            // Get transaction outputs
-            let outputs_result: Result<Vec<TxOu>, diesel::result::Error> = conn
-                .transaction(|conn| {
-                    TTxOu::table
-                        .filter(TTxOu::transaction_id.eq(transaction_id.clone()))
-                        .load::<TxOu>(conn)
-                });
-
-            let outputs = match outputs_result {
-                Ok(outputs) => outputs,
-                Err(e) => {
-                    log::warn!("Failed to fetch outputs for transaction {}: {}", transaction_id, e);
-                    Vec::new()
-                }
-            };
-
-            let response = serde_json::json!({
-                "success": true,
-                "data": {
-                    "transaction": {
-                        "transaction_id": tx.transaction_id,
-                        "hash": tx.hash,
-                        "subnetwork_id": tx.subnetwork_id,
-                        "mass": tx.mass,
-                        "payload": tx.payload,
-                        "block_time": tx.block_time
-                    },
-                    "outputs": outputs.into_iter().map(|output| {
-                        serde_json::json!({
-                            "index": output.index,
-                            "amount": output.amount,
-                            "script_public_key_address": output.script_public_key_address,
-                            "block_time": output.block_time
-                        })
-                    }).collect::<Vec<_>>()
-                }
-            });
-            Ok(Json(response))
-        }
-        Ok(None) => {
-            Err((
-                StatusCode::NOT_FOUND,
-                format!("Transaction not found: {}", transaction_id),
-            ))
-        }
-        Err(e) => {
-            log::error!("Failed to fetch transaction {}: {}", transaction_id, e);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to fetch transaction: {}", e),
-            ))
+/// How long an id found neither in the DB nor the mempool skips the RPC
+const MEMPOOL_MISS_TTL: Duration = Duration::from_secs(1);
+
+pub static MEMPOOL_MISSES: LazyLock<TtlCache<RpcTransactionId, ()>> =
+    LazyLock::new(|| TtlCache::new(MEMPOOL_MISS_TTL).named("mempool_misses"));
+
+/// A confirmed transaction; related rows are only present when requested with `?include=`
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionDetail {
+    pub transaction: Tx,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inputs: Option<Vec<TxIn>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outputs: Option<Vec<TxOu>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct DetailQuery {
+    /// Comma-separated related data to load: `inputs`, `outputs`
+    #[serde(default, alias = "fields")]
+    pub include: Option<String>,
+}
+
+/// Related data of a [`TransactionDetail`] to load, none by default
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Include {
+    pub inputs: bool,
+    pub outputs: bool,
+}
+
+impl Include {
+    pub fn parse(include: Option<&str>) -> Result<Self> {
+        let mut parsed = Self::default();
+        for token in include.unwrap_or_default().split(',').map(str::trim).filter(|token| !token.is_empty()) {
+            match token {
+                "inputs" => parsed.inputs = true,
+                "outputs" => parsed.outputs = true,
+                _ => {
+                    return Err(Error::BadRequest(format!(
+                        "Unknown `include` value `{token}`, expected `inputs` or `outputs`"
+                    )));
+                },
+            }
         }
+        Ok(parsed)
     }
 }
 
-/// Get transaction outputs by transaction ID
-pub async fn get_transaction_outputs(
+/// Transaction accepted by the node but not yet in the DB
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingTransaction {
+    pub transaction_id: RpcTransactionId,
+    pub fee: u64,
+    pub is_orphan: bool,
+    pub transaction: RpcTransaction,
+}
+
+/// A transaction from broadcast through confirmation, tagged by `"status"`
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum TransactionLookup {
+    Confirmed(TransactionDetail),
+    Pending(PendingTransaction),
+}
+
+/// `?encoding=` of `/transaction/{id}/raw`
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RawEncoding {
+    /// `application/octet-stream` bytes
+    #[default]
+    Binary,
+    /// Lowercase hex text
+    Hex,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RawQuery {
+    #[serde(default)]
+    pub encoding: RawEncoding,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionOutputs {
+    pub transaction_id: String,
+    pub outputs: Vec<TxOu>,
+}
+
+/// Get transaction by ID, with the inputs and outputs named in `?include=`, falling
+/// back to the node's mempool for transactions not confirmed yet, which must not be cached
+pub async fn get(
     Path(transaction_id): Path<String>,
-    State(pool): State<DieselPool>,
-) -> Result<Json<Value>, (StatusCode, String)> {
-    let conn = pool.get().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Database connection error: {}", e),
-        )
-    })?;
-
-    // Get transaction outputs by transaction ID
-    let result: Result<Vec<TxOu>, diesel::result::Error> = conn
-        .transaction(|conn| {
-            TTxOu::table
-                .filter(TTxOu::transaction_id.eq(transaction_id.clone()))
-                .load::<TxOu>(conn)
-        });
-
-    match result {
-        Ok(outputs) => {
-            let response = serde_json::json!({
-                "success": true,
-                "data": {
-                    "transaction_id": transaction_id,
-                    "outputs": outputs.into_iter().map(|output| {
-                        serde_json::json!({
-                            "index": output.index,
-                            "amount": output.amount,
-                            "script_public_key_address": output.script_public_key_address,
-                            "block_time": output.block_time
-                        })
-                    }).collect::<Vec<_>>()
-                }
-            });
-            Ok(Json(response))
-        }
-        Err(e) => {
-            log::error!("Failed to fetch outputs for transaction {}: {}", transaction_id, e);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to fetch transaction outputs: {}", e),
-            ))
-        }
+    Query(query): Query<DetailQuery>,
+    State(store): Db,
+    client_pool: ClientPool,
+    deadline: Deadline,
+) -> Result<Response> {
+    let include = Include::parse(query.include.as_deref())?;
+    let id = decode_id(&transaction_id)?;
+    let confirmed = run_store(store, move |store| match store.transaction_by_id(&id)? {
+        Some(transaction) => Ok(Some(load_detail(store, &id, transaction, include)?)),
+        None => Ok(None),
+    })
+    .await?;
+
+    let lookup = resolve(&transaction_id, confirmed, |rpc_id| async move {
+        let client = client_pool.get().await?;
+        let request =
+            GetMempoolEntryRequest { transaction_id: rpc_id, include_orphan_pool: true, filter_transaction_pool: false };
+        let call = client.rpc()?.get_mempool_entry_call(None, request);
+        let entry = match deadline.run(async { Ok::<_, Error>(call.await) }).await? {
+            Ok(response) => response.mempool_entry,
+            Err(RpcError::TransactionNotFound(_)) => return Ok(None),
+            Err(e) => return Err(Error::ServiceUnavailable(e.to_string())),
+        };
+        client_pool.record_success();
+        Ok(Some(PendingTransaction {
+            transaction_id: rpc_id,
+            fee: entry.fee,
+            is_orphan: entry.is_orphan,
+            transaction: entry.transaction,
+        }))
+    })
+    .await?;
+    Ok(match lookup {
+        TransactionLookup::Pending(_) => ([(CACHE_CONTROL, "no-cache")], Inner::new(lookup)).into_response(),
+        TransactionLookup::Confirmed(_) => Inner::new(lookup).into_response(),
+    })
+}
+
+/// DB hit first, then the mempool; misses of both are remembered briefly
+async fn resolve<F, Fut>(
+    transaction_id: &str,
+    confirmed: Option<TransactionDetail>,
+    pending: F,
+) -> Result<TransactionLookup>
+where
+    F: FnOnce(RpcTransactionId) -> Fut,
+    Fut: Future<Output = Result<Option<PendingTransaction>>>,
+{
+    if let Some(detail) = confirmed {
+        return Ok(TransactionLookup::Confirmed(detail))
+    }
+    let not_found = || Error::NotFound(format!("Transaction {transaction_id}"));
+
+    // Ids of the wrong length can never be in the mempool
+    let rpc_id = RpcTransactionId::from_str(transaction_id).map_err(|_| not_found())?;
+    if MEMPOOL_MISSES.get(&rpc_id).is_some() {
+        return Err(not_found())
+    }
+    match pending(rpc_id).await? {
+        Some(pending) => Ok(TransactionLookup::Pending(pending)),
+        None => {
+            MEMPOOL_MISSES.insert(rpc_id, ());
+            Err(not_found())
+        },
+    }
+}
+
+/// Raw transaction payload; 404 when the transaction is unknown or has no payload
+pub async fn raw(
+    Path(transaction_id): Path<String>,
+    Query(query): Query<RawQuery>,
+    State(store): Db,
+) -> Result<Response> {
+    let id = decode_id(&transaction_id)?;
+    let transaction = run_store(store, move |store| store.transaction_by_id(&id)).await?;
+    raw_response(&transaction_id, transaction.and_then(|transaction| transaction.payload), query.encoding)
+}
+
+fn raw_response(transaction_id: &str, payload: Option<Vec<u8>>, encoding: RawEncoding) -> Result<Response> {
+    let payload = payload.ok_or_else(|| Error::NotFound(format!("Payload of transaction {transaction_id}")))?;
+    let response = match encoding {
+        RawEncoding::Binary => ([(CONTENT_TYPE, "application/octet-stream")], payload).into_response(),
+        RawEncoding::Hex => {
+            let mut encoded = vec![0; payload.len() * 2];
+            hex::hex_encode(&payload, &mut encoded).map_err(|e| Error::from(format!("{e}")))?;
+            ([(CONTENT_TYPE, "text/plain")], encoded).into_response()
+        },
+    };
+    Ok(response)
+}
+
+/// Get transaction outputs by transaction ID
+pub async fn outputs(Path(transaction_id): Path<String>, State(store): Db) -> Data<TransactionOutputs> {
+    let id = decode_id(&transaction_id)?;
+    let outputs = run_store(store, move |store| store.outputs_for_tx(&id)).await?;
+    Ok(TransactionOutputs { transaction_id, outputs }.into())
+}
+
+fn decode_id(transaction_id: &str) -> Result<Vec<u8>> {
+    Ok(parse_hash256(transaction_id, "transaction id")?.to_vec())
+}
+
+/// Query only the related rows `include` asks for
+fn load_detail(
+    store: &dyn Store,
+    id: &[u8],
+    transaction: Tx,
+    include: Include,
+) -> tondi_listener_db::error::Result<TransactionDetail> {
+    let inputs = if include.inputs { Some(store.inputs_for_tx(id)?) } else { None };
+    let outputs = if include.outputs { Some(store.outputs_for_tx(id)?) } else { None };
+    Ok(TransactionDetail { transaction, inputs, outputs })
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::to_bytes, http::StatusCode, response::IntoResponse};
+    use tondi_consensus_core::subnets::SUBNETWORK_ID_NATIVE;
+    use tondi_listener_db::schema::tyext::subnetwork::SubnetworkId;
+
+    use super::*;
+
+    fn id(byte: u8) -> String {
+        format!("{byte:02x}").repeat(32)
+    }
+
+    fn detail(transaction_id: &str) -> TransactionDetail {
+        let transaction = Tx {
+            transaction_id: transaction_id.to_string().into(),
+            subnetwork_id: SubnetworkId::NATIVE,
+            hash: transaction_id.to_string().into(),
+            mass: None,
+            payload: None,
+            block_time: 0,
+        };
+        TransactionDetail { transaction, inputs: None, outputs: None }
+    }
+
+    /// Mempool stand-in that fails the lookup if it is ever reached
+    async fn unreachable_mempool(_: RpcTransactionId) -> Result<Option<PendingTransaction>> {
+        Err(Error::InternalServerError("mempool queried".to_string()))
+    }
+
+    fn pending(transaction_id: RpcTransactionId) -> PendingTransaction {
+        let transaction = RpcTransaction {
+            version: 0,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            lock_time: 0,
+            subnetwork_id: SUBNETWORK_ID_NATIVE,
+            gas: 0,
+            payload: Vec::new(),
+            mass: 0,
+            verbose_data: None,
+        };
+        PendingTransaction { transaction_id, fee: 10, is_orphan: false, transaction }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_id_uses_error_envelope() {
+        let response = decode_id("not-hex").unwrap_err().into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["code"], "BAD_REQUEST");
+        assert_eq!(body["error"]["status"], 400);
+    }
+
+    #[test]
+    fn test_short_id_is_400() {
+        let err = decode_id(&"ab".repeat(10)).unwrap_err();
+        assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+        assert!(err.to_string().contains("transaction id"), "{err}");
+        assert_eq!(decode_id(&id(7)).unwrap(), vec![7; 32]);
+    }
+
+    #[tokio::test]
+    async fn test_raw_payload_as_hex() {
+        let response = raw_response(&id(4), Some(vec![0xde, 0xad, 0xbe, 0xef]), RawEncoding::Hex).unwrap();
+        assert_eq!(response.headers()[CONTENT_TYPE], "text/plain");
+        assert_eq!(to_bytes(response.into_body(), usize::MAX).await.unwrap(), "deadbeef");
+
+        let response = raw_response(&id(4), Some(vec![0xde, 0xad]), RawEncoding::Binary).unwrap();
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/octet-stream");
+        assert_eq!(to_bytes(response.into_body(), usize::MAX).await.unwrap().as_ref(), [0xde, 0xad]);
+    }
+
+    #[test]
+    fn test_null_payload_is_404() {
+        let err = raw_response(&id(5), None, RawEncoding::Hex).unwrap_err();
+        assert_eq!(err.into_response().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_confirmed_skips_mempool() {
+        let transaction_id = id(1);
+        let lookup = resolve(&transaction_id, Some(detail(&transaction_id)), unreachable_mempool).await.unwrap();
+        assert_eq!(serde_json::to_value(&lookup).unwrap()["status"], "confirmed");
+    }
+
+    #[tokio::test]
+    async fn test_pending_falls_back_to_mempool() {
+        let lookup = resolve(&id(2), None, |rpc_id| async move { Ok(Some(pending(rpc_id))) }).await.unwrap();
+        let lookup = serde_json::to_value(&lookup).unwrap();
+        assert_eq!(lookup["status"], "pending");
+        assert_eq!(lookup["fee"], 10);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_is_404_and_miss_is_cached() {
+        let transaction_id = id(3);
+        let err = resolve(&transaction_id, None, |_| async { Ok(None) }).await.unwrap_err();
+        assert_eq!(err.into_response().status(), StatusCode::NOT_FOUND);
+
+        // A 500 here would mean the cached miss still reached the mempool
+        let err = resolve(&transaction_id, None, unreachable_mempool).await.unwrap_err();
+        assert_eq!(err.into_response().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_default_detail_is_lean() {
+        assert_eq!(Include::parse(None).unwrap(), Include::default());
+        assert_eq!(Include::parse(Some("")).unwrap(), Include::default());
+
+        let detail = serde_json::to_value(detail(&id(6))).unwrap();
+        assert!(detail.get("inputs").is_none() && detail.get("outputs").is_none());
+    }
+
+    #[test]
+    fn test_include_outputs() {
+        assert_eq!(Include::parse(Some("outputs")).unwrap(), Include { inputs: false, outputs: true });
+
+        let detail = TransactionDetail { outputs: Some(Vec::new()), ..detail(&id(7)) };
+        let detail = serde_json::to_value(detail).unwrap();
+        assert_eq!(detail["outputs"], serde_json::json!([]));
+        assert!(detail.get("inputs").is_none());
+    }
+
+    #[test]
+    fn test_include_inputs_and_outputs() {
+        let both = Include { inputs: true, outputs: true };
+        assert_eq!(Include::parse(Some("inputs,outputs")).unwrap(), both);
+        assert_eq!(Include::parse(Some(" outputs , inputs ")).unwrap(), both);
+    }
+
+    #[tokio::test]
+    async fn test_outputs_and_payload_come_from_the_store() {
+        use std::sync::Arc;
+
+        use crate::ctx::store::MemoryStore;
+
+        let output = |index| TxOu {
+            transaction_id: id(8).into(),
+            index,
+            amount: 100 * i64::from(index),
+            script_public_key: Vec::new(),
+            script_public_key_address: "tondi:known".to_string(),
+            block_time: 0,
+        };
+        let store = MemoryStore::default();
+        let transaction = Tx { payload: Some(vec![0xca, 0xfe]), ..detail(&id(8)).transaction };
+        store.insert_transaction(transaction, Vec::new(), vec![output(1), output(0)]);
+        let store: Arc<dyn Store> = Arc::new(store);
+
+        let found = outputs(Path(id(8)), State(store.clone())).await.unwrap().data.unwrap();
+        assert_eq!(found.outputs.iter().map(|output| output.index).collect::<Vec<_>>(), [0, 1]);
+        let none = outputs(Path(id(9)), State(store.clone())).await.unwrap().data.unwrap();
+        assert!(none.outputs.is_empty());
+
+        let query = || Query(RawQuery { encoding: RawEncoding::Hex });
+        let response = raw(Path(id(8)), query(), State(store.clone())).await.unwrap();
+        assert_eq!(to_bytes(response.into_body(), usize::MAX).await.unwrap(), "cafe");
+        let err = raw(Path(id(9)), query(), State(store)).await.unwrap_err();
+        assert_eq!(err.status_code(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_unknown_include_is_400() {
+        let err = Include::parse(Some("outputs,witnesses")).unwrap_err();
+        assert_eq!(err.into_response().status(), StatusCode::BAD_REQUEST);
     }
 }
@@ -0,0 +1,260 @@
+use std::{convert::Infallible, future::ready, pin::Pin, sync::Arc};
+
+use axum::{
+    extract::{Path, State},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use diesel::prelude::*;
+use futures::{Stream, StreamExt, stream};
+use serde::Serialize;
+use tondi_listener_db::schema::table::{TBlockTx, THeader};
+use tondi_listener_library::log::warn;
+use tondi_rpc_core::{GetSinkBlueScoreRequest, api::rpc::RpcApi};
+
+use crate::{
+    ctx::{
+        config::Config,
+        event_config::EventType,
+        pg_database::{PgDb, run_blocking},
+    },
+    error::{Error, Result},
+    extensions::client_pool::{Client, ClientPool, listener::ListenerManager},
+    middleware::timeout::Deadline,
+    shared::{
+        hash::parse_hash256,
+        pool::{NotificationPayload, NotificationReceiver, Pool},
+    },
+};
+
+/// Sink blue scores as the node reports them
+type BlueScores = Pin<Box<dyn Stream<Item = u64> + Send>>;
+
+/// Confirmations of a transaction at one sink blue score
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Progress {
+    pub confirmations: u64,
+    pub blue_score: u64,
+    pub target: u64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Update {
+    Confirmations(Progress),
+    /// `target` was reached; the stream ends after it
+    Done(Progress),
+}
+
+impl Update {
+    fn into_event(self) -> Event {
+        let (name, progress) = match self {
+            Self::Confirmations(progress) => ("confirmations", progress),
+            Self::Done(progress) => ("done", progress),
+        };
+        Event::default().event(name).json_data(progress).unwrap_or_else(|_| Event::default().event(name))
+    }
+}
+
+/// Server-Sent Events with the confirmations of a transaction: a `confirmations` event now and whenever the
+/// count changes as the sink blue score advances, then `done` once `transaction.confirmation_target` is
+/// reached. A transaction not indexed yet has `0`. Closing the connection drops the subscription.
+pub async fn stream(
+    Path(transaction_id): Path<String>,
+    State(db): PgDb<'static>,
+    State(config): State<&'static Config>,
+    client_pool: ClientPool,
+    deadline: Deadline,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    let id = parse_hash256(&transaction_id, "transaction id")?.to_vec();
+    let (current, scores) = {
+        let client = client_pool.get().await?;
+        // Subscribe first, so no advance is missed between the two
+        let scores = blue_scores(client_pool.0.clone(), client.listener_manager())?;
+        let call = client.rpc()?.get_sink_blue_score_call(None, GetSinkBlueScoreRequest {});
+        let response = deadline
+            .run(async { Ok::<_, Error>(call.await) })
+            .await?
+            .map_err(|e| Error::ServiceUnavailable(e.to_string()))?;
+        client_pool.record_success();
+        (response.blue_score, scores)
+    };
+
+    let lookup = move || {
+        let id = id.clone();
+        async move {
+            run_blocking(db, move |conn| included_at(conn, &id)).await.unwrap_or_else(|e| {
+                warn!("Cannot look up the block of a streamed transaction: {e}");
+                None
+            })
+        }
+    };
+    let scores = stream::once(ready(current)).chain(scores);
+    let events =
+        updates(config.transaction.confirmation_target, scores, lookup).map(|update| Ok(update.into_event()));
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// Follow the sink blue score with `sink-blue-score-changed` events, or the headers of `block-added` ones,
+/// moving on to the client of `pool` that replaces the one `listeners` belongs to
+fn blue_scores(pool: Arc<Pool<Client>>, listeners: &ListenerManager) -> Result<BlueScores> {
+    let receiver = blue_score_receiver(listeners)?;
+    let notifications = stream::unfold((receiver, pool), |(mut receiver, pool)| async move {
+        loop {
+            if let Some(notification) = receiver.recv().await {
+                return Some((notification, (receiver, pool)));
+            }
+            // The channel closes with the client it belongs to; end only when no successor serves the events
+            receiver = blue_score_receiver(pool.get().await.ok()?.listener_manager()).ok()?;
+        }
+    });
+    Ok(Box::pin(notifications.filter_map(|notification| {
+        ready(match notification.payload {
+            NotificationPayload::SinkBlueScoreChanged(n) => Some(n.sink_blue_score),
+            NotificationPayload::BlockAdded(n) => Some(n.block.header.blue_score),
+            _ => None,
+        })
+    })))
+}
+
+fn blue_score_receiver(listeners: &ListenerManager) -> Result<NotificationReceiver> {
+    listeners.get(&EventType::SinkBlueScoreChanged).or_else(|_| listeners.get(&EventType::BlockAdded)).map_err(|_| {
+        Error::ServiceUnavailable("Neither sink-blue-score-changed nor block-added events are subscribed".into())
+    })
+}
+
+/// Lowest blue score of the indexed blocks containing the transaction
+fn included_at(conn: &mut PgConnection, id: &[u8]) -> Result<Option<u64>> {
+    let blocks = TBlockTx::table
+        .filter(TBlockTx::transaction_id.eq(id))
+        .select(TBlockTx::block_hash)
+        .load::<Vec<u8>>(conn)?;
+    if blocks.is_empty() {
+        return Ok(None);
+    }
+    let blue_score = THeader::table
+        .filter(THeader::hash.eq_any(&blocks))
+        .select(diesel::dsl::min(THeader::blue_score))
+        .first::<Option<i64>>(conn)?;
+    Ok(blue_score.and_then(|score| u64::try_from(score).ok()))
+}
+
+/// The including block and every block above it up to `sink_blue_score`; `0` before inclusion
+pub fn confirmations(sink_blue_score: u64, included_at: Option<u64>) -> u64 {
+    match included_at {
+        Some(at) if sink_blue_score >= at => sink_blue_score - at + 1,
+        _ => 0,
+    }
+}
+
+struct Follow<S, L> {
+    scores: S,
+    lookup: L,
+    included_at: Option<u64>,
+    last_score: Option<u64>,
+    last_sent: Option<u64>,
+    /// Sent after the update that reached the target, ending the stream
+    done: Option<Progress>,
+    finished: bool,
+}
+
+/// An update for every advance of `scores` that changes the count, looking the transaction up with `lookup`
+/// until it is included, and `Done` once `target` confirmations are reached
+pub fn updates<S, L, F>(target: u64, scores: S, lookup: L) -> impl Stream<Item = Update>
+where
+    S: Stream<Item = u64> + Unpin,
+    L: FnMut() -> F,
+    F: Future<Output = Option<u64>>,
+{
+    let target = target.max(1);
+    let follow =
+        Follow { scores, lookup, included_at: None, last_score: None, last_sent: None, done: None, finished: false };
+    stream::unfold(follow, move |mut follow| async move {
+        if let Some(progress) = follow.done.take() {
+            follow.finished = true;
+            return Some((Update::Done(progress), follow));
+        }
+        if follow.finished {
+            return None;
+        }
+        loop {
+            let blue_score = follow.scores.next().await?;
+            if follow.last_score.is_some_and(|last| blue_score <= last) {
+                continue;
+            }
+            follow.last_score = Some(blue_score);
+            if follow.included_at.is_none() {
+                follow.included_at = (follow.lookup)().await;
+            }
+            let confirmations = confirmations(blue_score, follow.included_at);
+            let progress = Progress { confirmations, blue_score, target };
+            if follow.last_sent == Some(progress.confirmations) {
+                continue;
+            }
+            follow.last_sent = Some(progress.confirmations);
+            if progress.confirmations >= target {
+                follow.done = Some(progress);
+            }
+            return Some((Update::Confirmations(progress), follow));
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    };
+
+    use futures::channel::mpsc;
+
+    use super::*;
+
+    fn progress(confirmations: u64, blue_score: u64) -> Progress {
+        Progress { confirmations, blue_score, target: 3 }
+    }
+
+    #[tokio::test]
+    async fn test_score_advances_stream_confirmations_until_done() {
+        let (advance, scores) = mpsc::unbounded();
+        let lookups = Arc::new(AtomicU32::new(0));
+        let lookup = {
+            let lookups = lookups.clone();
+            // Indexed at blue score 10 from the second lookup on
+            move || ready((lookups.fetch_add(1, Ordering::Relaxed) > 0).then_some(10))
+        };
+        let mut updates = Box::pin(updates(3, scores, lookup));
+
+        advance.unbounded_send(9).unwrap();
+        assert_eq!(updates.next().await, Some(Update::Confirmations(progress(0, 9))));
+        // Stale and repeated scores are skipped
+        for score in [8, 9, 10] {
+            advance.unbounded_send(score).unwrap();
+        }
+        assert_eq!(updates.next().await, Some(Update::Confirmations(progress(1, 10))));
+        advance.unbounded_send(11).unwrap();
+        assert_eq!(updates.next().await, Some(Update::Confirmations(progress(2, 11))));
+        advance.unbounded_send(14).unwrap();
+        assert_eq!(updates.next().await, Some(Update::Confirmations(progress(5, 14))));
+        assert_eq!(updates.next().await, Some(Update::Done(progress(5, 14))));
+        assert_eq!(updates.next().await, None);
+
+        // Once found, the including block is not looked up again
+        assert_eq!(lookups.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn test_closed_notifications_end_the_stream() {
+        let scores = stream::iter([1, 2]);
+        let all: Vec<_> = updates(3, scores, || ready(None)).collect().await;
+        assert_eq!(all, [Update::Confirmations(progress(0, 1))]);
+    }
+
+    #[test]
+    fn test_confirmations_count_the_including_block() {
+        assert_eq!(confirmations(10, None), 0);
+        assert_eq!(confirmations(9, Some(10)), 0);
+        assert_eq!(confirmations(10, Some(10)), 1);
+        assert_eq!(confirmations(15, Some(10)), 6);
+    }
+}
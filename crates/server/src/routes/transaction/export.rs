@@ -0,0 +1,165 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{Query, State},
+    http::header::CONTENT_TYPE,
+    response::{IntoResponse, Response},
+};
+use diesel::prelude::*;
+use futures::{Stream, stream};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tondi_listener_db::{models::transaction::Tx, schema::table::TTx};
+
+use crate::{
+    ctx::{config::Config, pg_database::PgDb},
+    error::{Error, Result},
+};
+
+/// Keyset position of the last row emitted: `(block_time, transaction_id)`
+type Cursor = (i64, Vec<u8>);
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    /// Inclusive lower bound on `block_time`
+    pub from: i64,
+    /// Exclusive upper bound on `block_time`
+    pub to: i64,
+}
+
+/// Stream transactions in `[from, to)` as newline-delimited JSON, one `Tx` per line
+pub async fn get(
+    State(config): State<&'static Config>,
+    State(db): PgDb<'static>,
+    Query(ExportQuery { from, to }): Query<ExportQuery>,
+) -> Result<Response> {
+    check_range(from, to, config.export.max_range_ms)?;
+
+    let pool = (**db).clone();
+    let chunk_size = config.export.chunk_size;
+    let stream = export_stream(move |cursor| {
+        let mut conn = pool.get()?;
+        let mut query = TTx::table
+            .filter(TTx::block_time.ge(from))
+            .filter(TTx::block_time.lt(to))
+            .order((TTx::block_time.asc(), TTx::transaction_id.asc()))
+            .limit(chunk_size)
+            .into_boxed();
+        if let Some((block_time, transaction_id)) = cursor {
+            query = query.filter(
+                TTx::block_time
+                    .gt(*block_time)
+                    .or(TTx::block_time.eq(*block_time).and(TTx::transaction_id.gt(transaction_id.clone()))),
+            );
+        }
+        Ok(query.select(Tx::as_select()).load(&mut conn)?)
+    });
+
+    Ok(([(CONTENT_TYPE, "application/x-ndjson")], Body::from_stream(stream)).into_response())
+}
+
+/// `400` unless `from < to` and the range spans at most `max_range_ms`, including spans too wide for an `i64`
+fn check_range(from: i64, to: i64, max_range_ms: i64) -> Result<()> {
+    if to <= from {
+        return Err(Error::BadRequest("`to` must be greater than `from`".to_string()));
+    }
+    if to.checked_sub(from).is_none_or(|span| span > max_range_ms) {
+        return Err(Error::BadRequest(format!("Export range exceeds the maximum of {max_range_ms} ms")));
+    }
+    Ok(())
+}
+
+/// Drive `fetch_chunk` on the blocking pool until it returns an empty chunk,
+/// forwarding each row as one NDJSON line. Stops early once the client goes away.
+fn export_stream<F>(mut fetch_chunk: F) -> impl Stream<Item = Result<Bytes>>
+where
+    F: FnMut(Option<&Cursor>) -> Result<Vec<Tx>> + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel(4);
+
+    tokio::task::spawn_blocking(move || {
+        let mut cursor = None;
+        loop {
+            let rows = match fetch_chunk(cursor.as_ref()) {
+                Ok(rows) if rows.is_empty() => return,
+                Ok(rows) => rows,
+                Err(err) => {
+                    let _ = sender.blocking_send(Err(err));
+                    return;
+                },
+            };
+            for tx in &rows {
+                if sender.blocking_send(ndjson_line(tx)).is_err() {
+                    return;
+                }
+            }
+            match last_cursor(&rows) {
+                Ok(next) => cursor = Some(next),
+                Err(err) => {
+                    let _ = sender.blocking_send(Err(err));
+                    return;
+                },
+            }
+        }
+    });
+
+    stream::unfold(receiver, |mut receiver| async move { receiver.recv().await.map(|line| (line, receiver)) })
+}
+
+/// Cursor of the last row of a non-empty chunk
+fn last_cursor(rows: &[Tx]) -> Result<Cursor> {
+    let last = rows.last().ok_or_else(|| Error::InternalServerError("Empty export chunk".to_string()))?;
+    Ok((last.block_time, last.transaction_id.decode()?))
+}
+
+fn ndjson_line(tx: &Tx) -> Result<Bytes> {
+    let mut line = serde_json::to_vec(tx).map_err(|e| Error::InternalServerError(e.to_string()))?;
+    line.push(b'\n');
+    Ok(line.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use http::StatusCode;
+    use tondi_listener_db::schema::tyext::subnetwork::SubnetworkId;
+
+    use super::*;
+
+    fn tx(block_time: i64, id: u8) -> Tx {
+        Tx {
+            transaction_id: format!("{id:064x}").into(),
+            subnetwork_id: SubnetworkId::NATIVE,
+            hash: format!("{id:064x}").into(),
+            mass: None,
+            payload: None,
+            block_time,
+        }
+    }
+
+    #[test]
+    fn test_ranges_beyond_the_maximum_are_400() {
+        let max = 1_000;
+        assert!(check_range(0, 1_000, max).is_ok());
+        for (from, to) in [(0, 0), (10, 5), (0, 1_001), (i64::MIN, i64::MAX), (-1, i64::MAX)] {
+            let err = check_range(from, to, max).unwrap_err();
+            assert_eq!(err.status_code(), StatusCode::BAD_REQUEST, "{from}..{to}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export_stream_yields_one_line_per_row() {
+        let rows: Vec<(i64, u8)> = (0..5).map(|i| (1000 + i64::from(i), i)).collect();
+        let stream = export_stream(move |cursor| {
+            let after = cursor.map_or(i64::MIN, |(time, _)| *time);
+            Ok(rows.iter().filter(|(time, _)| *time > after).take(2).map(|&(time, id)| tx(time, id)).collect())
+        });
+
+        let lines: Vec<Bytes> = stream.map(Result::unwrap).collect().await;
+        assert_eq!(lines.len(), 5);
+        for line in &lines {
+            assert_eq!(line.last(), Some(&b'\n'));
+            let parsed: serde_json::Value = serde_json::from_slice(line).unwrap();
+            assert!(parsed.get("transactionId").is_some());
+        }
+    }
+}
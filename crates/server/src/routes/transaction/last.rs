@@ -1,105 +1,51 @@
-use axum::{
-    extract::State,
-    http::StatusCode,
-    response::Json,
-};
+use axum::extract::State;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
 use tondi_listener_db::{
-    models::transaction::{Tx, TxOu},
+    models::transaction::Tx,
     schema::table::{TTx, TTxOu},
-    DieselPool,
 };
-use diesel::prelude::*;
-use serde_json::Value;
-
-use crate::error::Result;
 
-/// Get the latest transaction information
-pub async fn get_last_transaction(
-    State(pool): State<DieselPool>,
-) -> Result<Json<Value>, (StatusCode, String)> {
-    let conn = pool.get().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Database connection error: {}", e),
-        )
-    })?;
+use crate::{
+    ctx::pg_database::{PgDb, run_blocking},
+    shared::data::Data,
+};
 
-    // Get the latest transaction by block time
-    let result: Result<Tx, diesel::result::Error> = conn
-        .transaction(|conn| {
-            TTx::table
-                .order(TTx::block_time.desc())
-                .first::<Tx>(conn)
-        });
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionStats {
+    pub total_transactions: i64,
+    pub total_outputs: i64,
+    /// `0` while nothing has been indexed
+    pub latest_block_time: i64,
+}
 
-    match result {
-        Ok(tx) => {
-            let response = serde_json::json!({
-                "success": true,
-                "data": {
-                    "transaction_id": tx.transaction_id,
-                    "hash": tx.hash,
-                    "subnetwork_id": tx.subnetwork_id,
-                    "mass": tx.mass,
-                    "block_time": tx.block_time
-                }
-            });
-            Ok(Json(response))
-        }
-        Err(e) => {
-            log::error!("Failed to fetch latest transaction: {}", e);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to fetch latest transaction: {}", e),
-            ))
-        }
-    }
+/// Get the latest transaction by block time; 404 while none has been indexed
+pub async fn get(State(db): PgDb<'static>) -> Data<Tx> {
+    let tx = run_blocking(db, |conn| {
+        Ok(TTx::table.order(TTx::block_time.desc()).select(Tx::as_select()).first(conn)?)
+    })
+    .await?;
+    Ok(tx.into())
 }
 
 /// Get transaction statistics
-pub async fn get_transaction_stats(
-    State(pool): State<DieselPool>,
-) -> Result<Json<Value>, (StatusCode, String)> {
-    let conn = pool.get().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Database connection error: {}", e),
-        )
-    })?;
-
-    // Get transaction statistics
-    let result: Result<(i64, i64, i64), diesel::result::Error> = conn
-        .transaction(|conn| {
-            let total_transactions = TTx::table.count().get_result::<i64>(conn)?;
-            let total_outputs = TTxOu::table.count().get_result::<i64>(conn)?;
-            let latest_block_time = TTx::table
-                .select(TTx::block_time)
-                .order(TTx::block_time.desc())
-                .first::<i64>(conn)
-                .optional()?
-                .unwrap_or(0);
-            
-            Ok((total_transactions, total_outputs, latest_block_time))
-        });
+pub async fn stats(State(db): PgDb<'static>) -> Data<TransactionStats> {
+    Ok(run_blocking(db, |conn| Ok(load_stats(conn)?)).await?.into())
+}
 
-    match result {
-        Ok((total_transactions, total_outputs, latest_block_time)) => {
-            let response = serde_json::json!({
-                "success": true,
-                "data": {
-                    "total_transactions": total_transactions,
-                    "total_outputs": total_outputs,
-                    "latest_block_time": latest_block_time
-                }
-            });
-            Ok(Json(response))
-        }
-        Err(e) => {
-            log::error!("Failed to fetch transaction stats: {}", e);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to fetch transaction stats: {}", e),
-            ))
-        }
-    }
+pub(crate) fn load_stats(conn: &mut PgConnection) -> QueryResult<TransactionStats> {
+    conn.transaction(|conn| {
+        let latest_block_time = TTx::table
+            .select(TTx::block_time)
+            .order(TTx::block_time.desc())
+            .first::<i64>(conn)
+            .optional()?
+            .unwrap_or(0);
+        Ok(TransactionStats {
+            total_transactions: TTx::table.count().get_result(conn)?,
+            total_outputs: TTxOu::table.count().get_result(conn)?,
+            latest_block_time,
+        })
+    })
 }
@@ -0,0 +1,132 @@
+use axum::extract::{Query, State};
+use serde::Deserialize;
+use tondi_listener_db::{
+    models::transaction::Tx,
+    query::{Order, TransactionQuery},
+};
+
+use crate::{
+    ctx::store::{Db, run_store},
+    error::{Error, Result},
+    routes::subnetwork::parse_id,
+    shared::data::Data,
+};
+
+/// Transactions per page without a `limit`
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 100;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TransactionsQuery {
+    /// Subnetwork number or 40-character consensus id
+    subnetwork_id: Option<String>,
+    block_time_from: Option<i64>,
+    block_time_to: Option<i64>,
+    min_mass: Option<i32>,
+    max_mass: Option<i32>,
+    /// `asc` or `desc` by block time
+    #[serde(default)]
+    order: Order,
+    limit: Option<i64>,
+}
+
+/// Indexed transactions passing the filters given, newest first unless `order=asc`
+pub async fn get(Query(query): Query<TransactionsQuery>, State(store): Db) -> Data<Vec<Tx>> {
+    let query = query.build()?;
+    Ok(run_store(store, move |store| store.transactions(&query)).await?.into())
+}
+
+impl TransactionsQuery {
+    /// `400` for an invalid subnetwork id, an empty range or a limit out of `1..=MAX_LIMIT`
+    fn build(&self) -> Result<TransactionQuery> {
+        if self.block_time_from.zip(self.block_time_to).is_some_and(|(from, to)| to <= from) {
+            return Err(Error::BadRequest("`block_time_to` must be greater than `block_time_from`".to_string()));
+        }
+        if self.min_mass.zip(self.max_mass).is_some_and(|(min, max)| max < min) {
+            return Err(Error::BadRequest("`max_mass` must not be less than `min_mass`".to_string()));
+        }
+        let limit = self.limit.unwrap_or(DEFAULT_LIMIT);
+        if !(1..=MAX_LIMIT).contains(&limit) {
+            return Err(Error::BadRequest(format!("`limit` must be between 1 and {MAX_LIMIT}")));
+        }
+        Ok(TransactionQuery {
+            subnetwork_id: self.subnetwork_id.as_deref().map(parse_id).transpose()?,
+            block_time_from: self.block_time_from,
+            block_time_to: self.block_time_to,
+            min_mass: self.min_mass,
+            max_mass: self.max_mass,
+            order: self.order,
+            limit: Some(limit),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use http::{StatusCode, Uri};
+    use tondi_listener_db::schema::tyext::subnetwork::SubnetworkId;
+
+    use super::*;
+    use crate::ctx::store::{MemoryStore, Store};
+
+    fn query(query: &str) -> TransactionsQuery {
+        let uri: Uri = format!("/transactions?{query}").parse().unwrap();
+        Query::try_from_uri(&uri).unwrap().0
+    }
+
+    fn tx(block_time: i64, subnetwork_id: SubnetworkId, mass: Option<i32>) -> Tx {
+        Tx {
+            transaction_id: format!("{block_time:064x}").into(),
+            subnetwork_id,
+            hash: format!("{block_time:064x}").into(),
+            mass,
+            payload: None,
+            block_time,
+        }
+    }
+
+    #[test]
+    fn test_invalid_filters_are_400() {
+        for params in [
+            "subnetwork_id=xyz",
+            "block_time_from=20&block_time_to=10",
+            "min_mass=5&max_mass=4",
+            "limit=0",
+            "limit=101",
+        ] {
+            let err = query(params).build().unwrap_err();
+            assert_eq!(err.status_code(), StatusCode::BAD_REQUEST, "{params}");
+        }
+        let built = query("").build().unwrap();
+        assert_eq!(built, TransactionQuery::default().limit(DEFAULT_LIMIT));
+    }
+
+    #[tokio::test]
+    async fn test_filters_combine() {
+        let store = MemoryStore::default();
+        for row in [
+            tx(1, SubnetworkId::NATIVE, Some(100)),
+            tx(2, SubnetworkId::COINBASE, Some(2_000)),
+            tx(3, SubnetworkId::NATIVE, None),
+            tx(4, SubnetworkId::NATIVE, Some(900)),
+        ] {
+            store.insert_transaction(row, Vec::new(), Vec::new());
+        }
+        let store: Arc<dyn Store> = Arc::new(store);
+
+        let times = |params: &'static str| {
+            let store = store.clone();
+            async move {
+                let rows = get(Query(query(params)), State(store)).await.unwrap().data.unwrap();
+                rows.iter().map(|tx| tx.block_time).collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(times("").await, [4, 3, 2, 1]);
+        assert_eq!(times("subnetwork_id=0").await, [4, 3, 1]);
+        assert_eq!(times("subnetwork_id=0&min_mass=500").await, [4]);
+        assert_eq!(times("block_time_from=2&block_time_to=4&order=asc").await, [2, 3]);
+        assert_eq!(times("min_mass=100&max_mass=1000&limit=1").await, [4]);
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+
+use axum::extract::{Path, State};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use tondi_hashes::{Hash, ZERO_HASH};
+use tondi_listener_db::schema::table::{TBlockTx, THeader, TTx};
+use tondi_merkle::merkle_hash;
+
+use crate::{
+    ctx::pg_database::{PgDb, run_blocking},
+    error::{Error, Result},
+    shared::{data::Data, hash::parse_hash256},
+};
+
+/// Inclusion proof of a transaction in the hash merkle root of a block.
+///
+/// Leaves are the transaction hashes (not ids) in block order. Each parent is
+/// BLAKE2b-256 keyed with `MerkleBranchHash` over `left || right`, a missing
+/// right node counts as the zero hash, and a lone leaf is its own root.
+/// `siblings` run from the leaf level up; bit `n` of `index` is `0` when the
+/// running hash is the left input at level `n`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MerkleProof {
+    pub transaction_id: String,
+    pub transaction_hash: String,
+    pub block_hash: String,
+    pub hash_merkle_root: String,
+    pub index: usize,
+    pub siblings: Vec<String>,
+}
+
+/// Merkle branch of a transaction within the (first indexed) block containing it
+pub async fn get(Path(transaction_id): Path<String>, State(db): PgDb<'static>) -> Data<MerkleProof> {
+    let id = parse_hash256(&transaction_id, "transaction id")?.to_vec();
+    Ok(run_blocking(db, move |conn| load_proof(conn, transaction_id, &id)).await?.into())
+}
+
+fn load_proof(conn: &mut PgConnection, transaction_id: String, id: &[u8]) -> Result<MerkleProof> {
+    let block_hash = TBlockTx::table
+        .filter(TBlockTx::transaction_id.eq(id))
+        .select(TBlockTx::block_hash)
+        .first::<Vec<u8>>(conn)
+        .optional()?
+        .ok_or_else(|| Error::NotFound(format!("Block containing transaction {transaction_id}")))?;
+    let root = THeader::table
+        .filter(THeader::hash.eq(&block_hash))
+        .select(THeader::hash_merkle_root)
+        .first::<Vec<u8>>(conn)
+        .optional()?
+        .ok_or_else(|| Error::NotFound(format!("Block {}", to_hash(&block_hash)?)))?;
+
+    let ids = TBlockTx::table
+        .filter(TBlockTx::block_hash.eq(&block_hash))
+        .order(TBlockTx::index.asc())
+        .select(TBlockTx::transaction_id)
+        .load::<Vec<u8>>(conn)?;
+    let hashes: HashMap<Vec<u8>, Vec<u8>> = TTx::table
+        .filter(TTx::transaction_id.eq_any(&ids))
+        .select((TTx::transaction_id, TTx::hash))
+        .load::<(Vec<u8>, Vec<u8>)>(conn)?
+        .into_iter()
+        .collect();
+    let leaves = ids
+        .iter()
+        .map(|id| match hashes.get(id) {
+            Some(hash) => to_hash(hash),
+            None => Err(Error::NotFound(format!("Transaction {} of the block", to_hash(id)?))),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let index = ids
+        .iter()
+        .position(|block_tx| *block_tx == *id)
+        .ok_or_else(|| Error::NotFound(format!("Transaction {transaction_id} in its block")))?;
+
+    let root = to_hash(&root)?;
+    let siblings = branch(&leaves, index);
+    if fold(leaves[index], index, &siblings) != root {
+        return Err(Error::InternalServerError(format!(
+            "Indexed transactions of block {} do not add up to its hash merkle root",
+            to_hash(&block_hash)?
+        )));
+    }
+
+    Ok(MerkleProof {
+        transaction_id,
+        transaction_hash: leaves[index].to_string(),
+        block_hash: to_hash(&block_hash)?.to_string(),
+        hash_merkle_root: root.to_string(),
+        index,
+        siblings: siblings.iter().map(ToString::to_string).collect(),
+    })
+}
+
+fn to_hash(bytes: &[u8]) -> Result<Hash> {
+    Hash::try_from_slice(bytes).map_err(|e| Error::InternalServerError(format!("Stored hash is malformed: {e}")))
+}
+
+/// Sibling of the node at `index` on every level below the root, leaf level first
+fn branch(leaves: &[Hash], mut index: usize) -> Vec<Hash> {
+    let mut level = leaves.to_vec();
+    let mut siblings = Vec::new();
+    while level.len() > 1 {
+        siblings.push(level.get(index ^ 1).copied().unwrap_or(ZERO_HASH));
+        level =
+            level.chunks(2).map(|pair| merkle_hash(pair[0], pair.get(1).copied().unwrap_or(ZERO_HASH))).collect();
+        index /= 2;
+    }
+    siblings
+}
+
+/// Root implied by `leaf` at `index` and its `siblings`
+fn fold(leaf: Hash, mut index: usize, siblings: &[Hash]) -> Hash {
+    siblings.iter().fold(leaf, |node, &sibling| {
+        let parent = if index.is_multiple_of(2) { merkle_hash(node, sibling) } else { merkle_hash(sibling, node) };
+        index /= 2;
+        parent
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(byte: u8) -> Hash {
+        Hash::from_bytes([byte; 32])
+    }
+
+    #[test]
+    fn test_branches_fold_to_the_known_root() {
+        let root: Hash = "36457e2a09aafae4a142eb100f90b96112f75f8187fd0ee00df0b33df7754cb0".parse().unwrap();
+        let leaves = [leaf(1), leaf(2), leaf(3)];
+        for (index, leaf) in leaves.iter().enumerate() {
+            let siblings = branch(&leaves, index);
+            assert_eq!(siblings.len(), 2);
+            assert_eq!(fold(*leaf, index, &siblings), root, "leaf {index}");
+        }
+        // The third leaf has no right neighbour and is paired with the zero hash
+        assert_eq!(branch(&leaves, 2)[0], ZERO_HASH);
+    }
+
+    #[test]
+    fn test_lone_leaf_is_the_root() {
+        assert!(branch(&[leaf(7)], 0).is_empty());
+        assert_eq!(fold(leaf(7), 0, &[]), leaf(7));
+    }
+}
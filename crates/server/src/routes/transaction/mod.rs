@@ -1,2 +1,7 @@
 pub mod _id_;
+pub mod confirmations;
+pub mod export;
 pub mod last;
+pub mod list;
+pub mod merkle_proof;
+pub mod submit;
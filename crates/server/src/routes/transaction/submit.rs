@@ -0,0 +1,233 @@
+use axum::Extension;
+use base64::{Engine, prelude::BASE64_STANDARD};
+use borsh::BorshDeserialize;
+use http::HeaderMap;
+use serde::{Deserialize, Serialize};
+use tondi_consensus_core::tx::Transaction;
+use tondi_rpc_core::{
+    RpcError, RpcTransaction, RpcTransactionId, SubmitTransactionRequest, SubmitTransactionResponse, api::rpc::RpcApi,
+};
+
+use crate::{
+    error::{Error, Result},
+    extensions::client_pool::ClientPool,
+    middleware::timeout::Deadline,
+    routes::grpc::{
+        grpc_return::GrpcReturn,
+        idempotency::{IdempotencyStore, idempotency_key},
+    },
+    shared::{data::Data, json::Json},
+};
+
+/// Idempotency scope shared with `SubmitTransaction` through `/grpc`, so a key
+/// retried on either route returns the first submission's result
+const METHOD: &str = "SubmitTransaction";
+
+/// Largest serialized transaction accepted; the node refuses anything near it as non-standard
+const MAX_TRANSACTION_BYTES: usize = 100_000;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmitTransaction {
+    /// Borsh-serialized transaction as hex, or as standard base64 when it is not valid hex
+    pub transaction: String,
+    #[serde(default)]
+    pub allow_orphan: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmittedTransaction {
+    pub transaction_id: RpcTransactionId,
+}
+
+/// Validate a serialized transaction and broadcast it through the node
+pub async fn post(
+    client_pool: ClientPool,
+    Extension(idempotency): Extension<IdempotencyStore<GrpcReturn>>,
+    deadline: Deadline,
+    headers: HeaderMap,
+    Json(body): Json<SubmitTransaction>,
+) -> Data<SubmittedTransaction> {
+    let key = idempotency_key(&headers)?;
+    let request = SubmitTransactionRequest { transaction: decode(&body.transaction)?, allow_orphan: body.allow_orphan };
+    let transaction_id = submit(&idempotency, key, request, |request| async move {
+        let client = client_pool.get().await?;
+        let call = client.rpc()?.submit_transaction_call(None, request);
+        let response = deadline.run(async { Ok::<_, Error>(call.await) }).await?.map_err(rejection)?;
+        client_pool.record_success();
+        Ok(response)
+    })
+    .await?;
+    Ok(SubmittedTransaction { transaction_id }.into())
+}
+
+/// Submit once per live `Idempotency-Key`, or on every call without one
+async fn submit<F, Fut>(
+    idempotency: &IdempotencyStore<GrpcReturn>,
+    key: Option<String>,
+    request: SubmitTransactionRequest,
+    node: F,
+) -> Result<RpcTransactionId>
+where
+    F: FnOnce(SubmitTransactionRequest) -> Fut,
+    Fut: Future<Output = Result<SubmitTransactionResponse>>,
+{
+    let submit = || async move { node(request).await.map(GrpcReturn::SubmitTransaction) };
+    let ret = match key {
+        Some(key) => idempotency.run(METHOD, key, submit).await?,
+        None => submit().await?,
+    };
+    match ret {
+        GrpcReturn::SubmitTransaction(response) => Ok(response.transaction_id),
+        _ => Err(Error::InternalServerError(format!("Stored result of `{METHOD}` has another type"))),
+    }
+}
+
+fn decode(encoded: &str) -> Result<RpcTransaction> {
+    let bytes = decode_bytes(encoded.trim())?;
+    if bytes.len() > MAX_TRANSACTION_BYTES {
+        return Err(Error::PayloadTooLarge(format!(
+            "the transaction is {} bytes, the limit is {MAX_TRANSACTION_BYTES} bytes",
+            bytes.len()
+        )));
+    }
+    let transaction = Transaction::try_from_slice(&bytes)
+        .map_err(|e| Error::BadRequest(format!("`transaction` is not a serialized transaction: {e}")))?;
+    if transaction.inputs.is_empty() {
+        return Err(Error::BadRequest("The transaction has no inputs".to_string()));
+    }
+    if transaction.outputs.is_empty() {
+        return Err(Error::BadRequest("The transaction has no outputs".to_string()));
+    }
+    Ok(RpcTransaction::from(&transaction))
+}
+
+fn decode_bytes(encoded: &str) -> Result<Vec<u8>> {
+    if encoded.is_empty() {
+        return Err(Error::BadRequest("`transaction` is empty".to_string()));
+    }
+    if encoded.len().is_multiple_of(2) && encoded.bytes().all(|b| b.is_ascii_hexdigit()) {
+        let mut bytes = vec![0; encoded.len() / 2];
+        if hex::hex_decode(encoded.as_bytes(), &mut bytes).is_ok() {
+            return Ok(bytes);
+        }
+    }
+    BASE64_STANDARD
+        .decode(encoded)
+        .map_err(|e| Error::BadRequest(format!("`transaction` is neither hex nor base64: {e}")))
+}
+
+/// The node's reason for refusing the transaction as a `422`; any other failure is the node's
+fn rejection(e: RpcError) -> Error {
+    match e {
+        RpcError::RejectedTransaction(_, reason) => Error::TransactionRejected(reason),
+        // Over gRPC the rejection arrives as the node's message
+        RpcError::General(message) if message.starts_with("Rejected transaction") => {
+            Error::TransactionRejected(message)
+        },
+        e => Error::ServiceUnavailable(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use axum::response::IntoResponse;
+    use http::StatusCode;
+    use tondi_consensus_core::{
+        subnets::SUBNETWORK_ID_NATIVE,
+        tx::{ScriptPublicKey, TransactionInput, TransactionOutpoint, TransactionOutput},
+    };
+    use tondi_hashes::Hash;
+
+    use super::*;
+    use crate::shared::language::Language;
+
+    fn transaction(outputs: usize) -> Transaction {
+        let input = TransactionInput::new(TransactionOutpoint::new(Hash::from_bytes([1; 32]), 0), vec![0x51], 0, 1);
+        let output = TransactionOutput::new(1_000, ScriptPublicKey::from_vec(0, vec![0x51]));
+        Transaction::new(0, vec![input], vec![output; outputs], 0, SUBNETWORK_ID_NATIVE, 0, Vec::new())
+    }
+
+    fn to_hex(bytes: &[u8]) -> String {
+        let mut encoded = vec![0; bytes.len() * 2];
+        hex::hex_encode(bytes, &mut encoded).unwrap();
+        String::from_utf8(encoded).unwrap()
+    }
+
+    fn status(err: Error) -> StatusCode {
+        err.into_response().status()
+    }
+
+    #[test]
+    fn test_malformed_transaction_is_400() {
+        assert_eq!(status(decode("").unwrap_err()), StatusCode::BAD_REQUEST);
+        assert_eq!(status(decode("not a transaction!").unwrap_err()), StatusCode::BAD_REQUEST);
+        assert_eq!(status(decode("deadbeef").unwrap_err()), StatusCode::BAD_REQUEST);
+
+        let no_outputs = borsh::to_vec(&transaction(0)).unwrap();
+        let err = decode(&to_hex(&no_outputs)).unwrap_err();
+        assert_eq!(err.user_message(Language::English), "Invalid request: The transaction has no outputs");
+    }
+
+    #[test]
+    fn test_hex_and_base64_decode_alike() {
+        let bytes = borsh::to_vec(&transaction(2)).unwrap();
+        let from_hex = decode(&to_hex(&bytes)).unwrap();
+        let from_base64 = decode(&BASE64_STANDARD.encode(&bytes)).unwrap();
+        assert_eq!((from_hex.inputs.len(), from_hex.outputs.len()), (1, 2));
+        assert_eq!(from_base64.outputs.len(), 2);
+    }
+
+    #[test]
+    fn test_oversized_transaction_is_413() {
+        let encoded = BASE64_STANDARD.encode(vec![0; MAX_TRANSACTION_BYTES + 1]);
+        assert_eq!(status(decode(&encoded).unwrap_err()), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_mocked_submission_returns_the_id_once_per_key() {
+        let store = IdempotencyStore::new(Duration::from_secs(60));
+        let bytes = borsh::to_vec(&transaction(1)).unwrap();
+        let id = Hash::from_bytes([9; 32]);
+        let node_calls = AtomicUsize::new(0);
+        let node = |request: SubmitTransactionRequest| {
+            let node_calls = &node_calls;
+            async move {
+                assert_eq!(request.transaction.outputs.len(), 1);
+                node_calls.fetch_add(1, Ordering::SeqCst);
+                Ok(SubmitTransactionResponse { transaction_id: id })
+            }
+        };
+
+        for _ in 0..2 {
+            let transaction = decode(&to_hex(&bytes)).unwrap();
+            let request = SubmitTransactionRequest { transaction, allow_orphan: false };
+            let submitted = submit(&store, Some("key-1".to_string()), request, node).await.unwrap();
+            assert_eq!(submitted, id);
+        }
+        assert_eq!(node_calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_node_rejection_is_422() {
+        let store = IdempotencyStore::new(Duration::from_secs(60));
+        let bytes = borsh::to_vec(&transaction(1)).unwrap();
+        let request = SubmitTransactionRequest { transaction: decode(&to_hex(&bytes)).unwrap(), allow_orphan: false };
+        let err = submit(&store, None, request, |_| async {
+            Err(rejection(RpcError::RejectedTransaction(Hash::from_bytes([9; 32]), "fee too low".to_string())))
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(err.error_code(), "TRANSACTION_REJECTED");
+        assert_eq!(status(err), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let err = rejection(RpcError::General("connection reset".to_string()));
+        assert_eq!(status(err), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
+
+use axum::extract::{Path, State};
+use serde::{Deserialize, Serialize};
+use tondi_listener_db::schema::tyext::hash::Hash256;
+use tondi_rpc_core::{GetUtxoReturnAddressRequest, RpcHash, api::rpc::RpcApi};
+
+use crate::{
+    ctx::store::{Db, Store, run_store},
+    error::{Error, Result},
+    extensions::client_pool::ClientPool,
+    middleware::timeout::Deadline,
+    shared::{cache::TtlCache, data::Data, hash::parse_hash256},
+};
+
+/// The funding address of an accepted transaction never changes
+const RETURN_ADDRESS_TTL: Duration = Duration::from_secs(3600);
+
+/// By outpoint, as the node's answer for one never changes once it has resolved it
+pub static RETURN_ADDRESSES: LazyLock<TtlCache<(RpcHash, u32), ReturnAddress>> =
+    LazyLock::new(|| TtlCache::new(RETURN_ADDRESS_TTL).named("utxo_return_address"));
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReturnAddress {
+    /// Address that funded the transaction, where a refund of the output goes
+    pub address: String,
+}
+
+/// Address that funded the outpoint `txid:index`, for refunding a payment. `404` unless the output and a
+/// block including its transaction are indexed, or when the node cannot resolve it; answers are cached, as
+/// they never change once the node has resolved them.
+pub async fn return_address(
+    Path((transaction_id, index)): Path<(String, String)>,
+    State(store): Db,
+    client_pool: ClientPool,
+    deadline: Deadline,
+) -> Data<ReturnAddress> {
+    let (id, index) = parse_outpoint(&transaction_id, &index)?;
+    let txid = RpcHash::from_bytes(id);
+    let address = RETURN_ADDRESSES
+        .get_or_try_insert_with((txid, index), || async {
+            let score = indexed_daa_score(store, id, index)
+                .await?
+                .ok_or_else(|| Error::NotFound(format!("Output {transaction_id}:{index}")))?;
+            let client = client_pool.get().await?;
+            let request = GetUtxoReturnAddressRequest { txid, accepting_block_daa_score: score };
+            let call = client.rpc()?.get_utxo_return_address_call(None, request);
+            let response = deadline.run(async { Ok::<_, Error>(call.await) }).await??;
+            client_pool.record_success();
+            Ok::<_, Error>(ReturnAddress { address: response.return_address.to_string() })
+        })
+        .await?;
+    Ok(address.into())
+}
+
+/// DAA score the node looks transaction `id` up by: that of the earliest indexed block including it. `None`
+/// unless output `index` of the transaction and a block including it are indexed.
+async fn indexed_daa_score(store: Arc<dyn Store>, id: Hash256, index: u32) -> Result<Option<u64>> {
+    let score = run_store(store, move |store| {
+        let outputs = store.outputs_for_tx(&id)?;
+        if !outputs.iter().any(|output| i64::from(output.index) == i64::from(index)) {
+            return Ok(None);
+        }
+        store.transaction_daa_score(&id)
+    })
+    .await?;
+    let negative = |_| Error::InternalServerError("Negative DAA score indexed".to_string());
+    score.map(u64::try_from).transpose().map_err(negative)
+}
+
+/// `400` unless the id is 64 hex characters and the index a non-negative integer
+fn parse_outpoint(transaction_id: &str, index: &str) -> Result<(Hash256, u32)> {
+    let id = parse_hash256(transaction_id, "transaction id")?;
+    let index = index
+        .parse()
+        .map_err(|_| Error::BadRequest(format!("Invalid output index `{index}`: expected a non-negative integer")))?;
+    Ok((id, index))
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+    use tondi_listener_db::{
+        models::transaction::{Tx, TxOu},
+        schema::tyext::subnetwork::SubnetworkId,
+    };
+
+    use super::*;
+    use crate::ctx::store::MemoryStore;
+
+    fn header(hash: &str, daa_score: i64) -> serde_json::Value {
+        let zero = "00".repeat(32);
+        serde_json::json!({
+            "hash": hash,
+            "acceptedIdMerkleRoot": zero,
+            "mergeSetBluesHashes": [],
+            "mergeSetRedsHashes": null,
+            "selectedParentHash": zero,
+            "bits": 503_382_015,
+            "blueScore": daa_score,
+            "blueWork": [1],
+            "daaScore": daa_score,
+            "hashMerkleRoot": zero,
+            "nonce": [0],
+            "pruningPoint": zero,
+            "timestamp": 1_700_000_000_000_i64,
+            "utxoCommitment": zero,
+            "version": 1,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_daa_score_comes_from_the_earliest_including_block() {
+        let id = "01".repeat(32);
+        let output = TxOu {
+            transaction_id: id.clone().into(),
+            index: 0,
+            amount: 1_000,
+            script_public_key: Vec::new(),
+            script_public_key_address: "tondi:known".to_string(),
+            block_time: 0,
+        };
+        let transaction = Tx {
+            transaction_id: id.clone().into(),
+            subnetwork_id: SubnetworkId::NATIVE,
+            hash: "02".repeat(32).into(),
+            mass: None,
+            payload: None,
+            block_time: 0,
+        };
+        let memory = Arc::new(MemoryStore::default());
+        memory.insert_transaction(transaction, Vec::new(), vec![output]);
+        let store = memory.clone() as Arc<dyn Store>;
+        let score = |index| indexed_daa_score(store.clone(), [1; 32], index);
+
+        // Indexed output, but no indexed block includes the transaction yet
+        assert_eq!(score(0).await.unwrap(), None);
+        for (hash, daa_score) in [("aa".repeat(32), 70), ("bb".repeat(32), 50), ("cc".repeat(32), 10)] {
+            memory.insert_header(serde_json::from_value(header(&hash, daa_score)).unwrap());
+            if daa_score != 10 {
+                memory.insert_block_transaction(&hash, &id, 0);
+            }
+        }
+        assert_eq!(score(0).await.unwrap(), Some(50));
+        assert_eq!(score(1).await.unwrap(), None);
+    }
+
+    #[test]
+    fn test_invalid_index_is_400() {
+        let id = "ab".repeat(32);
+        for index in ["-1", "", "x", "1.5", "4294967296"] {
+            let err = parse_outpoint(&id, index).unwrap_err();
+            assert_eq!(err.status_code(), StatusCode::BAD_REQUEST, "{index}");
+            assert!(err.to_string().contains("output index"), "{err}");
+        }
+        assert_eq!(parse_outpoint(&id, "2").unwrap(), ([0xab; 32], 2));
+
+        let err = parse_outpoint("xyz", "0").unwrap_err();
+        assert!(err.to_string().contains("transaction id"), "{err}");
+    }
+}
@@ -0,0 +1,38 @@
+use serde::Serialize;
+
+use crate::shared::data::Data;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Version {
+    pub version: &'static str,
+    pub git_commit: &'static str,
+    /// Unix seconds at compile time
+    pub build_timestamp: &'static str,
+    pub rustc_version: &'static str,
+}
+
+pub const VERSION: Version = Version {
+    version: env!("CARGO_PKG_VERSION"),
+    git_commit: env!("TONDI_LISTENER_GIT_COMMIT"),
+    build_timestamp: env!("TONDI_LISTENER_BUILD_TIMESTAMP"),
+    rustc_version: env!("TONDI_LISTENER_RUSTC_VERSION"),
+};
+
+/// Build information of the running binary
+pub async fn get() -> Data<Version> {
+    Ok(VERSION.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_info_is_embedded() {
+        assert_eq!(VERSION.version, env!("CARGO_PKG_VERSION"));
+        assert!(!VERSION.git_commit.is_empty());
+        assert!(VERSION.build_timestamp.parse::<u64>().is_ok());
+        assert!(VERSION.rustc_version.starts_with("rustc") || VERSION.rustc_version == "unknown");
+    }
+}
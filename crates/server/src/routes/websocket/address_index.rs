@@ -0,0 +1,120 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    sync::{Arc, RwLock},
+};
+
+use tondi_rpc_core::{RpcAddress, UtxosChangedNotification};
+
+/// Identifier of one WebSocket connection
+pub type ConnId = u64;
+
+/// Which connections watch which addresses, so a `UtxosChanged` event only
+/// touches the connections watching the addresses it affects
+#[derive(Debug)]
+pub struct AddressIndex<A = RpcAddress> {
+    by_address: HashMap<A, HashSet<ConnId>>,
+    by_conn: HashMap<ConnId, HashSet<A>>,
+}
+
+impl<A> Default for AddressIndex<A> {
+    fn default() -> Self {
+        Self { by_address: HashMap::new(), by_conn: HashMap::new() }
+    }
+}
+
+impl<A> AddressIndex<A>
+where
+    A: Eq + Hash + Clone,
+{
+    pub fn subscribe(&mut self, conn: ConnId, addresses: impl IntoIterator<Item = A>) {
+        let watched = self.by_conn.entry(conn).or_default();
+        for address in addresses {
+            self.by_address.entry(address.clone()).or_default().insert(conn);
+            watched.insert(address);
+        }
+    }
+
+    pub fn unsubscribe(&mut self, conn: ConnId, addresses: impl IntoIterator<Item = A>) {
+        let Some(watched) = self.by_conn.get_mut(&conn) else {
+            return;
+        };
+        for address in addresses {
+            watched.remove(&address);
+            Self::forget(&mut self.by_address, &address, conn);
+        }
+        if watched.is_empty() {
+            self.by_conn.remove(&conn);
+        }
+    }
+
+    /// Drop every subscription of a closed connection
+    pub fn remove_connection(&mut self, conn: ConnId) {
+        for address in self.by_conn.remove(&conn).unwrap_or_default() {
+            Self::forget(&mut self.by_address, &address, conn);
+        }
+    }
+
+    /// Connections watching any of `addresses`
+    pub fn connections_for<'a>(&self, addresses: impl IntoIterator<Item = &'a A>) -> HashSet<ConnId>
+    where
+        A: 'a,
+    {
+        addresses.into_iter().filter_map(|address| self.by_address.get(address)).flatten().copied().collect()
+    }
+
+    pub fn watched_by(&self, conn: ConnId) -> Option<&HashSet<A>> {
+        self.by_conn.get(&conn)
+    }
+
+    /// Connections watching at least one address
+    pub fn watching_connections(&self) -> HashSet<ConnId> {
+        self.by_conn.keys().copied().collect()
+    }
+
+    fn forget(by_address: &mut HashMap<A, HashSet<ConnId>>, address: &A, conn: ConnId) {
+        if let Some(conns) = by_address.get_mut(address) {
+            conns.remove(&conn);
+            if conns.is_empty() {
+                by_address.remove(address);
+            }
+        }
+    }
+}
+
+impl AddressIndex<RpcAddress> {
+    /// Connections affected by a `UtxosChanged` event, either side of the change
+    pub fn dispatch_targets(&self, notification: &UtxosChangedNotification) -> HashSet<ConnId> {
+        let entries = notification.added.iter().chain(notification.removed.iter());
+        self.connections_for(entries.filter_map(|entry| entry.address.as_ref()))
+    }
+}
+
+/// Index shared by all WebSocket connections of the router
+pub type SharedAddressIndex = Arc<RwLock<AddressIndex>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_watching_connections_are_targeted() {
+        let mut index = AddressIndex::<String>::default();
+        for conn in 0..10_000 {
+            index.subscribe(conn, [format!("addr-{conn}")]);
+        }
+        for conn in [10_000, 10_001, 10_002] {
+            index.subscribe(conn, ["hot".to_string()]);
+        }
+
+        let changed = ["hot".to_string(), "addr-42".to_string(), "unwatched".to_string()];
+        let targets = index.connections_for(&changed);
+        assert_eq!(targets, HashSet::from([42, 10_000, 10_001, 10_002]));
+
+        index.unsubscribe(10_000, ["hot".to_string()]);
+        index.remove_connection(42);
+        assert_eq!(index.connections_for(&changed), HashSet::from([10_001, 10_002]));
+        assert!(index.watched_by(42).is_none());
+        assert!(index.watched_by(10_000).is_none());
+    }
+}
@@ -0,0 +1,160 @@
+use std::fmt;
+
+use diesel::prelude::*;
+use serde::Serialize;
+use serde_json::{Value, json};
+use tondi_listener_db::{
+    models::chain::HeaderSummary,
+    schema::{
+        table::{THeader, TTx},
+        tyext::hex::Hex,
+    },
+};
+use tondi_rpc_core::VirtualChainChangedNotification;
+
+use crate::{ctx::pg_database::PgDatabase, error::Result};
+
+/// Most added plus removed blocks summarized in one event
+pub const MAX_ENRICHED_BLOCKS: usize = 32;
+/// Most accepted transactions summarized in one event
+pub const MAX_ENRICHED_TRANSACTIONS: usize = 256;
+
+/// Accepted transaction as sent inline, without its payload
+#[derive(Debug, Serialize, Queryable, Selectable)]
+#[diesel(table_name = TTx, check_for_backend(diesel::pg::Pg))]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionSummary {
+    pub transaction_id: Hex,
+    pub mass: Option<i32>,
+    pub block_time: i64,
+}
+
+/// Indexed rows a `virtual-chain-changed` event is enriched with
+pub trait ChainLookup: fmt::Debug + Send + Sync {
+    fn headers(&self, hashes: Vec<Vec<u8>>) -> Result<Vec<HeaderSummary>>;
+    fn transactions(&self, ids: Vec<Vec<u8>>) -> Result<Vec<TransactionSummary>>;
+}
+
+impl ChainLookup for PgDatabase {
+    fn headers(&self, hashes: Vec<Vec<u8>>) -> Result<Vec<HeaderSummary>> {
+        let mut conn = self.get_connection()?;
+        let headers =
+            THeader::table.filter(THeader::hash.eq_any(hashes)).select(HeaderSummary::as_select()).load(&mut conn)?;
+        Ok(headers)
+    }
+
+    fn transactions(&self, ids: Vec<Vec<u8>>) -> Result<Vec<TransactionSummary>> {
+        let mut conn = self.get_connection()?;
+        let transactions = TTx::table
+            .filter(TTx::transaction_id.eq_any(ids))
+            .select(TransactionSummary::as_select())
+            .load(&mut conn)?;
+        Ok(transactions)
+    }
+}
+
+/// Summaries of the blocks and transactions a chain change touches, capped at
+/// [`MAX_ENRICHED_BLOCKS`] and [`MAX_ENRICHED_TRANSACTIONS`] so a deep reorg stays one
+/// reasonably sized frame; `truncated` tells the client to fetch the rest itself.
+/// Hashes not indexed yet are simply absent.
+pub fn enrich(notification: &VirtualChainChangedNotification, lookup: &dyn ChainLookup) -> Result<Value> {
+    let added = notification.added_chain_block_hashes.iter();
+    let removed = notification.removed_chain_block_hashes.iter();
+    let accepted = notification.accepted_transaction_ids.iter().flat_map(|block| &block.accepted_transaction_ids);
+
+    let block_count = notification.added_chain_block_hashes.len() + notification.removed_chain_block_hashes.len();
+    let transaction_count = accepted.clone().count();
+
+    let added: Vec<Vec<u8>> = added.take(MAX_ENRICHED_BLOCKS).map(|hash| hash.as_bytes().to_vec()).collect();
+    let removed: Vec<Vec<u8>> =
+        removed.take(MAX_ENRICHED_BLOCKS - added.len()).map(|hash| hash.as_bytes().to_vec()).collect();
+    let accepted: Vec<Vec<u8>> =
+        accepted.take(MAX_ENRICHED_TRANSACTIONS).map(|id| id.as_bytes().to_vec()).collect();
+
+    let headers = lookup.headers(added.iter().chain(&removed).cloned().collect())?;
+    let removed_hashes: Vec<String> = removed.iter().map(|hash| to_hex(hash)).collect();
+    let (removed_blocks, added_blocks): (Vec<_>, Vec<_>) =
+        headers.into_iter().partition(|header| removed_hashes.contains(&header.hash.inner));
+
+    Ok(json!({
+        "addedBlocks": added_blocks,
+        "removedBlocks": removed_blocks,
+        "acceptedTransactions": lookup.transactions(accepted)?,
+        "truncated": block_count > MAX_ENRICHED_BLOCKS || transaction_count > MAX_ENRICHED_TRANSACTIONS,
+    }))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    let mut buf = vec![0; bytes.len() * 2];
+    hex::hex_encode(bytes, &mut buf).map(|encoded| encoded.to_string()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tondi_rpc_core::{RpcAcceptedTransactionIds, RpcHash};
+
+    use super::*;
+
+    /// Knows every block and transaction it is asked about
+    #[derive(Debug)]
+    struct Indexed;
+
+    impl ChainLookup for Indexed {
+        fn headers(&self, hashes: Vec<Vec<u8>>) -> Result<Vec<HeaderSummary>> {
+            Ok(hashes
+                .iter()
+                .map(|hash| HeaderSummary {
+                    hash: Hex::from(to_hex(hash)),
+                    timestamp: 1_700_000_000_000,
+                    blue_score: i64::from(hash[0]),
+                    daa_score: i64::from(hash[0]),
+                    bits: 0,
+                    version: 1,
+                })
+                .collect())
+        }
+
+        fn transactions(&self, ids: Vec<Vec<u8>>) -> Result<Vec<TransactionSummary>> {
+            Ok(ids
+                .iter()
+                .map(|id| TransactionSummary { transaction_id: Hex::from(to_hex(id)), mass: None, block_time: 0 })
+                .collect())
+        }
+    }
+
+    fn hash(byte: u8) -> RpcHash {
+        RpcHash::from_bytes([byte; 32])
+    }
+
+    fn chain_change(added: u8, removed: u8, accepted: u8) -> VirtualChainChangedNotification {
+        VirtualChainChangedNotification {
+            added_chain_block_hashes: Arc::new((0..added).map(hash).collect()),
+            removed_chain_block_hashes: Arc::new((100..100 + removed).map(hash).collect()),
+            accepted_transaction_ids: Arc::new(vec![RpcAcceptedTransactionIds {
+                accepting_block_hash: hash(0),
+                accepted_transaction_ids: (0..accepted).map(hash).collect(),
+            }]),
+        }
+    }
+
+    #[test]
+    fn test_small_chain_change_is_enriched() {
+        let enriched = enrich(&chain_change(2, 1, 3), &Indexed).unwrap();
+        assert_eq!(enriched["addedBlocks"].as_array().unwrap().len(), 2);
+        assert_eq!(enriched["removedBlocks"].as_array().unwrap().len(), 1);
+        assert_eq!(enriched["removedBlocks"][0]["blueScore"], 100);
+        assert_eq!(enriched["acceptedTransactions"].as_array().unwrap().len(), 3);
+        assert_eq!(enriched["truncated"], false);
+    }
+
+    #[test]
+    fn test_deep_reorg_is_truncated() {
+        let enriched = enrich(&chain_change(30, 10, 0), &Indexed).unwrap();
+        let blocks =
+            enriched["addedBlocks"].as_array().unwrap().len() + enriched["removedBlocks"].as_array().unwrap().len();
+        assert_eq!(blocks, MAX_ENRICHED_BLOCKS);
+        assert_eq!(enriched["truncated"], true);
+    }
+}
@@ -0,0 +1,787 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{
+        Arc, Mutex, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use serde_json::{Value, json};
+use tokio::{
+    sync::{Notify, mpsc},
+    task::JoinHandle,
+};
+use tokio_util::sync::{CancellationToken, WaitForCancellationFuture};
+use tondi_listener_library::log::warn;
+use tondi_rpc_core::{RpcAddress, UtxosChangedNotification};
+
+use crate::{
+    ctx::event_config::EventType,
+    extensions::client_pool::listener::ListenerManager,
+    routes::websocket::{
+        address_index::{ConnId, SharedAddressIndex},
+        enrich::{ChainLookup, enrich},
+        priority::PriorityQueue,
+        spill::SpillBuffer,
+        utxos::UtxoDiff,
+    },
+    shared::{
+        metrics::METRICS,
+        pool::{Notification, NotificationPayload},
+    },
+};
+
+/// Messages buffered per connection before new events are dropped for it
+const OUTBOX_BUFFER: usize = 256;
+
+/// Handle of one `subscribe` request, unique for the hub's lifetime
+pub type SubscriptionId = u64;
+
+#[derive(Debug)]
+struct Subscriber {
+    subscriptions: HashMap<SubscriptionId, HashSet<EventType>>,
+    outbox: mpsc::Sender<String>,
+    /// Receives enriched `virtual-chain-changed` messages when there are any
+    enrich: bool,
+    /// Receives `utxos-changed` data as the node sent it rather than as a [`UtxoDiff`]
+    raw: bool,
+}
+
+impl Subscriber {
+    fn wants(&self, ev: &EventType) -> bool {
+        self.subscriptions.values().any(|events| events.contains(ev))
+    }
+}
+
+/// One event's message, plus its enriched variant when one was built
+#[derive(Debug, Clone)]
+struct Messages {
+    seq: u64,
+    lean: String,
+    enriched: Option<String>,
+    /// The unnormalized message of a `utxos-changed` event
+    raw: Option<String>,
+    /// Kept so connections watching addresses get only their share of the change
+    utxos: Option<UtxosChangedNotification>,
+}
+
+impl Messages {
+    /// The message `subscriber` gets, narrowed to `watched` when it watches addresses
+    fn for_subscriber(&self, subscriber: &Subscriber, watched: Option<&HashSet<RpcAddress>>) -> String {
+        match (&self.raw, &self.utxos, watched) {
+            (Some(raw), _, _) if subscriber.raw => raw.clone(),
+            (_, Some(utxos), Some(watched)) => {
+                event_message(self.seq, EventType::UtxosChanged, json!(UtxoDiff::new(utxos, Some(watched))))
+                    .to_string()
+            },
+            _ => match (&self.enriched, subscriber.enrich) {
+                (Some(enriched), true) => enriched.clone(),
+                _ => self.lean.clone(),
+            },
+        }
+    }
+}
+
+fn event_message(seq: u64, ev: EventType, data: Value) -> Value {
+    json!({
+        "type": "event",
+        "seq": seq,
+        "event": ev.to_string(),
+        "data": data,
+    })
+}
+
+/// Buffered events a subscription starts with before going live
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Replay {
+    #[default]
+    None,
+    /// The last `n` buffered events of the subscribed types
+    Last(usize),
+    /// Every buffered event of the subscribed types after this sequence number
+    Since(u64),
+}
+
+#[derive(Debug)]
+struct ReplayEntry {
+    seq: u64,
+    messages: Messages,
+}
+
+/// Fans upstream notifications out to WebSocket connections by event type,
+/// narrowing `utxos-changed` to the connections watching the touched addresses
+#[derive(Debug, Default)]
+pub struct Hub {
+    subscribers: RwLock<HashMap<ConnId, Subscriber>>,
+    address_index: SharedAddressIndex,
+    next_subscription: AtomicU64,
+    /// Upstream `Listener.id` feeding each attached event type
+    listener_ids: RwLock<HashMap<EventType, u64>>,
+    /// Most recent events per type, at most `replay_capacity` each
+    replay: Mutex<HashMap<EventType, VecDeque<ReplayEntry>>>,
+    replay_capacity: usize,
+    /// Last sequence number handed out; sequence numbers start at 1
+    last_seq: AtomicU64,
+    /// Source of the rows `"enrich": true` subscribers get inline
+    chain_lookup: Option<Arc<dyn ChainLookup>>,
+    /// Reorders events by priority before dispatch; events go straight through without it
+    priority: Option<Arc<PriorityQueue>>,
+    /// Buffers events on disk past a high-water mark while dispatch falls behind
+    spill: Option<Arc<SpillBuffer>>,
+    /// Cancelled when the server shuts down, closing every connection
+    closing: CancellationToken,
+    /// Delay suggested to clients in the shutdown notice
+    reconnect_after_ms: u64,
+    /// Signalled whenever a connection is removed
+    removed: Notify,
+    /// Skip events whose data repeats the previous event of their type
+    deduplicate: bool,
+    /// Data of the last dispatched event per type, kept while deduplicating
+    last_data: Mutex<HashMap<EventType, Value>>,
+}
+
+impl Hub {
+    /// Hub remembering the last `replay_capacity` events of each type for [`Replay`]
+    pub fn with_replay(replay_capacity: usize) -> Self {
+        Self { replay_capacity, ..Default::default() }
+    }
+
+    /// Enable `"enrich": true` subscriptions, looking chain changes up in `lookup`
+    pub fn with_enrichment(self, lookup: Arc<dyn ChainLookup>) -> Self {
+        Self { chain_lookup: Some(lookup), ..self }
+    }
+
+    /// Dispatch attached events highest priority first, see [`PriorityQueue`]
+    pub fn with_priority(self, queue: PriorityQueue) -> Self {
+        Self { priority: Some(Arc::new(queue)), ..self }
+    }
+
+    /// Queue events in `spill` before dispatch, so the upstream receivers keep up while the database is slow
+    pub fn with_spill(self, spill: SpillBuffer) -> Self {
+        Self { spill: Some(Arc::new(spill)), ..self }
+    }
+
+    /// Drop events whose data is identical to the previous event of their type,
+    /// such as the repeats a resubscription upstream can produce
+    pub fn with_deduplication(self, deduplicate: bool) -> Self {
+        Self { deduplicate, ..self }
+    }
+
+    /// Suggest clients wait `reconnect_after_ms` before reconnecting after a shutdown
+    pub fn with_reconnect_after(self, reconnect_after_ms: u64) -> Self {
+        Self { reconnect_after_ms, ..self }
+    }
+
+    /// Close every connection with a going-away frame asking clients to reconnect later
+    pub fn shut_down(&self) {
+        self.closing.cancel();
+    }
+
+    /// [`Hub::shut_down`], then wait up to `timeout` for the connections to go away;
+    /// whether they all did
+    pub async fn drain(&self, timeout: Duration) -> bool {
+        self.shut_down();
+        let drained = async {
+            loop {
+                let removed = self.removed.notified();
+                if self.connection_count() == 0 {
+                    return;
+                }
+                removed.await;
+            }
+        };
+        tokio::time::timeout(timeout, drained).await.is_ok()
+    }
+
+    /// Message sent to every connection before it is closed for shutdown
+    pub fn shutdown_notice(&self) -> String {
+        json!({ "type": "server_shutdown", "reconnect_after_ms": self.reconnect_after_ms }).to_string()
+    }
+
+    fn connection_count(&self) -> usize {
+        self.subscribers.read().map_or(0, |subscribers| subscribers.len())
+    }
+
+    /// Resolves once [`Hub::shut_down`] is called
+    pub fn closing(&self) -> WaitForCancellationFuture<'_> {
+        self.closing.cancelled()
+    }
+
+    pub fn address_index(&self) -> &SharedAddressIndex {
+        &self.address_index
+    }
+
+    /// Register a connection; its serialized events arrive on the returned receiver
+    pub fn register(&self, conn: ConnId) -> mpsc::Receiver<String> {
+        let (outbox, receiver) = mpsc::channel(OUTBOX_BUFFER);
+        if let Ok(mut subscribers) = self.subscribers.write() {
+            let subscriber = Subscriber { subscriptions: HashMap::new(), outbox, enrich: false, raw: false };
+            subscribers.insert(conn, subscriber);
+        }
+        receiver
+    }
+
+    /// Subscribe `conn` to `events`; `None` if the connection is not registered
+    pub fn subscribe(
+        &self,
+        conn: ConnId,
+        events: impl IntoIterator<Item = EventType>,
+    ) -> Option<SubscriptionId> {
+        self.subscribe_with_replay(conn, events, Replay::None)
+    }
+
+    /// Subscribe `conn` to `events`, first queueing the buffered events `replay` selects.
+    /// Holding the subscriber lock keeps dispatch out, so nothing is missed or sent twice
+    /// between the replay and the first live event.
+    pub fn subscribe_with_replay(
+        &self,
+        conn: ConnId,
+        events: impl IntoIterator<Item = EventType>,
+        replay: Replay,
+    ) -> Option<SubscriptionId> {
+        let events: HashSet<EventType> = events.into_iter().collect();
+        let mut subscribers = self.subscribers.write().ok()?;
+        let subscriber = subscribers.get_mut(&conn)?;
+
+        for message in self.replayed(conn, &events, replay, subscriber) {
+            if subscriber.outbox.try_send(message).is_err() {
+                warn!("WebSocket connection {conn} cannot take its whole replay, truncating");
+                break;
+            }
+        }
+
+        let id = self.next_subscription.fetch_add(1, Ordering::Relaxed);
+        subscriber.subscriptions.insert(id, events);
+        Some(id)
+    }
+
+    /// Buffered messages of `events` selected by `replay`, oldest first
+    fn replayed(
+        &self,
+        conn: ConnId,
+        events: &HashSet<EventType>,
+        replay: Replay,
+        subscriber: &Subscriber,
+    ) -> Vec<String> {
+        if replay == Replay::None {
+            return Vec::new();
+        }
+        let Ok(index) = self.address_index.read() else { return Vec::new() };
+        let Ok(buffers) = self.replay.lock() else { return Vec::new() };
+
+        let watched = index.watched_by(conn);
+        let mut entries: Vec<&ReplayEntry> = events
+            .iter()
+            .filter_map(|ev| buffers.get(ev))
+            .flatten()
+            .filter(|entry| match (&entry.messages.utxos, watched) {
+                (Some(utxos), Some(_)) => index.dispatch_targets(utxos).contains(&conn),
+                _ => true,
+            })
+            .collect();
+        entries.sort_by_key(|entry| entry.seq);
+
+        let skip = match replay {
+            Replay::None => entries.len(),
+            Replay::Last(n) => entries.len().saturating_sub(n),
+            Replay::Since(seq) => entries.partition_point(|entry| entry.seq <= seq),
+        };
+        entries.into_iter().skip(skip).map(|entry| entry.messages.for_subscriber(subscriber, watched)).collect()
+    }
+
+    /// Whether `conn` gets enriched `virtual-chain-changed` messages; a no-op
+    /// when the hub has no lookup to enrich them with
+    pub fn set_enrich(&self, conn: ConnId, enrich: bool) {
+        let Ok(mut subscribers) = self.subscribers.write() else { return };
+        if let Some(subscriber) = subscribers.get_mut(&conn) {
+            subscriber.enrich = enrich && self.chain_lookup.is_some();
+        }
+    }
+
+    /// Whether `conn` gets `utxos-changed` data as the node sent it
+    pub fn set_raw(&self, conn: ConnId, raw: bool) {
+        let Ok(mut subscribers) = self.subscribers.write() else { return };
+        if let Some(subscriber) = subscribers.get_mut(&conn) {
+            subscriber.raw = raw;
+        }
+    }
+
+    /// Drop `events` from every subscription of `conn`
+    pub fn unsubscribe(&self, conn: ConnId, events: impl IntoIterator<Item = EventType>) {
+        let Ok(mut subscribers) = self.subscribers.write() else { return };
+        if let Some(subscriber) = subscribers.get_mut(&conn) {
+            for ev in events {
+                subscriber.subscriptions.values_mut().for_each(|subscribed| {
+                    subscribed.remove(&ev);
+                });
+            }
+            subscriber.subscriptions.retain(|_, subscribed| !subscribed.is_empty());
+        }
+    }
+
+    /// Drop one subscription of `conn`, returning the events it covered
+    pub fn unsubscribe_id(&self, conn: ConnId, id: SubscriptionId) -> Option<Vec<EventType>> {
+        let mut subscribers = self.subscribers.write().ok()?;
+        let events = subscribers.get_mut(&conn)?.subscriptions.remove(&id)?;
+        Some(events.into_iter().collect())
+    }
+
+    /// Events `conn` is subscribed to
+    pub fn events(&self, conn: ConnId) -> Vec<EventType> {
+        let Ok(subscribers) = self.subscribers.read() else { return Vec::new() };
+        let Some(subscriber) = subscribers.get(&conn) else { return Vec::new() };
+        let events: HashSet<_> = subscriber.subscriptions.values().flatten().copied().collect();
+        events.into_iter().collect()
+    }
+
+    /// Upstream listener ids feeding `events`; events without a listener are left out
+    pub fn listener_ids(&self, events: &[EventType]) -> HashMap<EventType, u64> {
+        let Ok(listener_ids) = self.listener_ids.read() else { return HashMap::new() };
+        events.iter().filter_map(|ev| Some((*ev, *listener_ids.get(ev)?))).collect()
+    }
+
+    /// Forget a connection entirely, including its watched addresses
+    /// Sender of the messages `conn` gets, for replies sent from outside the hub
+    pub fn outbox(&self, conn: ConnId) -> Option<mpsc::Sender<String>> {
+        Some(self.subscribers.read().ok()?.get(&conn)?.outbox.clone())
+    }
+
+    pub fn remove(&self, conn: ConnId) {
+        if let Ok(mut subscribers) = self.subscribers.write() {
+            subscribers.remove(&conn);
+        }
+        if let Ok(mut index) = self.address_index.write() {
+            index.remove_connection(conn);
+        }
+        self.removed.notify_waiters();
+    }
+
+    /// Queue `notification` for every connection subscribed to its event type
+    pub fn dispatch(&self, notification: &Notification) {
+        self.dispatch_enriched(notification, None);
+    }
+
+    /// [`Hub::dispatch`], sending `enrichment` inline to the subscribers asking for it
+    pub fn dispatch_enriched(&self, notification: &Notification, enrichment: Option<Value>) {
+        let Some(ev) = notification.payload.event_type() else { return };
+        let counters = METRICS.event(ev);
+        if self.is_repeat(ev, &notification.payload) {
+            counters.deduplicated.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        // Sequenced and buffered under the subscriber lock, see `subscribe_with_replay`
+        let Ok(subscribers) = self.subscribers.read() else { return };
+        // Connections watching addresses only get the changes touching them. Taken after the subscriber
+        // lock, in the order `subscribe_with_replay` takes them.
+        let index = self.address_index.read().ok();
+        let targets = match (&notification.payload, &index) {
+            (NotificationPayload::UtxosChanged(n), Some(index)) => index.dispatch_targets(n),
+            _ => HashSet::new(),
+        };
+        let Some(messages) = self.sequence(ev, &notification.payload, enrichment) else { return };
+
+        for (conn, subscriber) in subscribers.iter() {
+            let watched = index.as_ref().and_then(|index| index.watched_by(*conn));
+            let untouched = messages.utxos.is_some() && watched.is_some() && !targets.contains(conn);
+            if !subscriber.wants(&ev) || untouched {
+                continue;
+            }
+            if subscriber.outbox.try_send(messages.for_subscriber(subscriber, watched)).is_err() {
+                counters.dropped.fetch_add(1, Ordering::Relaxed);
+                warn!("WebSocket connection {conn} is lagging, dropping {ev} event");
+            } else {
+                counters.forwarded.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Whether deduplication is on and `payload` repeats the last event of type `ev`
+    fn is_repeat(&self, ev: EventType, payload: &NotificationPayload) -> bool {
+        if !self.deduplicate {
+            return false;
+        }
+        let Ok(mut last_data) = self.last_data.lock() else { return false };
+        let data = payload.to_json();
+        if last_data.get(&ev) == Some(&data) {
+            return true;
+        }
+        last_data.insert(ev, data);
+        false
+    }
+
+    /// Stamp the next sequence number on an event's messages and remember them for replays
+    fn sequence(&self, ev: EventType, payload: &NotificationPayload, enrichment: Option<Value>) -> Option<Messages> {
+        let mut buffers = self.replay.lock().ok()?;
+        let seq = self.last_seq.fetch_add(1, Ordering::Relaxed) + 1;
+        // `utxos-changed` goes out as a diff, unless the subscriber asked for the node's form
+        let (mut message, raw, utxos) = match payload {
+            NotificationPayload::UtxosChanged(n) => (
+                event_message(seq, ev, json!(UtxoDiff::new(n, None))),
+                Some(event_message(seq, ev, payload.to_json()).to_string()),
+                Some(n.clone()),
+            ),
+            _ => (event_message(seq, ev, payload.to_json()), None, None),
+        };
+        let lean = message.to_string();
+        let enriched = enrichment.map(|enrichment| {
+            message["enrichment"] = enrichment;
+            message.to_string()
+        });
+        let messages = Messages { seq, lean, enriched, raw, utxos };
+
+        if self.replay_capacity > 0 {
+            let buffer = buffers.entry(ev).or_default();
+            if buffer.len() == self.replay_capacity {
+                buffer.pop_front();
+            }
+            buffer.push_back(ReplayEntry { seq, messages: messages.clone() });
+        }
+        Some(messages)
+    }
+
+    /// Enrichment of a `virtual-chain-changed` notification, built only while
+    /// some subscriber of that event asked for it
+    async fn enrichment(&self, notification: &Notification) -> Option<Value> {
+        let NotificationPayload::VirtualChainChanged(change) = &notification.payload else { return None };
+        let lookup = self.chain_lookup.clone()?;
+        let wanted = self.subscribers.read().ok()?.values().any(|subscriber| {
+            subscriber.enrich && subscriber.wants(&EventType::VirtualChainChanged)
+        });
+        if !wanted {
+            return None;
+        }
+
+        let change = change.clone();
+        match tokio::task::spawn_blocking(move || enrich(&change, lookup.as_ref())).await {
+            Ok(Ok(enrichment)) => Some(enrichment),
+            Ok(Err(e)) => {
+                warn!("Sending virtual-chain-changed without enrichment: {e}");
+                None
+            },
+            Err(e) => {
+                warn!("Enrichment task failed: {e}");
+                None
+            },
+        }
+    }
+
+    async fn deliver(&self, notification: &Notification) {
+        let enrichment = self.enrichment(notification).await;
+        self.dispatch_enriched(notification, enrichment);
+    }
+
+    /// Drain every active listener of `listener_manager` into this hub, through the priority queue or spill
+    /// buffer when there is one. Attach again to the manager of each client replacing the upstream one: the
+    /// receivers of the old manager close with it.
+    pub fn attach(self: &Arc<Self>, listener_manager: &ListenerManager) -> Vec<JoinHandle<()>> {
+        listener_manager
+            .get_active_events()
+            .into_iter()
+            .filter_map(|ev| match listener_manager.get(&ev) {
+                Ok(mut receiver) => {
+                    let listener_id = listener_manager.listener_id(&ev);
+                    if let (Ok(mut listener_ids), Some(id)) = (self.listener_ids.write(), listener_id) {
+                        listener_ids.insert(ev, id);
+                    }
+                    let hub = self.clone();
+                    Some(tokio::spawn(async move {
+                        while let Some(notification) = receiver.recv().await {
+                            match (&hub.priority, &hub.spill) {
+                                (Some(queue), _) => queue.push(notification),
+                                (None, Some(spill)) => spill.push(notification).await,
+                                (None, None) => hub.deliver(&notification).await,
+                            }
+                        }
+                    }))
+                },
+                Err(e) => {
+                    warn!("Not forwarding {ev} events to WebSocket clients: {e}");
+                    None
+                },
+            })
+            .collect()
+    }
+
+    /// Deliver the events queued by [`Hub::attach`] under the priority strategy or while spilling; once per
+    /// hub, however often it is attached
+    pub fn spawn_dispatch(self: &Arc<Self>) -> Vec<JoinHandle<()>> {
+        let mut tasks = Vec::new();
+        if let Some(queue) = self.priority.clone() {
+            let hub = self.clone();
+            tasks.push(tokio::spawn(async move {
+                loop {
+                    let notification = queue.pop().await;
+                    hub.deliver(&notification).await;
+                }
+            }));
+        }
+        if let Some(spill) = self.spill.clone() {
+            let hub = self.clone();
+            tasks.push(tokio::spawn(async move {
+                loop {
+                    let notification = spill.pop().await;
+                    hub.deliver(&notification).await;
+                }
+            }));
+        }
+        tasks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tondi_rpc_core::VirtualDaaScoreChangedNotification;
+
+    use super::*;
+    use crate::{
+        extensions::client_pool::{Client, Upstream, mock::MockClient},
+        shared::pool::Pool,
+    };
+
+    fn daa_score(score: u64) -> Notification {
+        NotificationPayload::VirtualDaaScoreChanged(VirtualDaaScoreChangedNotification {
+            virtual_daa_score: score,
+        })
+        .into()
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_only_reaches_subscribers() {
+        let hub = Hub::default();
+        let mut subscribed = hub.register(1);
+        let mut idle = hub.register(2);
+        hub.subscribe(1, [EventType::VirtualDaaScoreChanged]);
+
+        hub.dispatch(&daa_score(5));
+
+        let message: serde_json::Value = serde_json::from_str(&subscribed.recv().await.unwrap()).unwrap();
+        assert_eq!(message["event"], "virtual-daa-score-changed");
+        assert_eq!(message["data"]["virtualDaaScore"], 5);
+        assert!(idle.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_unsubscribe_round_trip() {
+        let hub = Hub::default();
+        let mut receiver = hub.register(1);
+        let first = hub.subscribe(1, [EventType::VirtualDaaScoreChanged]).unwrap();
+        let second = hub.subscribe(1, [EventType::VirtualDaaScoreChanged, EventType::BlockAdded]).unwrap();
+        assert_ne!(first, second);
+
+        // Still covered by the second subscription
+        assert_eq!(hub.unsubscribe_id(1, first), Some(vec![EventType::VirtualDaaScoreChanged]));
+        hub.dispatch(&daa_score(1));
+        assert!(receiver.recv().await.is_some());
+
+        assert!(hub.unsubscribe_id(1, second).is_some());
+        assert!(hub.unsubscribe_id(1, second).is_none());
+        assert!(hub.events(1).is_empty());
+        hub.dispatch(&daa_score(2));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_replay_then_live() {
+        let hub = Hub::with_replay(2);
+        for score in 1..=3 {
+            hub.dispatch(&daa_score(score));
+        }
+
+        let mut receiver = hub.register(1);
+        hub.subscribe_with_replay(1, [EventType::VirtualDaaScoreChanged], Replay::Last(5)).unwrap();
+        hub.dispatch(&daa_score(4));
+
+        // Only the buffer's capacity survives, then the live event follows in order
+        let mut seqs = Vec::new();
+        while let Ok(message) = receiver.try_recv() {
+            seqs.push(serde_json::from_str::<serde_json::Value>(&message).unwrap()["seq"].as_u64().unwrap());
+        }
+        assert_eq!(seqs, [2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_replay_since_sequence() {
+        let hub = Hub::with_replay(8);
+        for score in 1..=4 {
+            hub.dispatch(&daa_score(score));
+        }
+
+        let mut receiver = hub.register(1);
+        hub.subscribe_with_replay(1, [EventType::VirtualDaaScoreChanged], Replay::Since(2)).unwrap();
+        let first: serde_json::Value = serde_json::from_str(&receiver.recv().await.unwrap()).unwrap();
+        assert_eq!(first["seq"], 3);
+        assert_eq!(first["data"]["virtualDaaScore"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_enrichment_only_reaches_enriching_subscribers() {
+        use crate::routes::websocket::enrich::TransactionSummary;
+        use tondi_listener_db::models::chain::HeaderSummary;
+
+        #[derive(Debug)]
+        struct Empty;
+        impl ChainLookup for Empty {
+            fn headers(&self, _: Vec<Vec<u8>>) -> crate::error::Result<Vec<HeaderSummary>> {
+                Ok(Vec::new())
+            }
+
+            fn transactions(&self, _: Vec<Vec<u8>>) -> crate::error::Result<Vec<TransactionSummary>> {
+                Ok(Vec::new())
+            }
+        }
+
+        let hub = Hub::default().with_enrichment(Arc::new(Empty));
+        let mut lean = hub.register(1);
+        let mut enriched = hub.register(2);
+        hub.subscribe(1, [EventType::VirtualDaaScoreChanged]);
+        hub.set_enrich(2, true);
+        hub.subscribe(2, [EventType::VirtualDaaScoreChanged]);
+
+        hub.dispatch_enriched(&daa_score(5), Some(json!({ "truncated": false })));
+
+        let lean: serde_json::Value = serde_json::from_str(&lean.recv().await.unwrap()).unwrap();
+        let enriched: serde_json::Value = serde_json::from_str(&enriched.recv().await.unwrap()).unwrap();
+        assert!(lean.get("enrichment").is_none());
+        assert_eq!(enriched["enrichment"]["truncated"], false);
+        assert_eq!(lean["seq"], enriched["seq"]);
+    }
+
+    #[tokio::test]
+    async fn test_utxo_changes_are_diffs_narrowed_to_watched_addresses() {
+        use tondi_addresses::{Prefix, Version};
+        use tondi_consensus_core::tx::ScriptPublicKey;
+        use tondi_rpc_core::{RpcHash, RpcTransactionOutpoint, RpcUtxoEntry, RpcUtxosByAddressesEntry};
+
+        let address = |byte| RpcAddress::new(Prefix::Mainnet, Version::PubKey, &[byte; 32]);
+        let entry = |byte, amount| RpcUtxosByAddressesEntry {
+            address: Some(address(byte)),
+            outpoint: RpcTransactionOutpoint { transaction_id: RpcHash::from_bytes([byte; 32]), index: 0 },
+            utxo_entry: RpcUtxoEntry::new(amount, ScriptPublicKey::from_vec(0, Vec::new()), 0, false),
+        };
+        let change = UtxosChangedNotification {
+            added: Arc::new(vec![entry(1, 100), entry(2, 200)]),
+            removed: Arc::new(Vec::new()),
+        };
+
+        let hub = Hub::default();
+        let (mut watcher, mut everything, mut raw) = (hub.register(1), hub.register(2), hub.register(3));
+        for conn in 1..=3 {
+            hub.subscribe(conn, [EventType::UtxosChanged]);
+        }
+        hub.address_index().write().unwrap().subscribe(1, [address(1)]);
+        hub.set_raw(3, true);
+
+        hub.dispatch(&NotificationPayload::UtxosChanged(change).into());
+
+        let data = |message: String| serde_json::from_str::<Value>(&message).unwrap()["data"].clone();
+        let watched = data(watcher.recv().await.unwrap());
+        assert_eq!(watched["added"].as_array().unwrap().len(), 1);
+        assert_eq!(watched["added"][0]["amount"], 100);
+        assert_eq!(watched["removed"], json!([]));
+        assert_eq!(data(everything.recv().await.unwrap())["added"].as_array().unwrap().len(), 2);
+        // The node's own shape, without the diff's `amount`
+        assert!(data(raw.recv().await.unwrap())["added"][0].get("amount").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_removed_connection_is_skipped() {
+        let hub = Hub::default();
+        let mut receiver = hub.register(1);
+        hub.subscribe(1, [EventType::VirtualDaaScoreChanged]);
+        hub.remove(1);
+
+        hub.dispatch(&daa_score(5));
+        assert!(receiver.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_drain_waits_for_connections_to_leave() {
+        let hub = Arc::new(Hub::default().with_reconnect_after(250));
+        let _receiver = hub.register(1);
+
+        assert!(!hub.drain(Duration::from_millis(20)).await, "drained with a connection still open");
+
+        let draining = tokio::spawn({
+            let hub = hub.clone();
+            async move { hub.drain(Duration::from_secs(5)).await }
+        });
+        hub.closing().await;
+        hub.remove(1);
+        assert!(draining.await.unwrap());
+
+        let notice: Value = serde_json::from_str(&hub.shutdown_notice()).unwrap();
+        assert_eq!(notice, json!({ "type": "server_shutdown", "reconnect_after_ms": 250 }));
+    }
+
+    #[tokio::test]
+    async fn test_events_are_counted_from_upstream_to_subscriber() {
+        let ev = EventType::VirtualDaaScoreChanged;
+        let counters = METRICS.event(ev);
+        let (received, forwarded) =
+            (counters.received.load(Ordering::Relaxed), counters.forwarded.load(Ordering::Relaxed));
+
+        let upstream = MockClient::new(&[ev]);
+        let hub = Arc::new(Hub::default());
+        let mut events = hub.register(1);
+        hub.subscribe(1, [ev]);
+        let _tasks = hub.attach(&upstream.listener_manager);
+
+        upstream.emit(daa_score(7).payload).await.unwrap();
+        assert!(events.recv().await.is_some());
+
+        // Other tests count the same event type, so only a lower bound holds
+        assert!(counters.received.load(Ordering::Relaxed) > received);
+        assert!(counters.forwarded.load(Ordering::Relaxed) > forwarded);
+    }
+
+    #[tokio::test]
+    async fn test_a_refreshed_pool_still_delivers_notifications() {
+        let ev = EventType::VirtualDaaScoreChanged;
+        let upstream = Upstream { url: "mock://node".to_string(), events: vec![ev] };
+        let pool = Arc::new(Pool::new(upstream, Client::mock(&[ev])));
+        let hub = Arc::new(Hub::default());
+        let mut events = hub.register(1);
+        hub.subscribe(1, [ev]);
+        let _tasks = hub.attach(pool.get().await.unwrap().listener_manager());
+        pool.on_refresh({
+            let hub = hub.clone();
+            move |client| {
+                hub.attach(client.listener_manager());
+            }
+        });
+
+        // The upstream goes away, so the next use of the pool connects a replacement
+        {
+            let client = pool.get().await.unwrap();
+            let Client::Mock(upstream) = &*client else { panic!("{client:?} is not the mock") };
+            upstream.set_live(false);
+        }
+        let client = pool.get().await.unwrap();
+        let Client::Mock(upstream) = &*client else { panic!("{client:?} is not the mock") };
+        assert!(upstream.is_live());
+        assert!(client.listener_manager().has_event(&ev));
+
+        upstream.emit(daa_score(8).payload).await.unwrap();
+        let message: Value = serde_json::from_str(&events.recv().await.unwrap()).unwrap();
+        assert_eq!(message["data"]["virtualDaaScore"], 8);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_events_are_deduplicated() {
+        let hub = Hub::default().with_deduplication(true);
+        let mut receiver = hub.register(1);
+        hub.subscribe(1, [EventType::VirtualDaaScoreChanged]);
+
+        for score in [5, 5, 6] {
+            hub.dispatch(&daa_score(score));
+        }
+
+        for expected in [5, 6] {
+            let message: Value = serde_json::from_str(&receiver.recv().await.unwrap()).unwrap();
+            assert_eq!(message["data"]["virtualDaaScore"], expected);
+        }
+        assert!(receiver.try_recv().is_err());
+    }
+}
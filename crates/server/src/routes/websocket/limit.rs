@@ -0,0 +1,71 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{ctx::config::WebSocketConfig, shared::metrics::METRICS};
+
+/// Global cap on concurrently open WebSocket connections
+#[derive(Debug, Clone)]
+pub struct ConnectionLimit {
+    open: Arc<AtomicUsize>,
+    max: usize,
+}
+
+impl ConnectionLimit {
+    /// Limit counting into `open`, so the gauge can be shared with `/metrics`
+    pub fn new(max: usize, open: Arc<AtomicUsize>) -> Self {
+        Self { open, max }
+    }
+
+    /// Limit from config, counted in the process-wide `/metrics` gauge
+    pub fn from_config(config: &WebSocketConfig) -> Self {
+        Self::new(config.max_ws_connections, METRICS.websocket_connections.clone())
+    }
+
+    /// Reserve a slot for one connection; `None` once `max` are open
+    pub fn try_acquire(&self) -> Option<ConnectionGuard> {
+        self.open
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |open| (open < self.max).then_some(open + 1))
+            .ok()
+            .map(|_| ConnectionGuard { open: self.open.clone() })
+    }
+
+    pub fn open(&self) -> usize {
+        self.open.load(Ordering::Acquire)
+    }
+
+    pub fn max(&self) -> usize {
+        self.max
+    }
+}
+
+/// Releases its slot when the connection closes or the upgrade is abandoned
+#[derive(Debug)]
+pub struct ConnectionGuard {
+    open: Arc<AtomicUsize>,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.open.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connection_past_the_limit_is_refused() {
+        let limit = ConnectionLimit::new(2, Arc::default());
+        let first = limit.try_acquire().expect("first connection");
+        let _second = limit.try_acquire().expect("second connection");
+        assert!(limit.try_acquire().is_none());
+        assert_eq!(limit.open(), 2);
+
+        drop(first);
+        assert_eq!(limit.open(), 1);
+        assert!(limit.try_acquire().is_some());
+    }
+}
@@ -1,59 +1,276 @@
+pub mod address_index;
+pub mod enrich;
+pub mod hub;
+pub mod limit;
+pub mod priority;
+pub mod registry;
+pub mod rpc;
+pub mod spill;
+pub mod throttle;
+pub mod utxos;
+
+use std::{
+    fmt::Display,
+    net::SocketAddr,
+    str::FromStr,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
 use axum::{
-    extract::{State, WebSocketUpgrade},
+    extract::{ConnectInfo, State, WebSocketUpgrade},
     response::IntoResponse,
-    routing::get,
-    Router,
+    Extension,
 };
-use axum::extract::ws::{Message, WebSocket};
+use axum::extract::ws::{CloseFrame, Message, WebSocket, close_code};
+use futures::{Sink, SinkExt};
 use serde_json::json;
+use tokio::{sync::mpsc, task::JoinSet};
+use tondi_listener_library::log::{debug, warn};
+use tondi_rpc_core::RpcAddress;
 
 use crate::{
-    error::Result,
+    ctx::{config::Config, event_config::EventType},
+    error::{Error, Result},
     extensions::client_pool::ClientPool,
+    routes::{
+        grpc::{self, grpc_call::GrpcCall, grpc_return::GrpcReturn},
+        websocket::{
+            address_index::ConnId,
+            hub::{Hub, Replay},
+            limit::ConnectionLimit,
+            registry::{ConnectionRegistry, Registration},
+            rpc::RpcRequest,
+            throttle::{MessageLimit, Throttle},
+        },
+    },
+    shared::{address::Address, language::Language},
 };
 
-pub fn router() -> Router<ClientPool> {
-    Router::new().route("/ws", get(handler))
+static NEXT_CONN_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Longest close reason a control frame can carry, in bytes
+const MAX_CLOSE_REASON: usize = 123;
+
+/// Close reason sent on shutdown, telling clients to back off before reconnecting
+pub const RECONNECT_LATER: &str = "Server shutting down, reconnect later";
+
+/// Why the server ended a connection, and so which close frame the client gets
+#[derive(Debug)]
+enum Disconnect {
+    /// The client closed the connection or went away; nothing left to send
+    Client,
+    /// The server is shutting down
+    Shutdown,
+    /// An operator closed the connection through `/admin/connections`
+    Evicted,
+    /// The client kept sending past its message rate
+    Throttled,
+    /// The connection failed while serving it
+    Failed(Error),
+}
+
+impl Disconnect {
+    fn close_frame(&self) -> Option<CloseFrame> {
+        let (code, reason) = match self {
+            Self::Client => return None,
+            Self::Shutdown => (close_code::AWAY, RECONNECT_LATER.to_string()),
+            Self::Evicted => (close_code::POLICY, "Closed by the operator".to_string()),
+            Self::Throttled => (close_code::POLICY, "Too many messages".to_string()),
+            // Input the protocol does not accept
+            Self::Failed(err @ (Error::JsonParse(_) | Error::BadRequest(_))) => (close_code::POLICY, err.to_string()),
+            Self::Failed(err) => (close_code::ERROR, err.to_string()),
+        };
+        Some(CloseFrame { code, reason: truncate(reason, MAX_CLOSE_REASON).into() })
+    }
+}
+
+/// `reason` cut to at most `max` bytes on a character boundary
+fn truncate(mut reason: String, max: usize) -> String {
+    if reason.len() > max {
+        let end = (0..=max).rev().find(|&end| reason.is_char_boundary(end)).unwrap_or_default();
+        reason.truncate(end);
+    }
+    reason
 }
 
 pub async fn handler(
-    State(_client_pool): State<ClientPool>,
+    State(config): State<&'static Config>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    client_pool: ClientPool,
+    Extension(hub): Extension<Arc<Hub>>,
+    Extension(registry): Extension<ConnectionRegistry>,
+    Extension(limit): Extension<ConnectionLimit>,
+    Extension(message_limit): Extension<MessageLimit>,
     ws: WebSocketUpgrade,
-) -> impl IntoResponse {
-    ws.on_upgrade(|socket| async move {
-        if let Err(e) = handle_socket(socket, _client_pool).await {
-            eprintln!("WebSocket error: {}", e);
-        }
-    })
+) -> Result<impl IntoResponse> {
+    // Refuse before upgrading so a flood never reaches a socket or a receiver
+    let Some(slot) = limit.try_acquire() else {
+        return Err(Error::ServiceUnavailable(format!("WebSocket connection limit of {} reached", limit.max())));
+    };
+
+    Ok(ws.on_upgrade(|socket| async move {
+        let _slot = slot;
+        let conn = NEXT_CONN_ID.fetch_add(1, Ordering::Relaxed);
+        let registration = registry.register(conn, remote);
+        handle_socket(socket, conn, &hub, &message_limit, &registration, Upstream { config, client_pool }).await;
+        hub.remove(conn);
+        message_limit.forget(conn);
+    }))
+}
+
+/// Node access of a connection, for the calls its client makes
+#[derive(Clone)]
+struct Upstream {
+    config: &'static Config,
+    client_pool: ClientPool,
+}
+
+impl Upstream {
+    /// One `/grpc` call, holding an upstream permit like any other and limited by the regular request timeout
+    async fn call(&self, call: GrpcCall) -> Result<GrpcReturn> {
+        let client = self.client_pool.get().await?;
+        let limit = Duration::from_secs(self.config.security.request_timeout_secs);
+        let ret = tokio::time::timeout(limit, grpc::execute(self.config, client.rpc()?, call))
+            .await
+            .map_err(|_| Error::GatewayTimeout(format!("No answer within {}s", limit.as_secs())))??;
+        self.client_pool.record_success();
+        Ok(ret)
+    }
 }
 
 async fn handle_socket(
     mut socket: WebSocket,
-    _client_pool: ClientPool,
-) -> Result<()> {
-    // Send welcome message
-    send_message(&mut socket, "welcome", "Connected to Tondi Listener WebSocket").await?;
-    
-    // Handle incoming messages
-    while let Some(msg) = socket.recv().await {
-        match msg {
-            Ok(Message::Text(text)) => {
-                if let Err(e) = handle_text_message(&mut socket, &text).await {
-                    eprintln!("Failed to handle message: {}", e);
-                    break;
+    conn: ConnId,
+    hub: &Hub,
+    limit: &MessageLimit,
+    registration: &Registration,
+    upstream: Upstream,
+) {
+    let disconnect = serve_socket(&mut socket, conn, hub, limit, registration, &upstream).await;
+    match &disconnect {
+        Disconnect::Client => debug!("WebSocket connection {conn} closed by the client"),
+        Disconnect::Shutdown => debug!("WebSocket connection {conn} closed for shutdown"),
+        Disconnect::Evicted => warn!("WebSocket connection {conn} closed by the operator"),
+        Disconnect::Throttled => warn!("WebSocket connection {conn} closed for sending too many messages"),
+        Disconnect::Failed(e) => warn!("WebSocket connection {conn} closed: {e}"),
+    }
+    // Best effort: the socket may already be gone
+    let _ = say_goodbye(&mut socket, &disconnect, hub).await;
+}
+
+/// Send the close frame for `disconnect`, preceded on shutdown by the notice telling the client
+/// when to reconnect
+async fn say_goodbye<S>(socket: &mut S, disconnect: &Disconnect, hub: &Hub) -> Result<(), S::Error>
+where
+    S: Sink<Message> + Unpin,
+{
+    if let Disconnect::Shutdown = disconnect {
+        socket.send(Message::Text(hub.shutdown_notice().into())).await?;
+    }
+    if let Some(frame) = disconnect.close_frame() {
+        socket.send(Message::Close(Some(frame))).await?;
+    }
+    Ok(())
+}
+
+/// Whether the message `conn` just sent may be handled. Refused messages are answered with an error,
+/// and the connection ends once too many are refused in a row.
+async fn admit<S>(socket: &mut S, limit: &MessageLimit, conn: ConnId, throttled: &mut u32) -> Result<bool, Disconnect>
+where
+    S: Sink<Message> + Unpin,
+    S::Error: Display,
+{
+    match limit.check(conn, throttled) {
+        Throttle::Handle => Ok(true),
+        Throttle::Refuse(wait) => {
+            let response = json!({
+                "type": "error",
+                "message": format!("Too many messages, retry in {}ms", wait.as_millis().max(1)),
+            });
+            socket.send(Message::Text(response.to_string().into())).await.map_err(|e| {
+                Disconnect::Failed(Error::InternalServerError(format!("Failed to send message: {e}")))
+            })?;
+            Ok(false)
+        },
+        Throttle::Close => Err(Disconnect::Throttled),
+    }
+}
+
+/// Handle incoming messages and forward subscribed events until the connection ends
+async fn serve_socket(
+    socket: &mut WebSocket,
+    conn: ConnId,
+    hub: &Hub,
+    limit: &MessageLimit,
+    registration: &Registration,
+    upstream: &Upstream,
+) -> Disconnect {
+    let mut events = hub.register(conn);
+    let mut throttled = 0;
+    // Node calls in flight, aborted when the connection ends
+    let mut calls = JoinSet::new();
+
+    if let Err(e) = send_message(socket, "welcome", "Connected to Tondi Listener WebSocket").await {
+        return Disconnect::Failed(e);
+    }
+    loop {
+        tokio::select! {
+            msg = socket.recv() => match msg {
+                Some(Ok(Message::Text(text))) => {
+                    match admit(socket, limit, conn, &mut throttled).await {
+                        Ok(true) => {},
+                        Ok(false) => continue,
+                        Err(disconnect) => return disconnect,
+                    }
+                    if let Err(e) = handle_text_message(socket, conn, hub, upstream, &mut calls, &text).await {
+                        return Disconnect::Failed(e);
+                    }
+                    registration.set_events(hub.events(conn));
+                },
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return Disconnect::Client,
+                // Every other frame costs the same budget, so a flood of binary frames or pings is throttled too
+                Some(Ok(_)) => {
+                    if let Err(disconnect) = admit(socket, limit, conn, &mut throttled).await {
+                        return disconnect;
+                    }
+                },
+            },
+            Some(_) = calls.join_next() => {},
+            Some(event) = events.recv() => {
+                if let Err(e) = socket.send(Message::Text(event.into())).await {
+                    return Disconnect::Failed(Error::InternalServerError(format!("Failed to send message: {e}")));
                 }
-            }
-            Ok(Message::Close(_)) => break,
-            _ => continue,
+            },
+            _ = hub.closing() => return Disconnect::Shutdown,
+            _ = registration.evicted() => return Disconnect::Evicted,
         }
     }
-    
-    Ok(())
 }
 
-async fn handle_text_message(socket: &mut WebSocket, text: &str) -> Result<()> {
-    let json_msg: serde_json::Value = serde_json::from_str(text)
-        .map_err(|e| crate::error::Error::InternalServerError(format!("Invalid JSON: {}", e)))?;
+async fn handle_text_message(
+    socket: &mut WebSocket,
+    conn: ConnId,
+    hub: &Hub,
+    upstream: &Upstream,
+    calls: &mut JoinSet<()>,
+    text: &str,
+) -> Result<()> {
+    let json_msg: serde_json::Value = serde_json::from_str(text)?;
+
+    // Request/response calls share the socket with subscriptions
+    if let Some(request) = RpcRequest::parse(&json_msg) {
+        let Some(outbox) = hub.outbox(conn) else {
+            return send_message(socket, "error", "Connection is not registered").await;
+        };
+        let max = upstream.config.websocket.max_calls_in_flight;
+        let upstream = upstream.clone();
+        spawn_call(calls, max, outbox, request, move |call| async move { upstream.call(call).await });
+        return Ok(());
+    }
     
     if let Some(msg_type) = json_msg.get("type").and_then(|v| v.as_str()) {
         match msg_type {
@@ -65,9 +282,79 @@ async fn handle_text_message(socket: &mut WebSocket, text: &str) -> Result<()> {
                 send_message(socket, "pong", &format!("{}", timestamp)).await?;
             }
             "subscribe" => {
-                send_message(socket, "subscribed", "Event subscription successful").await?;
+                // Parse everything first so a rejected request leaves the subscription untouched
+                let (mut events, addresses) = match parse_subscription(&json_msg) {
+                    Ok(parsed) => parsed,
+                    Err(message) => return send_message(socket, "error", &message).await,
+                };
+                let replay = match parse_replay(&json_msg) {
+                    Ok(replay) => replay,
+                    Err(message) => return send_message(socket, "error", &message).await,
+                };
+                // Chain changes with block/transaction summaries inline
+                let enrich = json_msg.get("enrich").and_then(|v| v.as_bool()) == Some(true);
+                // `utxos-changed` as the node sent it instead of the added/removed diff
+                let raw = json_msg.get("raw").and_then(|v| v.as_bool()) == Some(true);
+
+                // Watching addresses implies wanting their UTXO changes
+                if events.is_empty() && !addresses.is_empty() {
+                    events.push(EventType::UtxosChanged);
+                }
+                if let Ok(mut index) = hub.address_index().write() {
+                    index.subscribe(conn, addresses);
+                }
+                if enrich {
+                    hub.set_enrich(conn, true);
+                }
+                if raw {
+                    hub.set_raw(conn, true);
+                }
+                let listener_ids = hub.listener_ids(&events);
+                let Some(subscription_id) = hub.subscribe_with_replay(conn, events.iter().copied(), replay) else {
+                    return send_message(socket, "error", "Connection is not registered").await;
+                };
+                let response = json!({
+                    "type": "subscribed",
+                    "message": "Event subscription successful",
+                    "events": events.iter().map(ToString::to_string).collect::<Vec<_>>(),
+                    "subscription_id": subscription_id.to_string(),
+                    "listener_ids": listener_ids
+                        .iter()
+                        .map(|(ev, id)| (ev.to_string(), json!(id)))
+                        .collect::<serde_json::Map<_, _>>(),
+                });
+                send_json(socket, &response).await?;
+            }
+            "unsubscribe" if json_msg.get("subscription_id").is_some() => {
+                let id = json_msg.get("subscription_id").and_then(|id| match id {
+                    serde_json::Value::String(id) => id.parse().ok(),
+                    id => id.as_u64(),
+                });
+                let Some(events) = id.and_then(|id| hub.unsubscribe_id(conn, id)) else {
+                    return send_message(socket, "error", "Unknown subscription_id").await;
+                };
+                let response = json!({
+                    "type": "unsubscribed",
+                    "message": "Event unsubscription successful",
+                    "events": events.iter().map(ToString::to_string).collect::<Vec<_>>(),
+                    "subscription_id": json_msg["subscription_id"],
+                });
+                send_json(socket, &response).await?;
             }
             "unsubscribe" => {
+                let (events, addresses) = match parse_subscription(&json_msg) {
+                    Ok(parsed) => parsed,
+                    Err(message) => return send_message(socket, "error", &message).await,
+                };
+                if let Ok(mut index) = hub.address_index().write() {
+                    // Without an address list, drop every address watched by this connection
+                    if json_msg.get("addresses").is_some() {
+                        index.unsubscribe(conn, addresses);
+                    } else {
+                        index.remove_connection(conn);
+                    }
+                }
+                hub.unsubscribe(conn, events);
                 send_message(socket, "unsubscribed", "Event unsubscription successful").await?;
             }
             "get_status" => {
@@ -86,7 +373,7 @@ async fn handle_text_message(socket: &mut WebSocket, text: &str) -> Result<()> {
             "get_events" => {
                 let response = json!({
                     "type": "events",
-                    "events": Vec::<String>::new()
+                    "events": hub.events(conn).iter().map(ToString::to_string).collect::<Vec<_>>()
                 });
                 socket.send(Message::Text(response.to_string().into())).await
                     .map_err(|e| crate::error::Error::InternalServerError(format!("Failed to send message: {}", e)))?;
@@ -102,11 +389,91 @@ async fn handle_text_message(socket: &mut WebSocket, text: &str) -> Result<()> {
     Ok(())
 }
 
+fn parse_subscription(
+    json_msg: &serde_json::Value,
+) -> std::result::Result<(Vec<EventType>, Vec<RpcAddress>), String> {
+    Ok((parse_events(json_msg)?, parse_addresses(json_msg)?))
+}
+
+/// Optional `"replay": N` or `"since": <seq>` of a subscribe message
+fn parse_replay(json_msg: &serde_json::Value) -> std::result::Result<Replay, String> {
+    match (json_msg.get("replay"), json_msg.get("since")) {
+        (None, None) => Ok(Replay::None),
+        (Some(n), None) => {
+            let n = n.as_u64().ok_or_else(|| "`replay` must be a non-negative integer".to_string())?;
+            Ok(Replay::Last(usize::try_from(n).unwrap_or(usize::MAX)))
+        },
+        (None, Some(seq)) => {
+            Ok(Replay::Since(seq.as_u64().ok_or_else(|| "`since` must be a sequence number".to_string())?))
+        },
+        (Some(_), Some(_)) => Err("`replay` and `since` are mutually exclusive".to_string()),
+    }
+}
+
+/// Optional `"events": [..]` list of a subscribe/unsubscribe message
+fn parse_events(json_msg: &serde_json::Value) -> std::result::Result<Vec<EventType>, String> {
+    let Some(events) = json_msg.get("events") else {
+        return Ok(Vec::new());
+    };
+    let events = events.as_array().ok_or_else(|| "`events` must be an array".to_string())?;
+    events
+        .iter()
+        .map(|ev| {
+            let ev = ev.as_str().ok_or_else(|| "Events must be strings".to_string())?;
+            EventType::from_str(ev).map_err(|e| format!("Invalid event `{ev}`: {e}"))
+        })
+        .collect()
+}
+
+/// Optional `"addresses": [..]` list of a subscribe/unsubscribe message
+fn parse_addresses(json_msg: &serde_json::Value) -> std::result::Result<Vec<RpcAddress>, String> {
+    let Some(addresses) = json_msg.get("addresses") else {
+        return Ok(Vec::new());
+    };
+    let addresses = addresses.as_array().ok_or_else(|| "`addresses` must be an array".to_string())?;
+    addresses
+        .iter()
+        .map(|address| {
+            let address = address.as_str().ok_or_else(|| "Addresses must be strings".to_string())?;
+            address.parse::<Address>().map(RpcAddress::from).map_err(|e| e.user_message(Language::English))
+        })
+        .collect()
+}
+
+/// Answer `request` through `outbox` from a task of `calls`, so a slow node call holds up neither the
+/// connection's other messages nor its events. Past `max` calls in flight, the request is refused.
+fn spawn_call<F, Fut>(
+    calls: &mut JoinSet<()>,
+    max: usize,
+    outbox: mpsc::Sender<String>,
+    request: RpcRequest,
+    execute: F,
+) where
+    F: FnOnce(GrpcCall) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<GrpcReturn>> + Send + 'static,
+{
+    let busy = calls.len() >= max;
+    calls.spawn(async move {
+        let reply = if busy {
+            let refusal = Error::TooManyRequests(format!("More than {max} calls in flight"));
+            request.reply(|_| async move { Err(refusal) }).await
+        } else {
+            request.reply(execute).await
+        };
+        // Nobody is left to answer once the connection closed
+        let _ = outbox.send(reply.to_string()).await;
+    });
+}
+
 async fn send_message(socket: &mut WebSocket, msg_type: &str, message: &str) -> Result<()> {
     let response = json!({
         "type": msg_type,
         "message": message
     });
+    send_json(socket, &response).await
+}
+
+async fn send_json(socket: &mut WebSocket, response: &serde_json::Value) -> Result<()> {
     socket.send(Message::Text(response.to_string().into())).await
         .map_err(|e| crate::error::Error::InternalServerError(format!("Failed to send message: {}", e)))?;
     Ok(())
@@ -117,6 +484,115 @@ mod tests {
     use crate::ctx::event_config::EventType;
     use std::str::FromStr;
 
+    use super::*;
+
+    #[test]
+    fn test_malformed_message_closes_with_policy_violation() {
+        let err = Error::from(serde_json::from_str::<serde_json::Value>("{\"type\":").unwrap_err());
+        let frame = Disconnect::Failed(err).close_frame().unwrap();
+        assert_eq!(frame.code, close_code::POLICY);
+        assert!(!frame.reason.is_empty());
+    }
+
+    #[test]
+    fn test_close_codes() {
+        assert!(Disconnect::Client.close_frame().is_none());
+        let frame = Disconnect::Shutdown.close_frame().unwrap();
+        assert_eq!((frame.code, frame.reason.as_str()), (close_code::AWAY, RECONNECT_LATER));
+        assert_eq!(Disconnect::Evicted.close_frame().unwrap().code, close_code::POLICY);
+        let failed = Disconnect::Failed(Error::InternalServerError("x".repeat(200))).close_frame().unwrap();
+        assert_eq!(failed.code, close_code::ERROR);
+        assert!(failed.reason.len() <= MAX_CLOSE_REASON);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_notice_precedes_close() {
+        use futures::{StreamExt, channel::mpsc};
+
+        let hub = Hub::default().with_reconnect_after(1500);
+        let (mut socket, received) = mpsc::unbounded();
+        say_goodbye(&mut socket, &Disconnect::Shutdown, &hub).await.unwrap();
+        drop(socket);
+
+        let received: Vec<Message> = received.collect().await;
+        let [Message::Text(notice), Message::Close(Some(frame))] = received.as_slice() else {
+            panic!("expected a notice then a close frame, got {received:?}");
+        };
+        let notice: serde_json::Value = serde_json::from_str(notice.as_str()).unwrap();
+        assert_eq!(notice, json!({ "type": "server_shutdown", "reconnect_after_ms": 1500 }));
+        assert_eq!(frame.code, close_code::AWAY);
+    }
+
+    #[tokio::test]
+    async fn test_flooding_client_is_throttled_then_closed() {
+        use futures::{StreamExt, channel::mpsc};
+
+        let limit = MessageLimit::new(3, Duration::from_secs(60), 2);
+        let (mut socket, received) = mpsc::unbounded();
+        let mut throttled = 0;
+        let mut handled = 0;
+        let disconnect = loop {
+            match admit(&mut socket, &limit, 7, &mut throttled).await {
+                Ok(true) => handled += 1,
+                Ok(false) => {},
+                Err(disconnect) => break disconnect,
+            }
+        };
+        say_goodbye(&mut socket, &disconnect, &Hub::default()).await.unwrap();
+        drop(socket);
+
+        assert_eq!(handled, 3);
+        let received: Vec<Message> = received.collect().await;
+        let [Message::Text(first), Message::Text(_), Message::Close(Some(frame))] = received.as_slice() else {
+            panic!("expected two errors then a close frame, got {received:?}");
+        };
+        let first: serde_json::Value = serde_json::from_str(first.as_str()).unwrap();
+        assert_eq!(first["type"], "error");
+        assert!(first["message"].as_str().unwrap().starts_with("Too many messages"));
+        assert_eq!(frame.code, close_code::POLICY);
+    }
+
+    async fn unreachable(call: GrpcCall) -> Result<GrpcReturn> {
+        panic!("{call:?} must not reach the node")
+    }
+
+    #[tokio::test]
+    async fn test_calls_answer_out_of_order_within_the_budget() {
+        use tokio::sync::oneshot;
+        use tondi_rpc_core::GetBlockCountResponse;
+
+        let (outbox, mut replies) = mpsc::channel(8);
+        let mut calls = JoinSet::new();
+        let request = |id: u64| RpcRequest::parse(&json!({ "id": id, "method": "get_block_count" })).unwrap();
+        let (release, released) = oneshot::channel::<()>();
+        spawn_call(&mut calls, 2, outbox.clone(), request(1), |_| async move {
+            released.await.unwrap();
+            Ok(GrpcReturn::GetBlockCount(GetBlockCountResponse { header_count: 1, block_count: 1 }))
+        });
+        // The slow first call does not hold up the second
+        spawn_call(&mut calls, 2, outbox.clone(), request(2), |_| async {
+            Ok(GrpcReturn::GetBlockCount(GetBlockCountResponse { header_count: 2, block_count: 2 }))
+        });
+        let reply: serde_json::Value = serde_json::from_str(&replies.recv().await.unwrap()).unwrap();
+        assert_eq!((&reply["id"], &reply["result"]["blockCount"]), (&json!(2), &json!(2)));
+
+        // Over budget: refused without calling the node
+        calls.join_next().await.unwrap().unwrap();
+        spawn_call(&mut calls, 1, outbox, request(3), unreachable);
+        let reply: serde_json::Value = serde_json::from_str(&replies.recv().await.unwrap()).unwrap();
+        assert_eq!((&reply["id"], &reply["error"]["code"]), (&json!(3), &json!("TOO_MANY_REQUESTS")));
+
+        release.send(()).unwrap();
+        let reply: serde_json::Value = serde_json::from_str(&replies.recv().await.unwrap()).unwrap();
+        assert_eq!(reply["id"], 1);
+    }
+
+    #[test]
+    fn test_truncate_keeps_characters_whole() {
+        assert_eq!(truncate("ab".to_string(), 5), "ab");
+        assert_eq!(truncate("aé".to_string(), 2), "a");
+    }
+
     #[test]
     fn test_event_type_parsing() {
         // Test parsing valid event types
@@ -0,0 +1,192 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    str::FromStr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use tokio::sync::Notify;
+use tondi_listener_library::log::warn;
+
+use crate::{
+    ctx::event_config::{EventStrategy, EventType},
+    shared::{metrics::METRICS, pool::Notification},
+};
+
+/// Delivery class of an event type under [`EventStrategy::Priority`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Priority {
+    High,
+    Medium,
+    Low,
+}
+
+impl Priority {
+    pub const ALL: [Priority; 3] = [Priority::High, Priority::Medium, Priority::Low];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::High => "high",
+            Priority::Medium => "medium",
+            Priority::Low => "low",
+        }
+    }
+
+    /// Position in [`Priority::ALL`], and of the queue and depth of the priority
+    pub const fn index(self) -> usize {
+        match self {
+            Priority::High => 0,
+            Priority::Medium => 1,
+            Priority::Low => 2,
+        }
+    }
+}
+
+/// Queue depth per [`Priority`], by [`Priority::index`]
+pub type QueueDepths = Arc<[AtomicUsize; 3]>;
+
+/// Notifications waiting for the hub, handed out highest priority first so a
+/// flood of low-priority events cannot starve `block-added` or `utxos-changed`
+#[derive(Debug)]
+pub struct PriorityQueue {
+    classes: HashMap<EventType, Priority>,
+    queues: Mutex<[VecDeque<Notification>; 3]>,
+    /// Most notifications held per priority; newer ones are dropped beyond it
+    capacity: usize,
+    depths: QueueDepths,
+    ready: Notify,
+}
+
+impl PriorityQueue {
+    /// Classify by the lists of `strategy`; event types on no list are low priority
+    pub fn new(strategy: &EventStrategy, capacity: usize, depths: QueueDepths) -> Self {
+        let mut classes = HashMap::new();
+        if let EventStrategy::Priority { high_priority, medium_priority, low_priority } = strategy {
+            // Listed last wins over listed first, so the highest listing takes effect
+            let lists =
+                [(low_priority, Priority::Low), (medium_priority, Priority::Medium), (high_priority, Priority::High)];
+            for (events, priority) in lists {
+                for ev in events.iter().filter_map(|ev| EventType::from_str(ev).ok()) {
+                    classes.insert(ev, priority);
+                }
+            }
+        }
+        Self { classes, queues: Mutex::default(), capacity: capacity.max(1), depths, ready: Notify::new() }
+    }
+
+    /// Queue for `strategy` reporting into the process-wide metrics; `None`
+    /// unless the strategy is [`EventStrategy::Priority`]
+    pub fn from_strategy(strategy: &EventStrategy, capacity: usize) -> Option<Self> {
+        match strategy {
+            EventStrategy::Priority { .. } => Some(Self::new(strategy, capacity, METRICS.event_queue_depths.clone())),
+            _ => None,
+        }
+    }
+
+    pub fn classify(&self, ev: Option<EventType>) -> Priority {
+        ev.and_then(|ev| self.classes.get(&ev).copied()).unwrap_or(Priority::Low)
+    }
+
+    pub fn push(&self, notification: Notification) {
+        let priority = self.classify(notification.payload.event_type());
+        let Ok(mut queues) = self.queues.lock() else { return };
+        let queue = &mut queues[priority.index()];
+        if queue.len() >= self.capacity {
+            warn!("{} priority event queue is full, dropping an event", priority.as_str());
+            return;
+        }
+        queue.push_back(notification);
+        self.depths[priority.index()].store(queue.len(), Ordering::Relaxed);
+        drop(queues);
+        self.ready.notify_one();
+    }
+
+    /// Next notification of the highest non-empty priority, waiting while all are empty
+    pub async fn pop(&self) -> Notification {
+        loop {
+            if let Some(notification) = self.try_pop() {
+                return notification;
+            }
+            self.ready.notified().await;
+        }
+    }
+
+    fn try_pop(&self) -> Option<Notification> {
+        let mut queues = self.queues.lock().ok()?;
+        Priority::ALL.into_iter().find_map(|priority| {
+            let queue = &mut queues[priority.index()];
+            let notification = queue.pop_front()?;
+            self.depths[priority.index()].store(queue.len(), Ordering::Relaxed);
+            Some(notification)
+        })
+    }
+
+    /// Notifications currently waiting per priority, high first
+    pub fn depths(&self) -> [usize; 3] {
+        Priority::ALL.map(|priority| self.depths[priority.index()].load(Ordering::Relaxed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tondi_rpc_core::{SinkBlueScoreChangedNotification, VirtualDaaScoreChangedNotification};
+
+    use super::*;
+    use crate::shared::pool::NotificationPayload;
+
+    fn queue() -> PriorityQueue {
+        let strategy = EventStrategy::Priority {
+            high_priority: vec!["sink-blue-score-changed".to_string()],
+            medium_priority: vec!["block-added".to_string()],
+            low_priority: Vec::new(),
+        };
+        PriorityQueue::new(&strategy, 1000, QueueDepths::default())
+    }
+
+    fn daa_score(score: u64) -> Notification {
+        NotificationPayload::VirtualDaaScoreChanged(VirtualDaaScoreChangedNotification { virtual_daa_score: score })
+            .into()
+    }
+
+    fn blue_score(score: u64) -> Notification {
+        NotificationPayload::SinkBlueScoreChanged(SinkBlueScoreChangedNotification { sink_blue_score: score }).into()
+    }
+
+    #[test]
+    fn test_index_is_the_position_in_all() {
+        for (position, priority) in Priority::ALL.into_iter().enumerate() {
+            assert_eq!(priority.index(), position, "{}", priority.as_str());
+        }
+    }
+
+    #[test]
+    fn test_unlisted_events_are_low_priority() {
+        let queue = queue();
+        assert_eq!(queue.classify(Some(EventType::SinkBlueScoreChanged)), Priority::High);
+        assert_eq!(queue.classify(Some(EventType::BlockAdded)), Priority::Medium);
+        assert_eq!(queue.classify(Some(EventType::NewBlockTemplate)), Priority::Low);
+        assert_eq!(queue.classify(None), Priority::Low);
+    }
+
+    #[tokio::test]
+    async fn test_high_priority_event_overtakes_a_burst() {
+        let queue = queue();
+        for score in 1..=100 {
+            queue.push(daa_score(score));
+        }
+        queue.push(blue_score(7));
+        assert_eq!(queue.depths(), [1, 0, 100]);
+
+        let NotificationPayload::SinkBlueScoreChanged(first) = queue.pop().await.payload else {
+            panic!("the high-priority event must come first")
+        };
+        assert_eq!(first.sink_blue_score, 7);
+        let NotificationPayload::VirtualDaaScoreChanged(second) = queue.pop().await.payload else {
+            panic!("the burst must follow in order")
+        };
+        assert_eq!(second.virtual_daa_score, 1);
+        assert_eq!(queue.depths(), [0, 0, 99]);
+    }
+}
@@ -0,0 +1,147 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, PoisonError, RwLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+use tokio_util::sync::{CancellationToken, WaitForCancellationFuture};
+
+use crate::{ctx::event_config::EventType, routes::websocket::address_index::ConnId};
+
+/// Open WebSocket connections, shared with `/admin/connections` so operators can list and evict them
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionRegistry {
+    connections: Arc<RwLock<HashMap<ConnId, Entry>>>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    remote_address: SocketAddr,
+    connected_at: u64,
+    events: Vec<EventType>,
+    /// Cancelled to close the connection
+    evict: CancellationToken,
+}
+
+/// One open connection as `/admin/connections` lists it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionInfo {
+    pub id: ConnId,
+    pub remote_address: SocketAddr,
+    /// Unix seconds of the upgrade
+    pub connected_at: u64,
+    pub events: Vec<String>,
+}
+
+impl ConnectionRegistry {
+    /// Record `conn` until the returned registration is dropped
+    pub fn register(&self, conn: ConnId, remote_address: SocketAddr) -> Registration {
+        let connected_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let evict = CancellationToken::new();
+        let entry = Entry { remote_address, connected_at, events: Vec::new(), evict: evict.clone() };
+        self.connections.write().unwrap_or_else(PoisonError::into_inner).insert(conn, entry);
+        Registration { registry: self.clone(), conn, evict }
+    }
+
+    /// Every open connection, oldest first
+    pub fn list(&self) -> Vec<ConnectionInfo> {
+        let connections = self.connections.read().unwrap_or_else(PoisonError::into_inner);
+        let mut listed: Vec<_> = connections.iter().map(|(&conn, entry)| entry.info(conn)).collect();
+        listed.sort_by_key(|info| info.id);
+        listed
+    }
+
+    /// Ask `conn` to close; the connection it was, `None` if it is not open
+    pub fn close(&self, conn: ConnId) -> Option<ConnectionInfo> {
+        let connections = self.connections.read().unwrap_or_else(PoisonError::into_inner);
+        let entry = connections.get(&conn)?;
+        entry.evict.cancel();
+        Some(entry.info(conn))
+    }
+}
+
+impl Entry {
+    fn info(&self, id: ConnId) -> ConnectionInfo {
+        let mut events: Vec<_> = self.events.iter().map(ToString::to_string).collect();
+        events.sort();
+        ConnectionInfo { id, remote_address: self.remote_address, connected_at: self.connected_at, events }
+    }
+}
+
+/// A connection's place in the [`ConnectionRegistry`], given up when it closes
+#[derive(Debug)]
+pub struct Registration {
+    registry: ConnectionRegistry,
+    conn: ConnId,
+    evict: CancellationToken,
+}
+
+impl Registration {
+    /// Record the events the connection is now subscribed to
+    pub fn set_events(&self, events: Vec<EventType>) {
+        let mut connections = self.registry.connections.write().unwrap_or_else(PoisonError::into_inner);
+        if let Some(entry) = connections.get_mut(&self.conn) {
+            entry.events = events;
+        }
+    }
+
+    /// Resolves once an operator closes the connection
+    pub fn evicted(&self) -> WaitForCancellationFuture<'_> {
+        self.evict.cancelled()
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.registry.connections.write().unwrap_or_else(PoisonError::into_inner).remove(&self.conn);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn remote(port: u16) -> SocketAddr {
+        SocketAddr::from(([10, 0, 0, 1], port))
+    }
+
+    #[test]
+    fn test_connection_is_listed_until_it_closes() {
+        let registry = ConnectionRegistry::default();
+        let first = registry.register(1, remote(4000));
+        let second = registry.register(2, remote(4001));
+        second.set_events(vec![EventType::UtxosChanged, EventType::BlockAdded]);
+
+        let listed = registry.list();
+        assert_eq!(listed.iter().map(|info| info.id).collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(listed[0].remote_address, remote(4000));
+        assert!(listed[0].events.is_empty());
+        assert_eq!(listed[1].events, ["block-added", "utxos-changed"]);
+        assert!(listed[1].connected_at > 0);
+
+        drop(first);
+        assert_eq!(registry.list().iter().map(|info| info.id).collect::<Vec<_>>(), [2]);
+        drop(second);
+        assert!(registry.list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_close_evicts_only_that_connection() {
+        let registry = ConnectionRegistry::default();
+        let target = registry.register(1, remote(4000));
+        let bystander = registry.register(2, remote(4001));
+
+        assert_eq!(registry.close(1).map(|info| info.id), Some(1));
+        tokio::time::timeout(Duration::from_secs(1), target.evicted()).await.expect("connection was not evicted");
+        assert!(tokio::time::timeout(Duration::from_millis(10), bystander.evicted()).await.is_err());
+
+        // Gone once the connection handler lets go of it
+        drop(target);
+        assert_eq!(registry.close(1), None);
+    }
+}
@@ -0,0 +1,140 @@
+use serde_json::{Value, json};
+
+use crate::{
+    error::{Error, Result},
+    routes::grpc::{ADMIN_ONLY, grpc_call::GrpcCall, grpc_return::GrpcReturn},
+    shared::language::Language,
+};
+
+/// A `{"id", "method", "params"}` message asking for one node call
+#[derive(Debug)]
+pub struct RpcRequest {
+    /// Echoed in the reply so clients can match it to the request
+    id: Value,
+    call: Result<GrpcCall>,
+}
+
+impl RpcRequest {
+    /// `Some` for messages shaped like a call: a `method` and no `type`, which subscription messages carry.
+    /// Methods name a `/grpc` call in snake_case (`get_block_count`) or camelCase (`getBlockCount`);
+    /// anything `/grpc` does not accept is refused the same way.
+    pub fn parse(message: &Value) -> Option<Self> {
+        if message.get("type").is_some() {
+            return None;
+        }
+        let method = message.get("method")?;
+        let Some(id) = message.get("id").filter(|id| !id.is_null()) else {
+            return Some(Self { id: Value::Null, call: Err(Error::BadRequest("`id` is required".to_string())) });
+        };
+        let call = match method.as_str() {
+            Some(method) => parse_call(method, message.get("params")),
+            None => Err(Error::BadRequest("`method` must be a string".to_string())),
+        };
+        Some(Self { id: id.clone(), call })
+    }
+
+    /// `{"id", "result"}` with what `execute` returned for the call, or `{"id", "error": {"code", "message"}}`
+    pub async fn reply<F, Fut>(self, execute: F) -> Value
+    where
+        F: FnOnce(GrpcCall) -> Fut,
+        Fut: Future<Output = Result<GrpcReturn>>,
+    {
+        let result = match self.call {
+            Ok(call) if call.is_admin_only() => Err(Error::Forbidden(ADMIN_ONLY.to_string())),
+            Ok(call) => execute(call).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(ret) => json!({ "id": self.id, "result": ret }),
+            Err(e) => json!({
+                "id": self.id,
+                "error": { "code": e.error_code(), "message": e.user_message(Language::English) },
+            }),
+        }
+    }
+}
+
+fn parse_call(method: &str, params: Option<&Value>) -> Result<GrpcCall> {
+    let params = match params {
+        None | Some(Value::Null) => json!({}),
+        Some(params) => params.clone(),
+    };
+    serde_json::from_value(json!({ "op": op(method), "params": params }))
+        .map_err(|e| Error::BadRequest(format!("Invalid call `{method}`: {e}")))
+}
+
+/// `GrpcCall` variant name of a snake_case or camelCase method
+fn op(method: &str) -> String {
+    let mut op = String::with_capacity(method.len());
+    let mut upper = true;
+    for c in method.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            op.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            op.push(c);
+        }
+    }
+    op
+}
+
+#[cfg(test)]
+mod tests {
+    use tondi_rpc_core::{GetBlockCountResponse, RpcError};
+
+    use super::*;
+
+    fn request(message: Value) -> RpcRequest {
+        RpcRequest::parse(&message).expect("a call")
+    }
+
+    async fn unreachable(call: GrpcCall) -> Result<GrpcReturn> {
+        panic!("{call:?} must not reach the node")
+    }
+
+    #[tokio::test]
+    async fn test_get_block_count_round_trip() {
+        let request = request(json!({ "id": 7, "method": "get_block_count", "params": {} }));
+        let reply = request
+            .reply(|call| async move {
+                assert!(matches!(call, GrpcCall::GetBlockCount(_)), "{call:?}");
+                Ok(GrpcReturn::GetBlockCount(GetBlockCountResponse { header_count: 12, block_count: 11 }))
+            })
+            .await;
+        assert_eq!(reply["id"], 7);
+        assert_eq!(reply["result"]["blockCount"], 11);
+        assert!(reply.get("error").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_failures_reply_with_an_error() {
+        let reply = request(json!({ "id": "a", "method": "shutdown" })).reply(unreachable).await;
+        assert_eq!((&reply["id"], &reply["error"]["code"]), (&json!("a"), &json!("FORBIDDEN")));
+
+        let reply = request(json!({ "id": "b", "method": "drop_database" })).reply(unreachable).await;
+        assert_eq!(reply["error"]["code"], "BAD_REQUEST");
+
+        let reply = request(json!({ "method": "getBlockCount" })).reply(unreachable).await;
+        assert_eq!((&reply["id"], &reply["error"]["code"]), (&Value::Null, &json!("BAD_REQUEST")));
+
+        let reply = request(json!({ "id": 3, "method": "getBlockCount" }))
+            .reply(|_| async { Err(Error::from(RpcError::General("connection refused".to_string()))) })
+            .await;
+        assert_eq!(reply["error"]["code"], "SERVICE_UNAVAILABLE");
+    }
+
+    #[test]
+    fn test_subscription_messages_are_not_calls() {
+        assert!(RpcRequest::parse(&json!({ "type": "subscribe", "events": ["block-added"] })).is_none());
+        assert!(RpcRequest::parse(&json!({ "type": "ping", "id": 1, "method": "x" })).is_none());
+    }
+
+    #[test]
+    fn test_method_names() {
+        assert_eq!(op("get_block_count"), "GetBlockCount");
+        assert_eq!(op("getBlockCount"), "GetBlockCount");
+        assert_eq!(op("GetBlockCount"), "GetBlockCount");
+    }
+}
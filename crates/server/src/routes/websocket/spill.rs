@@ -0,0 +1,346 @@
+use std::{
+    collections::VecDeque,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, UNIX_EPOCH},
+};
+
+use serde_json::{Value, json};
+use tokio::sync::{Mutex, Notify};
+use tondi_listener_library::log::{info, warn};
+
+use crate::shared::{
+    metrics::METRICS,
+    pool::{Notification, NotificationPayload},
+};
+
+/// Name of the spill file in the configured directory
+pub const SPILL_FILE: &str = "events.spill";
+
+/// Notifications waiting for the hub, first in first out. At most `high_water` are held in memory; later ones
+/// are appended to a file as JSON lines and read back once memory has drained, so a hub held up by a slow
+/// database neither makes the upstream receivers lag nor grows without bound. Beyond `max_bytes` on disk, new
+/// notifications are dropped and counted.
+#[derive(Debug)]
+pub struct SpillBuffer {
+    /// The file is only read and written on a blocking thread holding this lock, which async callers wait
+    /// for without holding up their worker
+    state: Arc<Mutex<State>>,
+    high_water: usize,
+    max_bytes: u64,
+    path: PathBuf,
+    dropped: AtomicU64,
+    ready: Notify,
+}
+
+#[derive(Debug)]
+struct State {
+    memory: VecDeque<Notification>,
+    writer: BufWriter<File>,
+    reader: BufReader<File>,
+    /// Notifications in the file not read back yet; while any are, new ones are spilled too to keep the order
+    spilled: usize,
+    /// Bytes written since the file was last emptied
+    bytes: u64,
+}
+
+impl SpillBuffer {
+    /// Spill into [`SPILL_FILE`] in `dir`, created if missing; a file left by a previous run is emptied
+    pub fn new(dir: impl AsRef<Path>, high_water: usize, max_bytes: u64) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let path = dir.as_ref().join(SPILL_FILE);
+        let writer = OpenOptions::new().create(true).write(true).truncate(true).open(&path)?;
+        let reader = File::open(&path)?;
+        let state = State {
+            memory: VecDeque::new(),
+            writer: BufWriter::new(writer),
+            reader: BufReader::new(reader),
+            spilled: 0,
+            bytes: 0,
+        };
+        Ok(Self {
+            state: Arc::new(Mutex::new(state)),
+            high_water: high_water.max(1),
+            max_bytes,
+            path,
+            dropped: AtomicU64::new(0),
+            ready: Notify::new(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub async fn push(self: &Arc<Self>, notification: Notification) {
+        let mut state = self.state.clone().lock_owned().await;
+        if state.spilled == 0 && state.memory.len() < self.high_water {
+            state.memory.push_back(notification);
+            drop(state);
+        } else {
+            let this = self.clone();
+            let spilled = tokio::task::spawn_blocking(move || this.spill(&mut state, &notification))
+                .await
+                .unwrap_or_else(|e| Err(io::Error::other(e)));
+            match spilled {
+                Ok(true) => {},
+                Ok(false) => self.drop_events(1),
+                Err(e) => {
+                    warn!("Cannot spill an event to {}, dropping it: {e}", self.path.display());
+                    self.drop_events(1);
+                },
+            }
+        }
+        self.ready.notify_one();
+    }
+
+    /// Append `notification` to the file; `false` when it would grow past `max_bytes`
+    fn spill(&self, state: &mut State, notification: &Notification) -> io::Result<bool> {
+        let mut line = encode(notification).to_string();
+        line.push('\n');
+        let len = u64::try_from(line.len()).unwrap_or(u64::MAX);
+        if state.bytes.saturating_add(len) > self.max_bytes {
+            return Ok(false);
+        }
+        if state.spilled == 0 {
+            info!("More than {} events waiting, spilling to {}", self.high_water, self.path.display());
+        }
+        state.writer.write_all(line.as_bytes())?;
+        state.bytes += len;
+        state.spilled += 1;
+        METRICS.events_spilled.store(state.spilled, Ordering::Relaxed);
+        Ok(true)
+    }
+
+    fn drop_events(&self, count: u64) {
+        self.dropped.fetch_add(count, Ordering::Relaxed);
+        METRICS.events_spill_dropped.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Next notification, waiting while none is buffered
+    pub async fn pop(self: &Arc<Self>) -> Notification {
+        loop {
+            if let Some(notification) = self.try_pop().await {
+                return notification;
+            }
+            self.ready.notified().await;
+        }
+    }
+
+    async fn try_pop(self: &Arc<Self>) -> Option<Notification> {
+        let mut state = self.state.clone().lock_owned().await;
+        if state.memory.is_empty() && state.spilled > 0 {
+            let this = self.clone();
+            state = tokio::task::spawn_blocking(move || {
+                this.read_back(&mut state);
+                state
+            })
+            .await
+            .ok()?;
+        }
+        state.memory.pop_front()
+    }
+
+    /// [`SpillBuffer::refill`], dropping what is left in the file when it cannot be read
+    fn read_back(&self, state: &mut State) {
+        if let Err(e) = self.refill(state) {
+            warn!("Cannot read spilled events back from {}, dropping them: {e}", self.path.display());
+            self.drop_events(u64::try_from(state.spilled).unwrap_or(u64::MAX));
+            state.spilled = 0;
+            // Emptying can fail the same way; the next spill then appends after the unread lines
+            let _ = empty(state);
+        }
+        METRICS.events_spilled.store(state.spilled, Ordering::Relaxed);
+    }
+
+    /// Read up to `high_water` spilled notifications back into memory, emptying the file once all are
+    fn refill(&self, state: &mut State) -> io::Result<()> {
+        state.writer.flush()?;
+        let mut line = String::new();
+        while state.spilled > 0 && state.memory.len() < self.high_water {
+            line.clear();
+            if state.reader.read_line(&mut line)? == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "spill file ended early"));
+            }
+            state.spilled -= 1;
+            match decode(&line) {
+                Some(notification) => state.memory.push_back(notification),
+                None => self.drop_events(1),
+            }
+        }
+        if state.spilled == 0 {
+            empty(state)?;
+        }
+        Ok(())
+    }
+
+    /// Notifications held in memory and in the file
+    pub async fn len(&self) -> usize {
+        let state = self.state.lock().await;
+        state.memory.len() + state.spilled
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+
+    /// Notifications waiting in the file
+    pub async fn spilled(&self) -> usize {
+        self.state.lock().await.spilled
+    }
+
+    /// Notifications dropped because the file was full or unreadable
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// Truncate the file read to the end, so it takes no disk space while nothing is spilled
+fn empty(state: &mut State) -> io::Result<()> {
+    state.writer.flush()?;
+    state.writer.get_ref().set_len(0)?;
+    state.writer.seek(SeekFrom::Start(0))?;
+    state.reader.seek(SeekFrom::Start(0))?;
+    state.bytes = 0;
+    Ok(())
+}
+
+fn encode(notification: &Notification) -> Value {
+    let received_at = notification.received_at.duration_since(UNIX_EPOCH).unwrap_or_default();
+    json!({
+        "receivedAt": u64::try_from(received_at.as_millis()).unwrap_or(u64::MAX),
+        "payload": notification.payload.to_json(),
+    })
+}
+
+fn decode(line: &str) -> Option<Notification> {
+    let mut value: Value = serde_json::from_str(line).ok()?;
+    let received_at = UNIX_EPOCH + Duration::from_millis(value["receivedAt"].as_u64()?);
+    Some(Notification { payload: NotificationPayload::from_json(value["payload"].take()), received_at })
+}
+
+#[cfg(test)]
+mod tests {
+    use tondi_consensus_core::{header::Header, tx::ScriptPublicKey};
+    use tondi_rpc_core::{
+        BlockAddedNotification, FinalityConflictNotification, FinalityConflictResolvedNotification,
+        NewBlockTemplateNotification, PruningPointUtxoSetOverrideNotification, RpcAcceptedTransactionIds, RpcBlock,
+        RpcHash, RpcTransactionOutpoint, RpcUtxoEntry, RpcUtxosByAddressesEntry, SinkBlueScoreChangedNotification,
+        UtxosChangedNotification, VirtualChainChangedNotification, VirtualDaaScoreChangedNotification,
+    };
+
+    use super::*;
+
+    fn daa_score(score: u64) -> Notification {
+        NotificationPayload::VirtualDaaScoreChanged(VirtualDaaScoreChangedNotification { virtual_daa_score: score })
+            .into()
+    }
+
+    fn score(notification: &Notification) -> u64 {
+        match &notification.payload {
+            NotificationPayload::VirtualDaaScoreChanged(n) => n.virtual_daa_score,
+            payload => panic!("unexpected {payload:?}"),
+        }
+    }
+
+    fn spill_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("tondi-listener-{name}-{}", std::process::id()))
+    }
+
+    #[tokio::test]
+    async fn test_past_the_high_water_mark_events_spill_and_drain_in_order() {
+        let buffer = Arc::new(SpillBuffer::new(spill_dir("spill-drain"), 2, u64::MAX).unwrap());
+        for score in 0..5 {
+            buffer.push(daa_score(score)).await;
+        }
+        assert_eq!((buffer.len().await, buffer.spilled().await), (5, 3));
+
+        let mut drained = Vec::new();
+        for _ in 0..5 {
+            drained.push(score(&buffer.pop().await));
+        }
+        assert_eq!(drained, [0, 1, 2, 3, 4]);
+        assert!(buffer.is_empty().await);
+        // Read to the end, the file is emptied
+        assert_eq!(fs::metadata(buffer.path()).unwrap().len(), 0);
+
+        // Spilling again after draining starts over at the beginning of the file
+        for score in 5..8 {
+            buffer.push(daa_score(score)).await;
+        }
+        assert_eq!(buffer.spilled().await, 1);
+        assert!(fs::metadata(buffer.path()).unwrap().len() > 0);
+        for expected in 5..8 {
+            assert_eq!(score(&buffer.pop().await), expected);
+        }
+        assert_eq!(buffer.dropped(), 0);
+        fs::remove_dir_all(spill_dir("spill-drain")).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_events_past_the_disk_cap_are_dropped() {
+        let line = u64::try_from(encode(&daa_score(1)).to_string().len()).unwrap() + 1;
+        let buffer = Arc::new(SpillBuffer::new(spill_dir("spill-cap"), 1, 2 * line).unwrap());
+        for score in 0..5 {
+            buffer.push(daa_score(score)).await;
+        }
+        assert_eq!((buffer.spilled().await, buffer.dropped()), (2, 2));
+
+        let drained: Vec<_> = [buffer.pop().await, buffer.pop().await, buffer.pop().await].iter().map(score).collect();
+        assert_eq!(drained, [0, 1, 2]);
+        fs::remove_dir_all(spill_dir("spill-cap")).unwrap();
+    }
+
+    #[test]
+    fn test_every_payload_survives_the_file() {
+        let hash = |byte: u8| RpcHash::from_bytes([byte; 32]);
+        let entry = RpcUtxosByAddressesEntry {
+            address: None,
+            outpoint: RpcTransactionOutpoint { transaction_id: hash(4), index: 1 },
+            utxo_entry: RpcUtxoEntry::new(500, ScriptPublicKey::from_vec(0, vec![0xac]), 7, false),
+        };
+        let block = RpcBlock {
+            header: (&Header::from_precomputed_hash(hash(1), vec![vec![hash(2)]])).into(),
+            transactions: Vec::new(),
+            verbose_data: None,
+        };
+        let payloads = [
+            NotificationPayload::BlockAdded(BlockAddedNotification { block: Arc::new(block) }),
+            NotificationPayload::VirtualChainChanged(VirtualChainChangedNotification {
+                added_chain_block_hashes: Arc::new(vec![hash(1)]),
+                removed_chain_block_hashes: Arc::new(vec![hash(2)]),
+                accepted_transaction_ids: Arc::new(vec![RpcAcceptedTransactionIds {
+                    accepting_block_hash: hash(1),
+                    accepted_transaction_ids: vec![hash(3)],
+                }]),
+            }),
+            NotificationPayload::FinalityConflict(FinalityConflictNotification { violating_block_hash: hash(5) }),
+            NotificationPayload::FinalityConflictResolved(FinalityConflictResolvedNotification {
+                finality_block_hash: hash(6),
+            }),
+            NotificationPayload::UtxosChanged(UtxosChangedNotification {
+                added: Arc::new(vec![entry.clone()]),
+                removed: Arc::new(vec![entry]),
+            }),
+            NotificationPayload::SinkBlueScoreChanged(SinkBlueScoreChangedNotification { sink_blue_score: 42 }),
+            NotificationPayload::VirtualDaaScoreChanged(VirtualDaaScoreChangedNotification { virtual_daa_score: 9 }),
+            NotificationPayload::PruningPointUtxoSetOverride(PruningPointUtxoSetOverrideNotification {}),
+            NotificationPayload::NewBlockTemplate(NewBlockTemplateNotification {}),
+            NotificationPayload::Unknown { event_type: "something-new".to_string(), data: json!({ "x": 1 }) },
+        ];
+        for payload in payloads {
+            let notification = Notification::from(payload);
+            let line = encode(&notification).to_string();
+            let decoded = decode(&line).unwrap_or_else(|| panic!("cannot decode {line}"));
+            assert_eq!(decoded.payload.event_type(), notification.payload.event_type(), "{line}");
+            assert_eq!(decoded.payload.to_json(), notification.payload.to_json(), "{line}");
+            let millis = |n: &Notification| n.received_at.duration_since(UNIX_EPOCH).unwrap().as_millis();
+            assert_eq!(millis(&decoded), millis(&notification));
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use crate::{
+    ctx::config::{ConfigError, WebSocketConfig},
+    middleware::rate_limit::RateLimiter,
+    routes::websocket::address_index::ConnId,
+};
+
+/// Inbound message rate of each WebSocket connection, independent of the HTTP rate limiter
+#[derive(Debug, Clone)]
+pub struct MessageLimit {
+    limiter: RateLimiter<ConnId>,
+    /// Refused messages in a row after which the connection is closed
+    max_throttled: u32,
+}
+
+/// What to do with one inbound message
+#[derive(Debug, PartialEq, Eq)]
+pub enum Throttle {
+    Handle,
+    /// Answer with an error instead; the client may send again after the delay
+    Refuse(Duration),
+    /// Too many messages were refused in a row
+    Close,
+}
+
+impl MessageLimit {
+    /// `max_messages` per `window` in a burst, `0` for unlimited
+    pub fn new(max_messages: u32, window: Duration, max_throttled: u32) -> Self {
+        Self { limiter: RateLimiter::new(max_messages, window), max_throttled }
+    }
+
+    /// Fails on an empty window while the rate is limited, which would refill the budget on every message
+    pub fn from_config(config: &WebSocketConfig) -> Result<Self, ConfigError> {
+        if config.message_rate_limit > 0 && config.message_rate_window_secs == 0 {
+            return Err(ConfigError::InvalidWebSocketConfig(
+                "`message_rate_window_secs` must be at least 1 when `message_rate_limit` is set".to_string(),
+            ));
+        }
+        Ok(Self::new(
+            config.message_rate_limit,
+            Duration::from_secs(config.message_rate_window_secs),
+            config.max_throttled_messages,
+        ))
+    }
+
+    /// Spend one message of `conn`; `throttled` counts its messages refused since the last handled one
+    pub fn check(&self, conn: ConnId, throttled: &mut u32) -> Throttle {
+        match self.limiter.acquire(conn) {
+            Ok(()) => {
+                *throttled = 0;
+                Throttle::Handle
+            },
+            Err(_) if *throttled >= self.max_throttled => Throttle::Close,
+            Err(wait) => {
+                *throttled += 1;
+                Throttle::Refuse(wait)
+            },
+        }
+    }
+
+    /// Drop the bucket of a closed connection
+    pub fn forget(&self, conn: ConnId) {
+        self.limiter.forget(&conn);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refusals_in_a_row_close_the_connection() {
+        let limit = MessageLimit::new(2, Duration::from_secs(60), 3);
+        let mut throttled = 0;
+        assert_eq!(limit.check(1, &mut throttled), Throttle::Handle);
+        assert_eq!(limit.check(1, &mut throttled), Throttle::Handle);
+        for _ in 0..3 {
+            assert!(matches!(limit.check(1, &mut throttled), Throttle::Refuse(_)));
+        }
+        assert_eq!(limit.check(1, &mut throttled), Throttle::Close);
+
+        // Other connections have their own budget
+        assert_eq!(limit.check(2, &mut 0), Throttle::Handle);
+        limit.forget(1);
+        assert_eq!(limit.check(1, &mut 0), Throttle::Handle);
+    }
+
+    #[test]
+    fn test_empty_window_is_rejected() {
+        let config = WebSocketConfig { message_rate_limit: 5, message_rate_window_secs: 0, ..Default::default() };
+        assert!(matches!(MessageLimit::from_config(&config), Err(ConfigError::InvalidWebSocketConfig(_))));
+
+        let unlimited = WebSocketConfig { message_rate_limit: 0, ..config };
+        assert!(MessageLimit::from_config(&unlimited).is_ok());
+    }
+
+    #[test]
+    fn test_zero_rate_is_unlimited() {
+        let limit = MessageLimit::new(0, Duration::from_secs(60), 0);
+        for _ in 0..1000 {
+            assert_eq!(limit.check(1, &mut 0), Throttle::Handle);
+        }
+    }
+}
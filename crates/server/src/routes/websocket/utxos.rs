@@ -0,0 +1,116 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+use tondi_rpc_core::{RpcAddress, RpcUtxosByAddressesEntry, UtxosChangedNotification};
+
+/// `data` of a `utxos-changed` event: the UTXOs to add to and remove from a local set
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct UtxoDiff {
+    pub added: Vec<UtxoChange>,
+    pub removed: Vec<UtxoChange>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct UtxoChange {
+    /// `null` for scripts that pay to no standard address
+    pub address: Option<String>,
+    pub outpoint: Outpoint,
+    /// In sompi
+    pub amount: u64,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Outpoint {
+    pub transaction_id: String,
+    pub index: u32,
+}
+
+impl UtxoDiff {
+    /// The changes of `notification`, only those paying to `watched` when given
+    pub fn new(notification: &UtxosChangedNotification, watched: Option<&HashSet<RpcAddress>>) -> Self {
+        let changes = |entries: &[RpcUtxosByAddressesEntry]| {
+            entries
+                .iter()
+                .filter(|entry| match (watched, &entry.address) {
+                    (None, _) => true,
+                    (Some(watched), Some(address)) => watched.contains(address),
+                    (Some(_), None) => false,
+                })
+                .map(UtxoChange::from)
+                .collect()
+        };
+        Self { added: changes(&notification.added), removed: changes(&notification.removed) }
+    }
+}
+
+impl From<&RpcUtxosByAddressesEntry> for UtxoChange {
+    fn from(entry: &RpcUtxosByAddressesEntry) -> Self {
+        Self {
+            address: entry.address.as_ref().map(ToString::to_string),
+            outpoint: Outpoint {
+                transaction_id: entry.outpoint.transaction_id.to_string(),
+                index: entry.outpoint.index,
+            },
+            amount: entry.utxo_entry.amount,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+    use tondi_addresses::{Prefix, Version};
+    use tondi_consensus_core::tx::ScriptPublicKey;
+    use tondi_rpc_core::{RpcHash, RpcTransactionOutpoint, RpcUtxoEntry};
+
+    use super::*;
+
+    fn address(byte: u8) -> RpcAddress {
+        RpcAddress::new(Prefix::Mainnet, Version::PubKey, &[byte; 32])
+    }
+
+    fn entry(address: Option<RpcAddress>, tx: u8, index: u32, amount: u64) -> RpcUtxosByAddressesEntry {
+        RpcUtxosByAddressesEntry {
+            address,
+            outpoint: RpcTransactionOutpoint { transaction_id: RpcHash::from_bytes([tx; 32]), index },
+            utxo_entry: RpcUtxoEntry::new(amount, ScriptPublicKey::from_vec(0, Vec::new()), 7, false),
+        }
+    }
+
+    fn change() -> UtxosChangedNotification {
+        UtxosChangedNotification {
+            added: Arc::new(vec![entry(Some(address(1)), 0xaa, 0, 500), entry(Some(address(2)), 0xaa, 1, 20)]),
+            removed: Arc::new(vec![entry(Some(address(1)), 0xbb, 3, 520), entry(None, 0xcc, 0, 1)]),
+        }
+    }
+
+    #[test]
+    fn test_change_is_normalized_to_added_and_removed() {
+        let diff = serde_json::to_value(UtxoDiff::new(&change(), None)).unwrap();
+        let outpoint = |tx: u8, index: u32| json!({ "transactionId": format!("{tx:02x}").repeat(32), "index": index });
+        assert_eq!(
+            diff,
+            json!({
+                "added": [
+                    { "address": address(1).to_string(), "outpoint": outpoint(0xaa, 0), "amount": 500 },
+                    { "address": address(2).to_string(), "outpoint": outpoint(0xaa, 1), "amount": 20 },
+                ],
+                "removed": [
+                    { "address": address(1).to_string(), "outpoint": outpoint(0xbb, 3), "amount": 520 },
+                    { "address": null, "outpoint": outpoint(0xcc, 0), "amount": 1 },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn test_watchers_only_see_their_addresses() {
+        let watched = HashSet::from([address(1)]);
+        let diff = UtxoDiff::new(&change(), Some(&watched));
+        assert_eq!(diff.added.iter().map(|change| change.amount).collect::<Vec<_>>(), [500]);
+        assert_eq!(diff.removed.iter().map(|change| change.amount).collect::<Vec<_>>(), [520]);
+    }
+}
@@ -0,0 +1,109 @@
+use std::{fmt, str::FromStr};
+
+use axum::{
+    extract::{FromRequestParts, Path},
+    http::request::Parts,
+};
+use tondi_addresses::Prefix;
+use tondi_consensus_core::network::NetworkType;
+use tondi_rpc_core::RpcAddress;
+
+use crate::{
+    ctx::Context,
+    error::{Error, Result},
+};
+
+/// Tondi address whose prefix and checksum were validated on parse
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Address(RpcAddress);
+
+impl Address {
+    /// Reject addresses of another network than `network`
+    pub fn check_network(&self, network: NetworkType) -> Result<()> {
+        let expected = Prefix::from(network);
+        if self.0.prefix != expected {
+            return Err(Error::BadRequest(format!(
+                "Address `{self}` is not a {network} address (expected prefix `{expected}`)"
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> RpcAddress {
+        self.0
+    }
+}
+
+impl FromStr for Address {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        RpcAddress::try_from(s).map(Self).map_err(|e| Error::BadRequest(format!("Invalid address `{s}`: {e}")))
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<Address> for RpcAddress {
+    fn from(address: Address) -> Self {
+        address.0
+    }
+}
+
+/// `{address}` path segment, validated against the configured network
+impl FromRequestParts<Context> for Address {
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, ctx: &Context) -> Result<Self> {
+        let Path(address) = Path::<String>::from_request_parts(parts, ctx)
+            .await
+            .map_err(|e| Error::BadRequest(e.body_text()))?;
+        let address: Address = address.parse()?;
+        let network = ctx.config.upstream_network().map_err(|e| Error::InternalServerError(e.to_string()))?;
+        address.check_network(network)?;
+        Ok(address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tondi_addresses::Version;
+
+    use super::*;
+
+    fn address(prefix: Prefix) -> String {
+        RpcAddress::new(prefix, Version::PubKey, &[7; 32]).to_string()
+    }
+
+    #[test]
+    fn test_valid_address_round_trips() {
+        let text = address(Prefix::Mainnet);
+        let parsed: Address = text.parse().unwrap();
+        assert_eq!(parsed.to_string(), text);
+        assert!(parsed.check_network(NetworkType::Mainnet).is_ok());
+    }
+
+    #[test]
+    fn test_malformed_address_is_bad_request() {
+        let mut text = address(Prefix::Mainnet);
+        // Corrupt the checksum
+        let last = text.pop().unwrap();
+        text.push(if last == 'q' { 'p' } else { 'q' });
+
+        for invalid in [text.as_str(), "", "not-an-address"] {
+            let err = invalid.parse::<Address>().unwrap_err();
+            assert!(matches!(err, Error::BadRequest(_)), "{invalid}: {err:?}");
+        }
+    }
+
+    #[test]
+    fn test_cross_network_address_is_rejected() {
+        let testnet: Address = address(Prefix::Testnet).parse().unwrap();
+        let err = testnet.check_network(NetworkType::Mainnet).unwrap_err();
+        assert!(matches!(err, Error::BadRequest(_)));
+    }
+}
@@ -0,0 +1,389 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+    sync::{
+        Arc, LazyLock, Mutex, PoisonError, Weak,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use crate::shared::metrics::{CacheCounters, METRICS};
+
+/// Entries a cache holds unless [`TtlCache::with_max_entries`] says otherwise
+pub const DEFAULT_MAX_ENTRIES: usize = 10_000;
+
+/// Every cache created with [`TtlCache::named`], flushed through `/admin/cache/flush`
+pub static CACHES: LazyLock<CacheRegistry> = LazyLock::new(CacheRegistry::default);
+
+#[derive(Debug)]
+struct Entry<V> {
+    expires: Instant,
+    /// Position in `Entries::recency`
+    used: u64,
+    value: V,
+}
+
+#[derive(Debug)]
+struct Entries<K, V> {
+    map: HashMap<K, Entry<V>>,
+    /// Keys by last use, least recent first
+    recency: BTreeMap<u64, K>,
+    clock: u64,
+    /// Inserts since expired entries were last swept
+    unswept: usize,
+}
+
+impl<K, V> Default for Entries<K, V> {
+    fn default() -> Self {
+        Self { map: HashMap::new(), recency: BTreeMap::new(), clock: 0, unswept: 0 }
+    }
+}
+
+impl<K: Eq + Hash + Clone, V> Entries<K, V> {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    /// The live value of `key`, now the most recently used; an expired one is dropped
+    fn get(&mut self, key: &K) -> Option<&V> {
+        let now = Instant::now();
+        let used = self.tick();
+        let entry = self.map.get_mut(key)?;
+        if entry.expires <= now {
+            let stale = entry.used;
+            self.recency.remove(&stale);
+            self.map.remove(key);
+            return None;
+        }
+        self.recency.remove(&entry.used);
+        entry.used = used;
+        self.recency.insert(used, key.clone());
+        self.map.get(key).map(|entry| &entry.value)
+    }
+
+    /// Store `value`, then drop the least recently used entries while more than `max_entries` are held;
+    /// returns how many live entries were evicted. Expired entries nobody reads again are swept once the
+    /// inserts since the last sweep reach the entries that were kept, so each insert pays for one entry.
+    fn insert(&mut self, key: K, value: V, ttl: Duration, max_entries: usize) -> u64 {
+        let used = self.tick();
+        let now = Instant::now();
+        let entry = Entry { expires: now + ttl, used, value };
+        if let Some(previous) = self.map.insert(key.clone(), entry) {
+            self.recency.remove(&previous.used);
+        }
+        self.recency.insert(used, key);
+
+        self.unswept += 1;
+        if self.unswept * 2 >= self.map.len() {
+            self.sweep(now);
+        }
+        let mut evicted = 0;
+        while self.map.len() > max_entries {
+            let Some((_, key)) = self.recency.pop_first() else { break };
+            if self.map.remove(&key).is_some_and(|entry| entry.expires > now) {
+                evicted += 1;
+            }
+        }
+        evicted
+    }
+
+    /// Drop every entry expired at `now`
+    fn sweep(&mut self, now: Instant) {
+        self.map.retain(|_, entry| entry.expires > now);
+        let map = &self.map;
+        self.recency.retain(|_, key| map.contains_key(key));
+        self.unswept = 0;
+    }
+}
+
+/// Entries of a cache of any key and value type
+trait Flush: Send + Sync {
+    /// Drop every entry, returning how many there were
+    fn flush(&self) -> u64;
+}
+
+impl<K: Send, V: Send> Flush for Mutex<Entries<K, V>> {
+    fn flush(&self) -> u64 {
+        let mut entries = self.lock().unwrap_or_else(PoisonError::into_inner);
+        let held = u64::try_from(entries.map.len()).unwrap_or(u64::MAX);
+        entries.map.clear();
+        entries.recency.clear();
+        held
+    }
+}
+
+/// Caches by name, so operators can empty them instead of waiting out their TTLs. Holds them weakly: a dropped
+/// cache leaves the registry. Several caches may share a name, and are flushed together.
+#[derive(Default)]
+pub struct CacheRegistry {
+    caches: Mutex<BTreeMap<String, Vec<Weak<dyn Flush>>>>,
+}
+
+impl CacheRegistry {
+    fn register(&self, name: &str, entries: Weak<dyn Flush>) {
+        let mut caches = self.caches.lock().unwrap_or_else(PoisonError::into_inner);
+        let named = caches.entry(name.to_string()).or_default();
+        named.retain(|cache| cache.strong_count() > 0);
+        named.push(entries);
+    }
+
+    /// Names of the live caches
+    pub fn names(&self) -> Vec<String> {
+        let caches = self.caches.lock().unwrap_or_else(PoisonError::into_inner);
+        let live = caches.iter().filter(|(_, named)| named.iter().any(|cache| cache.strong_count() > 0));
+        live.map(|(name, _)| name.clone()).collect()
+    }
+
+    /// Empty every cache, returning how many entries were dropped
+    pub fn flush_all(&self) -> u64 {
+        let caches = self.caches.lock().unwrap_or_else(PoisonError::into_inner);
+        caches.values().flatten().filter_map(Weak::upgrade).map(|cache| cache.flush()).sum()
+    }
+
+    /// Empty the caches named `name`, returning how many entries were dropped; `None` when there are none
+    pub fn flush(&self, name: &str) -> Option<u64> {
+        let caches = self.caches.lock().unwrap_or_else(PoisonError::into_inner);
+        let live: Vec<_> = caches.get(name)?.iter().filter_map(Weak::upgrade).collect();
+        if live.is_empty() {
+            return None;
+        }
+        Some(live.iter().map(|cache| cache.flush()).sum())
+    }
+}
+
+/// Short-lived response cache shared by route handlers: entries live for a TTL, at most `max_entries` are held
+/// and the least recently used go first. Clones share the entries.
+#[derive(Debug)]
+pub struct TtlCache<K, V> {
+    ttl: Duration,
+    max_entries: usize,
+    entries: Arc<Mutex<Entries<K, V>>>,
+    counters: Arc<CacheCounters>,
+}
+
+impl<K, V> Clone for TtlCache<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            ttl: self.ttl,
+            max_entries: self.max_entries,
+            entries: self.entries.clone(),
+            counters: self.counters.clone(),
+        }
+    }
+}
+
+impl<K, V> TtlCache<K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+{
+    pub fn new(ttl: Duration) -> Self {
+        Self { ttl, max_entries: DEFAULT_MAX_ENTRIES, entries: Arc::default(), counters: Arc::default() }
+    }
+
+    /// Hold at most `max_entries`, at least one
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries.max(1);
+        self
+    }
+
+    /// Report hits, misses and evictions on `/metrics` as `name`, and register the cache in [`CACHES`]
+    pub fn named(self, name: &str) -> Self
+    where
+        K: Send + 'static,
+        V: Send + 'static,
+    {
+        let entries: Arc<dyn Flush> = self.entries.clone();
+        CACHES.register(name, Arc::downgrade(&entries));
+        self.counted(name)
+    }
+
+    /// [`TtlCache::named`] without registering, for entries that must outlive a flush
+    pub fn counted(mut self, name: &str) -> Self {
+        self.counters = METRICS.cache(name);
+        self
+    }
+
+    pub fn counters(&self) -> &CacheCounters {
+        &self.counters
+    }
+
+    fn count(&self, counter: fn(&CacheCounters) -> &AtomicU64, n: u64) {
+        if n > 0 {
+            counter(&self.counters).fetch_add(n, Ordering::Relaxed);
+        }
+    }
+
+    /// Get a live entry, dropping it if it has expired
+    pub fn get(&self, key: &K) -> Option<V> {
+        let value = self.entries.lock().ok()?.get(key).cloned();
+        match value {
+            Some(_) => self.count(|c| &c.hits, 1),
+            None => self.count(|c| &c.misses, 1),
+        }
+        value
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        self.insert_with_ttl(key, value, self.ttl);
+    }
+
+    /// Insert an entry living `ttl` rather than the cache's TTL
+    pub fn insert_with_ttl(&self, key: K, value: V, ttl: Duration) {
+        if let Ok(mut entries) = self.entries.lock() {
+            let evicted = entries.insert(key, value, ttl, self.max_entries);
+            self.count(|c| &c.evictions, evicted);
+        }
+    }
+
+    /// Return the live entry for `key`, atomically inserting `init()` if there is none
+    pub fn get_or_insert_with(&self, key: K, init: impl FnOnce() -> V) -> V {
+        let Ok(mut entries) = self.entries.lock() else {
+            return init()
+        };
+        if let Some(value) = entries.get(&key) {
+            self.count(|c| &c.hits, 1);
+            return value.clone();
+        }
+        self.count(|c| &c.misses, 1);
+        let value = init();
+        let evicted = entries.insert(key, value.clone(), self.ttl, self.max_entries);
+        self.count(|c| &c.evictions, evicted);
+        value
+    }
+
+    /// Return the cached value or compute, cache and return a fresh one.
+    /// Errors are not cached.
+    pub async fn get_or_try_insert_with<F, Fut, E>(&self, key: K, init: F) -> Result<V, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, E>>,
+    {
+        if let Some(value) = self.get(&key) {
+            return Ok(value)
+        }
+        let value = init().await?;
+        self.insert(key, value.clone());
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts<K: Eq + Hash + Clone, V: Clone>(cache: &TtlCache<K, V>) -> (u64, u64, u64) {
+        let counters = cache.counters();
+        (
+            counters.hits.load(Ordering::Relaxed),
+            counters.misses.load(Ordering::Relaxed),
+            counters.evictions.load(Ordering::Relaxed),
+        )
+    }
+
+    #[test]
+    fn test_entries_expire() {
+        let cache = TtlCache::new(Duration::from_millis(10));
+        cache.insert("tip", 1);
+        cache.insert_with_ttl("pinned", 2, Duration::from_secs(60));
+        assert_eq!(cache.get(&"tip"), Some(1));
+
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(cache.get(&"tip"), None);
+        assert_eq!(cache.get(&"pinned"), Some(2));
+    }
+
+    #[test]
+    fn test_least_recently_used_entries_are_evicted() {
+        let cache = TtlCache::new(Duration::from_secs(60)).with_max_entries(2);
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+        // Reading 1 leaves 2 as the least recently used
+        assert_eq!(cache.get(&1), Some("a"));
+        cache.insert(3, "c");
+        assert_eq!((cache.get(&1), cache.get(&2), cache.get(&3)), (Some("a"), None, Some("c")));
+
+        // Replacing a key makes no room and evicts nothing
+        cache.insert(3, "d");
+        assert_eq!(counts(&cache).2, 1);
+        for key in 4..10 {
+            cache.insert(key, "e");
+        }
+        assert_eq!(counts(&cache).2, 7);
+        assert_eq!((cache.get(&8), cache.get(&9)), (Some("e"), Some("e")));
+    }
+
+    #[test]
+    fn test_expired_entries_make_room_before_live_ones_are_evicted() {
+        let cache = TtlCache::new(Duration::from_secs(60)).with_max_entries(2);
+        cache.insert_with_ttl("stale", 0, Duration::ZERO);
+        cache.insert("live", 1);
+        cache.insert("new", 2);
+        assert_eq!((cache.get(&"live"), cache.get(&"new")), (Some(1), Some(2)));
+        assert_eq!(counts(&cache).2, 0);
+    }
+
+    #[test]
+    fn test_expired_entries_are_swept_without_reads() {
+        let mut entries = Entries::default();
+        for key in 0..50 {
+            entries.insert(key, (), Duration::from_secs(60), usize::MAX);
+        }
+        for key in 50..150 {
+            entries.insert(key, (), Duration::ZERO, usize::MAX);
+            assert!(entries.map.len() <= 100, "{} entries held", entries.map.len());
+            assert_eq!(entries.recency.len(), entries.map.len());
+        }
+    }
+
+    #[test]
+    fn test_hits_and_misses_are_counted() {
+        let cache = TtlCache::new(Duration::from_secs(60));
+        assert_eq!(cache.get(&"tip"), None);
+        assert_eq!(cache.get_or_insert_with("tip", || 1), 1);
+        assert_eq!(cache.get_or_insert_with("tip", || 2), 1);
+        assert_eq!(cache.get(&"tip"), Some(1));
+        assert_eq!(counts(&cache), (2, 2, 0));
+
+        // Clones share their counters
+        let clone = cache.clone();
+        clone.get(&"tip");
+        assert_eq!(counts(&cache).0, 3);
+    }
+
+    #[test]
+    fn test_flushing_empties_a_populated_cache() {
+        let cache = TtlCache::new(Duration::from_secs(60)).named("test_flushed");
+        let other = TtlCache::new(Duration::from_secs(60)).named("test_kept");
+        for key in 0..3 {
+            cache.insert(key, "value");
+        }
+        other.insert(0, "value");
+        assert!(CACHES.names().contains(&"test_flushed".to_string()));
+
+        assert_eq!(CACHES.flush("test_flushed"), Some(3));
+        assert_eq!((0..3).filter_map(|key| cache.get(&key)).count(), 0);
+        assert_eq!(other.get(&0), Some("value"));
+        assert_eq!(CACHES.flush("test_flushed"), Some(0));
+        assert_eq!(CACHES.flush("test_unknown"), None);
+
+        // A dropped cache leaves the registry
+        drop(other);
+        assert_eq!(CACHES.flush("test_kept"), None);
+    }
+
+    #[tokio::test]
+    async fn test_get_or_try_insert_with_skips_init_on_hit() {
+        let cache = TtlCache::new(Duration::from_secs(60));
+        let first: Result<_, ()> = cache.get_or_try_insert_with((), || async { Ok(1) }).await;
+        let second: Result<_, ()> = cache.get_or_try_insert_with((), || async { Ok(2) }).await;
+        assert_eq!((first, second), (Ok(1), Ok(1)));
+
+        let failed: Result<i32, &str> = TtlCache::new(Duration::from_secs(60))
+            .get_or_try_insert_with((), || async { Err("down") })
+            .await;
+        assert_eq!(failed, Err("down"));
+    }
+}
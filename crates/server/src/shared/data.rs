@@ -1,22 +1,57 @@
-use std::{error::Error as StdError, mem};
+use std::error::Error as StdError;
 
 use axum::{
     Json,
     response::{IntoResponse, Response},
 };
+use http::header::CONTENT_TYPE;
 use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
 
 use crate::error::Error;
 
-#[derive(Debug, PartialEq)]
-#[repr(u8)]
+tokio::task_local! {
+    /// Set per request by [`crate::middleware::pretty::pretty`]; indent JSON bodies when true
+    pub static PRETTY: bool;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Status {
-    Ok = 0,
+    Ok,
     Fail,
 }
 
+/// Every variant, in code order
+const VARIANTS: [Status; 2] = [Status::Ok, Status::Fail];
+
+// `code` and `last` stop compiling when a variant is added, until it is listed in `VARIANTS` too
+const _: () = {
+    let mut i = 0;
+    let mut code = 0;
+    while i < VARIANTS.len() {
+        assert!(VARIANTS[i].code() == code, "`VARIANTS` must list every Status in order");
+        i += 1;
+        code += 1;
+    }
+    assert!(Status::last().code() + 1 == code, "`VARIANTS` must end with the last Status");
+};
+
 impl Status {
-    pub const MAX: u8 = (mem::variant_count::<Self>() - 1) as u8;
+    pub const MAX: u8 = Self::last().code();
+
+    /// Number the status is sent as
+    pub const fn code(self) -> u8 {
+        match self {
+            Self::Ok => 0,
+            Self::Fail => 1,
+        }
+    }
+
+    /// The status with the highest code
+    const fn last() -> Self {
+        match Self::Ok {
+            Self::Ok | Self::Fail => Self::Fail,
+        }
+    }
 }
 
 impl Serialize for Status {
@@ -24,9 +59,7 @@ impl Serialize for Status {
     where
         S: Serializer,
     {
-        // Safe: std::mem::discriminant(_)
-        let code = unsafe { *<*const Self>::from(self).cast::<u8>() };
-        Serialize::serialize(&code, serializer)
+        Serialize::serialize(&self.code(), serializer)
     }
 }
 
@@ -36,11 +69,7 @@ impl<'de> Deserialize<'de> for Status {
         D: Deserializer<'de>,
     {
         match u8::deserialize(deserializer)? {
-            code @ 0..=Self::MAX => {
-                // Safe: Status in (0..=MAX)
-                let status = unsafe { mem::transmute(code) };
-                Ok(status)
-            },
+            code @ 0..=Self::MAX => Ok(VARIANTS[usize::from(code)]),
             rest => Err(de::Error::custom(format!("Invalid Status: {rest} > {}", Self::MAX))),
         }
     }
@@ -90,7 +119,13 @@ where
     T: Serialize,
 {
     fn into_response(self) -> Response {
-        Json(self).into_response()
+        if !PRETTY.try_with(|pretty| *pretty).unwrap_or(false) {
+            return Json(self).into_response()
+        }
+        match serde_json::to_string_pretty(&self) {
+            Ok(body) => ([(CONTENT_TYPE, "application/json")], body).into_response(),
+            Err(err) => Error::from(format!("{err}")).into_response(),
+        }
     }
 }
 
@@ -104,3 +139,40 @@ where
 }
 
 pub type Data<T, E = Error> = Result<Inner<T>, E>;
+
+#[cfg(test)]
+mod tests {
+    use axum::body::to_bytes;
+
+    use super::*;
+
+    async fn body(response: Response) -> String {
+        String::from_utf8(to_bytes(response.into_body(), usize::MAX).await.unwrap().to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_compact_by_default() {
+        let body = body(Inner::new(vec![1, 2]).into_response()).await;
+        assert_eq!(body, r#"{"status":0,"data":[1,2]}"#);
+    }
+
+    #[test]
+    fn test_max_is_the_last_variant() {
+        assert_eq!(usize::from(Status::MAX), VARIANTS.len() - 1);
+        assert_eq!(Status::MAX, Status::Fail.code());
+        for status in VARIANTS {
+            let code = serde_json::to_value(status).unwrap();
+            assert_eq!(serde_json::from_value::<Status>(code).unwrap(), status);
+        }
+        assert!(serde_json::from_value::<Status>(serde_json::json!(Status::MAX + 1)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pretty_when_requested() {
+        let response = PRETTY.scope(true, async { Inner::new(vec![1, 2]).into_response() }).await;
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+        let body = body(response).await;
+        assert!(body.contains('\n'));
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&body).unwrap()["data"][1], 2);
+    }
+}
@@ -0,0 +1,86 @@
+use std::fmt::Display;
+
+use axum::response::{IntoResponse, Response};
+use http::{
+    HeaderMap, HeaderValue, StatusCode,
+    header::{ETAG, IF_NONE_MATCH},
+};
+use serde::Serialize;
+
+use crate::shared::data::Inner;
+
+/// Weak entity tag of `version`: the body also varies with `?pretty` and compression
+pub fn etag(version: impl Display) -> String {
+    format!("W/\"{version}\"")
+}
+
+/// `304 Not Modified` with an empty body when `If-None-Match` already names `etag`,
+/// otherwise `data` carrying `etag` for the next poll
+pub fn conditional<T: Serialize>(headers: &HeaderMap, etag: &str, data: Inner<T>) -> Response {
+    let tag = HeaderValue::from_str(etag).ok();
+    let mut response =
+        if is_current(headers, etag) { StatusCode::NOT_MODIFIED.into_response() } else { data.into_response() };
+    if let Some(tag) = tag {
+        response.headers_mut().insert(ETAG, tag);
+    }
+    response
+}
+
+/// [`conditional`] for `HEAD` requests: the same status and `ETag`, with no body to build
+pub fn conditional_head(headers: &HeaderMap, etag: &str) -> Response {
+    let status = if is_current(headers, etag) { StatusCode::NOT_MODIFIED } else { StatusCode::OK };
+    let mut response = status.into_response();
+    if let Ok(tag) = HeaderValue::from_str(etag) {
+        response.headers_mut().insert(ETAG, tag);
+    }
+    response
+}
+
+/// Weak comparison, as `If-None-Match` requires
+fn is_current(headers: &HeaderMap, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = opaque(etag);
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|tag| tag.trim() == "*" || opaque(tag) == etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::to_bytes;
+
+    use super::*;
+
+    fn if_none_match(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(IF_NONE_MATCH, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_matching_tag_is_304_without_body() {
+        let tag = etag("ab".repeat(32));
+        let response = conditional(&if_none_match(&tag), &tag, Inner::new(1));
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[ETAG], tag.as_str());
+        assert!(to_bytes(response.into_body(), usize::MAX).await.unwrap().is_empty());
+
+        // Also inside a list and in its strong form
+        let listed = format!("\"other\", \"{}\"", "ab".repeat(32));
+        assert_eq!(conditional(&if_none_match(&listed), &tag, Inner::new(1)).status(), StatusCode::NOT_MODIFIED);
+    }
+
+    #[tokio::test]
+    async fn test_stale_or_missing_tag_is_200() {
+        let tag = etag(42);
+        let response = conditional(&if_none_match(&etag(41)), &tag, Inner::new(1));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[ETAG], "W/\"42\"");
+        assert_eq!(to_bytes(response.into_body(), usize::MAX).await.unwrap(), r#"{"status":0,"data":1}"#);
+
+        assert_eq!(conditional(&HeaderMap::new(), &tag, Inner::new(1)).status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,30 @@
+use tondi_listener_db::schema::tyext::hash::{FromHexString, Hash256};
+
+use crate::error::{Error, Result};
+
+/// A block hash or transaction id from a request, `what` naming it in errors. Anything but 64 hex
+/// characters is a `400` before a query or node call is spent on it.
+pub fn parse_hash256(hex: &str, what: &str) -> Result<Hash256> {
+    <Hash256 as FromHexString<&str>>::from_hex(hex)
+        .map_err(|e| Error::BadRequest(format!("Invalid {what} `{hex}`: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+
+    #[test]
+    fn test_only_32_byte_hashes_parse() {
+        let short = "ab".repeat(10);
+        let err = parse_hash256(&short, "block hash").unwrap_err();
+        assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+        assert!(err.to_string().contains("Expected 64 hex characters (32 bytes), got 20"), "{err}");
+
+        for input in ["", "abc", &"zz".repeat(32), &"ab".repeat(33)] {
+            assert_eq!(parse_hash256(input, "block hash").unwrap_err().status_code(), StatusCode::BAD_REQUEST);
+        }
+        assert_eq!(parse_hash256(&"0f".repeat(32), "block hash").unwrap(), [0x0f; 32]);
+    }
+}
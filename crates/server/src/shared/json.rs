@@ -0,0 +1,83 @@
+use axum::extract::{FromRequest, Request, rejection::JsonRejection};
+use http::StatusCode;
+
+use crate::error::Error;
+
+/// `axum::Json` whose rejections are [`Error`]s, so bad bodies get the usual error envelope
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json<T>(pub T);
+
+impl<T, S> FromRequest<S> for Json<T>
+where
+    axum::Json<T>: FromRequest<S, Rejection = JsonRejection>,
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::Json(value) = axum::Json::<T>::from_request(request, state).await?;
+        Ok(Self(value))
+    }
+}
+
+impl From<JsonRejection> for Error {
+    fn from(rejection: JsonRejection) -> Self {
+        match rejection {
+            JsonRejection::MissingJsonContentType(rejection) => Self::UnsupportedMediaType(rejection.body_text()),
+            // Bodies cut off by `DefaultBodyLimit` keep their 413
+            rejection if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+                Self::PayloadTooLarge(rejection.body_text())
+            },
+            rejection => Self::JsonParse(rejection.body_text()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        Router,
+        body::{Body, to_bytes},
+        response::Response,
+        routing::post,
+    };
+    use http::header::CONTENT_TYPE;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::routes::grpc::grpc_call::GrpcCall;
+
+    fn router() -> Router {
+        Router::new().route("/grpc", post(|Json(_): Json<GrpcCall>| async { "called" }))
+    }
+
+    async fn post_json(body: &'static str) -> Response {
+        let request = Request::post("/grpc").header(CONTENT_TYPE, "application/json").body(Body::from(body)).unwrap();
+        router().oneshot(request).await.unwrap()
+    }
+
+    async fn assert_json_parse_error(response: Response) {
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value =
+            serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!(body["error"]["code"], "JSON_PARSE_ERROR");
+        assert_eq!(body["error"]["status"], 400);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_json_to_grpc_is_400_envelope() {
+        assert_json_parse_error(post_json("{\"GetBlockCount\":").await).await;
+    }
+
+    #[tokio::test]
+    async fn test_unknown_call_is_400_envelope() {
+        assert_json_parse_error(post_json("{\"NoSuchCall\":{}}").await).await;
+    }
+
+    #[test]
+    fn test_serde_json_error_is_400() {
+        let err = Error::from(serde_json::from_str::<u64>("nope").unwrap_err());
+        assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(err.error_code(), "JSON_PARSE_ERROR");
+    }
+}
@@ -0,0 +1,71 @@
+tokio::task_local! {
+    /// Set per request by [`crate::middleware::language::language`]
+    pub static LANGUAGE: Language;
+}
+
+/// Language of user-facing error messages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Language {
+    #[default]
+    English,
+    Chinese,
+}
+
+impl Language {
+    /// Language of the request being served, English outside of one
+    pub fn current() -> Self {
+        LANGUAGE.try_with(|language| *language).unwrap_or_default()
+    }
+
+    /// Best supported match of an `Accept-Language` header, English when nothing matches
+    pub fn from_accept_language(header: &str) -> Self {
+        let mut best: Option<(Self, f32)> = None;
+        for range in header.split(',') {
+            let mut parts = range.split(';').map(str::trim);
+            let tag = parts.next().unwrap_or_default().to_ascii_lowercase();
+            let quality = parts
+                .find_map(|param| param.strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.parse::<f32>().ok())
+                .unwrap_or(0.0);
+            let language = match tag.split('-').next() {
+                Some("zh") => Self::Chinese,
+                Some("en") => Self::English,
+                _ => continue,
+            };
+            if quality > 0.0 && best.is_none_or(|(_, best)| quality > best) {
+                best = Some((language, quality));
+            }
+        }
+        best.map(|(language, _)| language).unwrap_or_default()
+    }
+
+    /// Joins a message's lead-in and its detail
+    pub fn separator(&self) -> &'static str {
+        match self {
+            Self::English => ": ",
+            Self::Chinese => "：",
+        }
+    }
+
+    /// Position of the language's text in per-language arrays
+    pub const fn index(self) -> usize {
+        match self {
+            Self::English => 0,
+            Self::Chinese => 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_language_negotiation() {
+        assert_eq!(Language::from_accept_language("zh-CN,zh;q=0.9,en;q=0.8"), Language::Chinese);
+        assert_eq!(Language::from_accept_language("en-US,en;q=0.9,zh;q=0.8"), Language::English);
+        assert_eq!(Language::from_accept_language("fr-FR, zh-TW;q=0.5"), Language::Chinese);
+        assert_eq!(Language::from_accept_language("zh;q=0, de"), Language::English);
+        assert_eq!(Language::from_accept_language(""), Language::English);
+    }
+}
@@ -0,0 +1,105 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{
+        LazyLock, Mutex, PoisonError,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use tondi_listener_library::log::{info, trace};
+
+use crate::ctx::event_config::EventType;
+
+/// Sampler of the upstream notification loops, configured from `logging` at startup
+pub static NOTIFICATION_LOG: LazyLock<LogSampler> = LazyLock::new(|| LogSampler::new(1000));
+
+/// Logs every Nth of a stream of messages at `info` and the rest at `trace`, counting them
+/// per event type for periodic summaries
+#[derive(Debug)]
+pub struct LogSampler {
+    /// `0` logs nothing at `info`
+    every: AtomicU64,
+    seen: AtomicU64,
+    /// Messages since the last summary
+    window: Mutex<HashMap<EventType, u64>>,
+}
+
+impl LogSampler {
+    pub fn new(every: u64) -> Self {
+        Self { every: AtomicU64::new(every), seen: AtomicU64::new(0), window: Mutex::default() }
+    }
+
+    pub fn set_sample_rate(&self, every: u64) {
+        self.every.store(every, Ordering::Relaxed);
+    }
+
+    /// Whether this message is the Nth since the last sampled one
+    pub fn sample(&self) -> bool {
+        let every = self.every.load(Ordering::Relaxed);
+        let seen = self.seen.fetch_add(1, Ordering::Relaxed) + 1;
+        every > 0 && seen % every == 0
+    }
+
+    /// Count and log one notification of `ev`
+    pub fn record(&self, ev: EventType, notification: &impl Debug) {
+        *self.window.lock().unwrap_or_else(PoisonError::into_inner).entry(ev).or_default() += 1;
+        if self.sample() {
+            info!("Received {ev} notification (1 in {}): {notification:?}", self.every.load(Ordering::Relaxed));
+        } else {
+            trace!("Received {ev} notification: {notification:?}");
+        }
+    }
+
+    /// Counts since the previous call, by event name
+    pub fn take_counts(&self) -> Vec<(String, u64)> {
+        let window = std::mem::take(&mut *self.window.lock().unwrap_or_else(PoisonError::into_inner));
+        let mut counts: Vec<_> = window.into_iter().map(|(ev, count)| (ev.to_string(), count)).collect();
+        counts.sort();
+        counts
+    }
+
+    /// Log at `info` how many notifications of each type arrived every `interval`
+    pub fn spawn_summaries(&'static self, interval: Duration) {
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            // The first tick completes immediately
+            ticks.tick().await;
+            loop {
+                ticks.tick().await;
+                for (ev, count) in self.take_counts() {
+                    info!("Processed {count} {ev} events in the last {}s", interval.as_secs());
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_nth_call_is_sampled() {
+        let sampler = LogSampler::new(3);
+        let sampled: Vec<_> = (1..=9).filter(|_| sampler.sample()).collect();
+        assert_eq!(sampled, [3, 6, 9]);
+
+        sampler.set_sample_rate(0);
+        assert!((0..10).all(|_| !sampler.sample()));
+        sampler.set_sample_rate(1);
+        assert!((0..10).all(|_| sampler.sample()));
+    }
+
+    #[test]
+    fn test_counts_are_per_event_and_reset() {
+        let sampler = LogSampler::new(0);
+        for _ in 0..3 {
+            sampler.record(EventType::BlockAdded, &"block");
+        }
+        sampler.record(EventType::UtxosChanged, &"utxos");
+        assert_eq!(sampler.take_counts(), [("block-added".to_string(), 3), ("utxos-changed".to_string(), 1)]);
+        assert!(sampler.take_counts().is_empty());
+    }
+}
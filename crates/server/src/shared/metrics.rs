@@ -0,0 +1,388 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        Arc, LazyLock, OnceLock, RwLock,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use tokio::sync::Semaphore;
+
+use crate::ctx::{event_config::EventType, pg_database::PgPool};
+
+/// Process-wide registry rendered by `/metrics`
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::default);
+
+/// Upper bounds in seconds of the queue time buckets, from half a millisecond to a second
+const QUEUE_BUCKETS: [f64; 11] = [0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0];
+
+/// Request count and body bytes of one route
+#[derive(Debug, Default)]
+pub struct RouteTraffic {
+    pub requests: AtomicU64,
+    pub bytes_in: AtomicU64,
+    pub bytes_out: AtomicU64,
+}
+
+/// What happened to the notifications of one event type
+#[derive(Debug, Default)]
+pub struct EventCounters {
+    /// Taken from the upstream node
+    pub received: AtomicU64,
+    /// Queued for a WebSocket subscriber, once per subscriber
+    pub forwarded: AtomicU64,
+    /// Not queued for a subscriber whose outbox was full
+    pub dropped: AtomicU64,
+    /// Not dispatched for repeating the previous event of the type
+    pub deduplicated: AtomicU64,
+}
+
+/// Lookups and evictions of one [`TtlCache`](crate::shared::cache::TtlCache)
+#[derive(Debug, Default)]
+pub struct CacheCounters {
+    pub hits: AtomicU64,
+    /// Lookups finding no live entry, expired ones included
+    pub misses: AtomicU64,
+    /// Live entries dropped to stay within the entry bound
+    pub evictions: AtomicU64,
+}
+
+/// Cumulative histogram of durations over [`QUEUE_BUCKETS`]
+#[derive(Debug, Default)]
+pub struct Histogram {
+    /// Observations at most each bound, the last one counting every observation
+    buckets: [AtomicU64; QUEUE_BUCKETS.len() + 1],
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    pub fn observe(&self, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        let first = QUEUE_BUCKETS.partition_point(|&bound| bound < seconds);
+        for bucket in &self.buckets[first..] {
+            bucket.fetch_add(1, Ordering::Relaxed);
+        }
+        let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        self.sum_micros.fetch_add(micros, Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.buckets[QUEUE_BUCKETS.len()].load(Ordering::Relaxed)
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} histogram");
+        let bounds = QUEUE_BUCKETS.iter().map(ToString::to_string).chain(["+Inf".to_string()]);
+        for (bound, bucket) in bounds.zip(&self.buckets) {
+            let _ = writeln!(out, "{name}_bucket{{le=\"{bound}\"}} {}", bucket.load(Ordering::Relaxed));
+        }
+        let sum = Duration::from_micros(self.sum_micros.load(Ordering::Relaxed)).as_secs_f64();
+        let _ = writeln!(out, "{name}_sum {sum}");
+        let _ = writeln!(out, "{name}_count {}", self.count());
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    routes: RwLock<BTreeMap<String, Arc<RouteTraffic>>>,
+    /// Notification pipeline counters by event name
+    events: RwLock<BTreeMap<String, Arc<EventCounters>>>,
+    /// Response cache counters by cache name
+    caches: RwLock<BTreeMap<String, Arc<CacheCounters>>>,
+    /// Currently open WebSocket connections
+    pub websocket_connections: Arc<AtomicUsize>,
+    /// Requests received and not answered yet
+    pub requests_in_flight: AtomicUsize,
+    /// Time from a request's arrival until its handler starts
+    pub request_queue_seconds: Histogram,
+    /// Events waiting per priority (high, medium, low) under the priority strategy
+    pub event_queue_depths: Arc<[AtomicUsize; 3]>,
+    /// Events waiting in the spill file
+    pub events_spilled: AtomicUsize,
+    /// Events dropped because the spill file was full or unreadable
+    pub events_spill_dropped: AtomicU64,
+    /// Permits bounding concurrent upstream calls; not rendered while unset (unlimited)
+    pub upstream_permits: OnceLock<Arc<Semaphore>>,
+    /// Database pools by name
+    db_pools: RwLock<BTreeMap<&'static str, PgPool>>,
+}
+
+impl Metrics {
+    /// Counters of `route`, created on first use
+    pub fn route(&self, route: &str) -> Arc<RouteTraffic> {
+        if let Some(traffic) = self.routes.read().ok().and_then(|routes| routes.get(route).cloned()) {
+            return traffic;
+        }
+        match self.routes.write() {
+            Ok(mut routes) => routes.entry(route.to_string()).or_default().clone(),
+            Err(_) => Arc::default(),
+        }
+    }
+
+    /// Counters of the `ev` notifications, created on first use
+    pub fn event(&self, ev: EventType) -> Arc<EventCounters> {
+        let name = ev.to_string();
+        if let Some(counters) = self.events.read().ok().and_then(|events| events.get(&name).cloned()) {
+            return counters;
+        }
+        match self.events.write() {
+            Ok(mut events) => events.entry(name).or_default().clone(),
+            Err(_) => Arc::default(),
+        }
+    }
+
+    /// Counters of the cache called `name`, created on first use
+    pub fn cache(&self, name: &str) -> Arc<CacheCounters> {
+        if let Some(counters) = self.caches.read().ok().and_then(|caches| caches.get(name).cloned()) {
+            return counters;
+        }
+        match self.caches.write() {
+            Ok(mut caches) => caches.entry(name.to_string()).or_default().clone(),
+            Err(_) => Arc::default(),
+        }
+    }
+
+    /// Report the connections of `pool` as `name`, replacing an earlier pool of that name
+    pub fn register_db_pool(&self, name: &'static str, pool: PgPool) {
+        if let Ok(mut pools) = self.db_pools.write() {
+            pools.insert(name, pool);
+        }
+    }
+
+    /// Prometheus text exposition of every counter
+    pub fn render(&self) -> String {
+        let routes = match self.routes.read() {
+            Ok(routes) => routes.clone(),
+            Err(_) => BTreeMap::new(),
+        };
+        let counters: [(&str, &str, fn(&RouteTraffic) -> &AtomicU64); 3] = [
+            ("tondi_listener_http_requests_total", "Requests served per route", |t| &t.requests),
+            ("tondi_listener_http_request_bytes_total", "Request body bytes received per route", |t| {
+                &t.bytes_in
+            }),
+            ("tondi_listener_http_response_bytes_total", "Response body bytes sent per route", |t| {
+                &t.bytes_out
+            }),
+        ];
+
+        let mut out = String::new();
+        for (name, help, counter) in counters {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} counter");
+            for (route, traffic) in &routes {
+                let value = counter(traffic).load(Ordering::Relaxed);
+                let _ = writeln!(out, "{name}{{route=\"{route}\"}} {value}");
+            }
+        }
+
+        let events = match self.events.read() {
+            Ok(events) => events.clone(),
+            Err(_) => BTreeMap::new(),
+        };
+        let counters: [(&str, &str, fn(&EventCounters) -> &AtomicU64); 4] = [
+            ("tondi_listener_events_received_total", "Notifications received from upstream per event", |c| {
+                &c.received
+            }),
+            ("tondi_listener_events_forwarded_total", "Notifications queued for subscribers per event", |c| {
+                &c.forwarded
+            }),
+            ("tondi_listener_events_dropped_total", "Notifications dropped for lagging subscribers per event", |c| {
+                &c.dropped
+            }),
+            ("tondi_listener_events_deduplicated_total", "Repeated notifications not dispatched per event", |c| {
+                &c.deduplicated
+            }),
+        ];
+        for (name, help, counter) in counters {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} counter");
+            for (event, counters) in &events {
+                let _ = writeln!(out, "{name}{{event=\"{event}\"}} {}", counter(counters).load(Ordering::Relaxed));
+            }
+        }
+
+        let caches = match self.caches.read() {
+            Ok(caches) => caches.clone(),
+            Err(_) => BTreeMap::new(),
+        };
+        let counters: [(&str, &str, fn(&CacheCounters) -> &AtomicU64); 3] = [
+            ("tondi_listener_cache_hits_total", "Lookups answered from each cache", |c| &c.hits),
+            ("tondi_listener_cache_misses_total", "Lookups each cache had no live entry for", |c| &c.misses),
+            ("tondi_listener_cache_evictions_total", "Live entries evicted to bound each cache", |c| &c.evictions),
+        ];
+        for (name, help, counter) in counters {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} counter");
+            for (cache, counters) in &caches {
+                let _ = writeln!(out, "{name}{{cache=\"{cache}\"}} {}", counter(counters).load(Ordering::Relaxed));
+            }
+        }
+
+        let name = "tondi_listener_websocket_connections";
+        let _ = writeln!(out, "# HELP {name} Currently open WebSocket connections");
+        let _ = writeln!(out, "# TYPE {name} gauge");
+        let _ = writeln!(out, "{name} {}", self.websocket_connections.load(Ordering::Relaxed));
+
+        let name = "tondi_listener_http_requests_in_flight";
+        let _ = writeln!(out, "# HELP {name} Requests received and not answered yet");
+        let _ = writeln!(out, "# TYPE {name} gauge");
+        let _ = writeln!(out, "{name} {}", self.requests_in_flight.load(Ordering::Relaxed));
+        self.request_queue_seconds.render(
+            &mut out,
+            "tondi_listener_http_request_queue_seconds",
+            "Time requests wait between arrival and their handler starting",
+        );
+
+        let name = "tondi_listener_event_queue_depth";
+        let _ = writeln!(out, "# HELP {name} Events waiting for delivery per priority");
+        let _ = writeln!(out, "# TYPE {name} gauge");
+        for (priority, depth) in ["high", "medium", "low"].iter().zip(self.event_queue_depths.iter()) {
+            let _ = writeln!(out, "{name}{{priority=\"{priority}\"}} {}", depth.load(Ordering::Relaxed));
+        }
+
+        let name = "tondi_listener_events_spilled";
+        let _ = writeln!(out, "# HELP {name} Events waiting for delivery in the spill file");
+        let _ = writeln!(out, "# TYPE {name} gauge");
+        let _ = writeln!(out, "{name} {}", self.events_spilled.load(Ordering::Relaxed));
+
+        let name = "tondi_listener_events_spill_dropped_total";
+        let _ = writeln!(out, "# HELP {name} Events dropped because the spill file was full or unreadable");
+        let _ = writeln!(out, "# TYPE {name} counter");
+        let _ = writeln!(out, "{name} {}", self.events_spill_dropped.load(Ordering::Relaxed));
+
+        if let Some(permits) = self.upstream_permits.get() {
+            let name = "tondi_listener_upstream_permits_available";
+            let _ = writeln!(out, "# HELP {name} Upstream calls that may still start without waiting");
+            let _ = writeln!(out, "# TYPE {name} gauge");
+            let _ = writeln!(out, "{name} {}", permits.available_permits());
+        }
+
+        let pools = match self.db_pools.read() {
+            Ok(pools) => pools.clone(),
+            Err(_) => BTreeMap::new(),
+        };
+        if !pools.is_empty() {
+            let gauges: [(&str, &str, fn(&PgPool) -> u32); 3] = [
+                ("tondi_listener_db_pool_connections", "Open connections per database pool", |p| {
+                    p.state().connections
+                }),
+                ("tondi_listener_db_pool_idle_connections", "Idle connections per database pool", |p| {
+                    p.state().idle_connections
+                }),
+                ("tondi_listener_db_pool_max_connections", "Connection limit per database pool", |p| p.max_size()),
+            ];
+            for (name, help, gauge) in gauges {
+                let _ = writeln!(out, "# HELP {name} {help}");
+                let _ = writeln!(out, "# TYPE {name} gauge");
+                for (pool_name, pool) in &pools {
+                    let _ = writeln!(out, "{name}{{pool=\"{pool_name}\"}} {}", gauge(pool));
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tondi_listener_db::diesel::r2d2::{ConnectionManager, Pool};
+
+    use super::*;
+
+    #[test]
+    fn test_route_counters_are_shared_and_rendered() {
+        let metrics = Metrics::default();
+        metrics.route("/chain/last").bytes_out.fetch_add(42, Ordering::Relaxed);
+        metrics.route("/chain/last").requests.fetch_add(1, Ordering::Relaxed);
+
+        let rendered = metrics.render();
+        assert!(rendered.contains("tondi_listener_http_requests_total{route=\"/chain/last\"} 1"));
+        assert!(rendered.contains("tondi_listener_http_response_bytes_total{route=\"/chain/last\"} 42"));
+        assert!(rendered.contains("# TYPE tondi_listener_http_request_bytes_total counter"));
+    }
+
+    #[test]
+    fn test_event_counters_are_rendered_per_event() {
+        let metrics = Metrics::default();
+        metrics.event(EventType::BlockAdded).received.fetch_add(2, Ordering::Relaxed);
+        metrics.event(EventType::BlockAdded).dropped.fetch_add(1, Ordering::Relaxed);
+
+        let rendered = metrics.render();
+        assert!(rendered.contains("tondi_listener_events_received_total{event=\"block-added\"} 2\n"));
+        assert!(rendered.contains("tondi_listener_events_dropped_total{event=\"block-added\"} 1\n"));
+        assert!(rendered.contains("tondi_listener_events_deduplicated_total{event=\"block-added\"} 0\n"));
+    }
+
+    #[test]
+    fn test_cache_counters_are_rendered_per_cache() {
+        let metrics = Metrics::default();
+        metrics.cache("headers").hits.fetch_add(4, Ordering::Relaxed);
+        metrics.cache("headers").evictions.fetch_add(1, Ordering::Relaxed);
+
+        let rendered = metrics.render();
+        assert!(rendered.contains("tondi_listener_cache_hits_total{cache=\"headers\"} 4\n"));
+        assert!(rendered.contains("tondi_listener_cache_misses_total{cache=\"headers\"} 0\n"));
+        assert!(rendered.contains("tondi_listener_cache_evictions_total{cache=\"headers\"} 1\n"));
+    }
+
+    #[test]
+    fn test_websocket_gauge_is_rendered() {
+        let metrics = Metrics::default();
+        metrics.websocket_connections.store(3, Ordering::Relaxed);
+        assert!(metrics.render().contains("tondi_listener_websocket_connections 3\n"));
+    }
+
+    #[test]
+    fn test_queue_time_is_rendered_as_a_cumulative_histogram() {
+        let metrics = Metrics::default();
+        metrics.request_queue_seconds.observe(Duration::from_micros(300));
+        metrics.request_queue_seconds.observe(Duration::from_millis(30));
+        metrics.request_queue_seconds.observe(Duration::from_secs(3));
+
+        let rendered = metrics.render();
+        let name = "tondi_listener_http_request_queue_seconds";
+        assert!(rendered.contains(&format!("# TYPE {name} histogram\n")));
+        assert!(rendered.contains(&format!("{name}_bucket{{le=\"0.0005\"}} 1\n")));
+        assert!(rendered.contains(&format!("{name}_bucket{{le=\"0.025\"}} 1\n")));
+        assert!(rendered.contains(&format!("{name}_bucket{{le=\"0.05\"}} 2\n")));
+        assert!(rendered.contains(&format!("{name}_bucket{{le=\"1\"}} 2\n")));
+        assert!(rendered.contains(&format!("{name}_bucket{{le=\"+Inf\"}} 3\n")));
+        assert!(rendered.contains(&format!("{name}_sum 3.0303\n")));
+        assert!(rendered.contains(&format!("{name}_count 3\n")));
+    }
+
+    #[test]
+    fn test_event_queue_depths_are_rendered() {
+        let metrics = Metrics::default();
+        metrics.event_queue_depths[2].store(5, Ordering::Relaxed);
+        let rendered = metrics.render();
+        assert!(rendered.contains("tondi_listener_event_queue_depth{priority=\"high\"} 0\n"));
+        assert!(rendered.contains("tondi_listener_event_queue_depth{priority=\"low\"} 5\n"));
+    }
+
+    #[test]
+    fn test_upstream_permits_are_rendered_once_limited() {
+        let metrics = Metrics::default();
+        assert!(!metrics.render().contains("tondi_listener_upstream_permits_available"));
+
+        let permits = Arc::new(Semaphore::new(4));
+        let _held = permits.try_acquire().unwrap();
+        metrics.upstream_permits.set(permits.clone()).unwrap();
+        assert!(metrics.render().contains("tondi_listener_upstream_permits_available 3\n"));
+    }
+
+    #[test]
+    fn test_db_pools_are_rendered_by_name() {
+        let metrics = Metrics::default();
+        assert!(!metrics.render().contains("tondi_listener_db_pool_connections"));
+
+        let manager = ConnectionManager::new("postgres://127.0.0.1:1/none");
+        metrics.register_db_pool("events", Pool::builder().max_size(4).build_unchecked(manager));
+        let rendered = metrics.render();
+        assert!(rendered.contains("tondi_listener_db_pool_connections{pool=\"events\"} 0\n"));
+        assert!(rendered.contains("tondi_listener_db_pool_max_connections{pool=\"events\"} 4\n"));
+    }
+}
@@ -1,2 +1,12 @@
+pub mod address;
+pub mod cache;
 pub mod data;
+pub mod etag;
+pub mod hash;
+pub mod json;
+pub mod language;
+pub mod log_sampler;
+pub mod metrics;
 pub mod pool;
+pub mod runtime;
+pub mod shutdown;
@@ -1,9 +1,44 @@
-use std::fmt::Debug as StdDebug;
+use std::{
+    fmt::Debug as StdDebug,
+    ops::Deref,
+    str::FromStr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant, SystemTime},
+};
 
-use tokio::sync::{RwLock, RwLockReadGuard, TryLockError};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tokio::sync::{
+    RwLock, RwLockReadGuard, Semaphore, SemaphorePermit, TryLockError,
+    broadcast::{self, Sender, error::RecvError},
+};
+use tondi_rpc_core::{
+    BlockAddedNotification, FinalityConflictNotification, FinalityConflictResolvedNotification,
+    NewBlockTemplateNotification, Notification as RpcNotification, PruningPointUtxoSetOverrideNotification,
+    SinkBlueScoreChangedNotification, UtxosChangedNotification, VirtualChainChangedNotification,
+    VirtualDaaScoreChangedNotification,
+};
+
+use tondi_listener_library::log::warn;
+
+use crate::ctx::event_config::EventType;
+
+/// Events a notification receiver may fall behind by before it skips ahead
+static NOTIFICATION_CAPACITY: AtomicUsize = AtomicUsize::new(1000);
+
+/// Capacity of the notification channels created from now on
+pub fn set_notification_capacity(capacity: usize) {
+    NOTIFICATION_CAPACITY.store(capacity.max(1), Ordering::Relaxed);
+}
 
 pub trait HealthCheck {
     fn is_live(&self) -> bool;
+
+    /// Round trip to the other end; unlike [`HealthCheck::is_live`] this notices a stale connection
+    fn probe(&self) -> impl Future<Output = bool>;
 }
 
 pub trait Metadata: Sized {
@@ -22,6 +57,51 @@ where
     meta: T::Meta,
     // TODO: Multi
     pool: RwLock<T>,
+    last_successful_rpc: Mutex<Option<Instant>>,
+    warmed_up: AtomicBool,
+    permits: Option<Permits>,
+    /// Run with every client that replaces the current one, see [`Pool::on_refresh`]
+    on_refresh: RefreshHooks<T>,
+}
+
+type RefreshHook<T> = Box<dyn Fn(&T) + Send + Sync>;
+
+struct RefreshHooks<T>(Mutex<Vec<RefreshHook<T>>>);
+
+impl<T> Default for RefreshHooks<T> {
+    fn default() -> Self {
+        Self(Mutex::new(Vec::new()))
+    }
+}
+
+impl<T> StdDebug for RefreshHooks<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let count = self.0.lock().map_or(0, |hooks| hooks.len());
+        f.debug_tuple("RefreshHooks").field(&count).finish()
+    }
+}
+
+/// Bound on concurrent users of the client, see [`Pool::with_concurrency_limit`]
+#[derive(Debug)]
+struct Permits {
+    semaphore: Arc<Semaphore>,
+    limit: usize,
+    wait: Duration,
+}
+
+/// The client, usable while this is held; holds one permit of a concurrency-limited pool
+#[derive(Debug)]
+pub struct PoolGuard<'a, T> {
+    elm: RwLockReadGuard<'a, T>,
+    _permit: Option<SemaphorePermit<'a>>,
+}
+
+impl<T> Deref for PoolGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.elm
+    }
 }
 
 impl<T> Pool<T>
@@ -32,24 +112,296 @@ where
     Error: From<T::Error>,
 {
     pub fn new(meta: T::Meta, init: T) -> Self {
-        Self { meta, pool: RwLock::new(init) }
+        Self {
+            meta,
+            pool: RwLock::new(init),
+            last_successful_rpc: Mutex::new(None),
+            warmed_up: AtomicBool::new(false),
+            permits: None,
+            on_refresh: RefreshHooks::default(),
+        }
     }
 
-    pub async fn get(&self) -> Result<RwLockReadGuard<'_, T>, Error> {
-        let Self { meta, pool } = self;
+    /// Let at most `limit` callers hold the client at once. A caller still without a
+    /// permit after `wait` fails with [`Error::Saturated`] rather than queuing on.
+    pub fn with_concurrency_limit(mut self, limit: usize, wait: Duration) -> Self {
+        self.permits = Some(Permits { semaphore: Arc::new(Semaphore::new(limit)), limit, wait });
+        self
+    }
+
+    /// Semaphore behind [`Pool::with_concurrency_limit`], `None` when unlimited
+    pub fn permits(&self) -> Option<Arc<Semaphore>> {
+        self.permits.as_ref().map(|permits| permits.semaphore.clone())
+    }
+
+    pub async fn get(&self) -> Result<PoolGuard<'_, T>, Error> {
+        let Self { pool, .. } = self;
+        let permit = self.acquire().await?;
         // Read
         {
             let elm = pool.try_read()?;
             if elm.is_live() {
-                return Ok(elm)
+                return Ok(PoolGuard { elm, _permit: permit })
             }
         }
-        // Refresh
-        {
-            let mut elm = pool.write().await;
-            *elm = T::try_from(meta).await?;
+        self.refresh().await?;
+        Ok(PoolGuard { elm: pool.try_read()?, _permit: permit })
+    }
+
+    async fn acquire(&self) -> Result<Option<SemaphorePermit<'_>>, Error> {
+        let Some(Permits { semaphore, limit, wait }) = &self.permits else {
+            return Ok(None)
+        };
+        match tokio::time::timeout(*wait, semaphore.acquire()).await {
+            Ok(Ok(permit)) => Ok(Some(permit)),
+            Ok(Err(_)) | Err(_) => Err(Error::Saturated(*limit)),
+        }
+    }
+
+    /// Run `hook` with every client that replaces the current one, so whatever was attached to the old
+    /// client (notification receivers, observers) can attach to the new one
+    pub fn on_refresh(&self, hook: impl Fn(&T) + Send + Sync + 'static) {
+        if let Ok(mut hooks) = self.on_refresh.0.lock() {
+            hooks.push(Box::new(hook));
+        }
+    }
+
+    /// Replace the current client with a freshly connected one
+    async fn refresh(&self) -> Result<(), Error> {
+        let new = T::try_from(&self.meta).await?;
+        let mut elm = self.pool.write().await;
+        let old = std::mem::replace(&mut *elm, new);
+        let elm = elm.downgrade();
+        if let Ok(hooks) = self.on_refresh.0.lock() {
+            for hook in hooks.iter() {
+                hook(&elm);
+            }
+        }
+        drop(elm);
+        // Only now close what hangs off the old client, so its users find the new one unlocked
+        drop(old);
+        self.record_success();
+        Ok(())
+    }
+
+    /// Connect if needed and make one round trip, so the first request finds a working connection
+    pub async fn warm_up(&self) -> Result<(), Error> {
+        if !self.get().await?.probe().await {
+            return Err(Error::from("Upstream did not answer the warm-up probe".to_string()))
+        }
+        self.record_success();
+        self.warmed_up.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Whether [`Pool::warm_up`] has succeeded
+    pub fn is_warmed_up(&self) -> bool {
+        self.warmed_up.load(Ordering::Relaxed)
+    }
+
+    /// Probe the client once it has gone `max_idle` without a successful round trip, or
+    /// when it has never had one, replacing it if the probe fails. Returns whether it was replaced.
+    pub async fn evict_idle(&self, max_idle: Duration) -> Result<bool, Error> {
+        if self.since_last_success().is_some_and(|idle| idle < max_idle) {
+            return Ok(false)
+        }
+        let answers = {
+            let elm = self.pool.read().await;
+            elm.is_live() && elm.probe().await
+        };
+        if answers {
+            self.record_success();
+            return Ok(false)
+        }
+        self.refresh().await?;
+        Ok(true)
+    }
+
+    /// Whether the current client is live, without triggering a refresh
+    pub fn is_live(&self) -> bool {
+        self.pool.try_read().is_ok_and(|elm| elm.is_live())
+    }
+
+    /// Record a successful round trip to the upstream
+    pub fn record_success(&self) {
+        if let Ok(mut last) = self.last_successful_rpc.lock() {
+            *last = Some(Instant::now());
+        }
+    }
+
+    /// Time elapsed since the last successful round trip to the upstream
+    pub fn since_last_success(&self) -> Option<Duration> {
+        self.last_successful_rpc.lock().ok()?.map(|last| last.elapsed())
+    }
+}
+
+/// Decoded upstream event, one variant per [`EventType`]
+#[derive(Debug, Clone)]
+pub enum NotificationPayload {
+    BlockAdded(BlockAddedNotification),
+    VirtualChainChanged(VirtualChainChangedNotification),
+    FinalityConflict(FinalityConflictNotification),
+    FinalityConflictResolved(FinalityConflictResolvedNotification),
+    UtxosChanged(UtxosChangedNotification),
+    SinkBlueScoreChanged(SinkBlueScoreChangedNotification),
+    VirtualDaaScoreChanged(VirtualDaaScoreChangedNotification),
+    PruningPointUtxoSetOverride(PruningPointUtxoSetOverrideNotification),
+    NewBlockTemplate(NewBlockTemplateNotification),
+    /// Fallback for events this build cannot decode
+    Unknown { event_type: String, data: Value },
+}
+
+impl NotificationPayload {
+    /// Decode a JSON event tagged with its kebab-case `"type"`
+    pub fn from_json(data: Value) -> Self {
+        let event_type = data.get("type").and_then(Value::as_str).unwrap_or_default().to_string();
+        let Ok(ev) = EventType::from_str(&event_type) else {
+            return Self::Unknown { event_type, data }
+        };
+
+        fn decode<N: DeserializeOwned>(data: &Value) -> Option<N> {
+            serde_json::from_value(data.clone()).ok()
+        }
+        let payload = match ev {
+            EventType::BlockAdded => decode(&data).map(Self::BlockAdded),
+            EventType::VirtualChainChanged => decode(&data).map(Self::VirtualChainChanged),
+            EventType::FinalityConflict => decode(&data).map(Self::FinalityConflict),
+            EventType::FinalityConflictResolved => decode(&data).map(Self::FinalityConflictResolved),
+            EventType::UtxosChanged => decode(&data).map(Self::UtxosChanged),
+            EventType::SinkBlueScoreChanged => decode(&data).map(Self::SinkBlueScoreChanged),
+            EventType::VirtualDaaScoreChanged => decode(&data).map(Self::VirtualDaaScoreChanged),
+            EventType::PruningPointUtxoSetOverride => decode(&data).map(Self::PruningPointUtxoSetOverride),
+            EventType::NewBlockTemplate => decode(&data).map(Self::NewBlockTemplate),
+        };
+        payload.unwrap_or(Self::Unknown { event_type, data })
+    }
+
+    /// JSON form tagged with the kebab-case `"type"`, the inverse of [`Self::from_json`]
+    pub fn to_json(&self) -> Value {
+        let data = match self {
+            Self::BlockAdded(n) => serde_json::to_value(n),
+            Self::VirtualChainChanged(n) => serde_json::to_value(n),
+            Self::FinalityConflict(n) => serde_json::to_value(n),
+            Self::FinalityConflictResolved(n) => serde_json::to_value(n),
+            Self::UtxosChanged(n) => serde_json::to_value(n),
+            Self::SinkBlueScoreChanged(n) => serde_json::to_value(n),
+            Self::VirtualDaaScoreChanged(n) => serde_json::to_value(n),
+            Self::PruningPointUtxoSetOverride(n) => serde_json::to_value(n),
+            Self::NewBlockTemplate(n) => serde_json::to_value(n),
+            Self::Unknown { data, .. } => return data.clone(),
+        };
+        let mut data = data.unwrap_or(Value::Null);
+        if let (Value::Object(fields), Some(ev)) = (&mut data, self.event_type()) {
+            fields.insert("type".to_string(), Value::String(ev.to_string()));
+        }
+        data
+    }
+
+    /// `None` for [`NotificationPayload::Unknown`]
+    pub fn event_type(&self) -> Option<EventType> {
+        let ev = match self {
+            Self::BlockAdded(_) => EventType::BlockAdded,
+            Self::VirtualChainChanged(_) => EventType::VirtualChainChanged,
+            Self::FinalityConflict(_) => EventType::FinalityConflict,
+            Self::FinalityConflictResolved(_) => EventType::FinalityConflictResolved,
+            Self::UtxosChanged(_) => EventType::UtxosChanged,
+            Self::SinkBlueScoreChanged(_) => EventType::SinkBlueScoreChanged,
+            Self::VirtualDaaScoreChanged(_) => EventType::VirtualDaaScoreChanged,
+            Self::PruningPointUtxoSetOverride(_) => EventType::PruningPointUtxoSetOverride,
+            Self::NewBlockTemplate(_) => EventType::NewBlockTemplate,
+            Self::Unknown { .. } => return None,
+        };
+        Some(ev)
+    }
+}
+
+impl From<RpcNotification> for NotificationPayload {
+    fn from(notification: RpcNotification) -> Self {
+        match notification {
+            RpcNotification::BlockAdded(n) => Self::BlockAdded(n),
+            RpcNotification::VirtualChainChanged(n) => Self::VirtualChainChanged(n),
+            RpcNotification::FinalityConflict(n) => Self::FinalityConflict(n),
+            RpcNotification::FinalityConflictResolved(n) => Self::FinalityConflictResolved(n),
+            RpcNotification::UtxosChanged(n) => Self::UtxosChanged(n),
+            RpcNotification::SinkBlueScoreChanged(n) => Self::SinkBlueScoreChanged(n),
+            RpcNotification::VirtualDaaScoreChanged(n) => Self::VirtualDaaScoreChanged(n),
+            RpcNotification::PruningPointUtxoSetOverride(n) => Self::PruningPointUtxoSetOverride(n),
+            RpcNotification::NewBlockTemplate(n) => Self::NewBlockTemplate(n),
+        }
+    }
+}
+
+/// Upstream event as handed to pool consumers
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub payload: NotificationPayload,
+    pub received_at: SystemTime,
+}
+
+impl From<NotificationPayload> for Notification {
+    fn from(payload: NotificationPayload) -> Self {
+        Self { payload, received_at: SystemTime::now() }
+    }
+}
+
+impl From<RpcNotification> for Notification {
+    fn from(notification: RpcNotification) -> Self {
+        NotificationPayload::from(notification).into()
+    }
+}
+
+/// Broadcast channel carrying one event type; every receiver gets every event
+#[derive(Debug)]
+pub struct NotificationChannel {
+    sender: Sender<Notification>,
+}
+
+impl Default for NotificationChannel {
+    fn default() -> Self {
+        Self::with_capacity(NOTIFICATION_CAPACITY.load(Ordering::Relaxed))
+    }
+}
+
+impl NotificationChannel {
+    pub fn with_capacity(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    pub fn sender(&self) -> Sender<Notification> {
+        self.sender.clone()
+    }
+
+    /// Queue an event for every current receiver; without receivers it is dropped
+    pub fn send(&self, notification: Notification) {
+        // Only fails when nobody is subscribed
+        let _ = self.sender.send(notification);
+    }
+
+    /// New receiver of every event sent from now on
+    pub fn receiver(&self) -> NotificationReceiver {
+        NotificationReceiver { inner: self.sender.subscribe() }
+    }
+}
+
+/// Receiving end of a [`NotificationChannel`]
+#[derive(Debug)]
+pub struct NotificationReceiver {
+    inner: broadcast::Receiver<Notification>,
+}
+
+impl NotificationReceiver {
+    /// Next event, `None` once the channel is closed. A receiver that fell more
+    /// than the channel capacity behind logs how many events it lost and resumes
+    /// with the oldest one still buffered.
+    pub async fn recv(&mut self) -> Option<Notification> {
+        loop {
+            match self.inner.recv().await {
+                Ok(notification) => return Some(notification),
+                Err(RecvError::Lagged(skipped)) => warn!("Notification receiver lagged, skipped {skipped} events"),
+                Err(RecvError::Closed) => return None,
+            }
         }
-        Ok(pool.try_read()?)
     }
 }
 
@@ -58,6 +410,9 @@ pub enum Error {
     #[error(transparent)]
     TryLockError(#[from] TryLockError),
 
+    #[error("All {0} upstream permits are in use")]
+    Saturated(usize),
+
     #[error("{0}")]
     PoolError(String),
 }
@@ -67,3 +422,175 @@ impl From<String> for Error {
         Self::PoolError(err)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn daa_score(score: u64) -> Notification {
+        NotificationPayload::VirtualDaaScoreChanged(VirtualDaaScoreChangedNotification { virtual_daa_score: score })
+            .into()
+    }
+
+    fn score(notification: Option<Notification>) -> u64 {
+        match notification.map(|n| n.payload) {
+            Some(NotificationPayload::VirtualDaaScoreChanged(n)) => n.virtual_daa_score,
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_every_receiver_gets_every_event() {
+        let channel = NotificationChannel::with_capacity(4);
+        let mut first = channel.receiver();
+        let mut second = channel.receiver();
+
+        channel.send(daa_score(7));
+        assert_eq!(score(first.recv().await), 7);
+        assert_eq!(score(second.recv().await), 7);
+    }
+
+    #[tokio::test]
+    async fn test_lagging_receiver_skips_ahead() {
+        let channel = NotificationChannel::with_capacity(2);
+        let mut receiver = channel.receiver();
+        for n in 1..=5 {
+            channel.send(daa_score(n));
+        }
+
+        // Events 1 to 3 were overwritten
+        assert_eq!(score(receiver.recv().await), 4);
+        assert_eq!(score(receiver.recv().await), 5);
+
+        drop(channel);
+        assert!(receiver.recv().await.is_none());
+    }
+
+    #[test]
+    fn test_json_payload_is_typed() {
+        let payload = NotificationPayload::from_json(json!({
+            "type": "sink-blue-score-changed",
+            "sinkBlueScore": 42,
+        }));
+        match payload {
+            NotificationPayload::SinkBlueScoreChanged(n) => assert_eq!(n.sink_blue_score, 42),
+            other => panic!("unexpected payload: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let data = json!({ "type": "virtual-daa-score-changed", "virtualDaaScore": 9 });
+        let payload = NotificationPayload::from_json(data.clone());
+        assert_eq!(payload.to_json(), data);
+    }
+
+    #[test]
+    fn test_unknown_json_payload_falls_back() {
+        let payload = NotificationPayload::from_json(json!({ "type": "something-new", "x": 1 }));
+        assert!(payload.event_type().is_none());
+        assert!(matches!(payload, NotificationPayload::Unknown { event_type, .. } if event_type == "something-new"));
+    }
+
+    #[test]
+    fn test_rpc_notification_conversion() {
+        let rpc = RpcNotification::VirtualDaaScoreChanged(VirtualDaaScoreChangedNotification { virtual_daa_score: 7 });
+        let notification = Notification::from(rpc);
+        assert_eq!(notification.payload.event_type(), Some(EventType::VirtualDaaScoreChanged));
+    }
+
+    /// Client counting its connections in `Meta`
+    #[derive(Debug)]
+    struct Connection {
+        live: bool,
+    }
+
+    impl Metadata for Connection {
+        type Error = Error;
+        type Meta = std::sync::Arc<AtomicUsize>;
+
+        async fn try_from(connects: &Self::Meta) -> Result<Self, Error> {
+            connects.fetch_add(1, Ordering::Relaxed);
+            Ok(Self { live: true })
+        }
+    }
+
+    impl HealthCheck for Connection {
+        fn is_live(&self) -> bool {
+            self.live
+        }
+
+        async fn probe(&self) -> bool {
+            self.live
+        }
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_connects_before_the_first_request() {
+        let connects = std::sync::Arc::new(AtomicUsize::new(0));
+        let pool = Pool::new(connects.clone(), Connection { live: false });
+        assert!(!pool.is_warmed_up());
+
+        pool.warm_up().await.unwrap();
+        assert!(pool.is_warmed_up());
+        assert_eq!(connects.load(Ordering::Relaxed), 1);
+
+        // The first request reuses the warmed connection
+        assert!(pool.get().await.unwrap().is_live());
+        assert_eq!(connects.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_evict_idle_replaces_a_dead_connection() {
+        let connects = std::sync::Arc::new(AtomicUsize::new(0));
+        let pool = Pool::new(connects.clone(), Connection { live: false });
+        assert_eq!(pool.since_last_success(), None);
+
+        // Never answered, so probed however short the idle time
+        assert!(pool.evict_idle(Duration::from_secs(60)).await.unwrap());
+        assert_eq!(connects.load(Ordering::Relaxed), 1);
+        // Connecting the replacement counts as contact
+        assert!(!pool.evict_idle(Duration::from_secs(60)).await.unwrap());
+        assert!(pool.since_last_success().is_some());
+
+        assert!(!pool.evict_idle(Duration::ZERO).await.unwrap());
+        assert_eq!(connects.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_hooks_see_the_new_client() {
+        let connects = std::sync::Arc::new(AtomicUsize::new(0));
+        let pool = Pool::new(connects.clone(), Connection { live: false });
+        let refreshed = std::sync::Arc::new(AtomicUsize::new(0));
+        pool.on_refresh({
+            let refreshed = refreshed.clone();
+            move |connection| {
+                assert!(connection.live);
+                refreshed.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+
+        assert!(pool.evict_idle(Duration::ZERO).await.unwrap());
+        assert_eq!(refreshed.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limit_is_enforced() {
+        let connects = std::sync::Arc::new(AtomicUsize::new(0));
+        let pool =
+            Pool::new(connects, Connection { live: true }).with_concurrency_limit(2, Duration::from_millis(10));
+        let permits = pool.permits().unwrap();
+
+        let first = pool.get().await.unwrap();
+        let second = pool.get().await.unwrap();
+        assert_eq!(permits.available_permits(), 0);
+        assert!(matches!(pool.get().await, Err(Error::Saturated(2))));
+
+        drop(first);
+        assert_eq!(permits.available_permits(), 1);
+        let third = pool.get().await.unwrap();
+        assert!(third.is_live() && second.is_live());
+    }
+}
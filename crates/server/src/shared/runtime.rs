@@ -0,0 +1,64 @@
+use tokio::runtime::{Builder, Runtime};
+use tondi_listener_library::log;
+
+use crate::{ctx::config::RuntimeConfig, error::Result};
+
+/// Install the log subscriber, serving `tokio-console` too when `enable_tokio_console` is set
+pub fn init_log(config: &RuntimeConfig) {
+    #[cfg(feature = "tokio-console")]
+    {
+        let console = config
+            .enable_tokio_console
+            .then(|| std::net::SocketAddr::from(([127, 0, 0, 1], config.tokio_console_port)));
+        log::init_tracing_subscriber_log_with_console(console);
+        if let Some(console) = console {
+            log::info!("tokio-console listening on {console}");
+        }
+    }
+    #[cfg(not(feature = "tokio-console"))]
+    {
+        log::init_tracing_subscriber_log();
+        if config.enable_tokio_console {
+            log::warn!("`enable_tokio_console` is set, but this binary was built without the `tokio-console` feature");
+        }
+    }
+}
+
+/// [`init_log`], then filter the output by the `log_level` setting, so `--log-level` and
+/// `TONDI_LISTENER_LOG_LEVEL` apply from the first line logged
+pub fn init_log_with_level(config: &RuntimeConfig, log_level: &str) {
+    init_log(config);
+    if let Err(e) = log::set_filter(log_level) {
+        log::warn!("Log level `{log_level}` not applied, `RUST_LOG` filters the output: {e}");
+    }
+}
+
+/// Build the Tokio runtime the binaries block on, sized from config
+pub fn build(config: &RuntimeConfig) -> Result<Runtime> {
+    let mut builder = if config.current_thread {
+        Builder::new_current_thread()
+    } else {
+        let mut builder = Builder::new_multi_thread();
+        if config.worker_threads > 0 {
+            builder.worker_threads(config.worker_threads);
+        }
+        builder
+    };
+
+    let runtime = builder.max_blocking_threads(config.max_blocking_threads).enable_all().build()?;
+    Ok(runtime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_runtime_flavors() {
+        let config = RuntimeConfig { current_thread: true, ..Default::default() };
+        assert_eq!(build(&config).unwrap().block_on(async { 1 + 1 }), 2);
+
+        let config = RuntimeConfig { worker_threads: 2, max_blocking_threads: 4, ..Default::default() };
+        assert_eq!(build(&config).unwrap().block_on(async { 2 + 2 }), 4);
+    }
+}
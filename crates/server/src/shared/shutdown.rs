@@ -0,0 +1,83 @@
+use std::future::Future;
+
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+/// Process shutdown: work registered with [`Shutdown::on_shutdown`] runs once it starts,
+/// and [`Shutdown::run`] returns when all of it has finished
+#[derive(Debug, Clone, Default)]
+pub struct Shutdown {
+    token: CancellationToken,
+    tasks: TaskTracker,
+}
+
+impl Shutdown {
+    /// Run `task` once shutdown starts, holding up [`Shutdown::run`] until it completes
+    pub fn on_shutdown(&self, task: impl Future<Output = ()> + Send + 'static) {
+        let token = self.token.clone();
+        self.tasks.spawn(async move {
+            token.cancelled().await;
+            task.await;
+        });
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Start shutting down and wait for the registered work
+    pub async fn run(&self) {
+        self.token.cancel();
+        self.tasks.close();
+        self.tasks.wait().await;
+    }
+}
+
+/// Resolves on Ctrl-C, or SIGTERM on Unix
+pub async fn signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            },
+            Err(_) => std::future::pending().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_run_waits_for_registered_work() {
+        let shutdown = Shutdown::default();
+        let done = Arc::new(AtomicBool::new(false));
+        let flag = done.clone();
+        shutdown.on_shutdown(async move {
+            tokio::task::yield_now().await;
+            flag.store(true, Ordering::SeqCst);
+        });
+        tokio::task::yield_now().await;
+        assert!(!done.load(Ordering::SeqCst), "ran before shutdown");
+
+        shutdown.run().await;
+        assert!(shutdown.is_started());
+        assert!(done.load(Ordering::SeqCst));
+    }
+}
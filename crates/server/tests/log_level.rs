@@ -0,0 +1,9 @@
+use tondi_listener_library::log::{Level, enabled};
+use tondi_listener_server::{ctx::config::RuntimeConfig, shared::runtime};
+
+#[test]
+fn test_log_level_setting_filters_from_the_start() {
+    runtime::init_log_with_level(&RuntimeConfig::default(), "warn");
+    assert!(enabled!(Level::WARN));
+    assert!(!enabled!(Level::INFO));
+}
@@ -0,0 +1,64 @@
+use std::{sync::Arc, time::Duration};
+
+use serde_json::Value;
+use tokio::sync::mpsc::Receiver;
+use tondi_listener_server::{
+    ctx::event_config::EventType,
+    extensions::client_pool::Client,
+    routes::websocket::hub::Hub,
+    shared::pool::{HealthCheck, NotificationPayload},
+};
+use tondi_rpc_core::{SinkBlueScoreChangedNotification, VirtualDaaScoreChangedNotification};
+
+const EVENTS: [EventType; 2] = [EventType::VirtualDaaScoreChanged, EventType::SinkBlueScoreChanged];
+
+async fn recv(receiver: &mut Receiver<String>) -> Value {
+    let message = tokio::time::timeout(Duration::from_secs(1), receiver.recv()).await.unwrap();
+    serde_json::from_str(&message.unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn test_mock_notifications_reach_websocket_subscribers() {
+    let client = Client::connect_with_events("mock://simnet".to_string(), &EVENTS, None).await.unwrap();
+    let Client::Mock(mock) = &client else { panic!("expected the mock client, got {client:?}") };
+
+    let hub = Arc::new(Hub::default());
+    assert_eq!(hub.attach(client.listener_manager()).len(), EVENTS.len());
+
+    let mut daa_watcher = hub.register(1);
+    hub.subscribe(1, [EventType::VirtualDaaScoreChanged]);
+    let mut blue_score_watcher = hub.register(2);
+    hub.subscribe(2, [EventType::SinkBlueScoreChanged]);
+
+    mock.emit(NotificationPayload::VirtualDaaScoreChanged(VirtualDaaScoreChangedNotification {
+        virtual_daa_score: 1234,
+    }))
+    .await
+    .unwrap();
+    mock.emit(NotificationPayload::SinkBlueScoreChanged(SinkBlueScoreChangedNotification {
+        sink_blue_score: 99,
+    }))
+    .await
+    .unwrap();
+
+    let daa = recv(&mut daa_watcher).await;
+    assert_eq!(daa["event"], "virtual-daa-score-changed");
+    assert_eq!(daa["data"]["virtualDaaScore"], 1234);
+
+    let blue_score = recv(&mut blue_score_watcher).await;
+    assert_eq!(blue_score["event"], "sink-blue-score-changed");
+    assert_eq!(blue_score["data"]["sinkBlueScore"], 99);
+
+    // Each connection only sees what it subscribed to
+    assert!(daa_watcher.try_recv().is_err());
+    assert!(blue_score_watcher.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_mock_client_liveness_is_scriptable() {
+    let client = Client::mock(&EVENTS);
+    let Client::Mock(mock) = &client else { unreachable!() };
+    assert!(client.is_live());
+    mock.set_live(false);
+    assert!(!client.is_live());
+}
@@ -0,0 +1,11 @@
+use tondi_listener_library::log::info;
+use tondi_listener_server::{ctx::config::RuntimeConfig, shared::runtime};
+
+/// Builds only with `--features tokio-console`, which is what it checks
+#[test]
+fn test_tokio_console_subscriber_installs() {
+    let config = RuntimeConfig { enable_tokio_console: true, tokio_console_port: 0, ..Default::default() };
+    runtime::init_log(&config);
+    let runtime = runtime::build(&config).unwrap();
+    runtime.block_on(async { tokio::spawn(async { info!("traced task") }).await.unwrap() });
+}